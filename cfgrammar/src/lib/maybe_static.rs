@@ -0,0 +1,98 @@
+use std::{fmt, ops::Deref};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A slice which is either borrowed from `'static` data (e.g. the tables which `lrpar`'s
+/// `CTParserBuilder` generates) or owned. This is the storage of grammars and state tables: those
+/// built at run-time own their data, while those generated at compile-time borrow it directly
+/// from `static` arrays, so that they can be constructed without any work at run-time. Unlike
+/// `Cow`, this places no bounds on `T` (beyond `'static`), so it can be used in types which are
+/// generic over their storage type.
+#[doc(hidden)]
+pub enum MaybeStatic<T: 'static> {
+    Static(&'static [T]),
+    Owned(Vec<T>),
+}
+
+impl<T> Deref for MaybeStatic<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            MaybeStatic::Static(s) => s,
+            MaybeStatic::Owned(v) => v,
+        }
+    }
+}
+
+impl<T> From<Vec<T>> for MaybeStatic<T> {
+    fn from(v: Vec<T>) -> Self {
+        MaybeStatic::Owned(v)
+    }
+}
+
+impl<T> Default for MaybeStatic<T> {
+    fn default() -> Self {
+        MaybeStatic::Static(&[])
+    }
+}
+
+impl<T: Clone> Clone for MaybeStatic<T> {
+    fn clone(&self) -> Self {
+        match self {
+            MaybeStatic::Static(s) => MaybeStatic::Static(s),
+            MaybeStatic::Owned(v) => MaybeStatic::Owned(v.clone()),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for MaybeStatic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: PartialEq<U>, U> PartialEq<MaybeStatic<U>> for MaybeStatic<T> {
+    fn eq(&self, other: &MaybeStatic<U>) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: PartialEq<U>, U> PartialEq<Vec<U>> for MaybeStatic<T> {
+    fn eq(&self, other: &Vec<U>) -> bool {
+        **self == **other
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Serialize> Serialize for MaybeStatic<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MaybeStatic<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(MaybeStatic::Owned)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MaybeStatic;
+
+    #[test]
+    fn test_maybe_static() {
+        static S: [u32; 3] = [1, 2, 3];
+        let s = MaybeStatic::Static(&S);
+        let o = MaybeStatic::from(vec![1, 2, 3]);
+        assert_eq!(s, o);
+        assert_eq!(s, vec![1, 2, 3]);
+        assert_eq!(s.len(), 3);
+        assert_eq!(o[1], 2);
+        assert!(MaybeStatic::<u32>::default().is_empty());
+        assert!(matches!(s.clone(), MaybeStatic::Static(_)));
+    }
+}
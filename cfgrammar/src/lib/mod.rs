@@ -51,10 +51,15 @@
 use serde::{Deserialize, Serialize};
 
 mod idxnewtype;
+mod maybe_static;
+mod span;
 pub mod yacc;
 
 /// A type specifically for rule indices.
 pub use crate::idxnewtype::{PIdx, RIdx, SIdx, TIdx};
+#[doc(hidden)]
+pub use crate::maybe_static::MaybeStatic;
+pub use crate::span::Span;
 
 #[derive(Clone, Copy, Debug, Hash, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
@@ -0,0 +1,86 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A `Span` records what portion of the user's input something (e.g. a lexeme or production)
+/// references (i.e. the `Span` doesn't hold a reference / copy of the actual input).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Span {
+    start: usize,
+    end: usize,
+}
+
+impl Span {
+    /// Create a new span starting at byte `start` and ending at byte `end`.
+    ///
+    /// # Panics
+    ///
+    /// If `end` is less than `start`.
+    pub fn new(start: usize, end: usize) -> Self {
+        if end < start {
+            panic!("Span starts ({}) after it ends ({})!", start, end);
+        }
+        Span { start, end }
+    }
+
+    /// Create a new span starting at byte `start` and ending at byte `end`, which must not be
+    /// less than `start`. This is used by grammars generated at compile-time.
+    #[doc(hidden)]
+    pub const fn _from_static(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Byte offset of the start of the span.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Byte offset of the end of the span.
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Length in bytes of the span.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if this `Span` covers 0 bytes, or `false` otherwise.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the line and column (both starting from 1, with columns counted in characters) of
+    /// the start of this span within `src`.
+    ///
+    /// # Panics
+    ///
+    /// If this span starts beyond the end of `src`.
+    pub fn line_col(&self, src: &str) -> (usize, usize) {
+        if self.start > src.len() {
+            panic!("Span {:?} exceeds known input length {}", self, src.len());
+        }
+        let line_off = src[..self.start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        (
+            src[..self.start].matches('\n').count() + 1,
+            src[line_off..self.start].chars().count() + 1,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Span;
+
+    #[test]
+    fn test_line_col() {
+        let src = "a\nbc\n\u{1F600}d";
+        assert_eq!(Span::new(0, 1).line_col(src), (1, 1));
+        assert_eq!(Span::new(1, 1).line_col(src), (1, 2));
+        assert_eq!(Span::new(2, 4).line_col(src), (2, 1));
+        assert_eq!(Span::new(3, 4).line_col(src), (2, 2));
+        assert_eq!(Span::new(5, 5).line_col(src), (3, 1));
+        assert_eq!(Span::new(9, 10).line_col(src), (3, 2));
+        assert_eq!(Span::new(10, 10).line_col(src), (3, 3));
+    }
+}
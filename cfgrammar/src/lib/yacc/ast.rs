@@ -1,50 +1,82 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     error::Error,
     fmt,
+    hash::{Hash, Hasher},
+    iter,
+    path::PathBuf,
 };
 
 use indexmap::{IndexMap, IndexSet};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-use super::Precedence;
+use super::{
+    source::{pp_location, YaccSource},
+    AssocKind, Precedence, YaccKind,
+};
+use crate::Span;
 
 /// An AST representing a grammar. This is built up gradually: when it is finished, the
 /// `complete_and_validate` must be called exactly once in order to finish the set-up. At that
 /// point, any further mutations made to the struct lead to undefined behaviour.
+///
+/// Names which were explicitly written in the grammar are paired with the `Span` of the input they
+/// were parsed from.
 pub struct GrammarAST {
-    pub start: Option<String>,
+    pub start: Option<(String, Span)>,
+    // Further rules listed in the `%start` declaration, each of which is an additional entry point
+    // into the grammar.
+    pub extra_starts: Vec<(String, Span)>,
     // map from a rule name to indexes into prods
     pub rules: IndexMap<String, Rule>,
     pub prods: Vec<Production>,
     pub tokens: IndexSet<String>,
+    // A map from a token name to the span of its first appearance in the grammar, whether in a
+    // declaration (e.g. `%token` or `%left`) or a production.
+    pub token_spans: HashMap<String, Span>,
     pub precs: HashMap<String, Precedence>,
     pub avoid_insert: Option<HashSet<String>>,
     pub implicit_tokens: Option<HashSet<String>>,
     pub parse_param_bindings: Option<Vec<(String, String)>>,
     pub parse_param_lifetimes: Option<HashSet<String>>,
-    // Error pretty-printers
-    pub epp: HashMap<String, String>,
+    // Error pretty-printers: a map from a token name to the span of the token name in the %epp
+    // declaration and the pretty-printed string.
+    pub epp: HashMap<String, (Span, String)>,
     pub programs: Option<String>,
+    // The number of shift/reduce and reduce/reduce conflicts the user expects (via `%expect` and
+    // `%expect-rr` respectively).
+    pub expect: Option<usize>,
+    pub expectrr: Option<usize>,
 }
 
 #[derive(Debug)]
 pub struct Rule {
-    pub name: String,
+    pub name: (String, Span),
     pub pidxs: Vec<usize>, // index into GrammarAST.prod
     pub actiont: Option<String>,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// A production. Two productions are equal if they have the same symbols, precedence, action, and
+/// labels, no matter where in the grammar source they were written.
+#[derive(Debug)]
 pub struct Production {
     pub symbols: Vec<Symbol>,
-    pub precedence: Option<String>,
-    pub action: Option<String>,
+    pub precedence: Option<(String, Span)>,
+    pub action: Option<(String, Span)>,
+    pub prod_span: Span,
+    // The labels (e.g. `lhs` in `lhs=Expr`) attached to symbols, as pairs of an index into
+    // `symbols` and a label, ordered by index.
+    pub labels: Vec<(usize, String)>,
 }
 
-#[derive(Clone, Debug, Hash, Eq, PartialEq)]
+/// A reference to a rule or token, paired with the span in the grammar source where the reference
+/// was made. Spans are ignored when comparing and hashing symbols, so two references to the same
+/// rule (or token) are equal.
+#[derive(Clone, Debug)]
 pub enum Symbol {
-    Rule(String),
-    Token(String),
+    Rule(String, Span),
+    Token(String, Span),
 }
 
 /// The various different possible grammar validation errors.
@@ -63,12 +95,45 @@ pub enum GrammarValidationErrorKind {
 pub struct GrammarValidationError {
     pub kind: GrammarValidationErrorKind,
     pub sym: Option<Symbol>,
+    // The line and column of `sym` in the grammar source, if it is known.
+    line_col: Option<(usize, usize)>,
+    // The included file `sym` is in (see `YaccSource`), or `None` if it is in the grammar file
+    // itself.
+    file: Option<PathBuf>,
 }
 
-impl Error for GrammarValidationError {}
+impl GrammarValidationError {
+    fn new(kind: GrammarValidationErrorKind, sym: Option<Symbol>) -> Self {
+        GrammarValidationError {
+            kind,
+            sym,
+            line_col: None,
+            file: None,
+        }
+    }
 
-impl fmt::Display for GrammarValidationError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// The span in the grammar source that caused this error, if it is known.
+    pub fn span(&self) -> Option<Span> {
+        self.sym.as_ref().map(|s| s.span())
+    }
+
+    /// Record the line and column of this error's span within `src`, the grammar source this
+    /// error was generated from, so that it can be included in error messages.
+    pub(crate) fn set_src(&mut self, src: &str) {
+        self.line_col = self.span().map(|s| s.line_col(src));
+    }
+
+    /// Map this error's location in `source.src()` (which must have been recorded with `set_src`)
+    /// back to the file it came from.
+    pub(crate) fn set_source(&mut self, source: &YaccSource) {
+        if let Some((line, col)) = self.line_col {
+            let (file, line) = source.origin(line);
+            self.file = file.map(|p| p.to_owned());
+            self.line_col = Some((line, col));
+        }
+    }
+
+    fn fmt_msg(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.kind {
             GrammarValidationErrorKind::NoStartRule => write!(f, "No start rule specified"),
             GrammarValidationErrorKind::InvalidStartRule => write!(
@@ -98,23 +163,158 @@ impl fmt::Display for GrammarValidationError {
     }
 }
 
-impl fmt::Display for Symbol {
+impl Error for GrammarValidationError {}
+
+impl fmt::Display for GrammarValidationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_msg(f)?;
+        if let Some((line, col)) = self.line_col {
+            write!(f, " {}", pp_location((self.file.as_deref(), line), col))?;
+        }
+        Ok(())
+    }
+}
+
+/// The various different possible grammar warnings.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum YaccGrammarWarningKind {
+    /// A rule which can't be reached from the start rule.
+    UnreachableRule,
+    /// A rule which can't derive a finite sentence (i.e. it can never match any input).
+    UnproductiveRule,
+    /// A token which is declared but not used in any production.
+    UnusedToken,
+    /// A token whose precedence never affects conflict resolution. This can only be determined
+    /// once a state table has been built, so such warnings are not included in
+    /// `YaccGrammar::warnings`: see `lrtable`'s `StateTable::precedence_warnings`.
+    UselessPrecedence,
+}
+
+/// A warning about a grammar which, unlike a `GrammarValidationError`, doesn't prevent the
+/// grammar from being used.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct YaccGrammarWarning {
+    pub kind: YaccGrammarWarningKind,
+    /// The name of the rule or token this warning refers to.
+    pub name: String,
+    /// The location of the rule or token in the grammar source, if it is known.
+    pub span: Option<Span>,
+}
+
+impl YaccGrammarWarning {
+    /// Returns a pretty-printed version of this warning, including its line and column in
+    /// `src` (the grammar source this warning was generated from) if its location is known.
+    pub fn pp_with_src(&self, src: &str) -> String {
+        match self.span {
+            Some(span) => {
+                let (line, col) = span.line_col(src);
+                format!("{} at line {} column {}", self, line, col)
+            }
+            None => self.to_string(),
+        }
+    }
+
+    /// Returns a pretty-printed version of this warning, including its location in `source` (the
+    /// grammar this warning was generated from) if it is known.
+    pub fn pp_with_source(&self, source: &YaccSource) -> String {
+        match self.span {
+            Some(span) => format!("{} {}", self, source.pp_span(span)),
+            None => self.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for YaccGrammarWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            YaccGrammarWarningKind::UnreachableRule => {
+                write!(f, "Rule '{}' is unreachable from the start rule", self.name)
+            }
+            YaccGrammarWarningKind::UnproductiveRule => {
+                write!(f, "Rule '{}' can never match any input", self.name)
+            }
+            YaccGrammarWarningKind::UnusedToken => {
+                write!(f, "Token '{}' is not used in any production", self.name)
+            }
+            YaccGrammarWarningKind::UselessPrecedence => {
+                write!(f, "The precedence of token '{}' is never used", self.name)
+            }
+        }
+    }
+}
+
+impl Symbol {
+    /// The name of the rule or token this symbol references.
+    pub fn name(&self) -> &str {
         match *self {
-            Symbol::Rule(ref s) => write!(f, "{}", s),
-            Symbol::Token(ref s) => write!(f, "{}", s),
+            Symbol::Rule(ref s, _) | Symbol::Token(ref s, _) => s,
         }
     }
+
+    /// The span in the grammar source of this symbol.
+    pub fn span(&self) -> Span {
+        match *self {
+            Symbol::Rule(_, span) | Symbol::Token(_, span) => span,
+        }
+    }
+}
+
+impl PartialEq for Production {
+    fn eq(&self, other: &Production) -> bool {
+        self.symbols == other.symbols
+            && self.precedence.as_ref().map(|(n, _)| n) == other.precedence.as_ref().map(|(n, _)| n)
+            && self.action.as_ref().map(|(a, _)| a) == other.action.as_ref().map(|(a, _)| a)
+            && self.labels == other.labels
+    }
+}
+
+impl Eq for Production {}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Symbol) -> bool {
+        match (self, other) {
+            (Symbol::Rule(m, _), Symbol::Rule(n, _))
+            | (Symbol::Token(m, _), Symbol::Token(n, _)) => m == n,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Symbol::Rule(n, _) => {
+                0u8.hash(state);
+                n.hash(state);
+            }
+            Symbol::Token(n, _) => {
+                1u8.hash(state);
+                n.hash(state);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
 }
 
 impl GrammarAST {
     pub fn new() -> GrammarAST {
         GrammarAST {
             start: None,
+            extra_starts: Vec::new(),
             rules: IndexMap::new(), // Using an IndexMap means that we retain the order
             // of rules as they're found in the input file.
             prods: Vec::new(),
             tokens: IndexSet::new(),
+            token_spans: HashMap::new(),
             precs: HashMap::new(),
             avoid_insert: None,
             implicit_tokens: None,
@@ -122,14 +322,16 @@ impl GrammarAST {
             programs: None,
             parse_param_bindings: None,
             parse_param_lifetimes: None,
+            expect: None,
+            expectrr: None,
         }
     }
 
-    pub fn add_rule(&mut self, name: String, actiont: Option<String>) {
+    pub fn add_rule(&mut self, (name, name_span): (String, Span), actiont: Option<String>) {
         self.rules.insert(
             name.clone(),
             Rule {
-                name,
+                name: (name, name_span),
                 pidxs: Vec::new(),
                 actiont,
             },
@@ -140,14 +342,17 @@ impl GrammarAST {
         &mut self,
         rule_name: String,
         symbols: Vec<Symbol>,
-        precedence: Option<String>,
-        action: Option<String>,
+        precedence: Option<(String, Span)>,
+        action: Option<(String, Span)>,
+        prod_span: Span,
     ) {
         self.rules[&rule_name].pidxs.push(self.prods.len());
         self.prods.push(Production {
             symbols,
             precedence,
             action,
+            prod_span,
+            labels: Vec::new(),
         });
     }
 
@@ -170,64 +375,68 @@ impl GrammarAST {
     ///   3) Every token reference references a declared token
     ///   4) If a production has a precedence token, then it references a declared token
     ///   5) Every token declared with %epp matches a known token
-    /// If the validation succeeds, None is returned.
-    pub(crate) fn complete_and_validate(&mut self) -> Result<(), GrammarValidationError> {
+    ///
+    /// If the validation fails, every error found is returned, ordered by their position in the
+    /// grammar source.
+    pub(crate) fn complete_and_validate(&mut self) -> Result<(), Vec<GrammarValidationError>> {
+        let mut errs = Vec::new();
         match self.start {
             None => {
-                return Err(GrammarValidationError {
-                    kind: GrammarValidationErrorKind::NoStartRule,
-                    sym: None,
-                });
-            }
-            Some(ref s) => {
-                if !self.rules.contains_key(s) {
-                    return Err(GrammarValidationError {
-                        kind: GrammarValidationErrorKind::InvalidStartRule,
-                        sym: Some(Symbol::Rule(s.clone())),
-                    });
+                errs.push(GrammarValidationError::new(
+                    GrammarValidationErrorKind::NoStartRule,
+                    None,
+                ));
+            }
+            Some(ref start) => {
+                for (s, span) in iter::once(start).chain(&self.extra_starts) {
+                    if !self.rules.contains_key(s) {
+                        errs.push(GrammarValidationError::new(
+                            GrammarValidationErrorKind::InvalidStartRule,
+                            Some(Symbol::Rule(s.clone(), *span)),
+                        ));
+                    }
                 }
             }
         }
         for rule in self.rules.values() {
             for &pidx in &rule.pidxs {
                 let prod = &self.prods[pidx];
-                if let Some(ref n) = prod.precedence {
+                if let Some((ref n, span)) = prod.precedence {
                     if !self.tokens.contains(n) {
-                        return Err(GrammarValidationError {
-                            kind: GrammarValidationErrorKind::UnknownToken,
-                            sym: Some(Symbol::Token(n.clone())),
-                        });
-                    }
-                    if !self.precs.contains_key(n) {
-                        return Err(GrammarValidationError {
-                            kind: GrammarValidationErrorKind::NoPrecForToken,
-                            sym: Some(Symbol::Token(n.clone())),
-                        });
+                        errs.push(GrammarValidationError::new(
+                            GrammarValidationErrorKind::UnknownToken,
+                            Some(Symbol::Token(n.clone(), span)),
+                        ));
+                    } else if !self.precs.contains_key(n) {
+                        errs.push(GrammarValidationError::new(
+                            GrammarValidationErrorKind::NoPrecForToken,
+                            Some(Symbol::Token(n.clone(), span)),
+                        ));
                     }
                 }
                 for sym in &prod.symbols {
                     match *sym {
-                        Symbol::Rule(ref name) => {
+                        Symbol::Rule(ref name, _) => {
                             if !self.rules.contains_key(name) {
-                                return Err(GrammarValidationError {
-                                    kind: GrammarValidationErrorKind::UnknownRuleRef,
-                                    sym: Some(sym.clone()),
-                                });
+                                errs.push(GrammarValidationError::new(
+                                    GrammarValidationErrorKind::UnknownRuleRef,
+                                    Some(sym.clone()),
+                                ));
                             }
                         }
-                        Symbol::Token(ref name) => {
+                        Symbol::Token(ref name, _) => {
                             if !self.tokens.contains(name) {
-                                return Err(GrammarValidationError {
-                                    kind: GrammarValidationErrorKind::UnknownToken,
-                                    sym: Some(sym.clone()),
-                                });
+                                errs.push(GrammarValidationError::new(
+                                    GrammarValidationErrorKind::UnknownToken,
+                                    Some(sym.clone()),
+                                ));
                             }
                         }
                     }
                 }
             }
         }
-        for k in self.epp.keys() {
+        for (k, (span, _)) in &self.epp {
             if self.tokens.contains(k) {
                 continue;
             }
@@ -236,12 +445,343 @@ impl GrammarAST {
                     continue;
                 }
             }
-            return Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::UnknownEPP,
-                sym: Some(Symbol::Token(k.clone())),
+            errs.push(GrammarValidationError::new(
+                GrammarValidationErrorKind::UnknownEPP,
+                Some(Symbol::Token(k.clone(), *span)),
+            ));
+        }
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            // Errors without a span (e.g. a missing start rule) come first.
+            errs.sort_by_key(|e| e.span().map(|s| s.start()));
+            Err(errs)
+        }
+    }
+
+    /// Return the warnings for this grammar, ordered by their position in the grammar source.
+    /// This must only be called after `complete_and_validate` has succeeded.
+    pub(crate) fn warnings(&self) -> Vec<YaccGrammarWarning> {
+        let mut warnings = Vec::new();
+        let rule_warning = |kind, name: &str| YaccGrammarWarning {
+            kind,
+            name: name.to_owned(),
+            span: Some(self.rules[name].name.1),
+        };
+
+        let rules = self
+            .rules
+            .iter()
+            .map(|(n, rule)| {
+                let prods = rule
+                    .pidxs
+                    .iter()
+                    .map(|&pidx| self.prods[pidx].symbols.as_slice())
+                    .collect();
+                (n.as_str(), prods)
+            })
+            .collect::<Vec<_>>();
+        let starts = iter::once(self.start.as_ref().unwrap())
+            .chain(&self.extra_starts)
+            .map(|(n, _)| n.as_str());
+        let reachable = reachable_rules(starts, &rules);
+        let productive = productive_rules(&rules);
+
+        for n in self.rules.keys() {
+            if !reachable.contains(n.as_str()) {
+                warnings.push(rule_warning(YaccGrammarWarningKind::UnreachableRule, n));
+            }
+            if !productive.contains(n.as_str()) {
+                warnings.push(rule_warning(YaccGrammarWarningKind::UnproductiveRule, n));
+            }
+        }
+
+        let mut used_tokens = HashSet::new();
+        let mut prec_tokens = HashSet::new();
+        for prod in &self.prods {
+            for sym in &prod.symbols {
+                if let Symbol::Token(ref n, _) = *sym {
+                    used_tokens.insert(n.as_str());
+                }
+            }
+            if let Some((ref n, _)) = prod.precedence {
+                prec_tokens.insert(n.as_str());
+            }
+        }
+        for n in &self.tokens {
+            if used_tokens.contains(n.as_str()) || prec_tokens.contains(n.as_str()) {
+                continue;
+            }
+            // Implicit tokens are used implicitly between every other token.
+            if let Some(ref it) = self.implicit_tokens {
+                if it.contains(n) {
+                    continue;
+                }
+            }
+            warnings.push(YaccGrammarWarning {
+                kind: YaccGrammarWarningKind::UnusedToken,
+                name: n.clone(),
+                span: self.token_spans.get(n).cloned(),
             });
         }
-        Ok(())
+
+        warnings.sort_by_key(|w| w.span.map(|s| s.start()));
+        warnings
+    }
+
+    /// Pretty-print this grammar as Yacc source in the `yacc_kind` variant, such that parsing the
+    /// output with the same `yacc_kind` produces an equivalent grammar. The output is canonical:
+    /// every token is quoted and declared (in order) with `%token`, declarations are emitted in a
+    /// fixed order, and each production is placed on its own line. Comments and whitespace in
+    /// the original source are not preserved.
+    ///
+    /// In the `Original` variant, `%actiontype` is only emitted if every rule has the same action
+    /// type. In the `Grmtools` variant, rules without an action type are given the type `()`. In
+    /// the `Bison` variant, rules' action types are emitted as `%type` declarations.
+    pub fn pp(&self, yacc_kind: YaccKind) -> String {
+        // Order token names by their position in `tokens`, falling back on alphabetical order for
+        // tokens (e.g. those which only have a precedence) which aren't in `tokens`.
+        let tok_order = |n: &&String| {
+            (
+                self.tokens.get_index_of(*n).unwrap_or(usize::MAX),
+                (*n).clone(),
+            )
+        };
+        let sorted_toks = |toks: Vec<&String>| {
+            let mut toks = toks;
+            toks.sort_by_key(tok_order);
+            toks.iter()
+                .map(|n| pp_token(n))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+
+        let mut s = String::new();
+        if let Some((ref n, _)) = self.start {
+            s.push_str(&format!("%start {}", n));
+            for (n, _) in &self.extra_starts {
+                s.push_str(&format!(" {}", n));
+            }
+            s.push('\n');
+        }
+        if let YaccKind::Original(_) = yacc_kind {
+            let mut actionts = self.rules.values().map(|r| r.actiont.as_ref());
+            if let Some(Some(t)) = actionts.next() {
+                if actionts.all(|u| u == Some(t)) {
+                    s.push_str(&format!("%actiontype {}\n", t.trim()));
+                }
+            }
+        }
+        if !self.tokens.is_empty() {
+            s.push_str(&format!(
+                "%token {}\n",
+                sorted_toks(self.tokens.iter().collect())
+            ));
+        }
+        if let Some(ref ai) = self.avoid_insert {
+            s.push_str(&format!(
+                "%avoid_insert {}\n",
+                sorted_toks(ai.iter().collect())
+            ));
+        }
+        if let YaccKind::Eco = yacc_kind {
+            if let Some(ref it) = self.implicit_tokens {
+                s.push_str(&format!(
+                    "%implicit_tokens {}\n",
+                    sorted_toks(it.iter().collect())
+                ));
+            }
+        }
+        let mut levels = BTreeMap::new();
+        for (n, prec) in &self.precs {
+            levels
+                .entry(prec.level)
+                .or_insert_with(|| (prec.kind, Vec::new()))
+                .1
+                .push(n);
+        }
+        for (kind, toks) in levels.into_values() {
+            let decl = match kind {
+                AssocKind::Left => "%left",
+                AssocKind::Right => "%right",
+                AssocKind::Nonassoc => "%nonassoc",
+                AssocKind::Precedence => "%precedence",
+            };
+            s.push_str(&format!("{} {}\n", decl, sorted_toks(toks)));
+        }
+        let mut epp = self.epp.iter().collect::<Vec<_>>();
+        epp.sort_by_key(|(n, _)| tok_order(n));
+        for (n, (_, v)) in epp {
+            s.push_str(&format!(
+                "%epp {} \"{}\"\n",
+                pp_token(n),
+                v.replace('"', "\\\"")
+            ));
+        }
+        if let YaccKind::Bison = yacc_kind {
+            // Group rules with the same action type into a single `%type` declaration.
+            let mut types: IndexMap<&str, Vec<&str>> = IndexMap::new();
+            for rule in self.rules.values() {
+                if let Some(ref t) = rule.actiont {
+                    types.entry(t.trim()).or_default().push(&rule.name.0);
+                }
+            }
+            for (t, names) in types {
+                s.push_str(&format!("%type <{}> {}\n", t, names.join(" ")));
+            }
+        }
+        if let Some(n) = self.expect {
+            s.push_str(&format!("%expect {}\n", n));
+        }
+        if let Some(n) = self.expectrr {
+            s.push_str(&format!("%expect-rr {}\n", n));
+        }
+        if self.parse_param_lifetimes.is_some() || self.parse_param_bindings.is_some() {
+            s.push_str("%parse_param");
+            if let Some(ref lifetimes) = self.parse_param_lifetimes {
+                let mut lifetimes = lifetimes.iter().map(String::as_str).collect::<Vec<_>>();
+                lifetimes.sort_unstable();
+                s.push_str(&format!(" <{}>", lifetimes.join(", ")));
+            }
+            if let Some(ref bindings) = self.parse_param_bindings {
+                let bindings = bindings
+                    .iter()
+                    .map(|(b, t)| format!("{}: {}", b, t))
+                    .collect::<Vec<_>>();
+                s.push_str(&format!("({})", bindings.join(", ")));
+            }
+            s.push('\n');
+        }
+
+        s.push_str("%%\n");
+        for (i, rule) in self.rules.values().enumerate() {
+            if i > 0 {
+                s.push('\n');
+            }
+            match yacc_kind {
+                YaccKind::Original(_) | YaccKind::Bison | YaccKind::Eco => {
+                    s.push_str(&format!("{}:\n", rule.name.0));
+                }
+                YaccKind::Grmtools => {
+                    let actiont = rule.actiont.as_ref().map_or("()", |t| t.trim());
+                    s.push_str(&format!("{} -> {}:\n", rule.name.0, actiont));
+                }
+            }
+            for (j, &pidx) in rule.pidxs.iter().enumerate() {
+                let prod = &self.prods[pidx];
+                let mut parts = prod
+                    .symbols
+                    .iter()
+                    .enumerate()
+                    .map(|(i, sym)| {
+                        let sym = match *sym {
+                            Symbol::Rule(ref n, _) => n.clone(),
+                            Symbol::Token(ref n, _) => pp_token(n),
+                        };
+                        match prod.labels.iter().find(|(j, _)| *j == i) {
+                            Some((_, l)) => format!("{}={}", l, sym),
+                            None => sym,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                if let Some((ref n, _)) = prod.precedence {
+                    parts.push(format!("%prec {}", pp_token(n)));
+                }
+                if let Some((ref a, _)) = prod.action {
+                    if a.is_empty() {
+                        parts.push("{}".to_owned());
+                    } else {
+                        parts.push(format!("{{ {} }}", a));
+                    }
+                }
+                let line = format!(
+                    "{}{}",
+                    if j == 0 { "      " } else { "    | " },
+                    parts.join(" ")
+                );
+                s.push_str(line.trim_end());
+                s.push('\n');
+            }
+            s.push_str("    ;\n");
+        }
+        if let Some(ref programs) = self.programs {
+            s.push_str("%%\n");
+            s.push_str(programs);
+            if !programs.ends_with('\n') {
+                s.push('\n');
+            }
+        }
+        s
+    }
+}
+
+/// Return the names of the rules in `rules` (each paired with the symbols of each of its
+/// productions) which can be reached from the rules `starts`.
+pub(crate) fn reachable_rules<'a>(
+    starts: impl IntoIterator<Item = &'a str>,
+    rules: &[(&'a str, Vec<&'a [Symbol]>)],
+) -> HashSet<&'a str> {
+    let prods = rules.iter().cloned().collect::<HashMap<_, _>>();
+    let mut reachable = HashSet::new();
+    let mut todo = starts.into_iter().collect::<Vec<_>>();
+    while let Some(n) = todo.pop() {
+        if !reachable.insert(n) {
+            continue;
+        }
+        for syms in prods.get(n).into_iter().flatten() {
+            for sym in syms.iter() {
+                if let Symbol::Rule(ref m, _) = *sym {
+                    todo.push(m);
+                }
+            }
+        }
+    }
+    reachable
+}
+
+/// Return the names of the rules in `rules` (each paired with the symbols of each of its
+/// productions) which can derive a finite sentence: a rule is productive if at least one of its
+/// productions only references tokens and productive rules.
+pub(crate) fn productive_rules<'a>(rules: &[(&'a str, Vec<&'a [Symbol]>)]) -> HashSet<&'a str> {
+    rules_fixpoint(rules, |syms, productive| {
+        syms.iter().all(|sym| match *sym {
+            Symbol::Rule(ref m, _) => productive.contains(m.as_str()),
+            Symbol::Token(_, _) => true,
+        })
+    })
+}
+
+/// Return the smallest set of the rules in `rules` (each paired with the symbols of each of its
+/// productions) such that a rule is in the set if any of its productions satisfies `f` (given the
+/// set computed so far). We iterate until no more rules are added.
+pub(crate) fn rules_fixpoint<'a, F>(
+    rules: &[(&'a str, Vec<&'a [Symbol]>)],
+    f: F,
+) -> HashSet<&'a str>
+where
+    F: Fn(&[Symbol], &HashSet<&'a str>) -> bool,
+{
+    let mut set = HashSet::new();
+    loop {
+        let mut changed = false;
+        for (n, prods) in rules {
+            if !set.contains(n) && prods.iter().any(|syms| f(syms, &set)) {
+                set.insert(*n);
+                changed = true;
+            }
+        }
+        if !changed {
+            return set;
+        }
+    }
+}
+
+/// Quote the token name `n` so that it can be parsed back in by `YaccParser`.
+fn pp_token(n: &str) -> String {
+    if n.contains('\'') {
+        format!("\"{}\"", n)
+    } else {
+        format!("'{}'", n)
     }
 }
 
@@ -249,25 +789,27 @@ impl GrammarAST {
 mod test {
     use super::{
         super::{AssocKind, Precedence},
-        GrammarAST, GrammarValidationError, GrammarValidationErrorKind, Symbol,
+        GrammarAST, GrammarValidationError, GrammarValidationErrorKind, Span, Symbol,
     };
 
     fn rule(n: &str) -> Symbol {
-        Symbol::Rule(n.to_string())
+        Symbol::Rule(n.to_string(), Span::new(0, 0))
     }
 
     fn token(n: &str) -> Symbol {
-        Symbol::Token(n.to_string())
+        Symbol::Token(n.to_string(), Span::new(0, 0))
     }
 
     #[test]
     fn test_empty_grammar() {
         let mut grm = GrammarAST::new();
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::NoStartRule,
-                ..
-            }) => (),
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::NoStartRule,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
@@ -275,14 +817,16 @@ mod test {
     #[test]
     fn test_invalid_start_rule() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("B".to_string(), None);
-        grm.add_prod("B".to_string(), vec![], None, None);
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::InvalidStartRule,
-                ..
-            }) => (),
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("B".to_string(), Span::new(0, 0)), None);
+        grm.add_prod("B".to_string(), vec![], None, None, Span::new(0, 0));
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::InvalidStartRule,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
@@ -290,34 +834,48 @@ mod test {
     #[test]
     fn test_valid_start_rule() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![], None, None);
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod("A".to_string(), vec![], None, None, Span::new(0, 0));
         assert!(grm.complete_and_validate().is_ok());
     }
 
     #[test]
     fn test_valid_rule_ref() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_rule("B".to_string(), None);
-        grm.add_prod("A".to_string(), vec![rule("B")], None, None);
-        grm.add_prod("B".to_string(), vec![], None, None);
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_rule(("B".to_string(), Span::new(0, 0)), None);
+        grm.add_prod(
+            "A".to_string(),
+            vec![rule("B")],
+            None,
+            None,
+            Span::new(0, 0),
+        );
+        grm.add_prod("B".to_string(), vec![], None, None, Span::new(0, 0));
         assert!(grm.complete_and_validate().is_ok());
     }
 
     #[test]
     fn test_invalid_rule_ref() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![rule("B")], None, None);
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::UnknownRuleRef,
-                ..
-            }) => (),
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod(
+            "A".to_string(),
+            vec![rule("B")],
+            None,
+            None,
+            Span::new(0, 0),
+        );
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownRuleRef,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
@@ -326,9 +884,15 @@ mod test {
     fn test_valid_token_ref() {
         let mut grm = GrammarAST::new();
         grm.tokens.insert("b".to_string());
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![token("b")], None, None);
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod(
+            "A".to_string(),
+            vec![token("b")],
+            None,
+            None,
+            Span::new(0, 0),
+        );
         assert!(grm.complete_and_validate().is_ok());
     }
 
@@ -338,23 +902,37 @@ mod test {
         // to redefine rules as tokens by adding them to '%token'
         let mut grm = GrammarAST::new();
         grm.tokens.insert("b".to_string());
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![rule("b")], None, None);
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod(
+            "A".to_string(),
+            vec![rule("b")],
+            None,
+            None,
+            Span::new(0, 0),
+        );
         assert!(grm.complete_and_validate().is_err());
     }
 
     #[test]
     fn test_invalid_token_ref() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![token("b")], None, None);
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::UnknownToken,
-                ..
-            }) => (),
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod(
+            "A".to_string(),
+            vec![token("b")],
+            None,
+            None,
+            Span::new(0, 0),
+        );
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownToken,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
@@ -362,14 +940,26 @@ mod test {
     #[test]
     fn test_invalid_rule_forgotten_token() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![rule("b"), token("b")], None, None);
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::UnknownRuleRef,
-                ..
-            }) => (),
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod(
+            "A".to_string(),
+            vec![rule("b"), token("b")],
+            None,
+            None,
+            Span::new(0, 0),
+        );
+        // Both the unknown rule and the unknown token are reported.
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownRuleRef,
+                    ..
+                }, GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownToken,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
@@ -377,15 +967,18 @@ mod test {
     #[test]
     fn test_invalid_epp() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
-        grm.add_prod("A".to_string(), vec![], None, None);
-        grm.epp.insert("k".to_owned(), "v".to_owned());
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::UnknownEPP,
-                ..
-            }) => (),
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
+        grm.add_prod("A".to_string(), vec![], None, None, Span::new(0, 0));
+        grm.epp
+            .insert("k".to_owned(), (Span::new(0, 0), "v".to_owned()));
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownEPP,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
@@ -400,14 +993,15 @@ mod test {
                 kind: AssocKind::Left,
             },
         );
-        grm.start = Some("A".to_string());
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
         grm.tokens.insert("b".to_string());
-        grm.add_rule("A".to_string(), None);
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
         grm.add_prod(
             "A".to_string(),
             vec![token("b")],
-            Some("b".to_string()),
+            Some(("b".to_string(), Span::new(0, 0))),
             None,
+            Span::new(0, 0),
         );
         assert!(grm.complete_and_validate().is_ok());
     }
@@ -415,27 +1009,35 @@ mod test {
     #[test]
     fn test_invalid_precedence_override() {
         let mut grm = GrammarAST::new();
-        grm.start = Some("A".to_string());
-        grm.add_rule("A".to_string(), None);
+        grm.start = Some(("A".to_string(), Span::new(0, 0)));
+        grm.add_rule(("A".to_string(), Span::new(0, 0)), None);
         grm.add_prod(
             "A".to_string(),
             vec![token("b")],
-            Some("b".to_string()),
+            Some(("b".to_string(), Span::new(0, 0))),
             None,
+            Span::new(0, 0),
         );
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::UnknownToken,
-                ..
-            }) => (),
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownToken,
+                    ..
+                }, GrammarValidationError {
+                    kind: GrammarValidationErrorKind::UnknownToken,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
         grm.tokens.insert("b".to_string());
-        match grm.complete_and_validate() {
-            Err(GrammarValidationError {
-                kind: GrammarValidationErrorKind::NoPrecForToken,
-                ..
-            }) => (),
+        match grm.complete_and_validate().as_ref().map_err(Vec::as_slice) {
+            Err(
+                [GrammarValidationError {
+                    kind: GrammarValidationErrorKind::NoPrecForToken,
+                    ..
+                }],
+            ) => (),
             _ => panic!("Validation error"),
         }
     }
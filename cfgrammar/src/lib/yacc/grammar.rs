@@ -1,18 +1,18 @@
-use std::{cell::RefCell, collections::HashMap, error::Error, fmt};
+use std::{any::type_name, borrow::Cow, cell::RefCell, collections::HashMap, error::Error, fmt};
 
 use num_traits::{self, AsPrimitive, PrimInt, Unsigned};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use vob::Vob;
 
 use super::{
-    ast::{self, GrammarValidationError},
+    ast::{self, GrammarValidationError, YaccGrammarWarning},
     firsts::YaccFirsts,
     follows::YaccFollows,
     parser::{YaccParser, YaccParserError},
+    source::YaccSource,
     YaccKind,
 };
-use crate::{PIdx, RIdx, SIdx, Symbol, TIdx};
+use crate::{MaybeStatic, PIdx, RIdx, SIdx, Span, Symbol, TIdx};
 
 const START_RULE: &str = "^";
 const IMPLICIT_RULE: &str = "~";
@@ -32,67 +32,92 @@ pub enum AssocKind {
     Left,
     Right,
     Nonassoc,
+    /// A precedence without associativity (Bison's `%precedence`): conflicts between tokens and
+    /// productions at the same precedence level are not resolved.
+    Precedence,
 }
 
 /// Representation of a `YaccGrammar`. See the [top-level documentation](../../index.html) for the
 /// guarantees this struct makes about rules, tokens, productions, and symbols.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct YaccGrammar<StorageT = u32> {
+pub struct YaccGrammar<StorageT: 'static = u32> {
     /// How many rules does this grammar have?
     rules_len: RIdx<StorageT>,
     /// A mapping from `RIdx` -> `String`.
-    rule_names: Vec<String>,
+    rule_names: MaybeStatic<Cow<'static, str>>,
+    /// A mapping from `RIdx` -> `Option<Span>`: the location of each rule's name in the grammar
+    /// source. Rules inserted by cfgrammar (e.g. the start rule) have no location.
+    rule_name_spans: MaybeStatic<Option<Span>>,
     /// A mapping from `TIdx` -> `Option<String>`. Every user-specified token will have a name,
     /// but tokens inserted by cfgrammar (e.g. the EOF token) won't.
-    token_names: Vec<Option<String>>,
+    token_names: MaybeStatic<Option<Cow<'static, str>>>,
     /// A mapping from `TIdx` -> `Option<Precedence>`
-    token_precs: Vec<Option<Precedence>>,
+    token_precs: MaybeStatic<Option<Precedence>>,
     /// A mapping from `TIdx` -> `Option<String>` for the %epp declaration, giving pretty-printed
     /// versions of token names that can be presented to the user in case of an error. Every
     /// user-specified token will have a name that can be presented to the user (if a token doesn't
     /// have an %epp entry, the token name will be used in lieu), but tokens inserted by cfgrammar
     /// (e.g. the EOF token) won't.
-    token_epp: Vec<Option<String>>,
+    token_epp: MaybeStatic<Option<Cow<'static, str>>>,
     /// How many tokens does this grammar have?
     tokens_len: TIdx<StorageT>,
     /// The offset of the EOF token.
     eof_token_idx: TIdx<StorageT>,
     /// How many productions does this grammar have?
     prods_len: PIdx<StorageT>,
-    /// Which production is the sole production of the start rule?
-    start_prod: PIdx<StorageT>,
+    /// The productions of the start rule: one per rule in the `%start` declaration, in the order
+    /// they were declared.
+    start_prods: MaybeStatic<PIdx<StorageT>>,
     /// A list of all productions.
-    prods: Vec<Vec<Symbol<StorageT>>>,
+    prods: MaybeStatic<MaybeStatic<Symbol<StorageT>>>,
     /// A mapping from rules to their productions. Note that 1) the order of rules is identical to
     /// that of `rule_names` 2) every rule will have at least 1 production 3) productions
     /// are not necessarily stored sequentially.
-    rules_prods: Vec<Vec<PIdx<StorageT>>>,
+    rules_prods: MaybeStatic<MaybeStatic<PIdx<StorageT>>>,
     /// A mapping from productions to their corresponding rule indexes.
-    prods_rules: Vec<RIdx<StorageT>>,
+    prods_rules: MaybeStatic<RIdx<StorageT>>,
     /// The precedence of each production.
-    prod_precs: Vec<Option<Precedence>>,
+    prod_precs: MaybeStatic<Option<Precedence>>,
+    /// The name of the token (either the `%prec` token or the last token) each production's
+    /// precedence comes from.
+    prod_prec_names: MaybeStatic<Option<Cow<'static, str>>>,
+    /// The names given a precedence by `%left`, `%right`, `%nonassoc` or `%precedence` and the
+    /// location of their first appearance in the grammar source, in order of appearance.
+    prec_names: MaybeStatic<(Cow<'static, str>, Option<Span>)>,
+    /// The location of each production in the grammar source. Productions inserted by cfgrammar
+    /// (e.g. the start rule's production) have no location.
+    prod_spans: MaybeStatic<Option<Span>>,
+    /// The labels (e.g. `lhs` in `lhs=Expr`) attached to each production's symbols.
+    prod_labels: MaybeStatic<MaybeStatic<(SIdx<StorageT>, Cow<'static, str>)>>,
     /// The index of the rule added for implicit tokens, if they were specified; otherwise
     /// `None`.
     implicit_rule: Option<RIdx<StorageT>>,
     /// User defined Rust programs which can be called within actions
-    actions: Vec<Option<String>>,
+    actions: MaybeStatic<Option<Cow<'static, str>>>,
     /// Extra Parameters to the parse function, for user actions.
-    param_args: Vec<(String, String)>,
+    param_args: MaybeStatic<(Cow<'static, str>, Cow<'static, str>)>,
     /// Lifetimes for `param_args`
-    param_lifetimes: Vec<String>,
+    param_lifetimes: MaybeStatic<Cow<'static, str>>,
     /// The programs section of a grammar, if specified; otherwise `None`.
-    programs: Option<String>,
+    programs: Option<Cow<'static, str>>,
     /// The actiontypes of rules (one per rule).
-    actiontypes: Vec<Option<String>>,
+    actiontypes: MaybeStatic<Option<Cow<'static, str>>>,
     /// Tokens marked as %avoid_insert (if any).
-    avoid_insert: Option<Vob>,
+    avoid_insert: Option<MaybeStatic<bool>>,
+    /// How many shift/reduce conflicts the grammar author expected (if any).
+    expect: Option<usize>,
+    /// How many reduce/reduce conflicts the grammar author expected (if any).
+    expectrr: Option<usize>,
+    /// Warnings about the grammar (e.g. unused tokens).
+    warnings: Vec<YaccGrammarWarning>,
 }
 
-// Internally, we assume that a grammar's start rule has a single production. Since we manually
-// create the start rule ourselves (without relying on user input), this is a safe assumption.
+// Internally, we assume that each of the start rule's productions references a single rule (one
+// of the rules in the `%start` declaration). Since we manually create the start rule ourselves
+// (without relying on user input), this is a safe assumption.
 
 impl YaccGrammar<u32> {
-    pub fn new(yacc_kind: YaccKind, s: &str) -> Result<Self, YaccGrammarError> {
+    pub fn new(yacc_kind: YaccKind, s: &str) -> Result<Self, Vec<YaccGrammarError>> {
         YaccGrammar::new_with_storaget(yacc_kind, s)
     }
 }
@@ -102,23 +127,86 @@ where
     usize: AsPrimitive<StorageT>,
 {
     /// Takes as input a Yacc grammar of [`YaccKind`](enum.YaccKind.html) as a `String` `s` and returns a
-    /// [`YaccGrammar`](grammar/struct.YaccGrammar.html) (or
-    /// ([`YaccGrammarError`](grammar/enum.YaccGrammarError.html) on error).
+    /// [`YaccGrammar`](grammar/struct.YaccGrammar.html) (or, on error, every
+    /// [`YaccGrammarError`](grammar/enum.YaccGrammarError.html) found: syntax errors first, in
+    /// the order they were found, then validation errors).
     ///
     /// As we're compiling the `YaccGrammar`, we add a new start rule (which we'll refer to as `^`,
     /// though the actual name is a fresh name that is guaranteed to be unique) that references the
-    /// user defined start rule.
-    pub fn new_with_storaget(yacc_kind: YaccKind, s: &str) -> Result<Self, YaccGrammarError> {
+    /// user defined start rule. If the `%start` declaration lists several rules, `^` has one
+    /// production per rule, each of which is a separate entry point into the grammar.
+    pub fn new_with_storaget(yacc_kind: YaccKind, s: &str) -> Result<Self, Vec<YaccGrammarError>> {
         let ast = match yacc_kind {
-            YaccKind::Original(_) | YaccKind::Grmtools | YaccKind::Eco => {
-                let mut yp = YaccParser::new(yacc_kind, s.to_string());
-                yp.parse()?;
+            YaccKind::Original(_) | YaccKind::Grmtools | YaccKind::Bison | YaccKind::Eco => {
+                let mut yp = YaccParser::new(yacc_kind, s.to_string(), type_name::<StorageT>());
+                let mut errs = match yp.parse() {
+                    Ok(_) => Vec::new(),
+                    Err(errs) => errs.into_iter().map(YaccGrammarError::from).collect(),
+                };
+                // Even if the grammar couldn't be parsed, we validate what we could parse, so
+                // that the user sees as many errors as possible at once. Rules which couldn't be
+                // parsed are missing from the AST, so references to them aren't reported again.
+                let unparsed = yp.unparsed_rules().to_owned();
                 let mut ast = yp.ast();
-                ast.complete_and_validate()?;
+                if let Err(verrs) = ast.complete_and_validate() {
+                    errs.extend(
+                        verrs
+                            .into_iter()
+                            .filter(|e| match (&e.kind, &e.sym) {
+                                (
+                                    ast::GrammarValidationErrorKind::UnknownRuleRef
+                                    | ast::GrammarValidationErrorKind::InvalidStartRule,
+                                    Some(ast::Symbol::Rule(n, _)),
+                                ) => !unparsed.contains(n),
+                                _ => true,
+                            })
+                            .map(|mut e| {
+                                e.set_src(s);
+                                YaccGrammarError::from(e)
+                            }),
+                    );
+                }
+                if !errs.is_empty() {
+                    return Err(errs);
+                }
                 ast
             }
         };
+        Ok(YaccGrammar::from_validated_ast(yacc_kind, ast))
+    }
+
+    /// As [`new_with_storaget`](#method.new_with_storaget), but reading the grammar from
+    /// `source` (see the [`source`](../source/index.html) module), which may be split across
+    /// several files. The locations in errors are relative to the file they occurred in.
+    pub fn from_source(
+        yacc_kind: YaccKind,
+        source: &YaccSource,
+    ) -> Result<Self, Vec<YaccGrammarError>> {
+        YaccGrammar::new_with_storaget(yacc_kind, source.src()).map_err(|mut errs| {
+            for e in &mut errs {
+                e.set_source(source);
+            }
+            errs
+        })
+    }
+
+    /// Build a grammar of [`YaccKind`](enum.YaccKind.html) `yacc_kind` from `ast` (e.g. a grammar
+    /// rewritten by the [`transform`](../transform/index.html) module), returning every
+    /// validation error found if the AST is not a valid grammar.
+    pub fn from_ast(
+        yacc_kind: YaccKind,
+        mut ast: ast::GrammarAST,
+    ) -> Result<Self, Vec<YaccGrammarError>> {
+        ast.complete_and_validate().map_err(|errs| {
+            errs.into_iter()
+                .map(YaccGrammarError::from)
+                .collect::<Vec<_>>()
+        })?;
+        Ok(YaccGrammar::from_validated_ast(yacc_kind, ast))
+    }
 
+    /// Build a grammar from `ast`, which must have been validated.
+    fn from_validated_ast(yacc_kind: YaccKind, ast: ast::GrammarAST) -> Self {
         // Check that StorageT is big enough to hold RIdx/PIdx/SIdx/TIdx values; after these
         // checks we can guarantee that things like RIdx(ast.rules.len().as_()) are safe.
         if ast.rules.len() > num_traits::cast(StorageT::max_value()).unwrap() {
@@ -151,7 +239,7 @@ where
         let implicit_rule;
         let implicit_start_rule;
         match yacc_kind {
-            YaccKind::Original(_) | YaccKind::Grmtools => {
+            YaccKind::Original(_) | YaccKind::Grmtools | YaccKind::Bison => {
                 implicit_rule = None;
                 implicit_start_rule = None;
             }
@@ -192,7 +280,7 @@ where
         for k in &ast.tokens {
             token_names.push(Some(k.clone()));
             token_precs.push(ast.precs.get(k).cloned());
-            token_epp.push(Some(ast.epp.get(k).unwrap_or(k).clone()));
+            token_epp.push(Some(ast.epp.get(k).map(|(_, s)| s).unwrap_or(k).clone()));
         }
         let eof_token_idx = TIdx(token_names.len().as_());
         token_names.push(None);
@@ -210,19 +298,22 @@ where
         // of the list of productions.
         let mut prods = vec![None; ast.prods.len()];
         let mut prod_precs: Vec<Option<Option<Precedence>>> = vec![None; ast.prods.len()];
+        let mut prod_prec_names = vec![None; ast.prods.len()];
+        let mut prod_spans = vec![None; ast.prods.len()];
+        let mut prod_labels = vec![Vec::new(); ast.prods.len()];
         let mut prods_rules = vec![None; ast.prods.len()];
         let mut actions = vec![None; ast.prods.len()];
         let mut actiontypes = vec![None; rule_names.len()];
         for astrulename in &rule_names {
             let ridx = rule_map[astrulename];
             if astrulename == &start_rule {
-                // Add the special start rule which has a single production which references a
-                // single rule.
+                // Add the special start rule which has one production per entry point, each of
+                // which references a single rule.
                 rules_prods[usize::from(ridx)].push(PIdx(prods.len().as_()));
                 let start_prod = match implicit_start_rule {
                     None => {
                         // Add ^: S;
-                        vec![Symbol::Rule(rule_map[ast.start.as_ref().unwrap()])]
+                        vec![Symbol::Rule(rule_map[&ast.start.as_ref().unwrap().0])]
                     }
                     Some(ref s) => {
                         // An implicit rule has been specified, so the special start rule
@@ -233,8 +324,22 @@ where
                 };
                 prods.push(Some(start_prod));
                 prod_precs.push(Some(None));
+                prod_prec_names.push(None);
+                prod_spans.push(None);
+                prod_labels.push(Vec::new());
                 prods_rules.push(Some(ridx));
                 actions.push(None);
+                // Add ^: T; for each additional entry point T.
+                for (n, _) in &ast.extra_starts {
+                    rules_prods[usize::from(ridx)].push(PIdx(prods.len().as_()));
+                    prods.push(Some(vec![Symbol::Rule(rule_map[n])]));
+                    prod_precs.push(Some(None));
+                    prod_prec_names.push(None);
+                    prod_spans.push(None);
+                    prod_labels.push(Vec::new());
+                    prods_rules.push(Some(ridx));
+                    actions.push(None);
+                }
                 continue;
             } else if implicit_start_rule
                 .as_ref()
@@ -246,9 +351,12 @@ where
                 rules_prods[usize::from(rule_map[astrulename])].push(PIdx(prods.len().as_()));
                 prods.push(Some(vec![
                     Symbol::Rule(rule_map[implicit_rule.as_ref().unwrap()]),
-                    Symbol::Rule(rule_map[ast.start.as_ref().unwrap()]),
+                    Symbol::Rule(rule_map[&ast.start.as_ref().unwrap().0]),
                 ]));
                 prod_precs.push(Some(None));
+                prod_prec_names.push(None);
+                prod_spans.push(None);
+                prod_labels.push(Vec::new());
                 prods_rules.push(Some(ridx));
                 continue;
             } else if implicit_rule.as_ref().map_or(false, |s| s == astrulename) {
@@ -259,12 +367,18 @@ where
                     implicit_prods.push(PIdx(prods.len().as_()));
                     prods.push(Some(vec![Symbol::Token(token_map[t]), Symbol::Rule(ridx)]));
                     prod_precs.push(Some(None));
+                    prod_prec_names.push(None);
+                    prod_spans.push(None);
+                    prod_labels.push(Vec::new());
                     prods_rules.push(Some(ridx));
                 }
                 // Add an empty production
                 implicit_prods.push(PIdx(prods.len().as_()));
                 prods.push(Some(vec![]));
                 prod_precs.push(Some(None));
+                prod_prec_names.push(None);
+                prod_spans.push(None);
+                prod_labels.push(Vec::new());
                 prods_rules.push(Some(ridx));
                 continue;
             } else {
@@ -275,12 +389,18 @@ where
             for &pidx in &ast.rules[astrulename].pidxs {
                 let astprod = &ast.prods[pidx];
                 let mut prod = Vec::with_capacity(astprod.symbols.len());
-                for astsym in &astprod.symbols {
+                let mut labels = Vec::with_capacity(astprod.labels.len());
+                for (i, astsym) in astprod.symbols.iter().enumerate() {
+                    // Implicit rule references may have been inserted before this symbol, so
+                    // its index in `prod` may not be the same as in `astprod.symbols`.
+                    if let Some((_, l)) = astprod.labels.iter().find(|(j, _)| *j == i) {
+                        labels.push((SIdx(prod.len().as_()), l.clone()));
+                    }
                     match *astsym {
-                        ast::Symbol::Rule(ref n) => {
+                        ast::Symbol::Rule(ref n, _) => {
                             prod.push(Symbol::Rule(rule_map[n]));
                         }
-                        ast::Symbol::Token(ref n) => {
+                        ast::Symbol::Token(ref n, _) => {
                             prod.push(Symbol::Token(token_map[n]));
                             if implicit_rule.is_some() {
                                 prod.push(Symbol::Rule(rule_map[&implicit_rule.clone().unwrap()]));
@@ -289,13 +409,16 @@ where
                     };
                 }
                 let mut prec = None;
-                if let Some(ref n) = astprod.precedence {
+                let mut prec_name = None;
+                if let Some((ref n, _)) = astprod.precedence {
                     prec = Some(ast.precs[n]);
+                    prec_name = Some(n.clone());
                 } else {
                     for astsym in astprod.symbols.iter().rev() {
-                        if let ast::Symbol::Token(ref n) = *astsym {
+                        if let ast::Symbol::Token(ref n, _) = *astsym {
                             if let Some(p) = ast.precs.get(n) {
                                 prec = Some(*p);
+                                prec_name = Some(n.clone());
                             }
                             break;
                         }
@@ -304,52 +427,123 @@ where
                 (*rule).push(PIdx(pidx.as_()));
                 prods[pidx] = Some(prod);
                 prod_precs[pidx] = Some(prec);
+                prod_prec_names[pidx] = prec_name;
+                prod_spans[pidx] = Some(astprod.prod_span);
+                prod_labels[pidx] = labels;
                 prods_rules[pidx] = Some(ridx);
-                if let Some(ref s) = astprod.action {
+                if let Some((ref s, _)) = astprod.action {
                     actions[pidx] = Some(s.clone());
                 }
             }
         }
 
-        let avoid_insert = if let Some(ai) = ast.avoid_insert {
-            let mut aiv = Vob::from_elem(token_names.len(), false);
+        let warnings = ast.warnings();
+        let mut prec_names = ast
+            .precs
+            .keys()
+            .map(|n| (n.clone(), ast.token_spans.get(n).cloned()))
+            .collect::<Vec<_>>();
+        prec_names.sort_by_key(|(n, span)| (span.map(|s| s.start()), n.clone()));
+        let rule_name_spans: Vec<_> = rule_names
+            .iter()
+            .map(|n| ast.rules.get(n).map(|r| r.name.1))
+            .collect();
+
+        let avoid_insert = ast.avoid_insert.map(|ai| {
+            let mut aiv = vec![false; token_names.len()];
             for n in ai.iter() {
-                aiv.set(usize::from(token_map[n]), true);
+                aiv[usize::from(token_map[n])] = true;
             }
-            Some(aiv)
-        } else {
-            None
-        };
+            aiv.into()
+        });
 
         assert!(!token_names.is_empty());
         assert!(!rule_names.is_empty());
-        Ok(YaccGrammar {
+        YaccGrammar {
             rules_len: RIdx(rule_names.len().as_()),
-            rule_names,
+            rule_names: rule_names
+                .into_iter()
+                .map(Cow::Owned)
+                .collect::<Vec<_>>()
+                .into(),
+            rule_name_spans: rule_name_spans.into(),
             tokens_len: TIdx(token_names.len().as_()),
             eof_token_idx,
-            token_names,
-            token_precs,
-            token_epp,
+            token_names: owned_names(token_names),
+            token_precs: token_precs.into(),
+            token_epp: owned_names(token_epp),
             prods_len: PIdx(prods.len().as_()),
-            start_prod: rules_prods[usize::from(rule_map[&start_rule])][0],
-            rules_prods,
-            prods_rules: prods_rules.into_iter().map(Option::unwrap).collect(),
-            prods: prods.into_iter().map(Option::unwrap).collect(),
-            prod_precs: prod_precs.into_iter().map(Option::unwrap).collect(),
+            start_prods: rules_prods[usize::from(rule_map[&start_rule])]
+                .clone()
+                .into(),
+            rules_prods: rules_prods
+                .into_iter()
+                .map(MaybeStatic::from)
+                .collect::<Vec<_>>()
+                .into(),
+            prods_rules: prods_rules
+                .into_iter()
+                .map(Option::unwrap)
+                .collect::<Vec<_>>()
+                .into(),
+            prods: prods
+                .into_iter()
+                .map(|x| MaybeStatic::from(x.unwrap()))
+                .collect::<Vec<_>>()
+                .into(),
+            prod_precs: prod_precs
+                .into_iter()
+                .map(Option::unwrap)
+                .collect::<Vec<_>>()
+                .into(),
+            prod_prec_names: owned_names(prod_prec_names),
+            prec_names: prec_names
+                .into_iter()
+                .map(|(n, span)| (Cow::Owned(n), span))
+                .collect::<Vec<_>>()
+                .into(),
+            prod_spans: prod_spans.into(),
+            prod_labels: prod_labels
+                .into_iter()
+                .map(|labels| {
+                    labels
+                        .into_iter()
+                        .map(|(sidx, l)| (sidx, Cow::Owned(l)))
+                        .collect::<Vec<_>>()
+                        .into()
+                })
+                .collect::<Vec<_>>()
+                .into(),
             implicit_rule: implicit_rule.map(|x| rule_map[&x]),
-            actions,
-            param_args: ast.parse_param_bindings.iter().flatten().cloned().collect(),
+            actions: owned_names(actions),
+            param_args: ast
+                .parse_param_bindings
+                .into_iter()
+                .flatten()
+                .map(|(n, t)| (Cow::Owned(n), Cow::Owned(t)))
+                .collect::<Vec<_>>()
+                .into(),
             param_lifetimes: ast
                 .parse_param_lifetimes
-                .iter()
+                .into_iter()
                 .flatten()
-                .cloned()
-                .collect(),
-            programs: ast.programs,
+                .map(Cow::Owned)
+                .collect::<Vec<_>>()
+                .into(),
+            programs: ast.programs.map(Cow::Owned),
             avoid_insert,
-            actiontypes,
-        })
+            actiontypes: owned_names(actiontypes),
+            expect: ast.expect,
+            expectrr: ast.expectrr,
+            warnings,
+        }
+    }
+
+    /// Return the warnings for this grammar (e.g. unreachable rules or unused tokens), ordered by
+    /// their position in the grammar source. Unlike errors, warnings don't prevent the grammar
+    /// from being used.
+    pub fn warnings(&self) -> &[YaccGrammarWarning] {
+        &self.warnings
     }
 
     /// How many productions does this grammar have?
@@ -389,10 +583,45 @@ where
         self.prod_precs[usize::from(pidx)]
     }
 
-    /// Return the production index of the start rule's sole production (for Yacc grammars the
-    /// start rule is defined to have precisely one production).
+    /// Return the name of the token (either the `%prec` token or the last token) that production
+    /// `pidx`'s precedence comes from (where `None` indicates "no precedence specified"). Panics if
+    /// `pidx` doesn't exist.
+    pub fn prod_precedence_name(&self, pidx: PIdx<StorageT>) -> Option<&str> {
+        self.prod_prec_names[usize::from(pidx)].as_deref()
+    }
+
+    /// Return the names given a precedence by `%left`, `%right`, `%nonassoc` or `%precedence`
+    /// and the location of their first appearance in the grammar source (if known), in order of
+    /// appearance.
+    pub fn precedence_names(&self) -> &[(Cow<'static, str>, Option<Span>)] {
+        &self.prec_names
+    }
+
+    /// Return the location of production `pidx` in the grammar source (where `None` indicates a
+    /// production inserted by cfgrammar). Panics if `pidx` doesn't exist.
+    pub fn prod_span(&self, pidx: PIdx<StorageT>) -> Option<Span> {
+        self.prod_spans[usize::from(pidx)]
+    }
+
+    /// Return the labels (e.g. `lhs` in `lhs=Expr`) attached to production `pidx`'s symbols, as
+    /// pairs of a symbol index and a label, ordered by symbol index. Panics if `pidx` doesn't
+    /// exist.
+    pub fn prod_labels(&self, pidx: PIdx<StorageT>) -> &[(SIdx<StorageT>, Cow<'static, str>)] {
+        &self.prod_labels[usize::from(pidx)]
+    }
+
+    /// Return the production index of the start rule's first production, which references the
+    /// first rule in the `%start` declaration (for most Yacc grammars this is the start rule's
+    /// sole production).
     pub fn start_prod(&self) -> PIdx<StorageT> {
-        self.start_prod
+        self.start_prods[0]
+    }
+
+    /// Return the production indexes of the start rule's productions: one per rule in the
+    /// `%start` declaration, in the order they were declared. Each is a separate entry point into
+    /// the grammar.
+    pub fn start_prods(&self) -> &[PIdx<StorageT>] {
+        &self.start_prods
     }
 
     /// How many rules does this grammar have?
@@ -419,6 +648,12 @@ where
         &self.rule_names[usize::from(ridx)]
     }
 
+    /// Return the location of rule `ridx`'s name in the grammar source (where `None` indicates a
+    /// rule inserted by cfgrammar). Panics if `ridx` doesn't exist.
+    pub fn rule_name_span(&self, ridx: RIdx<StorageT>) -> Option<Span> {
+        self.rule_name_spans[usize::from(ridx)]
+    }
+
     /// Return the `RIdx` of the implict rule if it exists, or `None` otherwise.
     pub fn implicit_rule(&self) -> Option<RIdx<StorageT>> {
         self.implicit_rule
@@ -437,7 +672,7 @@ where
     /// What is the index of the start rule? Note that cfgrammar will have inserted at least one
     /// rule "above" the user's start rule.
     pub fn start_rule_idx(&self) -> RIdx<StorageT> {
-        self.prod_to_rule(self.start_prod)
+        self.prod_to_rule(self.start_prod())
     }
 
     /// How many tokens does this grammar have?
@@ -478,25 +713,25 @@ where
     }
 
     /// Get the action for production `pidx`. Panics if `pidx` doesn't exist.
-    pub fn action(&self, pidx: PIdx<StorageT>) -> &Option<String> {
-        &self.actions[usize::from(pidx)]
+    pub fn action(&self, pidx: PIdx<StorageT>) -> Option<&str> {
+        self.actions[usize::from(pidx)].as_deref()
     }
 
-    pub fn actiontype(&self, ridx: RIdx<StorageT>) -> &Option<String> {
-        &self.actiontypes[usize::from(ridx)]
+    pub fn actiontype(&self, ridx: RIdx<StorageT>) -> Option<&str> {
+        self.actiontypes[usize::from(ridx)].as_deref()
     }
 
-    pub fn param_args(&self) -> &Vec<(String, String)> {
+    pub fn param_args(&self) -> &[(Cow<'static, str>, Cow<'static, str>)] {
         &self.param_args
     }
 
-    pub fn param_lifetimes(&self) -> &Vec<String> {
+    pub fn param_lifetimes(&self) -> &[Cow<'static, str>] {
         &self.param_lifetimes
     }
 
     /// Get the programs part of the grammar
-    pub fn programs(&self) -> &Option<String> {
-        &self.programs
+    pub fn programs(&self) -> Option<&str> {
+        self.programs.as_deref()
     }
 
     /// Returns a map from names to `TIdx`s of all tokens that a lexer will need to generate valid
@@ -524,12 +759,24 @@ where
     /// Is the token `tidx` marked as `%avoid_insert`?
     pub fn avoid_insert(&self, tidx: TIdx<StorageT>) -> bool {
         if let Some(ai) = &self.avoid_insert {
-            ai.get(usize::from(tidx)).unwrap()
+            ai[usize::from(tidx)]
         } else {
             false
         }
     }
 
+    /// How many shift/reduce conflicts were expected (via `%expect`)? Returns `None` if no
+    /// `%expect` declaration was made.
+    pub fn expect(&self) -> Option<usize> {
+        self.expect
+    }
+
+    /// How many reduce/reduce conflicts were expected (via `%expect-rr`)? Returns `None` if no
+    /// `%expect-rr` declaration was made.
+    pub fn expectrr(&self) -> Option<usize> {
+        self.expectrr
+    }
+
     /// Is there a path from the `from` rule to the `to` rule? Note that recursive rules
     /// return `true` for a path from themselves to themselves.
     pub fn has_path(&self, from: RIdx<StorageT>, to: RIdx<StorageT>) -> bool {
@@ -582,6 +829,135 @@ where
         sprod
     }
 
+    /// Pretty-print this grammar as Yacc source in the `yacc_kind` variant (see
+    /// [`GrammarAST::pp`](../ast/struct.GrammarAST.html#method.pp) for details of the output).
+    /// Parsing the output with the same `yacc_kind` produces an equivalent grammar, though
+    /// production indices may differ if a rule's productions were not contiguous in the original
+    /// source. Panics if this grammar has implicit tokens (i.e. it is an Eco grammar).
+    pub fn pp(&self, yacc_kind: YaccKind) -> String {
+        self.ast().pp(yacc_kind)
+    }
+
+    /// Convert this grammar back into a `GrammarAST` (e.g. so that it can be rewritten by the
+    /// [`transform`](../transform/index.html) module). The rules inserted by cfgrammar (e.g. the
+    /// start rule) are omitted, and each user rule's productions are carried across with their
+    /// actions, precedences, and labels. Panics if this grammar has implicit tokens (i.e. it is an
+    /// Eco grammar).
+    pub fn ast(&self) -> ast::GrammarAST {
+        if self.implicit_rule.is_some() {
+            panic!("Grammars with implicit tokens cannot be converted back into an AST.");
+        }
+        let mut ast = ast::GrammarAST::new();
+        for (i, &pidx) in self.start_prods.iter().enumerate() {
+            let start_ridx = match self.prod(pidx)[0] {
+                Symbol::Rule(ridx) => ridx,
+                Symbol::Token(_) => unreachable!(),
+            };
+            let start = (
+                self.rule_name(start_ridx).to_owned(),
+                self.rule_name_span(start_ridx)
+                    .unwrap_or_else(|| Span::new(0, 0)),
+            );
+            if i == 0 {
+                ast.start = Some(start);
+            } else {
+                ast.extra_starts.push(start);
+            }
+        }
+        for tidx in self.iter_tidxs() {
+            let n = match self.token_name(tidx) {
+                Some(n) => n,
+                None => continue,
+            };
+            ast.tokens.insert(n.to_owned());
+            if let Some(prec) = self.token_precedence(tidx) {
+                ast.precs.insert(n.to_owned(), prec);
+            }
+            if let Some(epp) = self.token_epp(tidx) {
+                if epp != n {
+                    ast.epp
+                        .insert(n.to_owned(), (Span::new(0, 0), epp.to_owned()));
+                }
+            }
+        }
+        if self.avoid_insert.is_some() {
+            ast.avoid_insert = Some(
+                self.iter_tidxs()
+                    .filter(|tidx| self.avoid_insert(*tidx))
+                    .map(|tidx| self.token_name(tidx).unwrap().to_owned())
+                    .collect(),
+            );
+        }
+        if !self.param_lifetimes.is_empty() {
+            ast.parse_param_lifetimes =
+                Some(self.param_lifetimes.iter().map(|l| l.to_string()).collect());
+        }
+        if !self.param_args.is_empty() {
+            ast.parse_param_bindings = Some(
+                self.param_args
+                    .iter()
+                    .map(|(n, t)| (n.to_string(), t.to_string()))
+                    .collect(),
+            );
+        }
+        ast.programs = self.programs().map(str::to_owned);
+        ast.expect = self.expect;
+        ast.expectrr = self.expectrr;
+
+        let start_rule = self.start_rule_idx();
+        for ridx in self.iter_rules().filter(|ridx| *ridx != start_rule) {
+            let name = self.rule_name(ridx).to_owned();
+            let name_span = self.rule_name_span(ridx).unwrap_or_else(|| Span::new(0, 0));
+            ast.add_rule(
+                (name.clone(), name_span),
+                self.actiontype(ridx).map(str::to_owned),
+            );
+            for &pidx in self.rule_to_prods(ridx) {
+                let symbols = self
+                    .prod(pidx)
+                    .iter()
+                    .map(|sym| match *sym {
+                        Symbol::Rule(ridx) => {
+                            ast::Symbol::Rule(self.rule_name(ridx).to_owned(), Span::new(0, 0))
+                        }
+                        Symbol::Token(tidx) => ast::Symbol::Token(
+                            self.token_name(tidx).unwrap().to_owned(),
+                            Span::new(0, 0),
+                        ),
+                    })
+                    .collect::<Vec<_>>();
+                // A production's precedence defaults to that of its last token: if that differs
+                // from its actual precedence, then the original must have used `%prec`, which we
+                // recreate with any token with the same precedence.
+                let default_prec = self.prod(pidx).iter().rev().find_map(|sym| match *sym {
+                    Symbol::Token(tidx) => Some(self.token_precedence(tidx)),
+                    Symbol::Rule(_) => None,
+                });
+                let prec = self.prod_precedence(pidx);
+                let precedence = match prec {
+                    Some(_) if default_prec.flatten() != prec => self
+                        .iter_tidxs()
+                        .find(|tidx| self.token_precedence(*tidx) == prec)
+                        .map(|tidx| (self.token_name(tidx).unwrap().to_owned(), Span::new(0, 0))),
+                    _ => None,
+                };
+                ast.add_prod(
+                    name.clone(),
+                    symbols,
+                    precedence,
+                    self.action(pidx).map(|a| (a.to_owned(), Span::new(0, 0))),
+                    self.prod_span(pidx).unwrap_or_else(|| Span::new(0, 0)),
+                );
+                ast.prods.last_mut().unwrap().labels = self
+                    .prod_labels(pidx)
+                    .iter()
+                    .map(|(sidx, l)| (usize::from(*sidx), l.to_string()))
+                    .collect();
+            }
+        }
+        ast
+    }
+
     /// Return a `SentenceGenerator` which can then generate minimal sentences for any rule
     /// based on the user-defined `token_cost` function which gives the associated cost for
     /// generating each token (where the cost must be greater than 0). Note that multiple
@@ -602,6 +978,238 @@ where
     pub fn follows(&self) -> YaccFollows<StorageT> {
         YaccFollows::new(self)
     }
+
+    /// Return Rust source code for an expression which evaluates (in a `const` context) to a
+    /// grammar equivalent to this one, borrowing all of its data from `static` arrays. The
+    /// expression's `StorageT` is inferred from the context it is used in. The grammar's
+    /// warnings are not included.
+    #[doc(hidden)]
+    pub fn _to_static(&self) -> String {
+        fn list<T>(xs: &[T], f: impl Fn(&T) -> String) -> String {
+            format!("&[{}]", xs.iter().map(f).collect::<Vec<_>>().join(", "))
+        }
+        fn name(n: &Option<Cow<str>>) -> String {
+            match n {
+                Some(n) => format!("Some(Cow::Borrowed({:?}))", n),
+                None => "None".to_owned(),
+            }
+        }
+        fn span(span: &Option<Span>) -> String {
+            match span {
+                Some(span) => format!("Some(Span::_from_static({}, {}))", span.start(), span.end()),
+                None => "None".to_owned(),
+            }
+        }
+        fn prec(prec: &Option<Precedence>) -> String {
+            match prec {
+                Some(p) => format!(
+                    "Some(Precedence {{ level: {}, kind: AssocKind::{:?} }})",
+                    p.level, p.kind
+                ),
+                None => "None".to_owned(),
+            }
+        }
+        let symbol = |sym: &Symbol<StorageT>| match sym {
+            Symbol::Rule(ridx) => format!("Symbol::Rule(RIdx({}))", usize::from(*ridx)),
+            Symbol::Token(tidx) => format!("Symbol::Token(TIdx({}))", usize::from(*tidx)),
+        };
+        let pidx = |pidx: &PIdx<StorageT>| format!("PIdx({})", usize::from(*pidx));
+        let opt = |x: Option<String>| match x {
+            Some(x) => format!("Some({})", x),
+            None => "None".to_owned(),
+        };
+        let fields = [
+            (
+                "rules_len",
+                format!("RIdx({})", usize::from(self.rules_len)),
+            ),
+            (
+                "rule_names",
+                list(&self.rule_names, |n| format!("Cow::Borrowed({:?})", n)),
+            ),
+            ("rule_name_spans", list(&self.rule_name_spans, span)),
+            ("token_names", list(&self.token_names, name)),
+            ("token_precs", list(&self.token_precs, prec)),
+            ("token_epp", list(&self.token_epp, name)),
+            (
+                "tokens_len",
+                format!("TIdx({})", usize::from(self.tokens_len)),
+            ),
+            (
+                "eof_token_idx",
+                format!("TIdx({})", usize::from(self.eof_token_idx)),
+            ),
+            (
+                "prods_len",
+                format!("PIdx({})", usize::from(self.prods_len)),
+            ),
+            ("start_prods", list(&self.start_prods, pidx)),
+            (
+                "prods",
+                list(&self.prods, |p| {
+                    format!("MaybeStatic::Static({})", list(p, symbol))
+                }),
+            ),
+            (
+                "rules_prods",
+                list(&self.rules_prods, |r| {
+                    format!("MaybeStatic::Static({})", list(r, pidx))
+                }),
+            ),
+            (
+                "prods_rules",
+                list(&self.prods_rules, |ridx| {
+                    format!("RIdx({})", usize::from(*ridx))
+                }),
+            ),
+            ("prod_precs", list(&self.prod_precs, prec)),
+            ("prod_prec_names", list(&self.prod_prec_names, name)),
+            (
+                "prec_names",
+                list(&self.prec_names, |(n, s)| {
+                    format!("(Cow::Borrowed({:?}), {})", n, span(s))
+                }),
+            ),
+            ("prod_spans", list(&self.prod_spans, span)),
+            (
+                "prod_labels",
+                list(&self.prod_labels, |ls| {
+                    let ls = list(ls, |(sidx, l)| {
+                        format!("(SIdx({}), Cow::Borrowed({:?}))", usize::from(*sidx), l)
+                    });
+                    format!("MaybeStatic::Static({})", ls)
+                }),
+            ),
+            (
+                "implicit_rule",
+                opt(self
+                    .implicit_rule
+                    .map(|ridx| format!("RIdx({})", usize::from(ridx)))),
+            ),
+            ("actions", list(&self.actions, name)),
+            (
+                "param_args",
+                list(&self.param_args, |(n, t)| {
+                    format!("(Cow::Borrowed({:?}), Cow::Borrowed({:?}))", n, t)
+                }),
+            ),
+            (
+                "param_lifetimes",
+                list(&self.param_lifetimes, |l| format!("Cow::Borrowed({:?})", l)),
+            ),
+            (
+                "programs",
+                opt(self.programs.as_ref().map(|p| format!("{:?}", p))),
+            ),
+            ("actiontypes", list(&self.actiontypes, name)),
+            (
+                "avoid_insert",
+                opt(self
+                    .avoid_insert
+                    .as_ref()
+                    .map(|ai| list(ai, bool::to_string))),
+            ),
+            ("expect", format!("{:?}", self.expect)),
+            ("expectrr", format!("{:?}", self.expectrr)),
+        ];
+        format!(
+            "{{
+    #[allow(unused_imports)]
+    use ::std::{{borrow::Cow, option::Option::{{None, Some}}}};
+    #[allow(unused_imports)]
+    use ::cfgrammar::{{
+        yacc::{{grammar::_StaticYaccGrammar, AssocKind, Precedence, YaccGrammar}},
+        MaybeStatic, PIdx, RIdx, SIdx, Span, Symbol, TIdx,
+    }};
+    YaccGrammar::_from_static(_StaticYaccGrammar {{
+{}
+    }})
+}}",
+            fields
+                .iter()
+                .map(|(f, v)| format!("        {}: {},", f, v))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+}
+
+impl<StorageT: Copy> YaccGrammar<StorageT> {
+    /// Create a grammar which borrows all of its data from `parts` (see
+    /// [`_to_static`](#method._to_static)).
+    #[doc(hidden)]
+    pub const fn _from_static(parts: _StaticYaccGrammar<StorageT>) -> Self {
+        YaccGrammar {
+            rules_len: parts.rules_len,
+            rule_names: MaybeStatic::Static(parts.rule_names),
+            rule_name_spans: MaybeStatic::Static(parts.rule_name_spans),
+            token_names: MaybeStatic::Static(parts.token_names),
+            token_precs: MaybeStatic::Static(parts.token_precs),
+            token_epp: MaybeStatic::Static(parts.token_epp),
+            tokens_len: parts.tokens_len,
+            eof_token_idx: parts.eof_token_idx,
+            prods_len: parts.prods_len,
+            start_prods: MaybeStatic::Static(parts.start_prods),
+            prods: MaybeStatic::Static(parts.prods),
+            rules_prods: MaybeStatic::Static(parts.rules_prods),
+            prods_rules: MaybeStatic::Static(parts.prods_rules),
+            prod_precs: MaybeStatic::Static(parts.prod_precs),
+            prod_prec_names: MaybeStatic::Static(parts.prod_prec_names),
+            prec_names: MaybeStatic::Static(parts.prec_names),
+            prod_spans: MaybeStatic::Static(parts.prod_spans),
+            prod_labels: MaybeStatic::Static(parts.prod_labels),
+            implicit_rule: parts.implicit_rule,
+            actions: MaybeStatic::Static(parts.actions),
+            param_args: MaybeStatic::Static(parts.param_args),
+            param_lifetimes: MaybeStatic::Static(parts.param_lifetimes),
+            programs: match parts.programs {
+                Some(p) => Some(Cow::Borrowed(p)),
+                None => None,
+            },
+            actiontypes: MaybeStatic::Static(parts.actiontypes),
+            avoid_insert: match parts.avoid_insert {
+                Some(ai) => Some(MaybeStatic::Static(ai)),
+                None => None,
+            },
+            expect: parts.expect,
+            expectrr: parts.expectrr,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// The `static` data of a grammar created by
+/// [`YaccGrammar::_from_static`](struct.YaccGrammar.html#method._from_static). Each field is as
+/// the field of the same name in `YaccGrammar`.
+#[doc(hidden)]
+pub struct _StaticYaccGrammar<StorageT: 'static> {
+    pub rules_len: RIdx<StorageT>,
+    pub rule_names: &'static [Cow<'static, str>],
+    pub rule_name_spans: &'static [Option<Span>],
+    pub token_names: &'static [Option<Cow<'static, str>>],
+    pub token_precs: &'static [Option<Precedence>],
+    pub token_epp: &'static [Option<Cow<'static, str>>],
+    pub tokens_len: TIdx<StorageT>,
+    pub eof_token_idx: TIdx<StorageT>,
+    pub prods_len: PIdx<StorageT>,
+    pub start_prods: &'static [PIdx<StorageT>],
+    pub prods: &'static [MaybeStatic<Symbol<StorageT>>],
+    pub rules_prods: &'static [MaybeStatic<PIdx<StorageT>>],
+    pub prods_rules: &'static [RIdx<StorageT>],
+    pub prod_precs: &'static [Option<Precedence>],
+    pub prod_prec_names: &'static [Option<Cow<'static, str>>],
+    pub prec_names: &'static [(Cow<'static, str>, Option<Span>)],
+    pub prod_spans: &'static [Option<Span>],
+    pub prod_labels: &'static [MaybeStatic<(SIdx<StorageT>, Cow<'static, str>)>],
+    pub implicit_rule: Option<RIdx<StorageT>>,
+    pub actions: &'static [Option<Cow<'static, str>>],
+    pub param_args: &'static [(Cow<'static, str>, Cow<'static, str>)],
+    pub param_lifetimes: &'static [Cow<'static, str>],
+    pub programs: Option<&'static str>,
+    pub actiontypes: &'static [Option<Cow<'static, str>>],
+    pub avoid_insert: Option<&'static [bool]>,
+    pub expect: Option<usize>,
+    pub expectrr: Option<usize>,
 }
 
 /// A `SentenceGenerator` can generate minimal sentences for any given rule. e.g. for the
@@ -624,7 +1232,7 @@ where
 /// C: [x]
 /// D: [y, x] or [y, z]
 /// ```
-pub struct SentenceGenerator<'a, StorageT> {
+pub struct SentenceGenerator<'a, StorageT: 'static> {
     grm: &'a YaccGrammar<StorageT>,
     rule_min_costs: RefCell<Option<Vec<u16>>>,
     rule_max_costs: RefCell<Option<Vec<u16>>>,
@@ -705,8 +1313,11 @@ where
             for (sidx, sym) in prod.iter().enumerate().skip(sym_idx) {
                 match sym {
                     Symbol::Rule(s_ridx) => {
+                        // Generate the rule's sentence before continuing with the rest of this
+                        // production.
                         st.push((pidx, sidx + 1));
                         st.push((cheapest_prod(*s_ridx), 0));
+                        break;
                     }
                     Symbol::Token(s_tidx) => {
                         s.push(*s_tidx);
@@ -1013,6 +1624,15 @@ impl From<GrammarValidationError> for YaccGrammarError {
     }
 }
 
+impl YaccGrammarError {
+    fn set_source(&mut self, source: &YaccSource) {
+        match self {
+            YaccGrammarError::YaccParserError(e) => e.set_source(source),
+            YaccGrammarError::GrammarValidationError(e) => e.set_source(source),
+        }
+    }
+}
+
 impl fmt::Display for YaccGrammarError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -1022,15 +1642,66 @@ impl fmt::Display for YaccGrammarError {
     }
 }
 
+/// Convert the names in `v` into the form stored by a `YaccGrammar`.
+fn owned_names(v: Vec<Option<String>>) -> MaybeStatic<Option<Cow<'static, str>>> {
+    v.into_iter()
+        .map(|x| x.map(Cow::Owned))
+        .collect::<Vec<_>>()
+        .into()
+}
+
 #[cfg(test)]
 mod test {
     use super::{
-        super::{AssocKind, Precedence, YaccGrammar, YaccKind, YaccOriginalActionKind},
-        rule_max_costs, rule_min_costs, IMPLICIT_RULE, IMPLICIT_START_RULE,
+        super::{
+            AssocKind, Precedence, YaccGrammar, YaccGrammarWarningKind, YaccKind,
+            YaccOriginalActionKind,
+        },
+        _StaticYaccGrammar, rule_max_costs, rule_min_costs, IMPLICIT_RULE, IMPLICIT_START_RULE,
     };
-    use crate::{PIdx, RIdx, Symbol, TIdx};
+    use crate::{MaybeStatic, PIdx, RIdx, SIdx, Span, Symbol, TIdx};
     use std::collections::HashMap;
 
+    #[test]
+    fn test_multiple_starts() {
+        let grm = YaccGrammar::new(
+            YaccKind::Grmtools,
+            "%start R S %token T %% R -> (): S { }; S -> (): 'T' { }; U -> (): 'T' { };",
+        )
+        .unwrap();
+        let start_rule = grm.rule_idx("^").unwrap();
+        assert_eq!(grm.start_rule_idx(), start_rule);
+        assert_eq!(grm.start_prods(), grm.rule_to_prods(start_rule));
+        assert_eq!(grm.start_prods().len(), 2);
+        assert_eq!(grm.start_prod(), grm.start_prods()[0]);
+        assert_eq!(
+            *grm.prod(grm.start_prods()[0]),
+            [Symbol::Rule(grm.rule_idx("R").unwrap())]
+        );
+        assert_eq!(
+            *grm.prod(grm.start_prods()[1]),
+            [Symbol::Rule(grm.rule_idx("S").unwrap())]
+        );
+        // Only rules unreachable from every start rule are reported.
+        assert_eq!(grm.warnings().len(), 1);
+        assert_eq!(grm.warnings()[0].name, "U");
+        let ast = grm.ast();
+        assert_eq!(ast.start.as_ref().unwrap().0, "R");
+        assert_eq!(ast.extra_starts[0].0, "S");
+        assert!(ast.pp(YaccKind::Grmtools).starts_with("%start R S\n"));
+
+        match YaccGrammar::new(
+            YaccKind::Grmtools,
+            "%start R X %token T %% R -> (): 'T' { };",
+        ) {
+            Ok(_) => panic!(),
+            Err(errs) => assert_eq!(
+                errs[0].to_string(),
+                "Start rule 'X' does not appear in grammar at line 1 column 10"
+            ),
+        }
+    }
+
     #[test]
     fn test_minimal() {
         let grm = YaccGrammar::new(
@@ -1039,7 +1710,7 @@ mod test {
         )
         .unwrap();
 
-        assert_eq!(grm.start_prod, PIdx(1));
+        assert_eq!(grm.start_prod(), PIdx(1));
         assert_eq!(grm.implicit_rule(), None);
         grm.rule_idx("^").unwrap();
         grm.rule_idx("R").unwrap();
@@ -1379,6 +2050,22 @@ mod test {
         find("D", vec![vec!["y", "x"], vec!["y", "z"]]);
     }
 
+    #[test]
+    fn test_min_sentence_order() {
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "%start S %% S: A 'x' A; A: 'a' | B 'b'; B: 'c' 'd';",
+        )
+        .unwrap();
+        let sg = grm.sentence_generator(|_| 1);
+        let s = sg
+            .min_sentence(grm.rule_idx("S").unwrap())
+            .iter()
+            .map(|tidx| grm.token_name(*tidx).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(s, vec!["a", "x", "a"]);
+    }
+
     #[test]
     #[rustfmt::skip]
     fn test_rule_max_costs1() {
@@ -1456,4 +2143,384 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn test_spans() {
+        let src = "%start S\n%%\nS: A 'c'\n | ;\nA: 'a';";
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap();
+        let s_ridx = grm.rule_idx("S").unwrap();
+        assert_eq!(grm.rule_name_span(s_ridx), Some(Span::new(12, 13)));
+        assert_eq!(grm.rule_name_span(grm.start_rule_idx()), None);
+        let s_prods = grm.rule_to_prods(s_ridx);
+        assert_eq!(grm.prod_span(s_prods[0]), Some(Span::new(15, 20)));
+        assert_eq!(grm.prod_span(s_prods[1]), Some(Span::new(24, 24)));
+        assert_eq!(grm.prod_span(grm.start_prod()), None);
+        let span = grm.prod_span(s_prods[0]).unwrap();
+        assert_eq!(&src[span.start()..span.end()], "A 'c'");
+        assert_eq!(span.line_col(src), (3, 4));
+    }
+
+    #[test]
+    fn test_validation_error_line_col() {
+        match YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "%%\nS: 'a'\n | B;",
+        ) {
+            Err(errs) => {
+                assert_eq!(errs.len(), 1);
+                assert_eq!(
+                    errs[0].to_string(),
+                    "Unknown reference to rule 'B' at line 3 column 4"
+                );
+            }
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_all_errors() {
+        match YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "%%\nA: B ) ;\nB: 'b' | C;\nD: E;",
+        ) {
+            Err(errs) => assert_eq!(
+                errs.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+                vec![
+                    "Illegal string at line 2 column 6",
+                    "Unknown reference to rule 'C' at line 3 column 10",
+                    "Unknown reference to rule 'E' at line 4 column 4"
+                ]
+            ),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_unparsed_rule_errors() {
+        // `B`'s header can't be parsed, so the references to it (including as the start rule)
+        // mustn't be reported as unknown, though the reference to `D` still is.
+        match YaccGrammar::new(
+            YaccKind::Grmtools,
+            "%start B\n%%\nA -> (): B 'a' { } | D { };\nB: 'b' { };\nC -> (): B { };",
+        ) {
+            Err(errs) => assert_eq!(
+                errs.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+                vec![
+                    "Missing '->' at line 4 column 2",
+                    "Unknown reference to rule 'D' at line 3 column 22"
+                ]
+            ),
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_warnings() {
+        let src = "%start A
+%token a x '-'
+%left '+' '-'
+%%
+A: 'a' | B '+';
+B: 'b' B;
+C: 'c';
+D: 'd' %prec '-';
+";
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap();
+        assert_eq!(
+            grm.warnings()
+                .iter()
+                .map(|w| w.pp_with_src(src))
+                .collect::<Vec<_>>(),
+            vec![
+                "Token 'x' is not used in any production at line 2 column 10",
+                "Rule 'B' can never match any input at line 6 column 1",
+                "Rule 'C' is unreachable from the start rule at line 7 column 1",
+                "Rule 'D' is unreachable from the start rule at line 8 column 1",
+            ]
+        );
+        assert_eq!(
+            grm.warnings()[1].kind,
+            YaccGrammarWarningKind::UnproductiveRule
+        );
+    }
+
+    #[test]
+    fn test_pp() {
+        let src = r#"
+%start Expr
+%avoid_insert "INT"
+%epp INT "an \"integer\""
+%expect 1
+%parse_param <'b, 'a>(x: &'a str, y: (u8, &'b str))
+%left '+' '-'
+%left '*'
+%right '^'
+%%
+Expr -> Result<u64, ()>: Expr '+' Expr { Ok($1? + $3?) }
+    | Expr '-' Expr { Ok($1? - $3?) } | Expr '*' Expr { Ok($1? * $3?) }
+    | '-' Expr %prec '*' { Ok(0 - $2?) }
+    | Expr '^' Expr { Ok($1?.pow($3? as u32)) }
+    | Factor { $1 };
+Factor -> Result<u64, ()>:
+    'INT' { parse_int($1) } | { Ok(0) };
+%%
+fn parse_int() {}
+"#;
+        let grm = YaccGrammar::new(YaccKind::Grmtools, src).unwrap();
+        let pp = grm.pp(YaccKind::Grmtools);
+        assert_eq!(
+            pp,
+            r#"%start Expr
+%token 'INT' '+' '-' '*' '^'
+%avoid_insert 'INT'
+%left '+' '-'
+%left '*'
+%right '^'
+%epp 'INT' "an \"integer\""
+%expect 1
+%parse_param <'a, 'b>(x: &'a str, y: (u8, &'b str))
+%%
+Expr -> Result<u64, ()>:
+      Expr '+' Expr { Ok($1? + $3?) }
+    | Expr '-' Expr { Ok($1? - $3?) }
+    | Expr '*' Expr { Ok($1? * $3?) }
+    | '-' Expr %prec '*' { Ok(0 - $2?) }
+    | Expr '^' Expr { Ok($1?.pow($3? as u32)) }
+    | Factor { $1 }
+    ;
+
+Factor -> Result<u64, ()>:
+      'INT' { parse_int($1) }
+    | { Ok(0) }
+    ;
+%%
+fn parse_int() {}
+"#
+        );
+        let grm2 = YaccGrammar::new(YaccKind::Grmtools, &pp).unwrap();
+        assert_eq!(grm2.pp(YaccKind::Grmtools), pp);
+        for pidx in grm.iter_pidxs() {
+            assert_eq!(grm.prod(pidx), grm2.prod(pidx));
+            assert_eq!(grm.prod_precedence(pidx), grm2.prod_precedence(pidx));
+            assert_eq!(grm.action(pidx), grm2.action(pidx));
+        }
+        let tidx = grm2.token_idx("INT").unwrap();
+        assert_eq!(grm2.token_epp(tidx), Some("an \"integer\""));
+        assert!(grm2.avoid_insert(tidx));
+        assert_eq!(grm2.param_lifetimes().len(), 2);
+        assert_eq!(grm2.expect(), Some(1));
+    }
+
+    #[test]
+    fn test_pp_original() {
+        let src = "
+%actiontype u8
+%token a
+%%
+A: a B;
+B: \"'\" | ;
+A: ;
+";
+        let yk = YaccKind::Original(YaccOriginalActionKind::UserAction);
+        let grm = YaccGrammar::new(yk, src).unwrap();
+        let pp = grm.pp(yk);
+        assert_eq!(
+            pp,
+            "%start A
+%actiontype u8
+%token 'a' \"'\"
+%%
+A:
+      'a' B
+    |
+    ;
+
+B:
+      \"'\"
+    |
+    ;
+"
+        );
+        let grm2 = YaccGrammar::new(yk, &pp).unwrap();
+        assert_eq!(grm2.pp(yk), pp);
+        assert_eq!(grm2.actiontype(grm2.rule_idx("B").unwrap()), Some("u8"));
+    }
+
+    #[test]
+    fn test_pp_bison() {
+        let src = "
+%union { num: u64; }
+%token <num> INT \"integer\" MINUS \"-\"
+%precedence \"-\"
+%type <num> A B
+%%
+A: A \"-\" B { $1 - $3 } | B { $1 };
+B: INT { 0 } | C { 1 };
+C: %empty { () };
+";
+        let grm = YaccGrammar::new(YaccKind::Bison, src).unwrap();
+        let pp = grm.pp(YaccKind::Bison);
+        assert_eq!(
+            pp,
+            "%start A
+%token 'INT' 'MINUS'
+%precedence 'MINUS'
+%epp 'INT' \"integer\"
+%epp 'MINUS' \"-\"
+%type <u64> A B
+%type <()> C
+%%
+A:
+      A 'MINUS' B { $1 - $3 }
+    | B { $1 }
+    ;
+
+B:
+      'INT' { 0 }
+    | C { 1 }
+    ;
+
+C:
+      { () }
+    ;
+"
+        );
+        let grm2 = YaccGrammar::new(YaccKind::Bison, &pp).unwrap();
+        assert_eq!(grm2.pp(YaccKind::Bison), pp);
+    }
+
+    #[test]
+    fn test_prod_labels() {
+        let src = "%%\nA -> u64: lhs=A '+' rhs=B { 0 } | B { 1 };\nB -> u64: 'b' { 2 };";
+        let grm = YaccGrammar::new(YaccKind::Grmtools, src).unwrap();
+        let pidxs = grm.rule_to_prods(grm.rule_idx("A").unwrap());
+        assert_eq!(
+            grm.prod_labels(pidxs[0]),
+            &[(SIdx(0), "lhs".into()), (SIdx(2), "rhs".into())]
+        );
+        assert!(grm.prod_labels(pidxs[1]).is_empty());
+        let pp = grm.pp(YaccKind::Grmtools);
+        assert!(pp.contains("      lhs=A '+' rhs=B { 0 }\n"));
+        let grm2 = YaccGrammar::new(YaccKind::Grmtools, &pp).unwrap();
+        assert_eq!(grm2.prod_labels(pidxs[0]), grm.prod_labels(pidxs[0]));
+
+        // Implicit tokens shift the indices of labelled symbols.
+        let src = "%implicit_tokens ws\n%%\nA: 'a' x=B;\nB: ;";
+        let grm = YaccGrammar::new(YaccKind::Eco, src).unwrap();
+        let pidx = grm.rule_to_prods(grm.rule_idx("A").unwrap())[0];
+        assert_eq!(grm.prod_labels(pidx), &[(SIdx(2), "x".into())]);
+    }
+
+    /// Return a grammar equivalent to `grm` which borrows its data from (leaked) `static`
+    /// arrays, as those generated by `_to_static` do.
+    fn static_copy(grm: &YaccGrammar) -> YaccGrammar {
+        fn leak<T: Clone>(xs: &[T]) -> &'static [T] {
+            Box::leak(xs.to_vec().into_boxed_slice())
+        }
+        fn leak_nested<T: Clone>(xs: &[MaybeStatic<T>]) -> &'static [MaybeStatic<T>] {
+            leak(
+                &xs.iter()
+                    .map(|x| MaybeStatic::Static(leak(x)))
+                    .collect::<Vec<_>>(),
+            )
+        }
+        YaccGrammar::_from_static(_StaticYaccGrammar {
+            rules_len: grm.rules_len,
+            rule_names: leak(&grm.rule_names),
+            rule_name_spans: leak(&grm.rule_name_spans),
+            token_names: leak(&grm.token_names),
+            token_precs: leak(&grm.token_precs),
+            token_epp: leak(&grm.token_epp),
+            tokens_len: grm.tokens_len,
+            eof_token_idx: grm.eof_token_idx,
+            prods_len: grm.prods_len,
+            start_prods: leak(&grm.start_prods),
+            prods: leak_nested(&grm.prods),
+            rules_prods: leak_nested(&grm.rules_prods),
+            prods_rules: leak(&grm.prods_rules),
+            prod_precs: leak(&grm.prod_precs),
+            prod_prec_names: leak(&grm.prod_prec_names),
+            prec_names: leak(&grm.prec_names),
+            prod_spans: leak(&grm.prod_spans),
+            prod_labels: leak_nested(&grm.prod_labels),
+            implicit_rule: grm.implicit_rule,
+            actions: leak(&grm.actions),
+            param_args: leak(&grm.param_args),
+            param_lifetimes: leak(&grm.param_lifetimes),
+            programs: grm
+                .programs()
+                .map(|p| &*Box::leak(p.to_owned().into_boxed_str())),
+            actiontypes: leak(&grm.actiontypes),
+            avoid_insert: grm.avoid_insert.as_ref().map(|ai| leak(ai)),
+            expect: grm.expect,
+            expectrr: grm.expectrr,
+        })
+    }
+
+    #[test]
+    fn test_from_static() {
+        let src = r#"
+%start Expr
+%avoid_insert "INT"
+%epp INT "an \"integer\""
+%expect 1
+%parse_param <'a>(x: &'a str)
+%left '+'
+%right '^'
+%%
+Expr -> Result<u64, ()>: lhs=Expr '+' Expr { Ok($lhs? + $3?) }
+    | Expr '^' Expr %prec '+' { Ok($1?.pow($3? as u32)) }
+    | 'INT' { parse_int($1) };
+%%
+fn parse_int() {}
+"#;
+        let grm = YaccGrammar::new(YaccKind::Grmtools, src).unwrap();
+        let sgrm = static_copy(&grm);
+        assert_eq!(sgrm.pp(YaccKind::Grmtools), grm.pp(YaccKind::Grmtools));
+        assert!(sgrm.warnings().is_empty());
+        assert_eq!(sgrm.start_prods(), grm.start_prods());
+        assert_eq!(sgrm.eof_token_idx(), grm.eof_token_idx());
+        assert_eq!(sgrm.implicit_rule(), grm.implicit_rule());
+        assert_eq!(sgrm.precedence_names(), grm.precedence_names());
+        assert_eq!(sgrm.param_args(), grm.param_args());
+        assert_eq!(sgrm.programs(), grm.programs());
+        assert_eq!(sgrm.expect(), Some(1));
+        for ridx in grm.iter_rules() {
+            assert_eq!(sgrm.rule_name(ridx), grm.rule_name(ridx));
+            assert_eq!(sgrm.rule_name_span(ridx), grm.rule_name_span(ridx));
+            assert_eq!(sgrm.rule_to_prods(ridx), grm.rule_to_prods(ridx));
+            assert_eq!(sgrm.actiontype(ridx), grm.actiontype(ridx));
+        }
+        for tidx in grm.iter_tidxs() {
+            assert_eq!(sgrm.token_name(tidx), grm.token_name(tidx));
+            assert_eq!(sgrm.token_epp(tidx), grm.token_epp(tidx));
+            assert_eq!(sgrm.token_precedence(tidx), grm.token_precedence(tidx));
+            assert_eq!(sgrm.avoid_insert(tidx), grm.avoid_insert(tidx));
+        }
+        for pidx in grm.iter_pidxs() {
+            assert_eq!(sgrm.prod(pidx), grm.prod(pidx));
+            assert_eq!(sgrm.prod_to_rule(pidx), grm.prod_to_rule(pidx));
+            assert_eq!(sgrm.prod_precedence(pidx), grm.prod_precedence(pidx));
+            assert_eq!(
+                sgrm.prod_precedence_name(pidx),
+                grm.prod_precedence_name(pidx)
+            );
+            assert_eq!(sgrm.prod_span(pidx), grm.prod_span(pidx));
+            assert_eq!(sgrm.prod_labels(pidx), grm.prod_labels(pidx));
+            assert_eq!(sgrm.action(pidx), grm.action(pidx));
+        }
+
+        let s = grm._to_static();
+        assert!(s.contains(r#"programs: Some("fn parse_int() {}\n"),"#));
+        assert!(s.contains(r#"token_epp: &[Some(Cow::Borrowed("an \"integer\"")), "#));
+        assert!(s.contains("avoid_insert: Some(&[true, false, false, false]),"));
+    }
 }
@@ -3,11 +3,18 @@ pub mod firsts;
 pub mod follows;
 pub mod grammar;
 pub mod parser;
+pub mod railroad;
+pub mod source;
+pub mod transform;
 
 pub use self::{
-    ast::{GrammarValidationError, GrammarValidationErrorKind},
+    ast::{
+        GrammarValidationError, GrammarValidationErrorKind, YaccGrammarWarning,
+        YaccGrammarWarningKind,
+    },
     grammar::{AssocKind, Precedence, SentenceGenerator, YaccGrammar, YaccGrammarError},
     parser::{YaccParserError, YaccParserErrorKind},
+    source::{YaccSource, YaccSourceError},
 };
 
 /// The particular Yacc variant this grammar makes use of.
@@ -19,6 +26,11 @@ pub enum YaccKind {
     /// Similar to the original Yacc style, but allowing individual rules' actions to have their
     /// own return type.
     Grmtools,
+    /// Bison-style grammars: rules' action types are taken from `%type <T>` declarations (where
+    /// `T` is either a `%union` field or a Rust type), token aliases (`%token NAME "alias"`)
+    /// become the tokens' `%epp` strings, and `%code` blocks are added to the programs section.
+    /// Actions must still be written in Rust, as in `Grmtools` grammars.
+    Bison,
     /// The variant used in the [Eco language composition editor](http://soft-dev.org/src/eco/)
     Eco,
 }
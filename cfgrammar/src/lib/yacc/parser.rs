@@ -1,6 +1,11 @@
 // Note: this is the parser for both YaccKind::Original(YaccOriginalActionKind::GenericParseTree) and YaccKind::Eco yacc kinds.
 
-use std::{collections::HashSet, error::Error, fmt};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, mem,
+    path::PathBuf,
+};
 
 use lazy_static::lazy_static;
 use regex::Regex;
@@ -9,8 +14,10 @@ type YaccResult<T> = Result<T, YaccParserError>;
 
 use super::{
     ast::{GrammarAST, Symbol},
+    source::{pp_location, YaccSource},
     AssocKind, Precedence, YaccKind,
 };
+use crate::Span;
 
 /// The various different possible Yacc parser errors.
 #[derive(Debug)]
@@ -34,8 +41,24 @@ pub enum YaccParserErrorKind {
     DuplicateStartDeclaration,
     DuplicateActiontypeDeclaration,
     DuplicateEPP,
+    DuplicateExpectDeclaration,
+    DuplicateExpectRRDeclaration,
+    IllegalInteger,
+    MismatchedParen,
+    MismatchedGroupTypes,
     ReachedEOL,
     InvalidString,
+    MissingBrace,
+    MissingTypeTag,
+    DuplicateUnionDeclaration,
+    InvalidUnionField,
+    UnknownTypeTag,
+    DuplicateLabel,
+    DuplicateTemplateParam,
+    MissingRightAngle,
+    UnknownTemplate,
+    TemplateArgCount,
+    TemplateTooDeep,
 }
 
 /// Any error from the Yacc parser returns an instance of this struct.
@@ -44,6 +67,9 @@ pub struct YaccParserError {
     pub kind: YaccParserErrorKind,
     line: usize,
     col: usize,
+    // The included file this error occurred in (see `YaccSource`), or `None` if it occurred in
+    // the grammar file itself.
+    file: Option<PathBuf>,
 }
 
 impl Error for YaccParserError {}
@@ -76,214 +102,548 @@ impl fmt::Display for YaccParserError {
                 "Duplicate %actiontype declaration"
             }
             YaccParserErrorKind::DuplicateEPP => "Duplicate %epp declaration for this token",
+            YaccParserErrorKind::DuplicateExpectDeclaration => "Duplicate %expect declaration",
+            YaccParserErrorKind::DuplicateExpectRRDeclaration => "Duplicate %expect-rr declaration",
+            YaccParserErrorKind::IllegalInteger => "Illegal integer",
+            YaccParserErrorKind::MismatchedParen => "Mismatched parenthesis",
+            YaccParserErrorKind::MismatchedGroupTypes => {
+                "Alternatives in a group must all have the same type"
+            }
             YaccParserErrorKind::ReachedEOL => {
                 "Reached end of line without finding expected content"
             }
             YaccParserErrorKind::InvalidString => "Invalid string",
+            YaccParserErrorKind::MissingBrace => "Missing '{'",
+            YaccParserErrorKind::MissingTypeTag => "Missing '<type>' tag",
+            YaccParserErrorKind::DuplicateUnionDeclaration => "Duplicate %union declaration",
+            YaccParserErrorKind::InvalidUnionField => {
+                "%union fields must be of the form 'name: type'"
+            }
+            YaccParserErrorKind::UnknownTypeTag => "Type tag is not a field of the %union",
+            YaccParserErrorKind::DuplicateLabel => "Duplicate label in production",
+            YaccParserErrorKind::DuplicateTemplateParam => "Duplicate rule template parameter",
+            YaccParserErrorKind::MissingRightAngle => "Missing '>'",
+            YaccParserErrorKind::UnknownTemplate => "Unknown rule template",
+            YaccParserErrorKind::TemplateArgCount => "Wrong number of arguments to rule template",
+            YaccParserErrorKind::TemplateTooDeep => {
+                "Rule template instantiations nested too deeply"
+            }
         };
-        write!(f, "{} at line {} column {}", s, self.line, self.col)
+        write!(
+            f,
+            "{} {}",
+            s,
+            pp_location((self.file.as_deref(), self.line), self.col)
+        )
+    }
+}
+
+impl YaccParserError {
+    /// Map this error's location in `source.src()` back to the file it came from.
+    pub(crate) fn set_source(&mut self, source: &YaccSource) {
+        let (file, line) = source.origin(self.line);
+        self.file = file.map(|p| p.to_owned());
+        self.line = line;
     }
 }
 
 pub(crate) struct YaccParser {
     yacc_kind: YaccKind,
     src: String,
+    // The name of the `StorageT` type which the grammar will be instantiated with: this is needed
+    // to give a type to tokens referenced by EBNF operators.
+    storaget: String,
     newlines: Vec<usize>,
     ast: GrammarAST,
     global_actiontype: Option<String>,
+    // The rules created by desugaring EBNF operators, in the order they were created, along with
+    // the offset in `src` of the EBNF expression. Since the types of rules referenced by an EBNF
+    // expression aren't necessarily known until all rules have been parsed, these rules' action
+    // types are filled in by `ebnf_actiontypes`.
+    ebnf_rules: Vec<(String, EBNFKind, usize)>,
+    // The errors found so far. After an error, the parser resynchronises at the next declaration
+    // or rule, so that as many errors as possible can be reported at once.
+    errs: Vec<YaccParserError>,
+    // The names of rules which couldn't be parsed, and which were thus never added to the AST.
+    unparsed_rules: Vec<String>,
+    // Declarations in `YaccKind::Bison` grammars which can only be resolved once the whole
+    // grammar has been parsed.
+    bison: BisonDecls,
+    // Rule templates (`YaccKind::Grmtools` only), keyed by name.
+    templates: HashMap<String, Template>,
+    // A map from a template instantiation (the template's name, and whether each argument is a
+    // token, along with its name) to the name of the rule it was instantiated as.
+    instance_names: HashMap<(String, Vec<(bool, String)>), String>,
+    // Template instantiations whose productions have not yet been added by `expand_templates`.
+    pending_instances: Vec<Instance>,
+    // Template instantiations whose productions have been added, but whose action types have not
+    // yet been filled in by `template_actiontypes`.
+    instances: Vec<Instance>,
+    // While a template is being instantiated, its parameters' arguments and the instantiation's
+    // nesting depth.
+    template_env: Option<(HashMap<String, Symbol>, usize)>,
+}
+
+/// A rule template `Name<P1, ..., Pn> -> T: ...;`.
+#[derive(Clone)]
+struct Template {
+    params: Vec<String>,
+    actiont: String,
+    // The offset just after the `:` which starts the template's productions.
+    body: usize,
+}
+
+/// An instantiation `Name<A1, ..., An>` of a rule template.
+struct Instance {
+    // The name of the rule the template is instantiated as.
+    name: String,
+    template: String,
+    args: Vec<Symbol>,
+    // The offset of the instantiation in `src`.
+    off: usize,
+    // How many instantiations this instantiation is nested within.
+    depth: usize,
+}
+
+/// The maximum nesting depth of template instantiations: without this, templates such as `T<X>:
+/// T<W<X>>;` would be instantiated forever.
+const MAX_TEMPLATE_DEPTH: usize = 64;
+
+#[derive(Default)]
+struct BisonDecls {
+    // A map from `%union` field names to their types.
+    union: Option<HashMap<String, String>>,
+    // `%type <tag> name ...` declarations: the tag, its offset in `src`, and the names it applies
+    // to.
+    types: Vec<(String, usize, Vec<String>)>,
+    // A map from token aliases (e.g. `"+"` in `%token PLUS "+"`) to token names.
+    aliases: HashMap<String, String>,
+    // The type set by `%define api.value.type {T}`, which rules without a `%type` are given.
+    value_type: Option<String>,
+    // The contents of `%code { ... }` blocks.
+    code: Vec<String>,
+}
+
+/// The EBNF operators supported in `YaccKind::Grmtools` grammars.
+enum EBNFKind {
+    /// `X?`
+    Opt(Symbol),
+    /// `X*`
+    Star(Symbol),
+    /// `X+`
+    Plus(Symbol),
+    /// `( A B | C )`
+    Group(Vec<Vec<Symbol>>),
 }
 
 lazy_static! {
     static ref RE_NAME: Regex = Regex::new(r"^[a-zA-Z_.][a-zA-Z0-9_.]*").unwrap();
+    static ref RE_INT: Regex = Regex::new(r"^[0-9]+").unwrap();
+    static ref RE_TEMPLATE_PARAM: Regex = Regex::new(r"\$([a-zA-Z_][a-zA-Z0-9_]*)").unwrap();
     static ref RE_TOKEN: Regex =
         Regex::new("^(?:(\".+?\")|('.+?')|([a-zA-Z_][a-zA-Z_0-9]*))").unwrap();
 }
 
 /// The actual parser is intended to be entirely opaque from outside users.
 impl YaccParser {
-    pub(crate) fn new(yacc_kind: YaccKind, src: String) -> YaccParser {
+    pub(crate) fn new(yacc_kind: YaccKind, src: String, storaget: &str) -> YaccParser {
         YaccParser {
             yacc_kind,
             src,
+            storaget: storaget.to_owned(),
             newlines: vec![0],
             ast: GrammarAST::new(),
             global_actiontype: None,
+            ebnf_rules: Vec::new(),
+            errs: Vec::new(),
+            unparsed_rules: Vec::new(),
+            bison: BisonDecls::default(),
+            templates: HashMap::new(),
+            instance_names: HashMap::new(),
+            pending_instances: Vec::new(),
+            instances: Vec::new(),
+            template_env: None,
         }
     }
 
-    pub(crate) fn parse(&mut self) -> YaccResult<usize> {
+    /// Parse the grammar, returning every error found if parsing fails.
+    pub(crate) fn parse(&mut self) -> Result<usize, Vec<YaccParserError>> {
         // We pass around an index into the *bytes* of self.src. We guarantee that at all times
         // this points to the beginning of a UTF-8 character (since multibyte characters exist, not
         // every byte within the string is also a valid character).
-        let mut i = self.parse_declarations(0)?;
-        i = self.parse_rules(i)?;
-        self.parse_programs(i)
+        let mut i = self.parse_declarations(0);
+        if i < self.src.len() {
+            i = self.parse_rules(i);
+            self.expand_templates();
+            self.template_actiontypes();
+            self.ebnf_actiontypes();
+            match self.parse_programs(i) {
+                Ok(j) => i = j,
+                Err(e) => self.errs.push(e),
+            }
+            if let YaccKind::Bison = self.yacc_kind {
+                self.bison_complete();
+            }
+        }
+        if self.errs.is_empty() {
+            Ok(i)
+        } else {
+            Err(mem::take(&mut self.errs))
+        }
     }
 
     pub(crate) fn ast(self) -> GrammarAST {
         self.ast
     }
 
-    fn parse_declarations(&mut self, mut i: usize) -> YaccResult<usize> {
-        i = self.parse_ws(i, true)?;
+    /// The names of rules which couldn't be parsed (e.g. because their action type was missing)
+    /// and which are therefore missing from the AST.
+    pub(crate) fn unparsed_rules(&self) -> &[String] {
+        &self.unparsed_rules
+    }
+
+    /// Parse the declarations section, returning the offset of the `%%` which ends it (or the
+    /// end of the input if there is no such `%%`).
+    fn parse_declarations(&mut self, i: usize) -> usize {
+        let mut i = match self.parse_ws(i, true) {
+            Ok(j) => j,
+            Err(e) => {
+                self.errs.push(e);
+                return self.src.len();
+            }
+        };
         let mut prec_level = 0;
         while i < self.src.len() {
             if self.lookahead_is("%%", i).is_some() {
-                return Ok(i);
+                return i;
             }
-            if let Some(j) = self.lookahead_is("%token", i) {
-                i = self.parse_ws(j, false)?;
-                while i < self.src.len() {
-                    if self.lookahead_is("%", i).is_some() {
-                        break;
+            match self.parse_declaration(i, &mut prec_level) {
+                Ok(j) => i = j,
+                Err(e) => {
+                    self.errs.push(e);
+                    i = self.resync(i, false);
+                }
+            }
+        }
+        self.errs
+            .push(self.mk_error(YaccParserErrorKind::PrematureEnd, i - 1));
+        i
+    }
+
+    /// Parse the declaration starting at `i`, returning the offset of the next declaration.
+    fn parse_declaration(&mut self, mut i: usize, prec_level: &mut u64) -> YaccResult<usize> {
+        if let Some(j) = self.lookahead_is("%token", i) {
+            i = self.parse_ws(j, false)?;
+            while i < self.src.len() {
+                if self.lookahead_is("%", i).is_some() {
+                    break;
+                }
+                if let YaccKind::Bison = self.yacc_kind {
+                    // Tokens' values are always lexemes, so their type tags are ignored.
+                    if self.lookahead_is("<", i).is_some() {
+                        let (j, _) = self.parse_type_tag(i)?;
+                        i = self.parse_ws(j, true)?;
+                        continue;
+                    }
+                }
+                let (j, n) = self.parse_token(i)?;
+                self.add_token_span(&n, Span::new(i, j));
+                self.ast.tokens.insert(n.clone());
+                let quoted =
+                    self.lookahead_is("\"", i).is_some() || self.lookahead_is("'", i).is_some();
+                i = self.parse_ws(j, true)?;
+                if let YaccKind::Bison = self.yacc_kind {
+                    if !quoted {
+                        i = self.parse_bison_token_suffix(i, n)?;
                     }
-                    let (j, n) = self.parse_token(i)?;
-                    self.ast.tokens.insert(n);
-                    i = self.parse_ws(j, true)?;
                 }
-                continue;
             }
-            if let YaccKind::Original(_) = self.yacc_kind {
-                if let Some(j) = self.lookahead_is("%actiontype", i) {
-                    if self.global_actiontype.is_some() {
+            return Ok(i);
+        }
+        if let YaccKind::Original(_) = self.yacc_kind {
+            if let Some(j) = self.lookahead_is("%actiontype", i) {
+                if self.global_actiontype.is_some() {
+                    return Err(
+                        self.mk_error(YaccParserErrorKind::DuplicateActiontypeDeclaration, i)
+                    );
+                }
+                i = self.parse_ws(j, false)?;
+                let (j, n) = self.parse_to_eol(i)?;
+                self.global_actiontype = Some(n);
+                i = self.parse_ws(j, true)?;
+                return Ok(i);
+            }
+        }
+        if let Some(j) = self.lookahead_is("%start", i) {
+            if self.ast.start.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateStartDeclaration, i));
+            }
+            i = self.parse_ws(j, false)?;
+            let (j, n) = self.parse_name(i)?;
+            self.ast.start = Some((n, Span::new(i, j)));
+            i = self.parse_ws(j, true)?;
+            if let YaccKind::Grmtools = self.yacc_kind {
+                // Further rules are additional entry points into the grammar.
+                while i < self.src.len() && self.lookahead_is("%", i).is_none() {
+                    let (j, n) = self.parse_name(i)?;
+                    if self.ast.start.as_ref().unwrap().0 == n
+                        || self.ast.extra_starts.iter().any(|(m, _)| *m == n)
+                    {
                         return Err(
-                            self.mk_error(YaccParserErrorKind::DuplicateActiontypeDeclaration, i)
+                            self.mk_error(YaccParserErrorKind::DuplicateStartDeclaration, i)
                         );
                     }
-                    i = self.parse_ws(j, false)?;
-                    let (j, n) = self.parse_to_eol(i)?;
-                    self.global_actiontype = Some(n);
+                    self.ast.extra_starts.push((n, Span::new(i, j)));
                     i = self.parse_ws(j, true)?;
-                    continue;
                 }
             }
-            if let Some(j) = self.lookahead_is("%start", i) {
-                if self.ast.start.is_some() {
-                    return Err(self.mk_error(YaccParserErrorKind::DuplicateStartDeclaration, i));
-                }
-                i = self.parse_ws(j, false)?;
-                let (j, n) = self.parse_name(i)?;
-                self.ast.start = Some(n);
-                i = self.parse_ws(j, true)?;
-                continue;
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%epp", i) {
+            i = self.parse_ws(j, false)?;
+            let (j, n) = self.parse_token(i)?;
+            if self.ast.epp.contains_key(&n) {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateEPP, i));
             }
-            if let Some(j) = self.lookahead_is("%epp", i) {
-                i = self.parse_ws(j, false)?;
+            let span = Span::new(i, j);
+            i = self.parse_ws(j, false)?;
+            let (j, v) = self.parse_string(i)?;
+            self.ast.epp.insert(n, (span, v));
+            i = self.parse_ws(j, true)?;
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%expect-rr", i) {
+            if self.ast.expectrr.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateExpectRRDeclaration, i));
+            }
+            i = self.parse_ws(j, false)?;
+            let (j, n) = self.parse_int(i)?;
+            self.ast.expectrr = Some(n);
+            i = self.parse_ws(j, true)?;
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%expect", i) {
+            if self.ast.expect.is_some() {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateExpectDeclaration, i));
+            }
+            i = self.parse_ws(j, false)?;
+            let (j, n) = self.parse_int(i)?;
+            self.ast.expect = Some(n);
+            i = self.parse_ws(j, true)?;
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%avoid_insert", i) {
+            i = self.parse_ws(j, false)?;
+            let num_newlines = self.newlines.len();
+            if self.ast.avoid_insert.is_none() {
+                self.ast.avoid_insert = Some(HashSet::new());
+            }
+            while j < self.src.len() && self.newlines.len() == num_newlines {
                 let (j, n) = self.parse_token(i)?;
-                if self.ast.epp.contains_key(&n) {
-                    return Err(self.mk_error(YaccParserErrorKind::DuplicateEPP, i));
+                self.add_token_span(&n, Span::new(i, j));
+                self.ast.tokens.insert(n.clone());
+                if self.ast.avoid_insert.as_ref().unwrap().contains(&n) {
+                    return Err(
+                        self.mk_error(YaccParserErrorKind::DuplicateAvoidInsertDeclaration, i)
+                    );
                 }
-                i = self.parse_ws(j, false)?;
-                let (j, v) = self.parse_string(i)?;
-                self.ast.epp.insert(n, v);
+                self.ast.avoid_insert.as_mut().unwrap().insert(n);
                 i = self.parse_ws(j, true)?;
-                continue;
             }
-            if let Some(j) = self.lookahead_is("%avoid_insert", i) {
+            return Ok(i);
+        }
+        if let Some(j) = self.lookahead_is("%parse_param", i) {
+            i = self.parse_param(j)?;
+            return Ok(i);
+        }
+        if let YaccKind::Bison = self.yacc_kind {
+            if let Some(j) = self.lookahead_is("%union", i) {
+                return self.parse_bison_union(i, j);
+            }
+            if let Some(j) = self
+                .lookahead_is("%type", i)
+                .or_else(|| self.lookahead_is("%nterm", i))
+            {
+                return self.parse_bison_type(j);
+            }
+            if let Some(j) = self.lookahead_is("%define", i) {
+                return self.parse_bison_define(j);
+            }
+            if let Some(j) = self.lookahead_is("%code", i) {
+                return self.parse_bison_code(j);
+            }
+        }
+        if let YaccKind::Eco = self.yacc_kind {
+            if let Some(j) = self.lookahead_is("%implicit_tokens", i) {
                 i = self.parse_ws(j, false)?;
                 let num_newlines = self.newlines.len();
-                if self.ast.avoid_insert.is_none() {
-                    self.ast.avoid_insert = Some(HashSet::new());
+                if self.ast.implicit_tokens.is_none() {
+                    self.ast.implicit_tokens = Some(HashSet::new());
                 }
                 while j < self.src.len() && self.newlines.len() == num_newlines {
                     let (j, n) = self.parse_token(i)?;
+                    self.add_token_span(&n, Span::new(i, j));
                     self.ast.tokens.insert(n.clone());
-                    if self.ast.avoid_insert.as_ref().unwrap().contains(&n) {
-                        return Err(
-                            self.mk_error(YaccParserErrorKind::DuplicateAvoidInsertDeclaration, i)
-                        );
+                    if self.ast.implicit_tokens.as_ref().unwrap().contains(&n) {
+                        return Err(self
+                            .mk_error(YaccParserErrorKind::DuplicateImplicitTokensDeclaration, i));
                     }
-                    self.ast.avoid_insert.as_mut().unwrap().insert(n);
+                    self.ast.implicit_tokens.as_mut().unwrap().insert(n);
                     i = self.parse_ws(j, true)?;
                 }
-                continue;
-            }
-            if let Some(j) = self.lookahead_is("%parse_param", i) {
-                i = self.parse_param(j)?;
-                continue;
-            }
-            if let YaccKind::Eco = self.yacc_kind {
-                if let Some(j) = self.lookahead_is("%implicit_tokens", i) {
-                    i = self.parse_ws(j, false)?;
-                    let num_newlines = self.newlines.len();
-                    if self.ast.implicit_tokens.is_none() {
-                        self.ast.implicit_tokens = Some(HashSet::new());
-                    }
-                    while j < self.src.len() && self.newlines.len() == num_newlines {
-                        let (j, n) = self.parse_token(i)?;
-                        self.ast.tokens.insert(n.clone());
-                        if self.ast.implicit_tokens.as_ref().unwrap().contains(&n) {
-                            return Err(self.mk_error(
-                                YaccParserErrorKind::DuplicateImplicitTokensDeclaration,
-                                i,
-                            ));
-                        }
-                        self.ast.implicit_tokens.as_mut().unwrap().insert(n);
-                        i = self.parse_ws(j, true)?;
-                    }
-                    continue;
-                }
+                return Ok(i);
             }
+        }
+        {
+            let k;
+            let kind;
+            if let Some(j) = self.lookahead_is("%left", i) {
+                kind = AssocKind::Left;
+                k = j;
+            } else if let Some(j) = self.lookahead_is("%right", i) {
+                kind = AssocKind::Right;
+                k = j;
+            } else if let Some(j) = self.lookahead_is("%nonassoc", i) {
+                kind = AssocKind::Nonassoc;
+                k = j;
+            } else if let (YaccKind::Bison, Some(j)) =
+                (self.yacc_kind, self.lookahead_is("%precedence", i))
             {
-                let k;
-                let kind;
-                if let Some(j) = self.lookahead_is("%left", i) {
-                    kind = AssocKind::Left;
-                    k = j;
-                } else if let Some(j) = self.lookahead_is("%right", i) {
-                    kind = AssocKind::Right;
-                    k = j;
-                } else if let Some(j) = self.lookahead_is("%nonassoc", i) {
-                    kind = AssocKind::Nonassoc;
-                    k = j;
-                } else {
-                    return Err(self.mk_error(YaccParserErrorKind::UnknownDeclaration, i));
-                }
+                kind = AssocKind::Precedence;
+                k = j;
+            } else {
+                return Err(self.mk_error(YaccParserErrorKind::UnknownDeclaration, i));
+            }
 
-                i = self.parse_ws(k, false)?;
-                let num_newlines = self.newlines.len();
-                while i < self.src.len() && num_newlines == self.newlines.len() {
-                    let (j, n) = self.parse_token(i)?;
-                    if self.ast.precs.contains_key(&n) {
-                        return Err(self.mk_error(YaccParserErrorKind::DuplicatePrecedence, i));
-                    }
-                    let prec = Precedence {
-                        level: prec_level,
-                        kind,
-                    };
-                    self.ast.precs.insert(n, prec);
+            i = self.parse_ws(k, false)?;
+            let num_newlines = self.newlines.len();
+            while i < self.src.len() && num_newlines == self.newlines.len() {
+                if let (YaccKind::Bison, true) =
+                    (self.yacc_kind, self.lookahead_is("<", i).is_some())
+                {
+                    let (j, _) = self.parse_type_tag(i)?;
                     i = self.parse_ws(j, true)?;
+                    continue;
+                }
+                let (j, n) = self.parse_token(i)?;
+                let n = self.resolve_alias(i, n);
+                if self.ast.precs.contains_key(&n) {
+                    return Err(self.mk_error(YaccParserErrorKind::DuplicatePrecedence, i));
                 }
-                prec_level += 1;
+                let prec = Precedence {
+                    level: *prec_level,
+                    kind,
+                };
+                self.add_token_span(&n, Span::new(i, j));
+                self.ast.precs.insert(n, prec);
+                i = self.parse_ws(j, true)?;
             }
+            *prec_level += 1;
         }
-        Err(self.mk_error(YaccParserErrorKind::PrematureEnd, i - 1))
+        Ok(i)
     }
 
-    fn parse_rules(&mut self, mut i: usize) -> YaccResult<usize> {
+    /// Parse the rules section, returning the offset of the `%%` which ends it (or the end of
+    /// the input if there is no such `%%`).
+    fn parse_rules(&mut self, mut i: usize) -> usize {
         // self.parse_declarations should have left the input at '%%'
         i = self.lookahead_is("%%", i).unwrap();
-        i = self.parse_ws(i, true)?;
         while i < self.src.len() {
-            if self.lookahead_is("%%", i).is_some() {
+            match self.parse_ws(i, true) {
+                Ok(j) => i = j,
+                Err(e) => {
+                    self.errs.push(e);
+                    return self.src.len();
+                }
+            }
+            if i == self.src.len() || self.lookahead_is("%%", i).is_some() {
                 break;
             }
-            i = self.parse_rule(i)?;
-            i = self.parse_ws(i, true)?;
+            match self.parse_rule(i) {
+                Ok(j) => i = j,
+                Err(e) => {
+                    self.errs.push(e);
+                    if let Ok((_, rn)) = self.parse_name(i) {
+                        if self.ast.get_rule(&rn).is_none() && !self.templates.contains_key(&rn) {
+                            self.unparsed_rules.push(rn);
+                        }
+                    }
+                    i = self.resync(i, true);
+                }
+            }
         }
-        Ok(i)
+        i
+    }
+
+    /// After an error in the declaration or rule starting at `i`, skip to the point where
+    /// parsing can resume: if `in_rules` is `true`, just after the `;` that ends the rule (or at
+    /// the `%%` which ends the rules section); otherwise at the next `%`. Strings, comments, and
+    /// (in rules) actions are skipped over, so `;` and `%` within them are ignored.
+    fn resync(&mut self, mut i: usize, in_rules: bool) -> usize {
+        // Always skip at least one character, so that parsing makes progress.
+        let mut first = true;
+        let mut depth = 0;
+        while i < self.src.len() {
+            let c = self.src[i..].chars().next().unwrap();
+            if !first && depth == 0 {
+                if in_rules && self.lookahead_is("%%", i).is_some() {
+                    return i;
+                } else if in_rules && c == ';' {
+                    return i + ';'.len_utf8();
+                } else if !in_rules && c == '%' {
+                    return i;
+                }
+            }
+            first = false;
+            match c {
+                '\n' | '\r' => self.push_newline(i + 1),
+                '{' if in_rules => depth += 1,
+                '}' if in_rules && depth > 0 => depth -= 1,
+                '"' | '\'' => {
+                    // Skip to the matching quote if it's on the same line; otherwise treat the
+                    // quote as an ordinary character.
+                    let j = i + c.len_utf8();
+                    if let Some(off) = self.src[j..].find(&[c, '\n', '\r'][..]) {
+                        if self.src[j + off..].starts_with(c) {
+                            i = j + off;
+                        }
+                    }
+                }
+                '/' if self.lookahead_is("//", i).is_some()
+                    || self.lookahead_is("/*", i).is_some() =>
+                {
+                    match self.parse_ws(i, true) {
+                        Ok(j) => {
+                            i = j;
+                            continue;
+                        }
+                        Err(_) => return self.src.len(),
+                    }
+                }
+                _ => (),
+            }
+            i += c.len_utf8();
+        }
+        i
     }
 
     fn parse_rule(&mut self, mut i: usize) -> YaccResult<usize> {
         let (j, rn) = self.parse_name(i)?;
+        let rn_span = Span::new(i, j);
+        if let YaccKind::Grmtools = self.yacc_kind {
+            let k = self.parse_ws(j, true)?;
+            if self.lookahead_is("<", k).is_some() {
+                return self.parse_template(i, rn, k);
+            }
+        }
         if self.ast.start.is_none() {
-            self.ast.start = Some(rn.clone());
+            self.ast.start = Some((rn.clone(), rn_span));
         }
         match self.yacc_kind {
-            YaccKind::Original(_) | YaccKind::Eco => {
+            YaccKind::Original(_) | YaccKind::Bison | YaccKind::Eco => {
                 if self.ast.get_rule(&rn).is_none() {
                     self.ast
-                        .add_rule(rn.clone(), self.global_actiontype.clone());
+                        .add_rule((rn.clone(), rn_span), self.global_actiontype.clone());
                 }
                 i = j;
             }
@@ -299,66 +659,727 @@ impl YaccParser {
                 }
                 i = self.parse_ws(i, true)?;
                 let (j, actiont) = self.parse_to_single_colon(i)?;
-                self.ast.add_rule(rn.clone(), Some(actiont));
+                self.ast.add_rule((rn.clone(), rn_span), Some(actiont));
                 i = j;
             }
         }
         i = self.parse_ws(i, true)?;
         match self.lookahead_is(":", i) {
-            Some(j) => i = j,
-            None => {
-                return Err(self.mk_error(YaccParserErrorKind::MissingColon, i));
-            }
+            Some(j) => self.parse_prods(rn, j),
+            None => Err(self.mk_error(YaccParserErrorKind::MissingColon, i)),
         }
+    }
+
+    /// Parse the productions of rule `rn`, starting just after the `:` at `i`, and add them to
+    /// the rule. Returns the offset just after the `;` which ends the rule.
+    fn parse_prods(&mut self, rn: String, mut i: usize) -> YaccResult<usize> {
         let mut syms = Vec::new();
+        let mut labels = Vec::new();
         let mut prec = None;
         let mut action = None;
         i = self.parse_ws(i, true)?;
+        // The start and end of the current production: an empty production's span is empty.
+        let mut prod_start = i;
+        let mut prod_end = i;
         while i < self.src.len() {
             if let Some(j) = self.lookahead_is("|", i) {
-                self.ast.add_prod(rn.clone(), syms, prec, action);
+                self.ast.add_prod(
+                    rn.clone(),
+                    syms,
+                    prec,
+                    action,
+                    Span::new(prod_start, prod_end),
+                );
+                self.ast.prods.last_mut().unwrap().labels = mem::take(&mut labels);
                 syms = Vec::new();
                 prec = None;
                 action = None;
                 i = self.parse_ws(j, true)?;
+                prod_start = i;
+                prod_end = i;
                 continue;
             } else if let Some(j) = self.lookahead_is(";", i) {
-                self.ast.add_prod(rn, syms, prec, action);
+                self.ast
+                    .add_prod(rn, syms, prec, action, Span::new(prod_start, prod_end));
+                self.ast.prods.last_mut().unwrap().labels = labels;
                 return Ok(j);
             }
 
-            if self.lookahead_is("\"", i).is_some() || self.lookahead_is("'", i).is_some() {
-                let (j, sym) = self.parse_token(i)?;
-                i = self.parse_ws(j, true)?;
-                self.ast.tokens.insert(sym.clone());
-                syms.push(Symbol::Token(sym));
+            if let (YaccKind::Bison, Some(j)) = (self.yacc_kind, self.lookahead_is("%empty", i)) {
+                i = j;
             } else if let Some(j) = self.lookahead_is("%prec", i) {
                 i = self.parse_ws(j, true)?;
                 let (k, sym) = self.parse_token(i)?;
+                let sym = self.resolve_alias(i, sym);
                 if self.ast.tokens.contains(&sym) {
-                    prec = Some(sym);
+                    prec = Some((sym, Span::new(i, k)));
                 } else {
                     return Err(self.mk_error(YaccParserErrorKind::PrecNotFollowedByToken, i));
                 }
                 i = k;
             } else if self.lookahead_is("{", i).is_some() {
                 let (j, a) = self.parse_action(i)?;
+                action = Some((a, Span::new(i, j)));
                 i = j;
-                action = Some(a);
             } else {
-                let (j, sym) = self.parse_token(i)?;
-                if self.ast.tokens.contains(&sym) {
-                    syms.push(Symbol::Token(sym));
-                } else {
-                    syms.push(Symbol::Rule(sym));
+                if let Some((j, label)) = self.parse_label(i)? {
+                    if labels.iter().any(|(_, l)| *l == label) {
+                        return Err(self.mk_error(YaccParserErrorKind::DuplicateLabel, i));
+                    }
+                    labels.push((syms.len(), label));
+                    i = j;
                 }
+                let (j, sym) = self.parse_symbol(&rn, i)?;
+                syms.push(sym);
                 i = j;
             }
+            prod_end = i;
             i = self.parse_ws(i, true)?;
         }
         Err(self.mk_error(YaccParserErrorKind::IncompleteRule, i))
     }
 
+    /// If a label `name=` starts at `i`, return the offset of the symbol it labels and the label.
+    fn parse_label(&mut self, i: usize) -> YaccResult<Option<(usize, String)>> {
+        if RE_NAME.is_match(&self.src[i..]) {
+            let (j, label) = self.parse_name(i)?;
+            let j = j + self.src[j..]
+                .find(|c| c != ' ' && c != '\t')
+                .unwrap_or(self.src.len() - j);
+            if let Some(k) = self.lookahead_is("=", j) {
+                let k = self.parse_ws(k, true)?;
+                return Ok(Some((k, label)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Parse a single symbol in a production of the rule `rn`. In `YaccKind::Grmtools` grammars,
+    /// this includes parenthesised groups and the EBNF operators `?`, `*`, and `+`, which are
+    /// desugared into fresh rules.
+    fn parse_symbol(&mut self, rn: &str, i: usize) -> YaccResult<(usize, Symbol)> {
+        let (mut j, mut sym) = if let YaccKind::Grmtools = self.yacc_kind {
+            if self.lookahead_is("(", i).is_some() {
+                self.parse_group(rn, i)?
+            } else {
+                self.parse_template_symbol(i)?
+            }
+        } else {
+            self.parse_plain_symbol(i)?
+        };
+        if let YaccKind::Grmtools = self.yacc_kind {
+            loop {
+                let k = self.parse_ws(j, true)?;
+                let kind = if self.lookahead_is("?", k).is_some() {
+                    EBNFKind::Opt(sym)
+                } else if self.lookahead_is("*", k).is_some() {
+                    EBNFKind::Star(sym)
+                } else if self.lookahead_is("+", k).is_some() {
+                    EBNFKind::Plus(sym)
+                } else {
+                    j = k;
+                    break;
+                };
+                j = k + 1;
+                sym = self.add_ebnf_rule(rn, kind, Span::new(i, j));
+            }
+        }
+        Ok((j, sym))
+    }
+
+    /// In `YaccKind::Bison` grammars, a double quoted token `n` starting at `i` which is the alias
+    /// of another token refers to that token.
+    fn resolve_alias(&self, i: usize, n: String) -> String {
+        if let YaccKind::Bison = self.yacc_kind {
+            if self.lookahead_is("\"", i).is_some() {
+                if let Some(t) = self.bison.aliases.get(&n) {
+                    return t.clone();
+                }
+            }
+        }
+        n
+    }
+
+    /// Parse a type tag `<T>` starting at `i`, returning `T`. Nested angle brackets (e.g.
+    /// `<Vec<u8>>`) are allowed.
+    fn parse_type_tag(&mut self, i: usize) -> YaccResult<(usize, String)> {
+        debug_assert!(self.lookahead_is("<", i).is_some());
+        let mut depth = 0;
+        let mut prev = ' ';
+        let mut j = i;
+        while j < self.src.len() {
+            let c = self.src[j..].chars().next().unwrap();
+            match c {
+                '<' => depth += 1,
+                // `->` (e.g. in `<fn() -> u8>`) doesn't close a tag.
+                '>' if prev != '-' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok((j + 1, self.src[i + 1..j].trim().to_string()));
+                    }
+                }
+                '\n' | '\r' => break,
+                _ => (),
+            }
+            prev = c;
+            j += c.len_utf8();
+        }
+        Err(self.mk_error(YaccParserErrorKind::ReachedEOL, j))
+    }
+
+    /// Parse what may follow the token `n` in a Bison `%token` declaration: an (ignored) token
+    /// number and/or an alias string, which becomes `n`'s `%epp` string.
+    fn parse_bison_token_suffix(&mut self, mut i: usize, n: String) -> YaccResult<usize> {
+        if RE_INT.is_match(&self.src[i..]) {
+            let (j, _) = self.parse_int(i)?;
+            i = self.parse_ws(j, true)?;
+        }
+        if self.lookahead_is("\"", i).is_some() {
+            let (j, alias) = self.parse_string(i)?;
+            if self.ast.epp.contains_key(&n) {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateEPP, i));
+            }
+            self.ast
+                .epp
+                .insert(n.clone(), (Span::new(i, j), alias.clone()));
+            self.bison.aliases.insert(alias, n);
+            i = self.parse_ws(j, true)?;
+        }
+        Ok(i)
+    }
+
+    /// Parse `%union [name] { field: type; ... }` where `i` is the offset of `%union` and `j` the
+    /// offset after it.
+    fn parse_bison_union(&mut self, i: usize, j: usize) -> YaccResult<usize> {
+        if self.bison.union.is_some() {
+            return Err(self.mk_error(YaccParserErrorKind::DuplicateUnionDeclaration, i));
+        }
+        let mut j = self.parse_ws(j, true)?;
+        if self.lookahead_is("{", j).is_none() {
+            let (k, _) = self.parse_name(j)?;
+            j = self.parse_ws(k, true)?;
+        }
+        if self.lookahead_is("{", j).is_none() {
+            return Err(self.mk_error(YaccParserErrorKind::MissingBrace, j));
+        }
+        let (k, body) = self.parse_action(j)?;
+        let mut fields = HashMap::new();
+        for f in body.split(';').map(str::trim).filter(|f| !f.is_empty()) {
+            match f.find(':') {
+                Some(c) => {
+                    fields.insert(f[..c].trim().to_owned(), f[c + 1..].trim().to_owned());
+                }
+                None => return Err(self.mk_error(YaccParserErrorKind::InvalidUnionField, j)),
+            }
+        }
+        self.bison.union = Some(fields);
+        self.parse_ws(k, true)
+    }
+
+    /// Parse the remainder of `%type <tag> name ...` starting at `i`.
+    fn parse_bison_type(&mut self, i: usize) -> YaccResult<usize> {
+        let mut i = self.parse_ws(i, false)?;
+        if self.lookahead_is("<", i).is_none() {
+            return Err(self.mk_error(YaccParserErrorKind::MissingTypeTag, i));
+        }
+        let tag_off = i;
+        let (j, tag) = self.parse_type_tag(i)?;
+        i = self.parse_ws(j, true)?;
+        let mut names = Vec::new();
+        while i < self.src.len() && self.lookahead_is("%", i).is_none() {
+            let (j, n) = self.parse_token(i)?;
+            names.push(n);
+            i = self.parse_ws(j, true)?;
+        }
+        self.bison.types.push((tag, tag_off, names));
+        Ok(i)
+    }
+
+    /// Parse the remainder of `%define name [value]` starting at `i`. Only `api.value.type` (whose
+    /// value must be of the form `{T}`) has any effect: other variables are ignored.
+    fn parse_bison_define(&mut self, i: usize) -> YaccResult<usize> {
+        let i = self.parse_ws(i, false)?;
+        let (j, n) = self.parse_name(i)?;
+        let (j, v) = self.parse_to_eol(j)?;
+        let v = v.trim();
+        if n == "api.value.type" && v.starts_with('{') && v.ends_with('}') {
+            self.bison.value_type = Some(v[1..v.len() - 1].trim().to_owned());
+        }
+        self.parse_ws(j, true)
+    }
+
+    /// Parse the remainder of `%code [qualifier] { ... }` starting at `i`.
+    fn parse_bison_code(&mut self, i: usize) -> YaccResult<usize> {
+        let mut i = self.parse_ws(i, false)?;
+        if self.lookahead_is("{", i).is_none() {
+            let (j, _) = self.parse_name(i)?;
+            i = self.parse_ws(j, true)?;
+        }
+        if self.lookahead_is("{", i).is_none() {
+            return Err(self.mk_error(YaccParserErrorKind::MissingBrace, i));
+        }
+        let (j, code) = self.parse_action(i)?;
+        self.bison.code.push(code);
+        self.parse_ws(j, true)
+    }
+
+    /// Once a `YaccKind::Bison` grammar has been parsed, give each rule the action type from its
+    /// `%type` declaration (falling back to the `api.value.type`, or `()` if that isn't defined)
+    /// and add `%code` blocks to the start of the programs section.
+    fn bison_complete(&mut self) {
+        let mut rule_types = HashMap::new();
+        for (tag, off, names) in mem::take(&mut self.bison.types) {
+            let ty = match self.bison.union {
+                Some(ref u) => match u.get(&tag) {
+                    Some(t) => t.clone(),
+                    None => {
+                        let e = self.mk_error(YaccParserErrorKind::UnknownTypeTag, off);
+                        self.errs.push(e);
+                        continue;
+                    }
+                },
+                None => tag,
+            };
+            for n in names {
+                rule_types.insert(n, ty.clone());
+            }
+        }
+        let default = self
+            .bison
+            .value_type
+            .clone()
+            .unwrap_or_else(|| "()".to_owned());
+        for rule in self.ast.rules.values_mut() {
+            let actiont = rule_types.get(&rule.name.0).unwrap_or(&default);
+            rule.actiont = Some(actiont.clone());
+        }
+        self.bison_default_actions();
+        if !self.bison.code.is_empty() {
+            let mut programs = self.bison.code.join("\n");
+            if let Some(p) = self.ast.programs.take() {
+                programs.push('\n');
+                programs.push_str(&p);
+            }
+            self.ast.programs = Some(programs);
+        }
+    }
+
+    /// Give productions without an action Bison's default action `$$ = $1`, provided that the
+    /// production's first symbol is a rule with the same action type as the production's rule.
+    /// Productions of rules with the type `()` (i.e. rules without a value) default to `()`.
+    /// Any other production without an action is left as-is, and will be rejected by lrpar.
+    fn bison_default_actions(&mut self) {
+        for rule in self.ast.rules.values() {
+            let actiont = rule.actiont.as_ref().unwrap();
+            for &pidx in &rule.pidxs {
+                let prod = &self.ast.prods[pidx];
+                if prod.action.is_some() {
+                    continue;
+                }
+                let action = if actiont == "()" {
+                    "()"
+                } else {
+                    match prod.symbols.first() {
+                        Some(Symbol::Rule(n, _))
+                            if self.ast.rules.get(n).and_then(|r| r.actiont.as_ref())
+                                == Some(actiont) =>
+                        {
+                            "$1"
+                        }
+                        _ => continue,
+                    }
+                };
+                let span = prod.prod_span;
+                self.ast.prods[pidx].action = Some((action.to_owned(), span));
+            }
+        }
+    }
+
+    /// Record that the token `n` appears at `span`, unless it has appeared earlier.
+    fn add_token_span(&mut self, n: &str, span: Span) {
+        if !self.ast.token_spans.contains_key(n) {
+            self.ast.token_spans.insert(n.to_owned(), span);
+        }
+    }
+
+    /// Parse a token or a rule name.
+    fn parse_plain_symbol(&mut self, i: usize) -> YaccResult<(usize, Symbol)> {
+        if self.lookahead_is("\"", i).is_some() || self.lookahead_is("'", i).is_some() {
+            let (j, sym) = self.parse_token(i)?;
+            let sym = self.resolve_alias(i, sym);
+            self.add_token_span(&sym, Span::new(i, j));
+            self.ast.tokens.insert(sym.clone());
+            Ok((j, Symbol::Token(sym, Span::new(i, j))))
+        } else {
+            let (j, sym) = self.parse_token(i)?;
+            if let Some((env, _)) = &self.template_env {
+                if let Some(arg) = env.get(&sym) {
+                    return Ok((j, arg.clone()));
+                }
+            }
+            if self.ast.tokens.contains(&sym) {
+                Ok((j, Symbol::Token(sym, Span::new(i, j))))
+            } else {
+                Ok((j, Symbol::Rule(sym, Span::new(i, j))))
+            }
+        }
+    }
+
+    /// Parse a parenthesised group `( A B | C )` starting at `i`, returning a reference to the
+    /// fresh rule the group has been desugared into.
+    fn parse_group(&mut self, rn: &str, i: usize) -> YaccResult<(usize, Symbol)> {
+        let mut alts = Vec::new();
+        let mut syms = Vec::new();
+        let mut j = self.parse_ws(i + "(".len(), true)?;
+        while j < self.src.len() {
+            if let Some(k) = self.lookahead_is(")", j) {
+                alts.push(syms);
+                let sym = self.add_ebnf_rule(rn, EBNFKind::Group(alts), Span::new(i, k));
+                return Ok((k, sym));
+            } else if let Some(k) = self.lookahead_is("|", j) {
+                alts.push(syms);
+                syms = Vec::new();
+                j = k;
+            } else if self.lookahead_is(";", j).is_some() {
+                break;
+            } else {
+                let (k, sym) = self.parse_symbol(rn, j)?;
+                syms.push(sym);
+                j = k;
+            }
+            j = self.parse_ws(j, true)?;
+        }
+        Err(self.mk_error(YaccParserErrorKind::MismatchedParen, i))
+    }
+
+    /// Add a fresh rule (whose action type will later be filled in by `ebnf_actiontypes`) for
+    /// the EBNF expression `kind` (which spans `span`) in the rule `rn`, returning a reference to
+    /// the new rule. The new rule, its productions, and its actions are all given `span`.
+    fn add_ebnf_rule(&mut self, rn: &str, kind: EBNFKind, span: Span) -> Symbol {
+        let suffix = match kind {
+            EBNFKind::Opt(_) => "opt",
+            EBNFKind::Star(_) => "star",
+            EBNFKind::Plus(_) => "plus",
+            EBNFKind::Group(_) => "group",
+        };
+        let mut n = 0;
+        let name = loop {
+            let name = format!("__{}_{}{}", rn, suffix, n);
+            if self.ast.get_rule(&name).is_none() {
+                break name;
+            }
+            n += 1;
+        };
+        self.ast.add_rule((name.clone(), span), None);
+        match kind {
+            EBNFKind::Opt(ref sym) => {
+                self.ast.add_prod(
+                    name.clone(),
+                    vec![sym.clone()],
+                    None,
+                    Some(("::std::option::Option::Some($1)".to_owned(), span)),
+                    span,
+                );
+                self.ast.add_prod(
+                    name.clone(),
+                    vec![],
+                    None,
+                    Some(("::std::option::Option::None".to_owned(), span)),
+                    span,
+                );
+            }
+            EBNFKind::Star(ref sym) => {
+                self.ast.add_prod(
+                    name.clone(),
+                    vec![Symbol::Rule(name.clone(), span), sym.clone()],
+                    None,
+                    Some(("$1.push($2); $1".to_owned(), span)),
+                    span,
+                );
+                self.ast.add_prod(
+                    name.clone(),
+                    vec![],
+                    None,
+                    Some(("::std::vec::Vec::new()".to_owned(), span)),
+                    span,
+                );
+            }
+            EBNFKind::Plus(ref sym) => {
+                self.ast.add_prod(
+                    name.clone(),
+                    vec![Symbol::Rule(name.clone(), span), sym.clone()],
+                    None,
+                    Some(("$1.push($2); $1".to_owned(), span)),
+                    span,
+                );
+                self.ast.add_prod(
+                    name.clone(),
+                    vec![sym.clone()],
+                    None,
+                    Some(("::std::vec![$1]".to_owned(), span)),
+                    span,
+                );
+            }
+            EBNFKind::Group(ref alts) => {
+                for alt in alts {
+                    let action = match alt.len() {
+                        0 => "()".to_owned(),
+                        1 => "$1".to_owned(),
+                        _ => format!(
+                            "({})",
+                            (1..=alt.len())
+                                .map(|i| format!("${}", i))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                    };
+                    self.ast
+                        .add_prod(name.clone(), alt.clone(), None, Some((action, span)), span);
+                }
+            }
+        }
+        self.ebnf_rules.push((name.clone(), kind, span.start()));
+        Symbol::Rule(name, span)
+    }
+
+    /// Fill in the action types of the rules created by `add_ebnf_rule`. Rules are processed in
+    /// the order they were created, so nested EBNF expressions have their types filled in before
+    /// the expressions that reference them.
+    fn ebnf_actiontypes(&mut self) {
+        let ebnf_rules = mem::take(&mut self.ebnf_rules);
+        for (name, kind, off) in &ebnf_rules {
+            let actiont = match kind {
+                EBNFKind::Opt(sym) => self
+                    .symbol_actiontype(sym)
+                    .map(|t| format!("::std::option::Option<{}>", t)),
+                EBNFKind::Star(sym) | EBNFKind::Plus(sym) => self
+                    .symbol_actiontype(sym)
+                    .map(|t| format!("::std::vec::Vec<{}>", t)),
+                EBNFKind::Group(alts) => {
+                    let mut alts_actiont = Vec::with_capacity(alts.len());
+                    for alt in alts {
+                        let tys = alt
+                            .iter()
+                            .map(|sym| self.symbol_actiontype(sym))
+                            .collect::<Option<Vec<_>>>();
+                        alts_actiont.push(tys.map(|tys| match tys.len() {
+                            1 => tys[0].clone(),
+                            _ => format!("({})", tys.join(", ")),
+                        }));
+                    }
+                    if alts_actiont.iter().any(|t| *t != alts_actiont[0]) {
+                        self.errs
+                            .push(self.mk_error(YaccParserErrorKind::MismatchedGroupTypes, *off));
+                        continue;
+                    }
+                    alts_actiont[0].clone()
+                }
+            };
+            // If `actiont` is `None` then the EBNF expression references an unknown rule, which
+            // will be reported when the grammar is validated.
+            self.ast.rules[name].actiont = actiont;
+        }
+    }
+
+    /// Return the action type of the symbol `sym`, or `None` if `sym` references an unknown rule.
+    fn symbol_actiontype(&self, sym: &Symbol) -> Option<String> {
+        match sym {
+            Symbol::Rule(n, _) => self.ast.get_rule(n).and_then(|r| r.actiont.clone()),
+            Symbol::Token(_, _) => Some(format!(
+                "::std::result::Result<::lrpar::Lexeme<{storaget}>, ::lrpar::Lexeme<{storaget}>>",
+                storaget = self.storaget
+            )),
+        }
+    }
+
+    /// Parse the definition of the rule template `name` (which starts at `i`), whose parameter
+    /// list starts at `j`. The template's productions are skipped over: they are parsed once per
+    /// instantiation by `expand_templates`.
+    fn parse_template(&mut self, i: usize, name: String, j: usize) -> YaccResult<usize> {
+        if self.ast.get_rule(&name).is_some() || self.templates.contains_key(&name) {
+            return Err(self.mk_error(YaccParserErrorKind::DuplicateRule, i));
+        }
+        let mut params = Vec::new();
+        let mut j = self.parse_ws(j + "<".len(), true)?;
+        loop {
+            let (k, param) = self.parse_name(j)?;
+            if params.contains(&param) {
+                return Err(self.mk_error(YaccParserErrorKind::DuplicateTemplateParam, j));
+            }
+            params.push(param);
+            j = self.parse_ws(k, true)?;
+            if let Some(k) = self.lookahead_is(",", j) {
+                j = self.parse_ws(k, true)?;
+            } else if let Some(k) = self.lookahead_is(">", j) {
+                j = k;
+                break;
+            } else {
+                return Err(self.mk_error(YaccParserErrorKind::MissingRightAngle, j));
+            }
+        }
+        j = self.parse_ws(j, true)?;
+        match self.lookahead_is("->", j) {
+            Some(k) => j = self.parse_ws(k, true)?,
+            None => return Err(self.mk_error(YaccParserErrorKind::MissingRightArrow, j)),
+        }
+        let (j, actiont) = self.parse_to_single_colon(j)?;
+        // `resync` skips over the `:` and everything up to and including the `;` which ends the
+        // rule (ignoring any `;`s in actions, strings and so on).
+        let k = self.resync(j, true);
+        if !self.src[..k].ends_with(';') {
+            return Err(self.mk_error(YaccParserErrorKind::IncompleteRule, i));
+        }
+        self.templates.insert(
+            name,
+            Template {
+                params,
+                actiont,
+                body: j + ":".len(),
+            },
+        );
+        Ok(k)
+    }
+
+    /// Parse a token, a rule name, or (in `YaccKind::Grmtools` grammars) an instantiation of a
+    /// rule template `Name<A1, ..., An>` (where each argument is itself one of these).
+    fn parse_template_symbol(&mut self, i: usize) -> YaccResult<(usize, Symbol)> {
+        let (j, sym) = self.parse_plain_symbol(i)?;
+        let name = match sym {
+            // If the name has been substituted for a template argument, it can't be a template.
+            Symbol::Rule(ref n, _) if self.src[i..j] == *n => n.clone(),
+            _ => return Ok((j, sym)),
+        };
+        let k = self.parse_ws(j, true)?;
+        if self.lookahead_is("<", k).is_none() {
+            return Ok((j, sym));
+        }
+        let mut args = Vec::new();
+        let mut j = self.parse_ws(k + "<".len(), true)?;
+        loop {
+            let (k, arg) = self.parse_template_symbol(j)?;
+            args.push(arg);
+            j = self.parse_ws(k, true)?;
+            if let Some(k) = self.lookahead_is(",", j) {
+                j = self.parse_ws(k, true)?;
+            } else if let Some(k) = self.lookahead_is(">", j) {
+                j = k;
+                break;
+            } else {
+                return Err(self.mk_error(YaccParserErrorKind::MissingRightAngle, j));
+            }
+        }
+        let key = (
+            name.clone(),
+            args.iter()
+                .map(|arg| match arg {
+                    Symbol::Rule(n, _) => (false, n.clone()),
+                    Symbol::Token(n, _) => (true, n.clone()),
+                })
+                .collect::<Vec<_>>(),
+        );
+        let span = Span::new(i, j);
+        if let Some(n) = self.instance_names.get(&key) {
+            return Ok((j, Symbol::Rule(n.clone(), span)));
+        }
+        let mut n = 0;
+        let inst_name = loop {
+            let inst_name = format!("__{}_inst{}", name, n);
+            if self.ast.get_rule(&inst_name).is_none() {
+                break inst_name;
+            }
+            n += 1;
+        };
+        // The rule is added now so that its name can't be reused; its productions are added by
+        // `expand_templates` and its action type by `template_actiontypes`.
+        self.ast.add_rule((inst_name.clone(), span), None);
+        self.instance_names.insert(key, inst_name.clone());
+        self.pending_instances.push(Instance {
+            name: inst_name.clone(),
+            template: name,
+            args,
+            off: i,
+            depth: self.template_env.as_ref().map(|(_, d)| d + 1).unwrap_or(0),
+        });
+        Ok((j, Symbol::Rule(inst_name, span)))
+    }
+
+    /// Add the productions of every template instantiation, including those found while doing so.
+    fn expand_templates(&mut self) {
+        while !self.pending_instances.is_empty() {
+            for inst in mem::take(&mut self.pending_instances) {
+                if let Err(e) = self.expand_template(&inst) {
+                    self.errs.push(e);
+                }
+                self.instances.push(inst);
+            }
+        }
+    }
+
+    /// Add the productions of the template instantiation `inst` by parsing the template's
+    /// productions with its parameters replaced by `inst`'s arguments.
+    fn expand_template(&mut self, inst: &Instance) -> YaccResult<()> {
+        let t = match self.templates.get(&inst.template) {
+            Some(t) => t.clone(),
+            None => return Err(self.mk_error(YaccParserErrorKind::UnknownTemplate, inst.off)),
+        };
+        if t.params.len() != inst.args.len() {
+            return Err(self.mk_error(YaccParserErrorKind::TemplateArgCount, inst.off));
+        }
+        if inst.depth >= MAX_TEMPLATE_DEPTH {
+            return Err(self.mk_error(YaccParserErrorKind::TemplateTooDeep, inst.off));
+        }
+        let env = t
+            .params
+            .into_iter()
+            .zip(inst.args.iter().cloned())
+            .collect();
+        self.template_env = Some((env, inst.depth));
+        let i = self.parse_ws(t.body, true);
+        let r = i.and_then(|i| self.parse_prods(inst.name.clone(), i));
+        self.template_env = None;
+        r.map(|_| ())
+    }
+
+    /// Fill in the action types of template instantiations by replacing each `$P` in the
+    /// template's action type with the action type of the argument for parameter `P`. Since an
+    /// argument may itself be an instantiation, this is repeated until no more types can be
+    /// filled in: any left unfilled reference unknown rules, which will be reported when the
+    /// grammar is validated.
+    fn template_actiontypes(&mut self) {
+        let mut todo = mem::take(&mut self.instances);
+        loop {
+            let mut unfilled = Vec::new();
+            let todo_len = todo.len();
+            for inst in todo {
+                let t = match self.templates.get(&inst.template) {
+                    Some(t) => t,
+                    None => continue,
+                };
+                let mut known = true;
+                let actiont =
+                    RE_TEMPLATE_PARAM.replace_all(&t.actiont, |caps: &regex::Captures| {
+                        match t.params.iter().position(|p| *p == caps[1]) {
+                            Some(k) => self.symbol_actiontype(&inst.args[k]).unwrap_or_else(|| {
+                                known = false;
+                                String::new()
+                            }),
+                            None => caps[0].to_owned(),
+                        }
+                    });
+                if known {
+                    let actiont = actiont.trim().to_owned();
+                    self.ast.rules[&inst.name].actiont = Some(actiont);
+                } else {
+                    unfilled.push(inst);
+                }
+            }
+            if unfilled.len() == todo_len {
+                break;
+            }
+            todo = unfilled;
+        }
+    }
+
     fn parse_name(&self, i: usize) -> YaccResult<(usize, String)> {
         match RE_NAME.find(&self.src[i..]) {
             Some(m) => {
@@ -369,6 +1390,16 @@ impl YaccParser {
         }
     }
 
+    fn parse_int(&self, i: usize) -> YaccResult<(usize, usize)> {
+        match RE_INT.find(&self.src[i..]) {
+            Some(m) => match m.as_str().parse::<usize>() {
+                Ok(n) => Ok((i + m.end(), n)),
+                Err(_) => Err(self.mk_error(YaccParserErrorKind::IllegalInteger, i)),
+            },
+            None => Err(self.mk_error(YaccParserErrorKind::IllegalInteger, i)),
+        }
+    }
+
     fn parse_token(&self, i: usize) -> YaccResult<(usize, String)> {
         match RE_TOKEN.find(&self.src[i..]) {
             Some(m) => {
@@ -398,7 +1429,7 @@ impl YaccParser {
                 }
                 '}' => c -= 1,
                 '\n' | '\r' => {
-                    self.newlines.push(j + 1);
+                    self.push_newline(j + 1);
                 }
                 _ => (),
             };
@@ -545,7 +1576,7 @@ impl YaccParser {
                     j += 2 * ':'.len_utf8();
                 }
                 '\n' | '\r' => {
-                    self.newlines.push(i + 1);
+                    self.push_newline(i + 1);
                     j += c.len_utf8();
                 }
                 _ => j += c.len_utf8(),
@@ -612,7 +1643,7 @@ impl YaccParser {
                     if !inc_newlines {
                         return Err(self.mk_error(YaccParserErrorKind::ReachedEOL, i));
                     }
-                    self.newlines.push(i + 1);
+                    self.push_newline(i + 1);
                     i += c.len_utf8();
                 }
                 '/' => {
@@ -627,7 +1658,7 @@ impl YaccParser {
                                 for c in self.src[i..].chars() {
                                     i += c.len_utf8();
                                     if c == '\n' || c == '\r' {
-                                        self.newlines.push(i);
+                                        self.push_newline(i);
                                         break;
                                     }
                                 }
@@ -646,7 +1677,7 @@ impl YaccParser {
                                                 return Err(self
                                                     .mk_error(YaccParserErrorKind::ReachedEOL, i));
                                             }
-                                            self.newlines.push(i + 1);
+                                            self.push_newline(k);
                                         }
                                         '*' => (),
                                         _ => continue,
@@ -676,6 +1707,14 @@ impl YaccParser {
         Ok(i)
     }
 
+    /// Record that a line starts at offset `off`. Since the parser can rescan input after an
+    /// error, `off` is ignored if it has already been recorded.
+    fn push_newline(&mut self, off: usize) {
+        if *self.newlines.last().unwrap() < off {
+            self.newlines.push(off);
+        }
+    }
+
     fn lookahead_is(&self, s: &'static str, i: usize) -> Option<usize> {
         if self.src[i..].starts_with(s) {
             Some(i + s.len())
@@ -686,7 +1725,12 @@ impl YaccParser {
 
     fn mk_error(&self, k: YaccParserErrorKind, off: usize) -> YaccParserError {
         let (line, col) = self.off_to_line_col(off);
-        YaccParserError { kind: k, line, col }
+        YaccParserError {
+            kind: k,
+            line,
+            col,
+            file: None,
+        }
     }
 
     fn off_to_line_col(&self, off: usize) -> (usize, usize) {
@@ -723,24 +1767,26 @@ mod test {
         },
         YaccParser, YaccParserError, YaccParserErrorKind,
     };
+    use crate::Span;
 
     fn parse(yacc_kind: YaccKind, s: &str) -> Result<GrammarAST, YaccParserError> {
-        let mut yp = YaccParser::new(yacc_kind, s.to_string());
-        yp.parse()?;
+        let mut yp = YaccParser::new(yacc_kind, s.to_string(), "u32");
+        // Most tests only care about the first error found.
+        yp.parse().map_err(|mut errs| errs.remove(0))?;
         Ok(yp.ast())
     }
 
     fn rule(n: &str) -> Symbol {
-        Symbol::Rule(n.to_string())
+        Symbol::Rule(n.to_string(), Span::new(0, 0))
     }
 
     fn token(n: &str) -> Symbol {
-        Symbol::Token(n.to_string())
+        Symbol::Token(n.to_string(), Span::new(0, 0))
     }
 
     #[test]
     fn test_macro() {
-        assert_eq!(Symbol::Token("A".to_string()), token("A"));
+        assert_eq!(Symbol::Token("A".to_string(), Span::new(0, 0)), token("A"));
     }
 
     #[test]
@@ -748,6 +1794,13 @@ mod test {
         assert_eq!(rule("A"), rule("A"));
         assert!(rule("A") != rule("B"));
         assert!(rule("A") != token("A"));
+        assert_eq!(
+            Symbol::Rule("A".to_string(), Span::new(1, 2)),
+            Symbol::Rule("A".to_string(), Span::new(3, 4))
+        );
+        let mut hs = HashSet::new();
+        hs.insert(Symbol::Token("a".to_string(), Span::new(1, 2)));
+        assert!(hs.contains(&Symbol::Token("a".to_string(), Span::new(3, 4))));
     }
 
     #[test]
@@ -768,7 +1821,9 @@ mod test {
             Production {
                 symbols: vec![token("a")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
     }
@@ -791,7 +1846,9 @@ mod test {
             Production {
                 symbols: vec![token("a")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
         assert_eq!(
@@ -799,7 +1856,9 @@ mod test {
             Production {
                 symbols: vec![token("b")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
     }
@@ -824,7 +1883,9 @@ mod test {
             Production {
                 symbols: vec![],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
 
@@ -833,7 +1894,9 @@ mod test {
             Production {
                 symbols: vec![token("b")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
         assert_eq!(
@@ -841,7 +1904,9 @@ mod test {
             Production {
                 symbols: vec![],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
 
@@ -850,7 +1915,9 @@ mod test {
             Production {
                 symbols: vec![],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
         assert_eq!(
@@ -858,7 +1925,9 @@ mod test {
             Production {
                 symbols: vec![token("c")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
     }
@@ -886,7 +1955,9 @@ mod test {
             Production {
                 symbols: vec![token("a"), rule("B")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
     }
@@ -904,7 +1975,9 @@ mod test {
             Production {
                 symbols: vec![token("a"), token("b")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
     }
@@ -917,7 +1990,7 @@ mod test {
             &src,
         )
         .unwrap();
-        assert_eq!(grm.start.unwrap(), "A");
+        assert_eq!(grm.start.unwrap(), ("A".to_string(), Span::new(9, 10)));
     }
 
     #[test]
@@ -980,7 +2053,9 @@ mod test {
             Production {
                 symbols: vec![token("T")],
                 precedence: None,
-                action: None
+                action: None,
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
             }
         );
     }
@@ -1008,6 +2083,7 @@ mod test {
                 kind: YaccParserErrorKind::IllegalString,
                 line: 1,
                 col: 12,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1025,6 +2101,7 @@ mod test {
                 kind: YaccParserErrorKind::IllegalString,
                 line: 3,
                 col: 11,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1064,6 +2141,7 @@ mod test {
                 kind: YaccParserErrorKind::IncompleteRule,
                 line: 1,
                 col: 5,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1083,6 +2161,7 @@ A:"
                 kind: YaccParserErrorKind::IncompleteRule,
                 line: 2,
                 col: 3,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1103,6 +2182,7 @@ A:
                 kind: YaccParserErrorKind::IncompleteRule,
                 line: 3,
                 col: 1,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1123,6 +2203,7 @@ A:
                 kind: YaccParserErrorKind::UnknownDeclaration,
                 line: 3,
                 col: 9,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1140,6 +2221,7 @@ A:
                 kind: YaccParserErrorKind::MissingColon,
                 line: 1,
                 col: 5,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1157,6 +2239,7 @@ A:
                 kind: YaccParserErrorKind::PrematureEnd,
                 line: 1,
                 col: 8,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e),
         }
@@ -1176,21 +2259,93 @@ x"
                 kind: YaccParserErrorKind::ReachedEOL,
                 line: 1,
                 col: 7,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_unknown_declaration() {
+        let src = "%woo".to_string();
+        match parse(YaccKind::Original(YaccOriginalActionKind::GenericParseTree), &src) {
+            Ok(_) => panic!("Unknown declaration parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::UnknownDeclaration,
+                line: 1,
+                col: 1,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_expect() {
+        let src = "
+          %expect 2
+          %expect-rr 1
+          %%
+          A: 'a';
+          "
+        .to_string();
+        let grm = parse(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            &src,
+        )
+        .unwrap();
+        assert_eq!(grm.expect, Some(2));
+        assert_eq!(grm.expectrr, Some(1));
+
+        let src = "%%
+          A: 'a';
+          "
+        .to_string();
+        let grm = parse(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            &src,
+        )
+        .unwrap();
+        assert_eq!(grm.expect, None);
+        assert_eq!(grm.expectrr, None);
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_expect_errors() {
+        let src = "%expect 1\n%expect 2\n%%\nA: 'a';".to_string();
+        match parse(YaccKind::Original(YaccOriginalActionKind::GenericParseTree), &src) {
+            Ok(_) => panic!("Duplicate %expect parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::DuplicateExpectDeclaration,
+                line: 2,
+                col: 1,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        let src = "%expect-rr 1\n%expect-rr 2\n%%\nA: 'a';".to_string();
+        match parse(YaccKind::Original(YaccOriginalActionKind::GenericParseTree), &src) {
+            Ok(_) => panic!("Duplicate %expect-rr parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::DuplicateExpectRRDeclaration,
+                line: 2,
+                col: 1,
+                ..
             }) => (),
-            Err(e) => panic!("Incorrect error returned {}", e),
+            Err(e) => panic!("Incorrect error returned {}", e)
         }
-    }
 
-    #[test]
-    #[rustfmt::skip]
-    fn test_unknown_declaration() {
-        let src = "%woo".to_string();
+        let src = "%expect x\n%%\nA: 'a';".to_string();
         match parse(YaccKind::Original(YaccOriginalActionKind::GenericParseTree), &src) {
-            Ok(_) => panic!("Unknown declaration parsed"),
+            Ok(_) => panic!("Illegal integer parsed"),
             Err(YaccParserError {
-                kind: YaccParserErrorKind::UnknownDeclaration,
+                kind: YaccParserErrorKind::IllegalInteger,
                 line: 1,
-                col: 1
+                col: 9,
+                ..
             }) => (),
             Err(e) => panic!("Incorrect error returned {}", e)
         }
@@ -1306,7 +2461,7 @@ x"
         assert_eq!(grm.prods[grm.rules["expr"].pidxs[0]].precedence, None);
         assert_eq!(grm.prods[grm.rules["expr"].pidxs[3]].symbols.len(), 3);
         assert_eq!(grm.prods[grm.rules["expr"].pidxs[4]].symbols.len(), 2);
-        assert_eq!(grm.prods[grm.rules["expr"].pidxs[4]].precedence, Some("*".to_string()));
+        assert_eq!(grm.prods[grm.rules["expr"].pidxs[4]].precedence.as_ref().unwrap().0, "*");
     }
 
     #[test]
@@ -1546,13 +2701,13 @@ x"
         )
         .unwrap();
         assert_eq!(ast.epp.len(), 7);
-        assert_eq!(ast.epp["A"], "a");
-        assert_eq!(ast.epp["B"], "a");
-        assert_eq!(ast.epp["C"], "\"");
-        assert_eq!(ast.epp["D"], "'");
-        assert_eq!(ast.epp["E"], "\"");
-        assert_eq!(ast.epp["F"], "'");
-        assert_eq!(ast.epp["G"], "a\"b");
+        assert_eq!(ast.epp["A"].1, "a");
+        assert_eq!(ast.epp["B"].1, "a");
+        assert_eq!(ast.epp["C"].1, "\"");
+        assert_eq!(ast.epp["D"].1, "'");
+        assert_eq!(ast.epp["E"].1, "\"");
+        assert_eq!(ast.epp["F"].1, "'");
+        assert_eq!(ast.epp["G"].1, "a\"b");
     }
 
     #[test]
@@ -1628,6 +2783,42 @@ x"
         }
     }
 
+    #[test]
+    fn test_multiple_starts() {
+        let src = "%start A B\n  C\n%token a\n%%\nA -> (): 'a';\nB -> (): 'a';\nC -> (): 'a';"
+            .to_string();
+        let grm = parse(YaccKind::Grmtools, &src).unwrap();
+        assert_eq!(grm.start.unwrap(), ("A".to_string(), Span::new(7, 8)));
+        assert_eq!(
+            grm.extra_starts,
+            vec![
+                ("B".to_string(), Span::new(9, 10)),
+                ("C".to_string(), Span::new(13, 14))
+            ]
+        );
+
+        match parse(
+            YaccKind::Grmtools,
+            "%start A B A\n%%\nA -> (): ;\nB -> (): ;",
+        ) {
+            Ok(_) => panic!(),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::DuplicateStartDeclaration,
+                line: 1,
+                col: 12,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+
+        // Only grmtools grammars can have multiple start rules.
+        assert!(parse(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "%start A B\n%%\nA: ;\nB: ;"
+        )
+        .is_err());
+    }
+
     #[test]
     fn test_implicit_start() {
         let ast = parse(
@@ -1640,7 +2831,88 @@ x"
           ",
         )
         .unwrap();
-        assert_eq!(ast.start, Some("R".to_string()));
+        assert_eq!(ast.start.unwrap().0, "R");
+    }
+
+    #[test]
+    fn test_multiple_errors() {
+        let src = "%token a
+%unknown x
+%start A
+%%
+A: 'a' ) ;
+B: 'b' { ; } | 1 ;
+/* ; */ C: 'c';
+D: 'd'
+";
+        let mut yp = YaccParser::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src.to_string(),
+            "u32",
+        );
+        let errs = yp
+            .parse()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (format!("{:?}", e.kind), e.line, e.col))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errs,
+            vec![
+                ("UnknownDeclaration".to_owned(), 2, 1),
+                ("IllegalString".to_owned(), 5, 8),
+                ("IllegalString".to_owned(), 6, 16),
+                ("IncompleteRule".to_owned(), 9, 1)
+            ]
+        );
+        // Parsing resumed after each error.
+        let ast = yp.ast();
+        assert_eq!(ast.start.as_ref().unwrap().0, "A");
+        assert!(ast.get_rule("C").is_some());
+    }
+
+    #[test]
+    fn test_resync_after_comment() {
+        // A comment immediately before the resync point mustn't cause the `;` to be skipped.
+        let src = "%%\nA: B ) // c\n;\nB: C;\nD: 'd';";
+        let mut yp = YaccParser::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src.to_string(),
+            "u32",
+        );
+        let errs = yp
+            .parse()
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (format!("{:?}", e.kind), e.line, e.col))
+            .collect::<Vec<_>>();
+        assert_eq!(errs, vec![("IllegalString".to_owned(), 2, 6)]);
+        let ast = yp.ast();
+        assert!(ast.get_rule("B").is_some());
+        assert!(ast.get_rule("D").is_some());
+    }
+
+    #[test]
+    fn test_spans() {
+        let src = "%token X\n%epp X \"x\"\n%%\nA -> (): B+ X %prec X { () } | ;\nB -> (): 'b' { };";
+        let grm = parse(YaccKind::Grmtools, src).unwrap();
+        let span_str = |span: Span| &src[span.start()..span.end()];
+        assert_eq!(span_str(grm.start.as_ref().unwrap().1), "A");
+        assert_eq!(span_str(grm.epp["X"].0), "X");
+        assert_eq!(span_str(grm.rules["A"].name.1), "A");
+
+        let prod = &grm.prods[grm.rules["A"].pidxs[0]];
+        assert_eq!(span_str(prod.prod_span), "B+ X %prec X { () }");
+        assert_eq!(span_str(prod.symbols[0].span()), "B+");
+        assert_eq!(span_str(prod.symbols[1].span()), "X");
+        assert_eq!(span_str(prod.precedence.as_ref().unwrap().1), "X");
+        assert_eq!(span_str(prod.action.as_ref().unwrap().1), "{ () }");
+        assert_eq!(grm.prods[grm.rules["A"].pidxs[1]].prod_span.len(), 0);
+
+        // Rules created for EBNF expressions are given the span of the expression.
+        assert_eq!(span_str(grm.rules["__A_plus0"].name.1), "B+");
+        let prod = &grm.prods[grm.rules["__A_plus0"].pidxs[0]];
+        assert_eq!(span_str(prod.symbols[1].span()), "B");
     }
 
     #[test]
@@ -1658,12 +2930,20 @@ x"
         )
         .unwrap();
         assert_eq!(
-            grm.prods[grm.rules["A"].pidxs[0]].action,
-            Some("println!(\"test\");".to_string())
+            grm.prods[grm.rules["A"].pidxs[0]]
+                .action
+                .as_ref()
+                .unwrap()
+                .0,
+            "println!(\"test\");"
         );
         assert_eq!(
-            grm.prods[grm.rules["B"].pidxs[0]].action,
-            Some("add($1, $2);".to_string())
+            grm.prods[grm.rules["B"].pidxs[0]]
+                .action
+                .as_ref()
+                .unwrap()
+                .0,
+            "add($1, $2);"
         );
         assert_eq!(grm.prods[grm.rules["B"].pidxs[1]].action, None);
     }
@@ -1831,4 +3111,460 @@ x"
         assert_eq!(grm.parse_param_lifetimes, Some(expect_lifetimes));
         assert_eq!(grm.parse_param_bindings, Some(expect_bindings));
     }
+
+    #[test]
+    fn test_ebnf() {
+        let src = "
+          %%
+          A -> Vec<u64>: B? C* 'd'+ { vec![] };
+          B -> u64: 'b' { 0 };
+          C -> u64: 'c' { 1 };
+          "
+        .to_string();
+        let grm = parse(YaccKind::Grmtools, &src).unwrap();
+        assert_eq!(
+            grm.prods[grm.get_rule("A").unwrap().pidxs[0]].symbols,
+            vec![rule("__A_opt0"), rule("__A_star0"), rule("__A_plus0")]
+        );
+
+        let opt = grm.get_rule("__A_opt0").unwrap();
+        assert_eq!(opt.actiont, Some("::std::option::Option<u64>".to_string()));
+        assert_eq!(opt.pidxs.len(), 2);
+        assert_eq!(grm.prods[opt.pidxs[0]].symbols, vec![rule("B")]);
+        assert_eq!(grm.prods[opt.pidxs[1]].symbols, vec![]);
+
+        let star = grm.get_rule("__A_star0").unwrap();
+        assert_eq!(star.actiont, Some("::std::vec::Vec<u64>".to_string()));
+        assert_eq!(
+            grm.prods[star.pidxs[0]].symbols,
+            vec![rule("__A_star0"), rule("C")]
+        );
+        assert_eq!(grm.prods[star.pidxs[1]].symbols, vec![]);
+
+        let plus = grm.get_rule("__A_plus0").unwrap();
+        assert_eq!(
+            plus.actiont,
+            Some(
+                "::std::vec::Vec<::std::result::Result<::lrpar::Lexeme<u32>, ::lrpar::Lexeme<u32>>>"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            grm.prods[plus.pidxs[0]].symbols,
+            vec![rule("__A_plus0"), token("d")]
+        );
+        assert_eq!(grm.prods[plus.pidxs[1]].symbols, vec![token("d")]);
+    }
+
+    #[test]
+    fn test_ebnf_groups() {
+        let src = "
+          %%
+          A -> (): (B C | D)* ( B )? { };
+          B -> u64: 'b' { 0 };
+          C -> String: 'c' { String::new() };
+          D -> (u64, String): 'd' { (0, String::new()) };
+          "
+        .to_string();
+        let grm = parse(YaccKind::Grmtools, &src).unwrap();
+        assert_eq!(
+            grm.prods[grm.get_rule("A").unwrap().pidxs[0]].symbols,
+            vec![rule("__A_star0"), rule("__A_opt0")]
+        );
+
+        let group = grm.get_rule("__A_group0").unwrap();
+        assert_eq!(group.actiont, Some("(u64, String)".to_string()));
+        assert_eq!(
+            grm.prods[group.pidxs[0]],
+            Production {
+                symbols: vec![rule("B"), rule("C")],
+                precedence: None,
+                action: Some(("($1, $2)".to_string(), Span::new(0, 0))),
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
+            }
+        );
+        assert_eq!(
+            grm.prods[group.pidxs[1]],
+            Production {
+                symbols: vec![rule("D")],
+                precedence: None,
+                action: Some(("$1".to_string(), Span::new(0, 0))),
+                prod_span: Span::new(0, 0),
+                labels: Vec::new()
+            }
+        );
+        assert_eq!(
+            grm.get_rule("__A_star0").unwrap().actiont,
+            Some("::std::vec::Vec<(u64, String)>".to_string())
+        );
+        assert_eq!(
+            grm.get_rule("__A_group1").unwrap().actiont,
+            Some("u64".to_string())
+        );
+        assert_eq!(
+            grm.get_rule("__A_opt0").unwrap().actiont,
+            Some("::std::option::Option<u64>".to_string())
+        );
+    }
+
+    #[test]
+    #[rustfmt::skip]
+    fn test_ebnf_errors() {
+        let src = "%%\nA -> (): (B | C) { };\nB -> u64: 'b' { 0 };\nC -> u32: 'c' { 0 };";
+        match parse(YaccKind::Grmtools, src) {
+            Ok(_) => panic!("Mismatched group types parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::MismatchedGroupTypes,
+                line: 2,
+                col: 10,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        let src = "%%\nA -> (): (B C { };\nB -> u64: 'b' { 0 };";
+        match parse(YaccKind::Grmtools, src) {
+            Ok(_) => panic!("Action in group parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::IllegalString,
+                line: 2,
+                col: 15,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        let src = "%%\nA -> (): (B C;\nB -> u64: 'b' { 0 };";
+        match parse(YaccKind::Grmtools, src) {
+            Ok(_) => panic!("Mismatched parenthesis parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::MismatchedParen,
+                line: 2,
+                col: 10,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+
+        // EBNF operators are only supported in Grmtools grammars.
+        let src = "%%\nA: B?;\nB: 'b';";
+        match parse(YaccKind::Original(YaccOriginalActionKind::GenericParseTree), src) {
+            Ok(_) => panic!("EBNF parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::IllegalString,
+                line: 2,
+                col: 5,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e)
+        }
+    }
+
+    #[test]
+    fn test_bison() {
+        let src = "
+          %union { num: u64; v: Vec<u8>; }
+          %define api.value.type {String}
+          %define parse.error verbose
+          %code requires { use std::fmt; }
+          %token <num> INT 258 \"integer\" PLUS \"+\"
+          %token NEG
+          %left <num> \"+\"
+          %precedence NEG
+          %type <num> A
+          %nterm <v> B
+          %%
+          A: A \"+\" A { $1 + $3 } | '-' A %prec NEG | INT | C;
+          B: %empty { vec![] };
+          C: ;
+          %%
+          fn f() {}
+          ";
+        let grm = parse(YaccKind::Bison, src).unwrap();
+        assert_eq!(grm.rules["A"].actiont, Some("u64".to_string()));
+        assert_eq!(grm.rules["B"].actiont, Some("Vec<u8>".to_string()));
+        assert_eq!(grm.rules["C"].actiont, Some("String".to_string()));
+        assert_eq!(grm.epp["INT"].1, "integer");
+        assert_eq!(grm.epp["PLUS"].1, "+");
+        assert!(!grm.tokens.contains("+"));
+        assert_eq!(
+            grm.precs["PLUS"],
+            Precedence {
+                level: 0,
+                kind: AssocKind::Left
+            }
+        );
+        assert_eq!(
+            grm.precs["NEG"],
+            Precedence {
+                level: 1,
+                kind: AssocKind::Precedence
+            }
+        );
+        // Aliases in productions refer to the aliased token.
+        assert_eq!(
+            grm.prods[grm.rules["A"].pidxs[0]].symbols,
+            vec![rule("A"), token("PLUS"), rule("A")]
+        );
+        assert_eq!(
+            grm.prods[grm.rules["A"].pidxs[1]]
+                .precedence
+                .as_ref()
+                .map(|(n, _)| n.as_str()),
+            Some("NEG")
+        );
+        assert!(grm.prods[grm.rules["B"].pidxs[0]].symbols.is_empty());
+        assert_eq!(
+            grm.programs,
+            Some("use std::fmt;\nfn f() {}\n          ".to_string())
+        );
+
+        // Without a %union, type tags are Rust types.
+        let grm = parse(YaccKind::Bison, "%type <Result<u8, ()>> A\n%%\nA: ;").unwrap();
+        assert_eq!(grm.rules["A"].actiont, Some("Result<u8, ()>".to_string()));
+
+        // Bison declarations are only supported in Bison grammars.
+        let src = "%union { a: u8; }\n%%\nA: ;";
+        match parse(YaccKind::Grmtools, src) {
+            Ok(_) => panic!("%union parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::UnknownDeclaration,
+                line: 1,
+                col: 1,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+    }
+
+    #[test]
+    fn test_bison_errors() {
+        let src = "%union { a: u8; }\n%union { b: u8; }\n%%\nA: ;";
+        match parse(YaccKind::Bison, src) {
+            Ok(_) => panic!("Duplicate %union parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::DuplicateUnionDeclaration,
+                line: 2,
+                col: 1,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+
+        let src = "%union { int a; }\n%%\nA: ;";
+        match parse(YaccKind::Bison, src) {
+            Ok(_) => panic!("Invalid %union field parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::InvalidUnionField,
+                line: 1,
+                col: 8,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+
+        let src = "%union { a: u8; }\n%type <b> A\n%%\nA: ;";
+        match parse(YaccKind::Bison, src) {
+            Ok(_) => panic!("Unknown type tag parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::UnknownTypeTag,
+                line: 2,
+                col: 7,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+
+        let src = "%type A\n%%\nA: ;";
+        match parse(YaccKind::Bison, src) {
+            Ok(_) => panic!("Missing type tag parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::MissingTypeTag,
+                line: 1,
+                col: 7,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+
+        let src = "%code requires\n%%\nA: ;";
+        match parse(YaccKind::Bison, src) {
+            Ok(_) => panic!("%code without a block parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::MissingBrace,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+    }
+
+    #[test]
+    fn test_bison_default_actions() {
+        let src = "
+          %type <u64> A B
+          %type <String> C
+          %%
+          A: B | C | B 'x' | 'y' | A '+' A { $1 + $3 };
+          B: A;
+          C: ;
+          D: A 'z' | ;
+          ";
+        let grm = parse(YaccKind::Bison, src).unwrap();
+        let action = |r: &str, i: usize| {
+            grm.prods[grm.rules[r].pidxs[i]]
+                .action
+                .as_ref()
+                .map(|(a, _)| a.as_str())
+        };
+        // `$$ = $1` when the first symbol has the rule's type...
+        assert_eq!(action("A", 0), Some("$1"));
+        assert_eq!(action("A", 2), Some("$1"));
+        assert_eq!(action("B", 0), Some("$1"));
+        // ...but not otherwise.
+        assert_eq!(action("A", 1), None);
+        assert_eq!(action("A", 3), None);
+        assert_eq!(action("C", 0), None);
+        // Explicit actions are left alone.
+        assert_eq!(action("A", 4), Some("$1 + $3"));
+        // Rules without a value default to `()`.
+        assert_eq!(action("D", 0), Some("()"));
+        assert_eq!(action("D", 1), Some("()"));
+
+        // Other Yacc variants don't have default actions.
+        let grm = parse(YaccKind::Grmtools, "%%\nA -> (): B;\nB -> (): ;").unwrap();
+        assert!(grm.prods.iter().all(|p| p.action.is_none()));
+    }
+
+    #[test]
+    fn test_labels() {
+        let src = "
+          %%
+          A -> u64: lhs=A '+' rhs = B { 0 } | b='b' { 1 };
+          B -> u64: 'b' { 2 };
+          ";
+        let grm = parse(YaccKind::Grmtools, src).unwrap();
+        let pidxs = &grm.rules["A"].pidxs;
+        assert_eq!(
+            grm.prods[pidxs[0]].labels,
+            vec![(0, "lhs".to_string()), (2, "rhs".to_string())]
+        );
+        assert_eq!(
+            grm.prods[pidxs[0]].symbols,
+            vec![rule("A"), token("+"), rule("B")]
+        );
+        assert_eq!(grm.prods[pidxs[1]].labels, vec![(0, "b".to_string())]);
+        assert!(grm.prods[grm.rules["B"].pidxs[0]].labels.is_empty());
+
+        let src = "%%\nA: x=B x='a';\nB: ;";
+        match parse(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        ) {
+            Ok(_) => panic!("Duplicate label parsed"),
+            Err(YaccParserError {
+                kind: YaccParserErrorKind::DuplicateLabel,
+                line: 2,
+                col: 8,
+                ..
+            }) => (),
+            Err(e) => panic!("Incorrect error returned {}", e),
+        }
+    }
+
+    #[test]
+    fn test_templates() {
+        let src = "
+          %start L
+          %token INT
+          %%
+          L -> Vec<u64>: SepList<',', N> { $1 };
+          N -> u64: INT { 0 };
+          SepList<Sep, X> -> Vec<$X>:
+                X { vec![$1] }
+              | SepList<Sep, X> Sep X { $1.push($3); $1 }
+              ;
+          P -> Vec<Vec<u64>>: SepList<';', SepList < ',' , N >> { $1 };
+          ";
+        let grm = parse(YaccKind::Grmtools, src).unwrap();
+        assert_eq!(grm.start.as_ref().unwrap().0, "L");
+        assert!(grm.get_rule("SepList").is_none());
+        assert_eq!(
+            grm.prods[grm.rules["L"].pidxs[0]].symbols,
+            vec![rule("__SepList_inst0")]
+        );
+        let inst0 = &grm.rules["__SepList_inst0"];
+        assert_eq!(inst0.actiont, Some("Vec<u64>".to_string()));
+        assert_eq!(inst0.pidxs.len(), 2);
+        assert_eq!(grm.prods[inst0.pidxs[0]].symbols, vec![rule("N")]);
+        assert_eq!(
+            grm.prods[inst0.pidxs[1]].symbols,
+            vec![rule("__SepList_inst0"), token(","), rule("N")]
+        );
+        assert_eq!(
+            grm.prods[inst0.pidxs[1]].action.as_ref().unwrap().0,
+            "$1.push($3); $1"
+        );
+        // The nested instantiation `SepList<',', N>` reuses `__SepList_inst0`.
+        let inst1 = &grm.rules["__SepList_inst1"];
+        assert_eq!(inst1.actiont, Some("Vec<Vec<u64>>".to_string()));
+        assert_eq!(
+            grm.prods[inst1.pidxs[1]].symbols,
+            vec![rule("__SepList_inst1"), token(";"), rule("__SepList_inst0")]
+        );
+        assert_eq!(grm.rules.len(), 5);
+    }
+
+    #[test]
+    fn test_template_errors() {
+        fn check(src: &str, kind: YaccParserErrorKind, line: usize, col: usize) {
+            match parse(YaccKind::Grmtools, src) {
+                Ok(_) => panic!("Invalid template parsed"),
+                Err(e) => {
+                    if std::mem::discriminant(&e.kind) != std::mem::discriminant(&kind)
+                        || e.line != line
+                        || e.col != col
+                    {
+                        panic!("Incorrect error returned {}", e)
+                    }
+                }
+            }
+        }
+        check(
+            "%%\nA -> (): T<'a'> { () };",
+            YaccParserErrorKind::UnknownTemplate,
+            2,
+            10,
+        );
+        check(
+            "%%\nA -> (): T<'a', 'b'> { () };\nT<X> -> (): X { () };",
+            YaccParserErrorKind::TemplateArgCount,
+            2,
+            10,
+        );
+        check(
+            "%%\nT<X, X> -> (): X { () };",
+            YaccParserErrorKind::DuplicateTemplateParam,
+            2,
+            6,
+        );
+        check(
+            "%%\nA -> (): T<'a' { () };\nT<X> -> (): X { () };",
+            YaccParserErrorKind::MissingRightAngle,
+            2,
+            16,
+        );
+        check(
+            "%%\nA -> (): T<'a'> { () };\nT<X> -> (): X T<W<X>> { () };\nW<X> -> (): X { () };",
+            YaccParserErrorKind::TemplateTooDeep,
+            3,
+            17,
+        );
+        check(
+            "%%\nA -> (): 'a' { () };\nA<X> -> (): X { () };",
+            YaccParserErrorKind::DuplicateRule,
+            3,
+            1,
+        );
+    }
 }
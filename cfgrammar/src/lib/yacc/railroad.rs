@@ -0,0 +1,508 @@
+//! Render a grammar's rules as railroad (syntax) diagrams.
+//!
+//! [`rule_svg`](fn.rule_svg.html) renders a single rule as a self-contained SVG image;
+//! [`html`](fn.html.html) renders every rule in a grammar as a self-contained HTML page, with
+//! references to rules linked to those rules' diagrams. Tokens are labelled with their `%epp`
+//! values (or their names if they have no `%epp` value). Left recursive rules of the form `A: A α
+//! | β` are drawn as `β` followed by a loop over `α` (and those of the form `A: A sep β | β` as a
+//! loop over `β` whose return path passes through `sep`), rather than as references to
+//! themselves.
+
+use std::fmt::Write;
+
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+
+use super::YaccGrammar;
+use crate::{RIdx, Symbol};
+
+/// The radius of the arcs joining lines.
+const ARC: i64 = 10;
+/// The minimum vertical separation between the alternatives of a choice.
+const VSEP: i64 = 8;
+/// The horizontal separation between the elements of a sequence.
+const HSEP: i64 = 10;
+/// The height of the boxes around tokens and rule references.
+const BOX_HEIGHT: i64 = 22;
+/// The width of a single character in a box: the CSS uses a monospace font to make this accurate.
+const CHAR_WIDTH: i64 = 9;
+/// The horizontal padding between a box's edges and its text.
+const BOX_PADDING: i64 = 10;
+/// The padding around the edge of a diagram.
+const PADDING: i64 = 20;
+
+const CSS: &str = "svg.railroad { background-color: white; }
+svg.railroad path { stroke-width: 2; stroke: black; fill: none; }
+svg.railroad rect { stroke-width: 2; stroke: black; fill: #ffffe0; }
+svg.railroad rect.rule { fill: #e0f0ff; }
+svg.railroad text { font: 14px monospace; text-anchor: middle; }
+svg.railroad a text { fill: #0000c0; }";
+
+/// Return a self-contained SVG image of rule `ridx`'s railroad diagram.
+pub fn rule_svg<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    ridx: RIdx<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    render_svg(&rule_node(grm, ridx), Some(CSS), false)
+}
+
+/// Return a self-contained HTML page containing the railroad diagram of every rule in `grm`
+/// (other than the rules grmtools adds internally). Each rule's diagram is preceded by a heading
+/// with the anchor `rule-{name}`, to which references to that rule in other diagrams link.
+pub fn html<StorageT: 'static + PrimInt + Unsigned>(grm: &YaccGrammar<StorageT>) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let mut s = String::new();
+    s.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<style>\n");
+    s.push_str(CSS);
+    s.push_str("\n</style>\n</head>\n<body>\n");
+    for ridx in grm.iter_rules() {
+        // Rules added by grmtools (e.g. the start rule) have no span.
+        if grm.rule_name_span(ridx).is_none() {
+            continue;
+        }
+        let name = escape(grm.rule_name(ridx));
+        writeln!(s, "<h2 id=\"rule-{}\">{}</h2>", name, name).ok();
+        s.push_str(&render_svg(&rule_node(grm, ridx), None, true));
+        s.push('\n');
+    }
+    s.push_str("</body>\n</html>\n");
+    s
+}
+
+/// An element of a railroad diagram. Every element has a single entry (on its left) and a single
+/// exit (on its right), both on the same horizontal line.
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Token(String),
+    Rule(String),
+    /// A horizontal line.
+    Skip,
+    Sequence(Vec<Node>),
+    /// A choice between alternatives: the first is on the main line, with the others beneath it.
+    Choice(Vec<Node>),
+    /// `Loop(body, sep)` passes through `body` one or more times, passing through `sep` (which is
+    /// drawn beneath `body`) between each iteration.
+    Loop(Box<Node>, Box<Node>),
+}
+
+impl Node {
+    fn sequence(mut nodes: Vec<Node>) -> Node {
+        nodes.retain(|n| *n != Node::Skip);
+        match nodes.len() {
+            0 => Node::Skip,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Sequence(nodes),
+        }
+    }
+
+    fn choice(mut nodes: Vec<Node>) -> Node {
+        let mut i = 1;
+        while i < nodes.len() {
+            if nodes[..i].contains(&nodes[i]) {
+                nodes.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+        match nodes.len() {
+            0 => Node::Skip,
+            1 => nodes.pop().unwrap(),
+            _ => Node::Choice(nodes),
+        }
+    }
+
+    /// The horizontal extent of this node.
+    fn width(&self) -> i64 {
+        match self {
+            Node::Token(s) | Node::Rule(s) => {
+                s.chars().count() as i64 * CHAR_WIDTH + 2 * BOX_PADDING
+            }
+            Node::Skip => 0,
+            Node::Sequence(nodes) => {
+                nodes.iter().map(|n| n.width()).sum::<i64>() + HSEP * (nodes.len() as i64 - 1)
+            }
+            Node::Choice(nodes) => nodes.iter().map(|n| n.width()).max().unwrap() + 4 * ARC,
+            Node::Loop(body, sep) => body.width().max(sep.width()) + 2 * ARC,
+        }
+    }
+
+    /// The vertical extent of this node above its entry/exit line.
+    fn up(&self) -> i64 {
+        match self {
+            Node::Token(_) | Node::Rule(_) => BOX_HEIGHT / 2,
+            Node::Skip => 0,
+            Node::Sequence(nodes) => nodes.iter().map(|n| n.up()).max().unwrap(),
+            Node::Choice(nodes) => nodes[0].up(),
+            Node::Loop(body, _) => body.up(),
+        }
+    }
+
+    /// The vertical extent of this node below its entry/exit line.
+    fn down(&self) -> i64 {
+        match self {
+            Node::Token(_) | Node::Rule(_) => BOX_HEIGHT / 2,
+            Node::Skip => 0,
+            Node::Sequence(nodes) => nodes.iter().map(|n| n.down()).max().unwrap(),
+            Node::Choice(nodes) => {
+                let offs = choice_offsets(nodes);
+                offs.last().unwrap() + nodes.last().unwrap().down()
+            }
+            Node::Loop(body, sep) => loop_offset(body, sep) + sep.down(),
+        }
+    }
+
+    /// Render this node with its entry at (`x`, `y`), appending the SVG to `s`. If `links` is
+    /// true, references to rules link to the anchor `#rule-{name}`.
+    fn render(&self, x: i64, y: i64, links: bool, s: &mut String) {
+        let w = self.width();
+        match self {
+            Node::Token(t) | Node::Rule(t) => {
+                let is_rule = matches!(self, Node::Rule(_));
+                if is_rule && links {
+                    write!(s, "<a href=\"#rule-{}\">", escape(t)).ok();
+                }
+                write!(
+                    s,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+                    x,
+                    y - BOX_HEIGHT / 2,
+                    w,
+                    BOX_HEIGHT
+                )
+                .ok();
+                if is_rule {
+                    s.push_str(" class=\"rule\"/>");
+                } else {
+                    write!(s, " rx=\"{}\" ry=\"{}\"/>", ARC, ARC).ok();
+                }
+                write!(
+                    s,
+                    "<text x=\"{}\" y=\"{}\">{}</text>",
+                    x + w / 2,
+                    y + 5,
+                    escape(t)
+                )
+                .ok();
+                if is_rule && links {
+                    s.push_str("</a>");
+                }
+            }
+            Node::Skip => (),
+            Node::Sequence(nodes) => {
+                let mut cx = x;
+                for (i, n) in nodes.iter().enumerate() {
+                    if i > 0 {
+                        line(cx, y, cx + HSEP, s);
+                        cx += HSEP;
+                    }
+                    n.render(cx, y, links, s);
+                    cx += n.width();
+                }
+            }
+            Node::Choice(nodes) => {
+                for (n, off) in nodes.iter().zip(choice_offsets(nodes)) {
+                    let ny = y + off;
+                    if off == 0 {
+                        line(x, y, x + 2 * ARC, s);
+                    } else {
+                        write!(
+                            s,
+                            "<path d=\"M{} {} a{a} {a} 0 0 1 {a} {a} v{} a{a} {a} 0 0 0 {a} {a}\"/>",
+                            x,
+                            y,
+                            off - 2 * ARC,
+                            a = ARC
+                        )
+                        .ok();
+                    }
+                    n.render(x + 2 * ARC, ny, links, s);
+                    if off == 0 {
+                        line(x + 2 * ARC + n.width(), y, x + w, s);
+                    } else {
+                        write!(
+                            s,
+                            "<path d=\"M{} {} H{} a{a} {a} 0 0 0 {a} -{a} v-{} a{a} {a} 0 0 1 {a} -{a}\"/>",
+                            x + 2 * ARC + n.width(),
+                            ny,
+                            x + w - 2 * ARC,
+                            off - 2 * ARC,
+                            a = ARC
+                        )
+                        .ok();
+                    }
+                }
+            }
+            Node::Loop(body, sep) => {
+                let off = loop_offset(body, sep);
+                line(x, y, x + ARC, s);
+                body.render(x + ARC, y, links, s);
+                line(x + ARC + body.width(), y, x + w, s);
+                write!(
+                    s,
+                    "<path d=\"M{} {} a{a} {a} 0 0 1 {a} {a} v{} a{a} {a} 0 0 1 -{a} {a} H{}\"/>",
+                    x + w - ARC,
+                    y,
+                    off - 2 * ARC,
+                    x + ARC + sep.width(),
+                    a = ARC
+                )
+                .ok();
+                sep.render(x + ARC, y + off, links, s);
+                write!(
+                    s,
+                    "<path d=\"M{} {} a{a} {a} 0 0 1 -{a} -{a} v-{} a{a} {a} 0 0 1 {a} -{a}\"/>",
+                    x + ARC,
+                    y + off,
+                    off - 2 * ARC,
+                    a = ARC
+                )
+                .ok();
+            }
+        }
+    }
+}
+
+/// The vertical offset of each of a choice's alternatives from the choice's entry/exit line.
+fn choice_offsets(nodes: &[Node]) -> Vec<i64> {
+    let mut offs = vec![0];
+    for w in nodes.windows(2) {
+        let prev = *offs.last().unwrap();
+        offs.push(prev + (w[0].down() + VSEP + w[1].up()).max(2 * ARC));
+    }
+    offs
+}
+
+/// The vertical offset of a loop's separator from the loop's entry/exit line.
+fn loop_offset(body: &Node, sep: &Node) -> i64 {
+    (body.down() + VSEP + sep.up()).max(2 * ARC)
+}
+
+fn line(x1: i64, y: i64, x2: i64, s: &mut String) {
+    if x1 != x2 {
+        write!(s, "<path d=\"M{} {} H{}\"/>", x1, y, x2).ok();
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Render `node` as a complete SVG image, with start and end markers. If `css` is not `None`, it
+/// is embedded in the image.
+fn render_svg(node: &Node, css: Option<&str>, links: bool) -> String {
+    let width = node.width() + 2 * PADDING + 2 * HSEP;
+    let height = node.up() + node.down() + 2 * PADDING;
+    let y = PADDING + node.up();
+    let mut s = String::new();
+    write!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"railroad\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">",
+        w = width,
+        h = height
+    )
+    .ok();
+    if let Some(css) = css {
+        write!(s, "<style>{}</style>", css).ok();
+    }
+    // The start and end markers are short vertical bars.
+    let end = PADDING + HSEP + node.width();
+    write!(
+        s,
+        "<path d=\"M{} {} v{} M{} {} v{}\"/>",
+        PADDING,
+        y - ARC,
+        2 * ARC,
+        end + HSEP,
+        y - ARC,
+        2 * ARC
+    )
+    .ok();
+    line(PADDING, y, PADDING + HSEP, &mut s);
+    node.render(PADDING + HSEP, y, links, &mut s);
+    line(end, y, end + HSEP, &mut s);
+    s.push_str("</svg>");
+    s
+}
+
+/// Build the diagram for rule `ridx`.
+fn rule_node<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    ridx: RIdx<StorageT>,
+) -> Node
+where
+    usize: AsPrimitive<StorageT>,
+{
+    // Eco grammars reference their implicit rule throughout, which would only clutter diagrams.
+    let prods = grm
+        .rule_to_prods(ridx)
+        .iter()
+        .map(|&pidx| {
+            grm.prod(pidx)
+                .iter()
+                .filter(|sym| match (sym, grm.implicit_rule()) {
+                    (Symbol::Rule(r), Some(ir)) => *r != ir,
+                    _ => true,
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let seq = |syms: &[Symbol<StorageT>]| {
+        Node::sequence(
+            syms.iter()
+                .map(|sym| match *sym {
+                    Symbol::Rule(r) => Node::Rule(grm.rule_name(r).to_owned()),
+                    Symbol::Token(tidx) => Node::Token(
+                        grm.token_epp(tidx)
+                            .or_else(|| grm.token_name(tidx))
+                            .unwrap_or("")
+                            .to_owned(),
+                    ),
+                })
+                .collect(),
+        )
+    };
+
+    // Split the productions into directly left recursive productions `A: A α` (for which we
+    // record `α`) and the rest. `A: A` derives nothing new, so we drop it.
+    let mut tails = Vec::new();
+    let mut bases = Vec::new();
+    for p in &prods {
+        match p.split_first() {
+            Some((Symbol::Rule(r), tail)) if *r == ridx => {
+                if !tail.is_empty() {
+                    tails.push(tail);
+                }
+            }
+            _ => bases.push(p.as_slice()),
+        }
+    }
+    if tails.is_empty() || bases.is_empty() {
+        return Node::choice(prods.iter().map(|p| seq(p)).collect());
+    }
+    if bases.len() == 1 && tails.iter().all(|t| t.ends_with(bases[0])) {
+        // `A: A sep β | β` is a list of `β`s separated by `sep`s.
+        let base = bases[0];
+        let seps = tails
+            .iter()
+            .map(|t| seq(&t[..t.len() - base.len()]))
+            .collect();
+        return Node::Loop(Box::new(seq(base)), Box::new(Node::choice(seps)));
+    }
+    // `A: A α | β` is a `β` followed by zero or more `α`s.
+    Node::sequence(vec![
+        Node::choice(bases.iter().map(|p| seq(p)).collect()),
+        Node::choice(vec![
+            Node::Skip,
+            Node::Loop(
+                Box::new(Node::choice(tails.iter().map(|t| seq(t)).collect())),
+                Box::new(Node::Skip),
+            ),
+        ]),
+    ])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::{YaccGrammar, YaccKind, YaccOriginalActionKind},
+        html, rule_node, rule_svg, Node,
+    };
+
+    fn grm(src: &str) -> YaccGrammar {
+        YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap()
+    }
+
+    fn tok(s: &str) -> Node {
+        Node::Token(s.to_owned())
+    }
+
+    fn rule(s: &str) -> Node {
+        Node::Rule(s.to_owned())
+    }
+
+    #[test]
+    fn test_rule_node() {
+        let grm = grm("%token INT
+%epp INT 'integer'
+%%
+S: A 'x' | 'y' | ;
+A: INT;
+");
+        assert_eq!(
+            rule_node(&grm, grm.rule_idx("S").unwrap()),
+            Node::Choice(vec![
+                Node::Sequence(vec![rule("A"), tok("x")]),
+                tok("y"),
+                Node::Skip
+            ])
+        );
+        assert_eq!(rule_node(&grm, grm.rule_idx("A").unwrap()), tok("integer"));
+    }
+
+    #[test]
+    fn test_left_recursion() {
+        let grm = grm("%%
+Expr: Expr '+' Term | Expr '-' Term | Term | '-' Term;
+Term: 'x';
+List: List ',' Item | Item;
+Items: Items Item | ;
+Item: 'i';
+");
+        assert_eq!(
+            rule_node(&grm, grm.rule_idx("Expr").unwrap()),
+            Node::Sequence(vec![
+                Node::Choice(vec![
+                    rule("Term"),
+                    Node::Sequence(vec![tok("-"), rule("Term")])
+                ]),
+                Node::Choice(vec![
+                    Node::Skip,
+                    Node::Loop(
+                        Box::new(Node::Choice(vec![
+                            Node::Sequence(vec![tok("+"), rule("Term")]),
+                            Node::Sequence(vec![tok("-"), rule("Term")]),
+                        ])),
+                        Box::new(Node::Skip)
+                    )
+                ])
+            ])
+        );
+        assert_eq!(
+            rule_node(&grm, grm.rule_idx("List").unwrap()),
+            Node::Loop(Box::new(rule("Item")), Box::new(tok(",")))
+        );
+        assert_eq!(
+            rule_node(&grm, grm.rule_idx("Items").unwrap()),
+            Node::Loop(Box::new(Node::Skip), Box::new(rule("Item")))
+        );
+    }
+
+    #[test]
+    fn test_svg() {
+        let grm = grm("%%\nS: 'a' S | '<b>';");
+        let svg = rule_svg(&grm, grm.rule_idx("S").unwrap());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.contains("<style>"));
+        assert!(svg.contains(">&lt;b&gt;</text>"));
+        assert!(!svg.contains("<a href"));
+        assert!(svg.ends_with("</svg>"));
+
+        let html = html(&grm);
+        assert!(html.contains("<h2 id=\"rule-S\">S</h2>"));
+        assert!(html.contains("<a href=\"#rule-S\">"));
+        // Only the user's rules are rendered, not the start rule grmtools adds.
+        assert_eq!(html.matches("<svg").count(), 1);
+    }
+}
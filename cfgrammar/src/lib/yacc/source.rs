@@ -0,0 +1,318 @@
+//! Grammars split across multiple files.
+//!
+//! A line of the form `%include "path"` (with optional surrounding whitespace) anywhere in a
+//! grammar file is replaced with the contents of the file at `path` (which, if relative, is
+//! relative to the directory of the file containing the `%include`). Included files can
+//! themselves contain `%include`s. Since inclusion is purely textual, an included file can contain
+//! declarations, rules, or both, depending on where it is included.
+
+use std::{
+    error::Error,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::Span;
+
+lazy_static! {
+    static ref RE_INCLUDE: Regex = Regex::new(r#"^\s*%include\s+"([^"]+)"\s*$"#).unwrap();
+}
+
+/// A grammar read from a file, with all `%include`s expanded.
+#[derive(Debug)]
+pub struct YaccSource {
+    src: String,
+    /// Every file read, starting with the file originally passed to `read`.
+    paths: Vec<PathBuf>,
+    /// For each contiguous run of lines in `src` which came from a single file: the (zero-based)
+    /// line in `src` the run starts at, the index into `paths` of the file it came from, and the
+    /// (zero-based) line in that file it starts at. Sorted by the first element.
+    segments: Vec<(usize, usize, usize)>,
+}
+
+/// The errors that can occur when reading a grammar with `YaccSource::read`.
+#[derive(Debug)]
+pub enum YaccSourceError {
+    /// A file couldn't be read. `included_at` is the file and line of the `%include` which
+    /// referenced it (or `None` if it is the file passed to `read`).
+    Io {
+        path: PathBuf,
+        included_at: Option<(PathBuf, usize)>,
+        err: io::Error,
+    },
+    /// A file (directly or indirectly) includes itself.
+    RecursiveInclude {
+        path: PathBuf,
+        included_at: (PathBuf, usize),
+    },
+}
+
+impl Error for YaccSourceError {}
+
+impl fmt::Display for YaccSourceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            YaccSourceError::Io {
+                path,
+                included_at,
+                err,
+            } => {
+                write!(f, "Can't read {}", path.display())?;
+                if let Some((p, line)) = included_at {
+                    write!(f, " (included in {} at line {})", p.display(), line)?;
+                }
+                write!(f, ": {}", err)
+            }
+            YaccSourceError::RecursiveInclude {
+                path,
+                included_at: (p, line),
+            } => write!(
+                f,
+                "{} includes itself (included in {} at line {})",
+                path.display(),
+                p.display(),
+                line
+            ),
+        }
+    }
+}
+
+impl YaccSource {
+    /// Read the grammar in the file at `path`, expanding `%include`s.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self, YaccSourceError> {
+        let mut source = YaccSource {
+            src: String::new(),
+            paths: Vec::new(),
+            segments: Vec::new(),
+        };
+        source.include(path.as_ref(), None, &mut Vec::new())?;
+        Ok(source)
+    }
+
+    /// Append the contents of the file at `path` to `self.src`. `stack` contains the canonical
+    /// paths of the files currently being included, so that recursive includes can be detected.
+    fn include(
+        &mut self,
+        path: &Path,
+        included_at: Option<(PathBuf, usize)>,
+        stack: &mut Vec<PathBuf>,
+    ) -> Result<(), YaccSourceError> {
+        let io_err = |err| YaccSourceError::Io {
+            path: path.to_owned(),
+            included_at: included_at.clone(),
+            err,
+        };
+        let canonical = fs::canonicalize(path).map_err(io_err)?;
+        if stack.contains(&canonical) {
+            return Err(YaccSourceError::RecursiveInclude {
+                path: path.to_owned(),
+                included_at: included_at.unwrap(),
+            });
+        }
+        let contents = fs::read_to_string(path).map_err(io_err)?;
+        let pidx = self.paths.len();
+        self.paths.push(path.to_owned());
+        stack.push(canonical);
+        let mut new_segment = true;
+        for (i, line) in contents.lines().enumerate() {
+            if let Some(caps) = RE_INCLUDE.captures(line) {
+                let inc_path = path
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(caps.get(1).unwrap().as_str());
+                self.include(&inc_path, Some((path.to_owned(), i + 1)), stack)?;
+                new_segment = true;
+                continue;
+            }
+            if new_segment {
+                self.segments
+                    .push((self.src.matches('\n').count(), pidx, i));
+                new_segment = false;
+            }
+            self.src.push_str(line);
+            self.src.push('\n');
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    /// The grammar source, with all `%include`s expanded.
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// The paths of every file read, starting with the file passed to `read`.
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Map the (one-based) line `line` in `src()` to the file it came from and its (one-based)
+    /// line in that file. The file is `None` if it is the file passed to `read`.
+    pub fn origin(&self, line: usize) -> (Option<&Path>, usize) {
+        let i = match self.segments.binary_search_by(|s| s.0.cmp(&(line - 1))) {
+            Ok(i) => i,
+            Err(0) => return (None, line),
+            Err(i) => i - 1,
+        };
+        let (src_line, pidx, file_line) = self.segments[i];
+        let path = if pidx == 0 {
+            None
+        } else {
+            Some(self.paths[pidx].as_path())
+        };
+        (path, file_line + line - src_line)
+    }
+
+    /// Return a description of where `span` (an offset into `src()`) is, of the form `at line L
+    /// column C`, preceded by `in F` if it is in an included file `F`.
+    pub fn pp_span(&self, span: Span) -> String {
+        let (line, col) = span.line_col(&self.src);
+        pp_location(self.origin(line), col)
+    }
+}
+
+/// Format the location `line` and `col` in `path` (where `None` means the grammar file itself).
+pub(crate) fn pp_location((path, line): (Option<&Path>, usize), col: usize) -> String {
+    match path {
+        Some(p) => format!("in {} at line {} column {}", p.display(), line, col),
+        None => format!("at line {} column {}", line, col),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::{YaccGrammar, YaccKind, YaccOriginalActionKind},
+        YaccSource, YaccSourceError,
+    };
+    use crate::Span;
+    use std::{env, fs, path::Path, process};
+
+    #[test]
+    fn test_include() {
+        let dir = env::temp_dir().join(format!("cfgrammar_include_{}", process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(
+            dir.join("main.y"),
+            "%start S\n%include \"sub/toks.y\"\n%%\nS: A;\n  %include \"sub/rules.y\"  \n",
+        )
+        .unwrap();
+        fs::write(dir.join("sub/toks.y"), "%token a\n%token b").unwrap();
+        fs::write(dir.join("sub/rules.y"), "%include \"more.y\"\nA: 'a';\n").unwrap();
+        fs::write(dir.join("sub/more.y"), "B: 'b';\n").unwrap();
+
+        let source = YaccSource::read(dir.join("main.y")).unwrap();
+        assert_eq!(
+            source.src(),
+            "%start S\n%token a\n%token b\n%%\nS: A;\nB: 'b';\nA: 'a';\n"
+        );
+        assert_eq!(
+            source.paths(),
+            &[
+                dir.join("main.y"),
+                dir.join("sub/toks.y"),
+                dir.join("sub/rules.y"),
+                dir.join("sub/more.y")
+            ]
+        );
+        assert_eq!(source.origin(1), (None, 1));
+        assert_eq!(
+            source.origin(3),
+            (Some(dir.join("sub/toks.y").as_path()), 2)
+        );
+        assert_eq!(source.origin(5), (None, 4));
+        assert_eq!(
+            source.origin(6),
+            (Some(dir.join("sub/more.y").as_path()), 1)
+        );
+        assert_eq!(
+            source.origin(7),
+            (Some(dir.join("sub/rules.y").as_path()), 2)
+        );
+        assert_eq!(
+            source.pp_span(Span::new(48, 49)),
+            format!(
+                "in {} at line 2 column 5",
+                dir.join("sub/rules.y").display()
+            )
+        );
+
+        fs::write(dir.join("sub/more.y"), "%include \"rules.y\"\n").unwrap();
+        match YaccSource::read(dir.join("main.y")) {
+            Err(YaccSourceError::RecursiveInclude { path, included_at }) => {
+                assert_eq!(path, dir.join("sub/rules.y"));
+                assert_eq!(included_at, (dir.join("sub/more.y"), 1));
+            }
+            _ => panic!(),
+        }
+
+        fs::write(dir.join("sub/more.y"), "%include \"missing.y\"\n").unwrap();
+        match YaccSource::read(dir.join("main.y")) {
+            Err(YaccSourceError::Io {
+                path, included_at, ..
+            }) => {
+                assert_eq!(path, dir.join("sub/missing.y"));
+                assert_eq!(included_at, Some((dir.join("sub/more.y"), 1)));
+            }
+            _ => panic!(),
+        }
+        match YaccSource::read(Path::new(&dir).join("nonexistent.y")) {
+            Err(YaccSourceError::Io { included_at, .. }) => assert_eq!(included_at, None),
+            _ => panic!(),
+        }
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_from_source_errors() {
+        let dir = env::temp_dir().join(format!("cfgrammar_include_errs_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.y"),
+            "%start S\n%%\nS: A;\n%include \"rules.y\"\nC: D;\n",
+        )
+        .unwrap();
+        fs::write(dir.join("rules.y"), "A: 'a'\n  | B;\n").unwrap();
+        let source = YaccSource::read(dir.join("main.y")).unwrap();
+        match YaccGrammar::<u32>::from_source(YaccKind::Grmtools, &source) {
+            Ok(_) => panic!(),
+            Err(errs) => {
+                let errs = errs.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                assert_eq!(
+                    errs[..3],
+                    vec![
+                        "Missing '->' at line 3 column 2".to_owned(),
+                        format!(
+                            "Missing '->' in {} at line 1 column 2",
+                            dir.join("rules.y").display()
+                        ),
+                        "Missing '->' at line 5 column 2".to_owned(),
+                    ]
+                );
+            }
+        }
+        fs::write(
+            dir.join("main.y"),
+            "%start S\n%token a\n%%\nS: A;\n%include \"rules.y\"\n",
+        )
+        .unwrap();
+        let source = YaccSource::read(dir.join("main.y")).unwrap();
+        match YaccGrammar::<u32>::from_source(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            &source,
+        ) {
+            Ok(_) => panic!(),
+            Err(errs) => assert_eq!(
+                errs[0].to_string(),
+                format!(
+                    "Unknown reference to rule 'B' in {} at line 2 column 5",
+                    dir.join("rules.y").display()
+                )
+            ),
+        }
+        fs::remove_dir_all(dir).ok();
+    }
+}
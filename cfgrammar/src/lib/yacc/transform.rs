@@ -0,0 +1,768 @@
+//! Standard context-free grammar transformations over `GrammarAST`s.
+//!
+//! Each transformation takes a grammar and returns a rewritten copy of it (leaving the original
+//! untouched) as a [`Transformed`](struct.Transformed.html), which also maps each of the new
+//! grammar's productions back to the productions in the original grammar it was derived from.
+//! Transformations can be chained by passing one's output grammar to the next, although note that
+//! each mapping is relative to its own input grammar.
+//!
+//! Declarations (e.g. tokens, precedences, and `%epp`) are copied unchanged. Productions which are
+//! copied unchanged keep their actions and labels, but since actions refer to symbols by position,
+//! rewritten productions have no action: the mapping can be used to relate them back to the
+//! original actions.
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt, iter,
+};
+
+use indexmap::IndexMap;
+
+use super::ast::{
+    productive_rules, reachable_rules, rules_fixpoint, GrammarAST, Production, Symbol,
+};
+use crate::Span;
+
+/// The largest number of nullable symbols a production can reference in `remove_epsilons`, which
+/// adds a variant of each production for every subset of the nullable symbols it references.
+pub const MAX_NULLABLE_SYMBOLS: usize = 16;
+
+/// The various different possible transformation errors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransformErrorKind {
+    /// A production references more than `MAX_NULLABLE_SYMBOLS` nullable symbols (the number it
+    /// references is given), so `remove_epsilons` would have to add too many variants of it.
+    TooManyNullableSymbols(usize),
+}
+
+/// Any error from a transformation returns an instance of this struct.
+#[derive(Debug)]
+pub struct TransformError {
+    pub kind: TransformErrorKind,
+    /// The location of the production the error relates to in the grammar source.
+    pub span: Span,
+}
+
+impl Error for TransformError {}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            TransformErrorKind::TooManyNullableSymbols(n) => write!(
+                f,
+                "Production references {} nullable symbols (the maximum is {})",
+                n, MAX_NULLABLE_SYMBOLS
+            ),
+        }
+    }
+}
+
+/// The result of a transformation.
+pub struct Transformed {
+    /// The rewritten grammar.
+    pub ast: GrammarAST,
+    /// For each production in `ast.prods`, the indexes of the productions in the original
+    /// grammar's `prods` it was derived from: first the production it replaces (if any), followed
+    /// by any productions which were inlined into it. Productions with no counterpart in the
+    /// original grammar (e.g. the empty production of a rule introduced by
+    /// `eliminate_left_recursion`) map to an empty list.
+    pub prod_map: Vec<Vec<usize>>,
+}
+
+/// Remove rules which can't derive a finite sentence (unproductive rules) along with every
+/// production which references them, and then remove every rule which can't be reached from the
+/// start rules. If a start rule is itself unproductive, only unreachable rules are removed.
+pub fn remove_useless(ast: &GrammarAST) -> Transformed {
+    let mut grm = TGrammar::from_ast(ast);
+    let productive = grm.productive();
+    if grm.starts.iter().all(|(s, _)| productive.contains(s)) {
+        grm.rules.retain(|n, _| productive.contains(n));
+        for rule in grm.rules.values_mut() {
+            rule.prods.retain(|p| {
+                p.symbols.iter().all(|sym| match sym {
+                    Symbol::Rule(n, _) => productive.contains(n),
+                    Symbol::Token(_, _) => true,
+                })
+            });
+        }
+    }
+    let reachable = grm.reachable();
+    grm.rules.retain(|n, _| reachable.contains(n));
+    grm.into_transformed(ast)
+}
+
+/// Remove empty productions, adding to each production which references a rule that can derive
+/// the empty string (a nullable rule) a variant which omits that reference. Rules which can only
+/// derive the empty string are removed entirely. If a start rule is nullable, a new start rule is
+/// added in its place with two productions: one referencing the old start rule, and one empty.
+///
+/// Since a production referencing `n` nullable symbols can have up to `2^n` variants, an error is
+/// returned if any production references more than
+/// [`MAX_NULLABLE_SYMBOLS`](constant.MAX_NULLABLE_SYMBOLS.html) nullable symbols.
+pub fn remove_epsilons(ast: &GrammarAST) -> Result<Transformed, TransformError> {
+    let mut grm = TGrammar::from_ast(ast);
+    let nullable = grm.nullable();
+    for (n, rule) in &mut grm.rules {
+        let mut prods: Vec<TProd> = Vec::new();
+        for p in &rule.prods {
+            let opt = p
+                .symbols
+                .iter()
+                .enumerate()
+                .filter(|(_, sym)| match sym {
+                    Symbol::Rule(m, _) => nullable.contains(m),
+                    Symbol::Token(_, _) => false,
+                })
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            if opt.len() > MAX_NULLABLE_SYMBOLS {
+                return Err(TransformError {
+                    kind: TransformErrorKind::TooManyNullableSymbols(opt.len()),
+                    span: p.prod_span,
+                });
+            }
+            // Every subset of the nullable symbols can be omitted.
+            for mask in 0..1usize << opt.len() {
+                let symbols = p
+                    .symbols
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| match opt.iter().position(|j| j == i) {
+                        Some(k) => mask & (1 << k) == 0,
+                        None => true,
+                    })
+                    .map(|(_, sym)| sym.clone())
+                    .collect::<Vec<_>>();
+                if symbols.is_empty() || is_self_unit(n, &symbols) {
+                    continue;
+                }
+                let np = if mask == 0 {
+                    p.clone()
+                } else {
+                    p.rewrite(symbols, Vec::new())
+                };
+                if !prods.iter().any(|q| q.symbols == np.symbols) {
+                    prods.push(np);
+                }
+            }
+        }
+        rule.prods = prods;
+    }
+    // Rules which could only derive the empty string now have no productions: remove them, and
+    // any productions which reference them, until nothing changes.
+    loop {
+        let empty = grm
+            .rules
+            .iter()
+            .filter(|(_, r)| r.prods.is_empty())
+            .map(|(n, _)| n.clone())
+            .collect::<HashSet<_>>();
+        if empty.is_empty() {
+            break;
+        }
+        grm.rules.retain(|n, _| !empty.contains(n));
+        for rule in grm.rules.values_mut() {
+            rule.prods.retain(|p| {
+                !p.symbols.iter().any(|sym| match sym {
+                    Symbol::Rule(n, _) => empty.contains(n),
+                    Symbol::Token(_, _) => false,
+                })
+            });
+        }
+    }
+    for i in 0..grm.starts.len() {
+        let (old_start, old_span) = grm.starts[i].clone();
+        if !nullable.contains(&old_start) {
+            continue;
+        }
+        let name = grm.fresh_name(&old_start, "start");
+        let mut prods = Vec::new();
+        if let Some(rule) = grm.rules.get(&old_start) {
+            let span = rule.name_span;
+            prods.push(TProd::new(
+                vec![Symbol::Rule(old_start.clone(), span)],
+                span,
+            ));
+        }
+        prods.push(TProd::new(Vec::new(), Span::new(0, 0)));
+        let (span, actiont) = match grm.rules.get(&old_start) {
+            Some(r) => (r.name_span, r.actiont.clone()),
+            None => (old_span, None),
+        };
+        grm.rules.insert(
+            name.clone(),
+            TRule {
+                name_span: span,
+                actiont,
+                prods,
+            },
+        );
+        grm.starts[i].0 = name;
+    }
+    Ok(grm.into_transformed(ast))
+}
+
+/// Replace every unit production `A: B` (i.e. a production consisting solely of a reference to
+/// a rule) with copies of the non-unit productions of `B` (and, transitively, of any rules `B`
+/// has unit productions for). Rules which are no longer referenced are left in place: use
+/// `remove_useless` to remove them.
+pub fn inline_units(ast: &GrammarAST) -> Transformed {
+    let mut grm = TGrammar::from_ast(ast);
+    let old = grm.rules.clone();
+    for (n, rule) in &mut grm.rules {
+        let mut prods: Vec<TProd> = Vec::new();
+        // A depth-first search over unit productions: each entry is a rule and the origins of the
+        // unit productions used to reach it.
+        let mut todo = vec![(n.clone(), Vec::new())];
+        let mut seen = HashSet::new();
+        while let Some((m, origins)) = todo.pop() {
+            if !seen.insert(m.clone()) {
+                continue;
+            }
+            for p in &old[&m].prods {
+                match p.symbols.as_slice() {
+                    [Symbol::Rule(o, _)] => {
+                        let mut origins = origins.clone();
+                        origins.extend(p.origins.iter().cloned());
+                        todo.push((o.clone(), origins));
+                    }
+                    _ => {
+                        let np = if m == *n {
+                            p.clone()
+                        } else {
+                            let mut np = p.rewrite(p.symbols.clone(), Vec::new());
+                            np.origins = origins.iter().chain(&p.origins).cloned().collect();
+                            np
+                        };
+                        if !prods.iter().any(|q| q.symbols == np.symbols) {
+                            prods.push(np);
+                        }
+                    }
+                }
+            }
+        }
+        rule.prods = prods;
+    }
+    grm.into_transformed(ast)
+}
+
+/// Inline every rule (other than the start rule) which is referenced exactly once in the grammar
+/// and which doesn't reference itself: the production referencing it is replaced with one copy
+/// per production of the inlined rule, and the inlined rule is removed.
+pub fn inline_single_use(ast: &GrammarAST) -> Transformed {
+    let mut grm = TGrammar::from_ast(ast);
+    loop {
+        let mut uses = HashMap::new();
+        for rule in grm.rules.values() {
+            for p in &rule.prods {
+                for sym in &p.symbols {
+                    if let Symbol::Rule(n, _) = sym {
+                        *uses.entry(n.as_str()).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        let candidate = grm
+            .rules
+            .iter()
+            .find(|(n, rule)| {
+                !grm.starts.iter().any(|(s, _)| s == *n)
+                    && uses.get(n.as_str()) == Some(&1)
+                    && !rule.prods.iter().any(|p| {
+                        p.symbols
+                            .iter()
+                            .any(|sym| matches!(sym, Symbol::Rule(m, _) if m == *n))
+                    })
+            })
+            .map(|(n, _)| n.clone());
+        let n = match candidate {
+            Some(n) => n,
+            None => break,
+        };
+        let inlined = grm.rules.shift_remove(&n).unwrap();
+        for rule in grm.rules.values_mut() {
+            let mut prods = Vec::with_capacity(rule.prods.len());
+            for p in rule.prods.drain(..) {
+                let i = match p
+                    .symbols
+                    .iter()
+                    .position(|sym| matches!(sym, Symbol::Rule(m, _) if *m == n))
+                {
+                    Some(i) => i,
+                    None => {
+                        prods.push(p);
+                        continue;
+                    }
+                };
+                for q in &inlined.prods {
+                    let symbols = p.symbols[..i]
+                        .iter()
+                        .chain(&q.symbols)
+                        .chain(&p.symbols[i + 1..])
+                        .cloned()
+                        .collect();
+                    prods.push(p.rewrite(symbols, q.origins.clone()));
+                }
+            }
+            rule.prods = prods;
+        }
+    }
+    grm.into_transformed(ast)
+}
+
+/// Eliminate left recursion, both direct (`A: A 'a'`) and indirect (`A: B 'a'; B: A 'b'`), using
+/// the standard algorithm: each directly left recursive rule `A: A α | β` is rewritten as
+/// `A: β A_tail0; A_tail0: α A_tail0 | ;`. The algorithm is only guaranteed to remove all left
+/// recursion from grammars without empty productions or cycles of unit productions, so
+/// `remove_epsilons` and `inline_units` should generally be applied first. Rules whose every
+/// production is left recursive (which are unproductive) are left unchanged.
+pub fn eliminate_left_recursion(ast: &GrammarAST) -> Transformed {
+    let mut grm = TGrammar::from_ast(ast);
+    let order = grm.rules.keys().cloned().collect::<Vec<_>>();
+    for (i, ai) in order.iter().enumerate() {
+        // Substitute references to earlier rules at the start of `ai`'s productions, so that any
+        // indirect left recursion becomes direct.
+        for aj in &order[..i] {
+            let ajprods = grm.rules[aj].prods.clone();
+            let rule = &mut grm.rules[ai];
+            let mut prods = Vec::with_capacity(rule.prods.len());
+            for p in rule.prods.drain(..) {
+                match p.symbols.first() {
+                    Some(Symbol::Rule(n, _)) if n == aj => {
+                        for q in &ajprods {
+                            let symbols =
+                                q.symbols.iter().chain(&p.symbols[1..]).cloned().collect();
+                            prods.push(p.rewrite(symbols, q.origins.clone()));
+                        }
+                    }
+                    _ => prods.push(p),
+                }
+            }
+            rule.prods = prods;
+        }
+
+        let is_rec = |p: &TProd| matches!(p.symbols.first(), Some(Symbol::Rule(n, _)) if n == ai);
+        let rule = &grm.rules[ai];
+        if !rule.prods.iter().any(is_rec) || rule.prods.iter().all(is_rec) {
+            continue;
+        }
+        let tail = grm.fresh_name(ai, "tail");
+        let rule = &mut grm.rules[ai];
+        let tail_sym = Symbol::Rule(tail.clone(), rule.name_span);
+        let mut prods = Vec::new();
+        let mut tail_prods = Vec::new();
+        for p in rule.prods.drain(..) {
+            if is_rec(&p) {
+                // `A: A` is a cycle which derives nothing new, so it is dropped.
+                if p.symbols.len() > 1 {
+                    let symbols = p.symbols[1..]
+                        .iter()
+                        .cloned()
+                        .chain(Some(tail_sym.clone()))
+                        .collect();
+                    tail_prods.push(p.rewrite(symbols, Vec::new()));
+                }
+            } else {
+                let symbols = p
+                    .symbols
+                    .iter()
+                    .cloned()
+                    .chain(Some(tail_sym.clone()))
+                    .collect();
+                prods.push(p.rewrite(symbols, Vec::new()));
+            }
+        }
+        tail_prods.push(TProd::new(Vec::new(), rule.name_span));
+        rule.prods = prods;
+        let tail_rule = TRule {
+            name_span: rule.name_span,
+            actiont: rule.actiont.clone(),
+            prods: tail_prods,
+        };
+        grm.rules.insert(tail, tail_rule);
+    }
+    grm.into_transformed(ast)
+}
+
+fn to_owned(names: HashSet<&str>) -> HashSet<String> {
+    names.into_iter().map(str::to_owned).collect()
+}
+
+/// Is `symbols` solely a reference to the rule `n`?
+fn is_self_unit(n: &str, symbols: &[Symbol]) -> bool {
+    matches!(symbols, [Symbol::Rule(m, _)] if m == n)
+}
+
+/// A mutable representation of a grammar's rules which is easier to rewrite than a `GrammarAST`.
+struct TGrammar {
+    /// The start rule followed by any additional entry points, each paired with the span of its
+    /// `%start` declaration (or, if it was implicit, its name) in the original grammar.
+    starts: Vec<(String, Span)>,
+    rules: IndexMap<String, TRule>,
+    tokens: HashSet<String>,
+}
+
+#[derive(Clone)]
+struct TRule {
+    name_span: Span,
+    actiont: Option<String>,
+    prods: Vec<TProd>,
+}
+
+#[derive(Clone)]
+struct TProd {
+    symbols: Vec<Symbol>,
+    precedence: Option<(String, Span)>,
+    action: Option<(String, Span)>,
+    labels: Vec<(usize, String)>,
+    prod_span: Span,
+    origins: Vec<usize>,
+}
+
+impl TProd {
+    /// A fresh production with no counterpart in the original grammar.
+    fn new(symbols: Vec<Symbol>, prod_span: Span) -> Self {
+        TProd {
+            symbols,
+            precedence: None,
+            action: None,
+            labels: Vec::new(),
+            prod_span,
+            origins: Vec::new(),
+        }
+    }
+
+    /// A production derived from this one, with symbols `symbols`, into which the productions
+    /// `inlined` have been inlined.
+    fn rewrite(&self, symbols: Vec<Symbol>, inlined: Vec<usize>) -> Self {
+        TProd {
+            symbols,
+            precedence: self.precedence.clone(),
+            action: None,
+            labels: Vec::new(),
+            prod_span: self.prod_span,
+            origins: self.origins.iter().cloned().chain(inlined).collect(),
+        }
+    }
+}
+
+impl TGrammar {
+    fn from_ast(ast: &GrammarAST) -> Self {
+        let rules = ast
+            .rules
+            .iter()
+            .map(|(n, rule)| {
+                let prods = rule
+                    .pidxs
+                    .iter()
+                    .map(|&pidx| {
+                        let p = &ast.prods[pidx];
+                        TProd {
+                            symbols: p.symbols.clone(),
+                            precedence: p.precedence.clone(),
+                            action: p.action.clone(),
+                            labels: p.labels.clone(),
+                            prod_span: p.prod_span,
+                            origins: vec![pidx],
+                        }
+                    })
+                    .collect();
+                let trule = TRule {
+                    name_span: rule.name.1,
+                    actiont: rule.actiont.clone(),
+                    prods,
+                };
+                (n.clone(), trule)
+            })
+            .collect();
+        TGrammar {
+            starts: iter::once(ast.start.as_ref().unwrap())
+                .chain(&ast.extra_starts)
+                .cloned()
+                .collect(),
+            rules,
+            tokens: ast.tokens.iter().cloned().collect(),
+        }
+    }
+
+    /// Return a rule name of the form `{base}_{suffix}{n}` which isn't used by any other rule or
+    /// token.
+    fn fresh_name(&self, base: &str, suffix: &str) -> String {
+        let mut n = 0;
+        loop {
+            let name = format!("{}_{}{}", base, suffix, n);
+            if !self.rules.contains_key(&name) && !self.tokens.contains(&name) {
+                return name;
+            }
+            n += 1;
+        }
+    }
+
+    /// Each rule's name paired with the symbols of each of its productions.
+    fn rule_prods(&self) -> Vec<(&str, Vec<&[Symbol]>)> {
+        self.rules
+            .iter()
+            .map(|(n, rule)| {
+                let prods = rule.prods.iter().map(|p| p.symbols.as_slice()).collect();
+                (n.as_str(), prods)
+            })
+            .collect()
+    }
+
+    /// The names of the rules which can derive a finite sentence.
+    fn productive(&self) -> HashSet<String> {
+        to_owned(productive_rules(&self.rule_prods()))
+    }
+
+    /// The names of the rules which can derive the empty string.
+    fn nullable(&self) -> HashSet<String> {
+        to_owned(rules_fixpoint(&self.rule_prods(), |syms, nullable| {
+            syms.iter().all(|sym| match sym {
+                Symbol::Rule(n, _) => nullable.contains(n.as_str()),
+                Symbol::Token(_, _) => false,
+            })
+        }))
+    }
+
+    /// The names of the rules reachable from the start rules.
+    fn reachable(&self) -> HashSet<String> {
+        let starts = self.starts.iter().map(|(n, _)| n.as_str());
+        to_owned(reachable_rules(starts, &self.rule_prods()))
+    }
+
+    /// Convert this grammar back into a `GrammarAST`, copying the declarations from `orig`.
+    fn into_transformed(self, orig: &GrammarAST) -> Transformed {
+        let mut ast = GrammarAST::new();
+        for (i, (n, span)) in self.starts.into_iter().enumerate() {
+            let span = self.rules.get(&n).map(|r| r.name_span).unwrap_or(span);
+            if i == 0 {
+                ast.start = Some((n, span));
+            } else {
+                ast.extra_starts.push((n, span));
+            }
+        }
+        ast.tokens = orig.tokens.clone();
+        ast.token_spans = orig.token_spans.clone();
+        ast.precs = orig.precs.clone();
+        ast.avoid_insert = orig.avoid_insert.clone();
+        ast.implicit_tokens = orig.implicit_tokens.clone();
+        ast.parse_param_bindings = orig.parse_param_bindings.clone();
+        ast.parse_param_lifetimes = orig.parse_param_lifetimes.clone();
+        ast.epp = orig.epp.clone();
+        ast.programs = orig.programs.clone();
+        ast.expect = orig.expect;
+        ast.expectrr = orig.expectrr;
+
+        let mut prod_map = Vec::new();
+        for (n, rule) in self.rules {
+            ast.add_rule((n.clone(), rule.name_span), rule.actiont);
+            for p in rule.prods {
+                ast.add_prod(n.clone(), p.symbols, p.precedence, p.action, p.prod_span);
+                let prod: &mut Production = ast.prods.last_mut().unwrap();
+                prod.labels = p.labels;
+                prod_map.push(p.origins);
+            }
+        }
+        Transformed { ast, prod_map }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        super::{ast::GrammarAST, YaccGrammar, YaccKind, YaccOriginalActionKind},
+        eliminate_left_recursion, inline_single_use, inline_units, remove_epsilons, remove_useless,
+        TransformErrorKind, MAX_NULLABLE_SYMBOLS,
+    };
+
+    const YK: YaccKind = YaccKind::Original(YaccOriginalActionKind::GenericParseTree);
+
+    fn ast(src: &str) -> GrammarAST {
+        YaccGrammar::new(YK, src).unwrap().ast()
+    }
+
+    /// Pretty-print `ast`'s rules (but not its declarations).
+    fn pp_rules(ast: &GrammarAST) -> String {
+        let pp = ast.pp(YK);
+        pp[pp.find("%%\n").unwrap() + 3..].to_owned()
+    }
+
+    #[test]
+    fn test_remove_useless() {
+        let orig = ast("%%\nS: 'a' A | B;\nA: 'a';\nB: B 'b';\nC: 'c';");
+        let t = remove_useless(&orig);
+        assert_eq!(
+            pp_rules(&t.ast),
+            "S:\n      'a' A\n    ;\n\nA:\n      'a'\n    ;\n"
+        );
+        assert_eq!(t.prod_map, vec![vec![0], vec![2]]);
+        YaccGrammar::<u32>::from_ast(YK, t.ast).unwrap();
+    }
+
+    #[test]
+    fn test_remove_epsilons() {
+        let orig = ast("%%\nS: A 'b' A | ;\nA: 'a' | ;");
+        let t = remove_epsilons(&orig).unwrap();
+        assert_eq!(
+            pp_rules(&t.ast),
+            "S:
+      A 'b' A
+    | 'b' A
+    | A 'b'
+    | 'b'
+    ;
+
+A:
+      'a'
+    ;
+
+S_start0:
+      S
+    |
+    ;
+"
+        );
+        assert_eq!(t.ast.start.as_ref().unwrap().0, "S_start0");
+        assert_eq!(
+            t.prod_map,
+            vec![vec![0], vec![0], vec![0], vec![0], vec![2], vec![], vec![]]
+        );
+        YaccGrammar::<u32>::from_ast(YK, t.ast).unwrap();
+
+        // Rules which only derive the empty string are removed.
+        let t = remove_epsilons(&ast("%%\nS: 'a' A;\nA: ;")).unwrap();
+        assert_eq!(pp_rules(&t.ast), "S:\n      'a'\n    ;\n");
+
+        // Productions which reference too many nullable symbols would have too many variants.
+        let refs = |n| vec!["A"; n].join(" ");
+        let src = format!("%%\nS: 'b' {};\nA: 'a' | ;", refs(MAX_NULLABLE_SYMBOLS));
+        let t = remove_epsilons(&ast(&src)).unwrap();
+        // Variants which omit the same number of `A`s are identical.
+        assert_eq!(t.ast.rules["S"].pidxs.len(), MAX_NULLABLE_SYMBOLS + 1);
+        let src = format!("%%\nS: 'b' {};\nA: 'a' | ;", refs(64));
+        let orig = ast(&src);
+        match remove_epsilons(&orig) {
+            Err(e) => {
+                assert_eq!(e.kind, TransformErrorKind::TooManyNullableSymbols(64));
+                assert_eq!(e.span, orig.prods[orig.rules["S"].pidxs[0]].prod_span);
+            }
+            Ok(_) => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_inline_units() {
+        let orig = ast("%%\nS: A | 'x';\nA: B | 'a';\nB: A | 'b';");
+        let t = inline_units(&orig);
+        assert_eq!(
+            pp_rules(&t.ast),
+            "S:
+      'x'
+    | 'a'
+    | 'b'
+    ;
+
+A:
+      'a'
+    | 'b'
+    ;
+
+B:
+      'b'
+    | 'a'
+    ;
+"
+        );
+        assert_eq!(
+            t.prod_map,
+            vec![
+                vec![1],
+                vec![0, 3],
+                vec![0, 2, 5],
+                vec![3],
+                vec![2, 5],
+                vec![5],
+                vec![4, 3]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inline_single_use() {
+        let orig = ast("%%\nS: A A | B 'x';\nA: 'a';\nB: C 'b' | 'c';\nC: 'd' | 'e';");
+        let t = inline_single_use(&orig);
+        assert_eq!(
+            pp_rules(&t.ast),
+            "S:
+      A A
+    | 'd' 'b' 'x'
+    | 'e' 'b' 'x'
+    | 'c' 'x'
+    ;
+
+A:
+      'a'
+    ;
+"
+        );
+        assert_eq!(
+            t.prod_map,
+            vec![vec![0], vec![1, 3, 5], vec![1, 3, 6], vec![1, 4], vec![2]]
+        );
+    }
+
+    #[test]
+    fn test_eliminate_left_recursion() {
+        let orig = ast("%%\nE: E '+' T | T;\nT: T '*' 'x' | 'x';");
+        let t = eliminate_left_recursion(&orig);
+        assert_eq!(
+            pp_rules(&t.ast),
+            "E:
+      T E_tail0
+    ;
+
+T:
+      'x' T_tail0
+    ;
+
+E_tail0:
+      '+' T E_tail0
+    |
+    ;
+
+T_tail0:
+      '*' 'x' T_tail0
+    |
+    ;
+"
+        );
+        assert_eq!(
+            t.prod_map,
+            vec![vec![1], vec![3], vec![0], vec![], vec![2], vec![]]
+        );
+        YaccGrammar::<u32>::from_ast(YK, t.ast).unwrap();
+
+        // Indirect left recursion.
+        let t = eliminate_left_recursion(&ast("%%\nA: B 'a' | 'c';\nB: A 'b' | 'd';"));
+        assert_eq!(
+            pp_rules(&t.ast),
+            "A:
+      B 'a'
+    | 'c'
+    ;
+
+B:
+      'c' 'b' B_tail0
+    | 'd' B_tail0
+    ;
+
+B_tail0:
+      'a' 'b' B_tail0
+    |
+    ;
+"
+        );
+        assert_eq!(t.prod_map[2], vec![2, 1]);
+    }
+}
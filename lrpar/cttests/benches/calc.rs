@@ -0,0 +1,136 @@
+//! Benchmarks the different ways that a parser can execute actions, using the calculator grammar
+//! from `src/calc_actiontype.test`:
+//!
+//!   * `dyn`: the run-time parser, calling each action through an array of `&dyn Fn`s.
+//!   * `dispatch`: the compile-time parser, whose actions are statically dispatched.
+//!   * `direct`: the compile-time parser with `CTParserBuilder::direct_coded(true)`.
+//!
+//! Run with `cargo bench -p lrpar-tests`.
+
+use std::{fs, vec};
+
+use cfgrammar::{
+    yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind},
+    RIdx,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use lrlex::{lrlex_mod, LRNonStreamingLexerDef, LexerDef};
+use lrpar::{
+    lrpar_mod, parser::AStackType, LexParseError, NonStreamingLexer, RTParserBuilder, Span,
+};
+use lrtable::{from_yacc, Minimiser, StateTable};
+use yaml_rust::YamlLoader;
+
+lrlex_mod!("calc_actiontype.l");
+lrpar_mod!("calc_actiontype.y");
+
+lrlex_mod!("direct.l");
+lrpar_mod!("direct.y");
+
+/// Return an expression with `n` terms, which evaluates to `5 * n`.
+fn input(n: usize) -> String {
+    vec!["1*(2+3)"; n].join("+")
+}
+
+/// The calculator's actions, written (as a user of `RTParserBuilder::parse_actions` would) as a
+/// single function which works out which production it has been called for from its arguments.
+fn calc_action(
+    _ridx: RIdx<u32>,
+    lexer: &dyn NonStreamingLexer<u32>,
+    _span: Span,
+    mut args: vec::Drain<AStackType<u64, u32>>,
+) -> u64 {
+    match (args.next().unwrap(), args.next(), args.next()) {
+        (AStackType::ActionType(v), None, None) => v,
+        (AStackType::Lexeme(l), None, None) => lexer.span_str(l.span()).parse().unwrap(),
+        (AStackType::Lexeme(_), Some(AStackType::ActionType(v)), Some(AStackType::Lexeme(_))) => v,
+        (
+            AStackType::ActionType(lhs),
+            Some(AStackType::Lexeme(op)),
+            Some(AStackType::ActionType(rhs)),
+        ) => match lexer.span_str(op.span()) {
+            "+" => lhs + rhs,
+            "*" => lhs * rhs,
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+/// Parse `lexer` with the run-time parser, executing `calc_action` through an array of `&dyn Fn`s
+/// (as parsers generated by `CTParserBuilder` used to).
+fn parse_dyn<'lexer, 'input: 'lexer>(
+    grm: &YaccGrammar<u32>,
+    stable: &StateTable<u32, u16>,
+    lexer: &'lexer dyn NonStreamingLexer<'input, u32>,
+) -> (Option<u64>, Vec<LexParseError<u32>>) {
+    #[allow(clippy::type_complexity)]
+    let actions: Vec<
+        &dyn Fn(
+            RIdx<u32>,
+            &'lexer dyn NonStreamingLexer<'input, u32>,
+            Span,
+            vec::Drain<AStackType<u64, u32>>,
+        ) -> u64,
+    > = vec![&calc_action; usize::from(grm.prods_len())];
+    RTParserBuilder::new(grm, stable).parse_actions(lexer, &actions)
+}
+
+fn bench_calc(c: &mut Criterion) {
+    let s = fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/src/calc_actiontype.test"
+    ))
+    .unwrap();
+    let docs = YamlLoader::load_from_str(&s).unwrap();
+    let grm = YaccGrammar::<u32>::new(
+        YaccKind::Original(YaccOriginalActionKind::UserAction),
+        docs[0]["grammar"].as_str().unwrap(),
+    )
+    .unwrap();
+    let (_, stable) = from_yacc(&grm, Minimiser::Pager).unwrap();
+    let mut rt_lexerdef =
+        LRNonStreamingLexerDef::<u32>::from_str(docs[0]["lexer"].as_str().unwrap()).unwrap();
+    let rule_ids = grm
+        .tokens_map()
+        .iter()
+        .map(|(&n, &i)| (n, u32::from(i)))
+        .collect();
+    rt_lexerdef.set_rule_ids(&rule_ids);
+    let ct_lexerdef = calc_actiontype_l::lexerdef();
+    let direct_lexerdef = direct_l::lexerdef();
+
+    let n = 1000;
+    let input = input(n);
+    let expected = 5 * n as u64;
+
+    let mut group = c.benchmark_group("calc");
+    group.bench_function("dyn", |b| {
+        let lexer = rt_lexerdef.lexer(&input);
+        b.iter(|| {
+            let (r, errs) = parse_dyn(&grm, &stable, &lexer);
+            assert_eq!(r, Some(expected));
+            assert!(errs.is_empty());
+        })
+    });
+    group.bench_function("dispatch", |b| {
+        let lexer = ct_lexerdef.lexer(&input);
+        b.iter(|| {
+            let (r, errs) = calc_actiontype_y::parse(&lexer);
+            assert_eq!(r, Some(Ok(expected)));
+            assert!(errs.is_empty());
+        })
+    });
+    group.bench_function("direct", |b| {
+        let lexer = direct_lexerdef.lexer(&input);
+        b.iter(|| {
+            let (r, errs) = direct_y::parse(&lexer);
+            assert_eq!(r, Some(Ok(expected)));
+            assert!(errs.is_empty());
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_calc);
+criterion_main!(benches);
@@ -7,9 +7,14 @@ use yaml_rust::YamlLoader;
 
 // Compiles the `*.test` files within `src`. Test files are written in Yaml syntax and have 4
 // mandatory sections: name (describing what the test does), yacckind (defining the grammar type
-// used), grammar (the grammar rules), and lexer (the lexing rules). The tests are compiled into
+// used), grammar (the grammar rules), and lexer (the lexing rules). An optional `glr` section set to
+// `true` compiles the grammar with a GLR parser, an optional `direct` section set to `true`
+// compiles the grammar with a direct-coded parser, and an optional `compress` section set to
+// `true` compresses the grammar's state table. The tests are compiled into
 // two modules `<filename>_y` and `<filename>_l`, which we can then import into src/lib.rs and
-// write tests for.
+// write tests for. If an optional `fails` section is set to `true`, building the grammar must
+// fail: no modules are created, and the error is instead written to `<filename>.err` in OUT_DIR
+// so that src/lib.rs can check it.
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let out_dir = env::var("OUT_DIR").unwrap();
     for entry in glob("src/*.test")? {
@@ -28,6 +33,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     YaccKind::Original(YaccOriginalActionKind::UserAction)
                 }
                 "Grmtools" => YaccKind::Grmtools,
+                "Bison" => YaccKind::Bison,
                 "Original(YaccOriginalActionKind::GenericParseTree)" => {
                     YaccKind::Original(YaccOriginalActionKind::GenericParseTree)
                 }
@@ -50,9 +56,23 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let mut outp = PathBuf::from(&out_dir);
             outp.push(format!("{}.y.rs", base));
             outp.set_extension("rs");
-            let lex_rule_ids_map = CTParserBuilder::new()
+            let r = CTParserBuilder::new()
                 .yacckind(yacckind)
-                .process_file(pg.to_str().unwrap(), &outp)?;
+                .glr(docs[0]["glr"].as_bool().unwrap_or(false))
+                .direct_coded(docs[0]["direct"].as_bool().unwrap_or(false))
+                .compress_tables(docs[0]["compress"].as_bool().unwrap_or(false))
+                .process_file(pg.to_str().unwrap(), &outp);
+            let lex_rule_ids_map = match (r, docs[0]["fails"].as_bool().unwrap_or(false)) {
+                (Ok(m), false) => m,
+                (Err(e), false) => return Err(e),
+                (Ok(_), true) => panic!("{} was expected to fail to build", path.display()),
+                (Err(e), true) => {
+                    let mut pe = PathBuf::from(&out_dir);
+                    pe.push(format!("{}.err", base));
+                    fs::write(&pe, e.to_string()).unwrap();
+                    continue;
+                }
+            };
 
             let mut outl = PathBuf::from(&out_dir);
             outl.push(format!("{}.l.rs", base));
@@ -3,7 +3,10 @@ use lrpar::lrpar_mod;
 #[cfg(test)]
 use lrpar::Span;
 #[cfg(test)]
-use lrpar::{Lexer, NonStreamingLexer};
+use lrpar::{ForestNode, Lexer, Node, NonStreamingLexer};
+
+lrlex_mod!("bison.l");
+lrpar_mod!("bison.y");
 
 lrlex_mod!("calc_multitypes.l");
 lrpar_mod!("calc_multitypes.y");
@@ -14,18 +17,54 @@ lrpar_mod!("calc_actiontype.y");
 lrlex_mod!("calc_noactions.l");
 lrpar_mod!("calc_noactions.y");
 
+lrlex_mod!("ebnf.l");
+lrpar_mod!("ebnf.y");
+
 lrlex_mod!("lexer_lifetime.l");
 lrpar_mod!("lexer_lifetime.y");
 
 lrlex_mod!("multitypes.l");
 lrpar_mod!("multitypes.y");
 
+lrlex_mod!("named_refs.l");
+lrpar_mod!("named_refs.y");
+
 lrlex_mod!("passthrough.l");
 lrpar_mod!("passthrough.y");
 
 lrlex_mod!("span.l");
 lrpar_mod!("span.y");
 
+lrlex_mod!("templates.l");
+lrpar_mod!("templates.y");
+
+lrlex_mod!("multistart.l");
+lrpar_mod!("multistart.y");
+
+lrlex_mod!("direct.l");
+lrpar_mod!("direct.y");
+
+lrlex_mod!("compressed.l");
+lrpar_mod!("compressed.y");
+
+lrlex_mod!("glr.l");
+lrpar_mod!("glr.y");
+
+lrlex_mod!("action_order.l");
+lrpar_mod!("action_order.y");
+
+lrlex_mod!("parse_param.l");
+lrpar_mod!("parse_param.y");
+
+lrlex_mod!("empty.l");
+lrpar_mod!("empty.y");
+
+lrlex_mod!("recovery.l");
+lrpar_mod!("recovery.y");
+
+lrlex_mod!("direct_multitypes.l");
+lrpar_mod!("direct_multitypes.y");
+
 #[test]
 fn multitypes() {
     let lexerdef = multitypes_l::lexerdef();
@@ -90,6 +129,87 @@ fn test_error_recovery_and_actions() {
     assert!(errs.is_empty());
 }
 
+// Inputs for comparing parsers built from the calculator grammar, and whether their results can
+// be compared (they can't when there's more than one equally good repair sequence).
+#[cfg(test)]
+const CALC_INPUTS: &[(&str, bool)] = &[
+    ("2+3", true),
+    ("2*(3+4)*5", true),
+    ("2++3", true),
+    ("2+3)", true),
+    ("(2+3", true),
+    ("2 3", false),
+    ("2+3+18446744073709551616", true),
+    ("2+a", true),
+    ("", true),
+];
+
+// Since the order in which repair sequences are reported (and thus which one is applied) is not
+// deterministic, we sort them before comparison.
+#[cfg(test)]
+fn errs_str(errs: &[lrpar::LexParseError<u32>]) -> Vec<String> {
+    use lrpar::LexParseError;
+
+    errs.iter()
+        .map(|e| match e {
+            LexParseError::LexError(e) => format!("{:?}", e),
+            LexParseError::ParseError(e) => {
+                let mut repairs = e
+                    .repairs()
+                    .iter()
+                    .map(|r| format!("{:?}", r))
+                    .collect::<Vec<_>>();
+                repairs.sort();
+                format!("{:?} {:?} {:?}", e.stidx(), e.lexeme(), repairs)
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_direct() {
+    // The direct-coded parser should produce the same results and errors as the table parser,
+    // including when it has to fall back to error recovery.
+    let direct_lexerdef = direct_l::lexerdef();
+    let table_lexerdef = calc_actiontype_l::lexerdef();
+    for &(s, cmp_r) in CALC_INPUTS {
+        let direct_lexer = direct_lexerdef.lexer(s);
+        let table_lexer = table_lexerdef.lexer(s);
+        let (direct_r, direct_errs) = direct_y::parse(&direct_lexer);
+        let (table_r, table_errs) = calc_actiontype_y::parse(&table_lexer);
+        if cmp_r {
+            assert_eq!(direct_r, table_r);
+        } else {
+            assert_eq!(direct_r.is_some(), table_r.is_some());
+        }
+        assert_eq!(errs_str(&direct_errs), errs_str(&table_errs));
+    }
+    let lexer = direct_lexerdef.lexer("2*(3+4)*5");
+    let (r, errs) = direct_y::parse(&lexer);
+    assert_eq!(r, Some(Ok(70)));
+    assert!(errs.is_empty());
+}
+
+#[test]
+fn test_compressed() {
+    // Compressing the state table should change neither the results nor the errors (including
+    // the repairs found by error recovery).
+    let compressed_lexerdef = compressed_l::lexerdef();
+    let table_lexerdef = calc_actiontype_l::lexerdef();
+    for &(s, cmp_r) in CALC_INPUTS {
+        let compressed_lexer = compressed_lexerdef.lexer(s);
+        let table_lexer = table_lexerdef.lexer(s);
+        let (compressed_r, compressed_errs) = compressed_y::parse(&compressed_lexer);
+        let (table_r, table_errs) = calc_actiontype_y::parse(&table_lexer);
+        if cmp_r {
+            assert_eq!(compressed_r, table_r);
+        } else {
+            assert_eq!(compressed_r.is_some(), table_r.is_some());
+        }
+        assert_eq!(errs_str(&compressed_errs), errs_str(&table_errs));
+    }
+}
+
 #[test]
 fn test_calc_multitypes() {
     let lexerdef = calc_multitypes_l::lexerdef();
@@ -227,6 +347,15 @@ fn test_span() {
     }
 }
 
+#[test]
+fn test_ebnf() {
+    let lexerdef = ebnf_l::lexerdef();
+    let lexer = lexerdef.lexer("[1, 2, 3] [] [4]");
+    let (r, errs) = ebnf_y::parse(&lexer);
+    assert_eq!(r, Some(vec![vec![1, 2, 3], vec![], vec![4]]));
+    assert!(errs.is_empty());
+}
+
 #[test]
 fn test_passthrough() {
     let lexerdef = passthrough_l::lexerdef();
@@ -236,3 +365,254 @@ fn test_passthrough() {
         _ => unreachable!(),
     }
 }
+
+#[test]
+fn test_bison() {
+    let lexerdef = bison_l::lexerdef();
+    let lexer = lexerdef.lexer("2 + double 3 * (4 + 1)");
+    let (r, errs) = bison_y::parse(&lexer);
+    assert_eq!(r, Some(Ok(32)));
+    assert!(errs.is_empty());
+}
+
+#[test]
+fn test_named_refs() {
+    let lexerdef = named_refs_l::lexerdef();
+    let lexer = lexerdef.lexer("2 * (3 + 4) + 1");
+    let (r, errs) = named_refs_y::parse(&lexer);
+    assert_eq!(r, Some(Ok(15)));
+    assert!(errs.is_empty());
+    // Labels which start with `span` or `lexer` are references, not `$span` or `$lexer`.
+    let lexer = lexerdef.lexer("8 / 2 * 3");
+    let (r, errs) = named_refs_y::parse(&lexer);
+    assert_eq!(r, Some(Ok(12)));
+    assert!(errs.is_empty());
+}
+
+#[test]
+fn test_templates() {
+    let lexerdef = templates_l::lexerdef();
+    let lexer = lexerdef.lexer("1, 2; 3; 4, 5, 6");
+    let (r, errs) = templates_y::parse(&lexer);
+    assert_eq!(r, Some(vec![vec![1, 2], vec![3], vec![4, 5, 6]]));
+    assert!(errs.is_empty());
+}
+
+#[test]
+fn test_multistart() {
+    let lexerdef = multistart_l::lexerdef();
+    let lexer = lexerdef.lexer("x: int = 1 + 2; y: str = 3");
+    let (r, errs) = multistart_y::parse(&lexer);
+    assert_eq!(
+        r,
+        Some(vec!["x: int = 3".to_owned(), "y: str = 3".to_owned()])
+    );
+    assert!(errs.is_empty());
+    let (r, errs) = multistart_y::parse_stmts(&lexer);
+    assert_eq!(r.unwrap().len(), 2);
+    assert!(errs.is_empty());
+
+    let lexer = lexerdef.lexer("1 + 2 + 3");
+    let (r, errs) = multistart_y::parse_expr(&lexer);
+    assert_eq!(r, Some(6));
+    assert!(errs.is_empty());
+    // Input which is valid for one entry point isn't valid for the others.
+    let (r, errs) = multistart_y::parse_type(&lexer);
+    assert_eq!(r, Some("<missing>".to_owned()));
+    assert!(!errs.is_empty());
+
+    let lexer = lexerdef.lexer("int");
+    let (r, errs) = multistart_y::parse_type(&lexer);
+    assert_eq!(r, Some("int".to_owned()));
+    assert!(errs.is_empty());
+}
+
+#[test]
+fn test_multistart_names() {
+    // Start rules whose parse functions would clash, or not be valid identifiers, are rejected
+    // when the grammar is built.
+    assert_eq!(
+        include_str!(concat!(env!("OUT_DIR"), "/start_case.err")),
+        "Start rules 'Expr' and 'expr' at line 4 column 1 would both have the parse function \
+         'parse_expr'"
+    );
+    assert_eq!(
+        include_str!(concat!(env!("OUT_DIR"), "/start_ident.err")),
+        "Start rule 'expr.list' at line 4 column 1 would have the parse function \
+         'parse_expr.list', which is not a valid Rust identifier"
+    );
+}
+
+#[test]
+fn test_glr() {
+    fn eval(lexer: &dyn NonStreamingLexer<u32>, n: &Node<u32>) -> u64 {
+        match n {
+            Node::Nonterm { nodes, .. } if nodes.len() == 3 => {
+                let (l, r) = (eval(lexer, &nodes[0]), eval(lexer, &nodes[2]));
+                match &nodes[1] {
+                    Node::Term { lexeme } if lexer.span_str(lexeme.span()) == "+" => l + r,
+                    _ => l * r,
+                }
+            }
+            Node::Nonterm { nodes, .. } => eval(lexer, &nodes[0]),
+            Node::Term { lexeme } => lexer.span_str(lexeme.span()).parse().unwrap(),
+        }
+    }
+
+    let lexerdef = glr_l::lexerdef();
+    let lexer = lexerdef.lexer("1 + 2 * 3");
+    let (r, errs) = glr_y::parse(&lexer);
+    assert!(errs.is_empty());
+    let forest = r.unwrap();
+    assert!(forest.is_ambiguous());
+    let mut vals = forest
+        .trees()
+        .iter()
+        .map(|t| eval(&lexer, t))
+        .collect::<Vec<_>>();
+    vals.sort();
+    assert_eq!(vals, vec![7, 9]);
+    // Give `*` higher precedence than `+` by preferring additions nearer the root.
+    let t = forest.disambiguate(|f, alts| {
+        alts.iter()
+            .position(|a| match f.node(a.children[1]) {
+                ForestNode::Term { lexeme } => lexer.span_str(lexeme.span()) == "+",
+                _ => false,
+            })
+            .unwrap_or(0)
+    });
+    assert_eq!(eval(&lexer, &t), 7);
+
+    let lexer = lexerdef.lexer("1 + * 3");
+    let (r, errs) = glr_y::parse(&lexer);
+    assert!(r.is_none());
+    assert_eq!(errs.len(), 1);
+}
+
+// Return whether the first (i.e. the applied) repair sequence of the only error in `errs` starts
+// with an insertion.
+#[cfg(test)]
+fn inserted_first(errs: &[lrpar::LexParseError<u32>]) -> bool {
+    use lrpar::{LexParseError, ParseRepair};
+
+    assert_eq!(errs.len(), 1);
+    match &errs[0] {
+        LexParseError::ParseError(e) => matches!(e.repairs()[0][0], ParseRepair::Insert(_)),
+        LexParseError::LexError(_) => unreachable!(),
+    }
+}
+
+#[test]
+fn test_action_order() {
+    let lexerdef = action_order_l::lexerdef();
+    for &(s, v) in &[
+        ("1-2", 1),
+        ("(1,2)", 201),
+        ("(1,2)-5", -196),
+        ("1-(2,3)-4", -299),
+    ] {
+        let lexer = lexerdef.lexer(s);
+        let (r, errs) = action_order_y::parse(&lexer);
+        assert_eq!(r, Some(Ok(v)));
+        assert!(errs.is_empty());
+    }
+    // The inserted ',' must not shift the arguments of the '(' Expr ',' Expr ')' action.
+    let lexer = lexerdef.lexer("(1 2)");
+    let (r, errs) = action_order_y::parse(&lexer);
+    assert_eq!(r, Some(Ok(201)));
+    assert!(inserted_first(&errs));
+}
+
+#[test]
+fn test_parse_param() {
+    let lexerdef = parse_param_l::lexerdef();
+    let lexer = lexerdef.lexer("a+10");
+    let (r, errs) = parse_param_y::parse(&lexer, &3, (1, 16));
+    assert_eq!(r, Some(80));
+    assert!(errs.is_empty());
+    let lexer = lexerdef.lexer("1+2");
+    let (r, errs) = parse_param_y::parse(&lexer, &2, (0, 10));
+    assert_eq!(r, Some(6));
+    assert!(errs.is_empty());
+    // After a parse error, the direct-coded parser hands over to the table-driven parser, which
+    // must still pass the parameters to the actions.
+    let lexer = lexerdef.lexer("1 2");
+    let (r, errs) = parse_param_y::parse(&lexer, &3, (1, 16));
+    if inserted_first(&errs) {
+        assert_eq!(r, Some(11));
+    } else {
+        assert_eq!(r, Some(4));
+    }
+}
+
+#[test]
+fn test_empty() {
+    let lexerdef = empty_l::lexerdef();
+    for &(s, sign, items) in &[
+        ("", false, &[][..]),
+        ("-", true, &[]),
+        ("a", false, &["a"]),
+        ("- a *b c", true, &["a", "*b", "c"]),
+    ] {
+        let lexer = lexerdef.lexer(s);
+        let (r, errs) = empty_y::parse(&lexer);
+        assert_eq!(
+            r,
+            Some((sign, items.iter().map(|s| s.to_string()).collect()))
+        );
+        assert!(errs.is_empty());
+    }
+    let lexer = lexerdef.lexer("a *");
+    let (r, errs) = empty_y::parse(&lexer);
+    assert_eq!(r, Some((false, vec!["a".to_owned(), "*?".to_owned()])));
+    assert!(inserted_first(&errs));
+    let lexer = lexerdef.lexer("- -a");
+    let (r, errs) = empty_y::parse(&lexer);
+    assert_eq!(r, Some((true, vec!["a".to_owned()])));
+    assert!(!inserted_first(&errs));
+}
+
+#[test]
+fn test_direct_multitypes() {
+    // When rules have different action types, the direct-coded parser and the table-driven
+    // parser it falls back to must agree on where each value is stored.
+    let direct_lexerdef = direct_multitypes_l::lexerdef();
+    let table_lexerdef = empty_l::lexerdef();
+    for s in &["", "- a *b c", "a *", "- -a"] {
+        let direct_lexer = direct_lexerdef.lexer(s);
+        let table_lexer = table_lexerdef.lexer(s);
+        let (direct_r, direct_errs) = direct_multitypes_y::parse(&direct_lexer);
+        let (table_r, table_errs) = empty_y::parse(&table_lexer);
+        assert_eq!(direct_r, table_r);
+        assert_eq!(errs_str(&direct_errs), errs_str(&table_errs));
+    }
+}
+
+#[test]
+fn test_recovery() {
+    let lexerdef = recovery_l::lexerdef();
+    for &(s, ins, stmts) in &[
+        ("a b;", true, &["a=b"][..]),
+        ("a = = b;", false, &["a=b"]),
+        ("a = b;; c = d;", false, &["a=b", "c=d"]),
+        ("a = b; c = d + ;", true, &["a=b", "c=(d+?)"]),
+    ] {
+        let lexer = lexerdef.lexer(s);
+        let (r, errs) = recovery_y::parse(&lexer);
+        assert_eq!(r, Some(stmts.iter().map(|s| s.to_string()).collect()));
+        assert_eq!(inserted_first(&errs), ins);
+    }
+    // These inputs can be repaired by either inserting or deleting a token.
+    for &(s, ins_stmt, del_stmt) in &[
+        ("a = b c;", "a=(b+c)", "a=b"),
+        ("a = b + + c;", "a=((b+?)+c)", "a=(b+c)"),
+    ] {
+        let lexer = lexerdef.lexer(s);
+        let (r, errs) = recovery_y::parse(&lexer);
+        if inserted_first(&errs) {
+            assert_eq!(r, Some(vec![ins_stmt.to_owned()]));
+        } else {
+            assert_eq!(r, Some(vec![del_stmt.to_owned()]));
+        }
+    }
+}
@@ -39,14 +39,14 @@ enum RepairMerge<StorageT> {
 }
 
 #[derive(Clone, Debug)]
-struct PathFNode<StorageT> {
-    pstack: Cactus<StIdx>,
+struct PathFNode<StorageT, StIdxT> {
+    pstack: Cactus<StIdx<StIdxT>>,
     laidx: usize,
     repairs: Cactus<RepairMerge<StorageT>>,
     cf: u16,
 }
 
-impl<StorageT: PrimInt + Unsigned> PathFNode<StorageT> {
+impl<StorageT: PrimInt + Unsigned, StIdxT> PathFNode<StorageT, StIdxT> {
     fn last_repair(&self) -> Option<Repair<StorageT>> {
         match *self.repairs.val().unwrap() {
             RepairMerge::Repair(r) => Some(r),
@@ -56,15 +56,15 @@ impl<StorageT: PrimInt + Unsigned> PathFNode<StorageT> {
     }
 }
 
-impl<StorageT: PrimInt + Unsigned> Hash for PathFNode<StorageT> {
+impl<StorageT: Hash + PrimInt + Unsigned, StIdxT: Hash> Hash for PathFNode<StorageT, StIdxT> {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.pstack.hash(state);
         self.laidx.hash(state);
     }
 }
 
-impl<StorageT: PrimInt + Unsigned> PartialEq for PathFNode<StorageT> {
-    fn eq(&self, other: &PathFNode<StorageT>) -> bool {
+impl<StorageT: PrimInt + Unsigned, StIdxT: PartialEq> PartialEq for PathFNode<StorageT, StIdxT> {
+    fn eq(&self, other: &PathFNode<StorageT, StIdxT>) -> bool {
         if self.laidx != other.laidx || self.pstack != other.pstack {
             return false;
         }
@@ -98,17 +98,29 @@ impl<StorageT: PrimInt + Unsigned> PartialEq for PathFNode<StorageT> {
     }
 }
 
-impl<StorageT: PrimInt + Unsigned> Eq for PathFNode<StorageT> {}
-
-struct CPCTPlus<'a, 'b: 'a, 'input: 'b, StorageT: 'static + Eq + Hash, ActionT: 'a> {
-    parser: &'a Parser<'a, 'b, 'input, StorageT, ActionT>,
+impl<StorageT: PrimInt + Unsigned, StIdxT: Eq> Eq for PathFNode<StorageT, StIdxT> {}
+
+struct CPCTPlus<
+    'a,
+    'b: 'a,
+    'input: 'b,
+    StorageT: 'static + Eq + Hash,
+    StIdxT: 'static + Eq + Hash,
+    ActionT: 'a,
+> {
+    parser: &'a Parser<'a, 'b, 'input, StorageT, StIdxT, ActionT>,
 }
 
-pub(crate) fn recoverer<'a, StorageT: 'static + Debug + Hash + PrimInt + Unsigned, ActionT: 'a>(
-    parser: &'a Parser<StorageT, ActionT>,
-) -> Box<dyn Recoverer<StorageT, ActionT> + 'a>
+pub(crate) fn recoverer<
+    'a,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    ActionT: 'a,
+>(
+    parser: &'a Parser<StorageT, StIdxT, ActionT>,
+) -> Box<dyn Recoverer<StorageT, StIdxT, ActionT> + 'a>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     Box::new(CPCTPlus { parser })
@@ -119,18 +131,19 @@ impl<
         'b: 'a,
         'input: 'b,
         StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
         ActionT: 'a,
-    > Recoverer<StorageT, ActionT> for CPCTPlus<'a, 'b, 'input, StorageT, ActionT>
+    > Recoverer<StorageT, StIdxT, ActionT> for CPCTPlus<'a, 'b, 'input, StorageT, StIdxT, ActionT>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     fn recover(
         &self,
         finish_by: Instant,
-        parser: &Parser<StorageT, ActionT>,
+        parser: &Parser<StorageT, StIdxT, ActionT>,
         in_laidx: usize,
-        mut in_pstack: &mut Vec<StIdx>,
+        mut in_pstack: &mut Vec<StIdx<StIdxT>>,
         mut astack: &mut Vec<AStackType<ActionT, StorageT>>,
         mut spans: &mut Vec<Span>,
     ) -> (usize, Vec<Vec<ParseRepair<StorageT>>>) {
@@ -252,13 +265,18 @@ impl<
         'b: 'a,
         'input: 'b,
         StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
         ActionT: 'a,
-    > CPCTPlus<'a, 'b, 'input, StorageT, ActionT>
+    > CPCTPlus<'a, 'b, 'input, StorageT, StIdxT, ActionT>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
-    fn insert(&self, n: &PathFNode<StorageT>, nbrs: &mut Vec<(u16, PathFNode<StorageT>)>) {
+    fn insert(
+        &self,
+        n: &PathFNode<StorageT, StIdxT>,
+        nbrs: &mut Vec<(u16, PathFNode<StorageT, StIdxT>)>,
+    ) {
         let laidx = n.laidx;
         for tidx in self.parser.stable.state_actions(*n.pstack.val().unwrap()) {
             if tidx == self.parser.grm.eof_token_idx() {
@@ -295,7 +313,11 @@ where
         }
     }
 
-    fn delete(&self, n: &PathFNode<StorageT>, nbrs: &mut Vec<(u16, PathFNode<StorageT>)>) {
+    fn delete(
+        &self,
+        n: &PathFNode<StorageT, StIdxT>,
+        nbrs: &mut Vec<(u16, PathFNode<StorageT, StIdxT>)>,
+    ) {
         if n.laidx == self.parser.lexemes.len() {
             return;
         }
@@ -311,7 +333,11 @@ where
         nbrs.push((nn.cf, nn));
     }
 
-    fn shift(&self, n: &PathFNode<StorageT>, nbrs: &mut Vec<(u16, PathFNode<StorageT>)>) {
+    fn shift(
+        &self,
+        n: &PathFNode<StorageT, StIdxT>,
+        nbrs: &mut Vec<(u16, PathFNode<StorageT, StIdxT>)>,
+    ) {
         // Forward move rule (ER3)
         //
         // Note the rule in Corchuelo et al. is confusing and, I think, wrong. It reads:
@@ -359,7 +385,7 @@ where
     fn collect_repairs(
         &self,
         in_laidx: usize,
-        cnds: Vec<PathFNode<StorageT>>,
+        cnds: Vec<PathFNode<StorageT, StIdxT>>,
     ) -> Vec<Vec<Vec<ParseRepair<StorageT>>>> {
         fn traverse<StorageT: PrimInt>(
             rm: &Cactus<RepairMerge<StorageT>>,
@@ -435,16 +461,21 @@ where
 
 /// Apply the `repairs` to `pstack` starting at position `laidx`: return the resulting parse
 /// distance and a new pstack.
-pub fn apply_repairs<'a, StorageT: 'static + Debug + Hash + PrimInt + Unsigned, ActionT: 'a>(
-    parser: &Parser<StorageT, ActionT>,
+pub fn apply_repairs<
+    'a,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    ActionT: 'a,
+>(
+    parser: &Parser<StorageT, StIdxT, ActionT>,
     mut laidx: usize,
-    mut pstack: &mut Vec<StIdx>,
+    mut pstack: &mut Vec<StIdx<StIdxT>>,
     mut astack: &mut Option<&mut Vec<AStackType<ActionT, StorageT>>>,
     mut spans: &mut Option<&mut Vec<Span>>,
     repairs: &[ParseRepair<StorageT>],
 ) -> usize
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     for r in repairs.iter() {
@@ -478,8 +509,12 @@ where
 }
 
 /// Simplifies repair sequences, removes duplicates, and sorts them into order.
-pub fn simplify_repairs<StorageT: 'static + Hash + PrimInt + Unsigned, ActionT>(
-    parser: &Parser<StorageT, ActionT>,
+pub fn simplify_repairs<
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    ActionT,
+>(
+    parser: &Parser<StorageT, StIdxT, ActionT>,
     all_rprs: &mut Vec<Vec<ParseRepair<StorageT>>>,
 ) where
     usize: AsPrimitive<StorageT>,
@@ -532,15 +567,20 @@ pub fn simplify_repairs<StorageT: 'static + Hash + PrimInt + Unsigned, ActionT>(
 /// `ParseRepair`s allow the same distance of parsing, then the `ParseRepair` which requires
 /// repairs over the shortest distance is preferred. Amongst `ParseRepair`s of the same rank, the
 /// ordering is non-deterministic.
-fn rank_cnds<'a, StorageT: 'static + Debug + Hash + PrimInt + Unsigned, ActionT: 'a>(
-    parser: &Parser<StorageT, ActionT>,
+fn rank_cnds<
+    'a,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    ActionT: 'a,
+>(
+    parser: &Parser<StorageT, StIdxT, ActionT>,
     finish_by: Instant,
     in_laidx: usize,
-    in_pstack: &[StIdx],
+    in_pstack: &[StIdx<StIdxT>],
     in_cnds: Vec<Vec<Vec<ParseRepair<StorageT>>>>,
 ) -> Vec<Vec<ParseRepair<StorageT>>>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     let mut cnds = Vec::new();
@@ -7,89 +7,137 @@ use std::{
     convert::AsRef,
     env::{current_dir, var},
     error::Error,
-    fmt::{self, Debug, Write as fmtWrite},
+    fmt::{self, Debug},
     fs::{self, create_dir_all, read_to_string, File},
     hash::Hash,
-    io::{self, Write},
+    io::Write,
     marker::PhantomData,
     path::{Path, PathBuf},
 };
 
-use bincode::{deserialize, serialize_into};
 use cfgrammar::{
-    yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind},
-    RIdx, Symbol,
+    yacc::{
+        YaccGrammar, YaccGrammarError, YaccGrammarWarning, YaccKind, YaccOriginalActionKind,
+        YaccSource,
+    },
+    PIdx, RIdx, Symbol,
 };
 use filetime::FileTime;
 use lazy_static::lazy_static;
-use lrtable::{from_yacc, statetable::Conflicts, Minimiser, StateGraph, StateTable};
+use lrtable::{
+    report::report, stategraph, Action, CompressionStats, Minimiser, StIdx, StateGraph, StateTable,
+};
 use num_traits::{AsPrimitive, PrimInt, Unsigned};
 use regex::Regex;
-use serde::{de::DeserializeOwned, Serialize};
+use vob::Vob;
 
 use crate::RecoveryKind;
 
 const ACTION_PREFIX: &str = "__gt_";
 const GLOBAL_PREFIX: &str = "__GT_";
 const ACTIONS_KIND: &str = "__GTActionsKind";
-const ACTIONS_KIND_PREFIX: &str = "AK";
-const ACTIONS_KIND_HIDDEN: &str = "__GTActionsKindHidden";
+const ACTION_VALUES: &str = "__GTActionValues";
 
 const RUST_FILE_EXT: &str = "rs";
 
-const GRM_CONST_NAME: &str = "__GRM_DATA";
-const STABLE_CONST_NAME: &str = "__STABLE_DATA";
+const TABLES_STATIC_NAME: &str = "__TABLES";
 
 lazy_static! {
     static ref RE_DOL_NUM: Regex = Regex::new(r"\$([0-9]+)").unwrap();
+    static ref RE_DOL_NAME: Regex =
+        Regex::new(r"^\$(?:([a-zA-Z_][a-zA-Z0-9_]*)|\[([^\]]+)\])").unwrap();
 }
 
-struct CTConflictsError<StorageT: Eq + Hash> {
+struct CTConflictsError<StorageT: 'static + Eq + Hash, StIdxT: 'static> {
     pub grm: YaccGrammar<StorageT>,
     pub sgraph: StateGraph<StorageT>,
-    pub stable: StateTable<StorageT>,
+    pub stable: StateTable<StorageT, StIdxT>,
 }
 
-impl<StorageT> fmt::Display for CTConflictsError<StorageT>
+impl<StorageT, StIdxT> fmt::Display for CTConflictsError<StorageT, StIdxT>
 where
-    StorageT: 'static + Debug + Hash + PrimInt + Serialize + Unsigned,
-    usize: AsPrimitive<StorageT>,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let conflicts = self.stable.conflicts().unwrap();
-        write!(
-            f,
-            "CTConflictsError{{{} Shift/Reduce, {} Reduce/Reduce}}",
-            conflicts.sr_len(),
-            conflicts.rr_len()
-        )
+        let (sr_len, rr_len) = conflicts_len(&self.stable);
+        if self.grm.expect().is_some() || self.grm.expectrr().is_some() {
+            write!(
+                f,
+                "CTConflictsError{{{} Shift/Reduce (expected {}), {} Reduce/Reduce (expected {})}}",
+                sr_len,
+                self.grm.expect().unwrap_or(0),
+                rr_len,
+                self.grm.expectrr().unwrap_or(0)
+            )?;
+        } else {
+            write!(
+                f,
+                "CTConflictsError{{{} Shift/Reduce, {} Reduce/Reduce}}",
+                sr_len, rr_len
+            )?;
+        }
+        if let Some(c) = self.stable.conflicts() {
+            write!(
+                f,
+                "\n{}",
+                c.pp_counterexamples(&self.grm, &self.sgraph).trim_end()
+            )?;
+        }
+        Ok(())
     }
 }
 
-impl<StorageT> fmt::Debug for CTConflictsError<StorageT>
+impl<StorageT, StIdxT> fmt::Debug for CTConflictsError<StorageT, StIdxT>
 where
-    StorageT: 'static + Debug + Hash + PrimInt + Serialize + Unsigned,
-    usize: AsPrimitive<StorageT>,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let conflicts = self.stable.conflicts().unwrap();
-        write!(
-            f,
-            "CTConflictsError{{{} Shift/Reduce, {} Reduce/Reduce}}",
-            conflicts.sr_len(),
-            conflicts.rr_len()
-        )
+        fmt::Display::fmt(self, f)
     }
 }
 
-impl<StorageT> Error for CTConflictsError<StorageT>
+impl<StorageT, StIdxT> Error for CTConflictsError<StorageT, StIdxT>
 where
-    StorageT: 'static + Debug + Hash + PrimInt + Serialize + Unsigned,
-    usize: AsPrimitive<StorageT>,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
+    u32: AsPrimitive<StorageT>,
+{
+}
+
+/// All of the errors found in a grammar, which are reported together, one per line.
+#[derive(Debug)]
+struct CTGrammarErrors {
+    errs: Vec<YaccGrammarError>,
+}
+
+impl fmt::Display for CTGrammarErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let errs = self.errs.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", errs.join("\n"))
+    }
+}
+
+impl Error for CTGrammarErrors {}
+
+/// Return the number of (Shift/Reduce, Reduce/Reduce) conflicts in `stable`.
+fn conflicts_len<StorageT, StIdxT>(stable: &StateTable<StorageT, StIdxT>) -> (usize, usize)
+where
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
+    stable
+        .conflicts()
+        .map(|c| (c.sr_len(), c.rr_len()))
+        .unwrap_or((0, 0))
 }
 
 /// Specify the visibility of the module generated by `CTBuilder`.
@@ -122,25 +170,40 @@ impl Visibility {
     }
 }
 
+/// A state table built by [`CTParserBuilder`], whose state indexes are stored in the smallest
+/// type that can index all of the grammar's states.
+pub enum CTStateTable<StorageT: 'static + Eq + Hash> {
+    U16(StateTable<StorageT, u16>),
+    U32(StateTable<StorageT, u32>),
+}
+
 /// A `CTParserBuilder` allows one to specify the criteria for building a statically generated
 /// parser.
 pub struct CTParserBuilder<'a, StorageT = u32>
 where
-    StorageT: Eq + Hash,
+    StorageT: 'static + Eq + Hash,
 {
-    // Anything stored in here (except `conflicts` and `error_on_conflict`) almost certainly needs
-    // to be included as part of the rebuild_cache function below so that, if it's changed, the
-    // grammar is rebuilt.
+    // Anything stored in here (except `conflicts`, `compression_stats`, `error_on_conflict`, and
+    // `show_warnings`)
+    // almost certainly needs to be included as part of the rebuild_cache function below so that,
+    // if it's changed, the grammar is rebuilt.
     mod_name: Option<&'a str>,
     recoverer: RecoveryKind,
     yacckind: Option<YaccKind>,
     error_on_conflicts: bool,
+    show_warnings: bool,
     visibility: Visibility,
+    glr: bool,
+    direct_coded: bool,
+    compress_tables: bool,
+    minimiser: Minimiser,
+    report_file: Option<PathBuf>,
     conflicts: Option<(
         YaccGrammar<StorageT>,
         StateGraph<StorageT>,
-        StateTable<StorageT>,
+        CTStateTable<StorageT>,
     )>,
+    compression_stats: Option<CompressionStats>,
     phantom: PhantomData<StorageT>,
 }
 
@@ -161,7 +224,7 @@ impl<'a> CTParserBuilder<'a, u32> {
 
 impl<'a, StorageT> CTParserBuilder<'a, StorageT>
 where
-    StorageT: 'static + Debug + Hash + PrimInt + Serialize + Unsigned,
+    StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
     usize: AsPrimitive<StorageT>,
     u32: AsPrimitive<StorageT>,
 {
@@ -174,7 +237,9 @@ where
     /// can safely specify `u8` here; but if any of those counts becomes 256 you will need to
     /// specify `u16`. If you are parsing large files, the additional storage requirements of
     /// larger integer types can be noticeable, and in such cases it can be worth specifying a
-    /// smaller type. `StorageT` defaults to `u32` if unspecified.
+    /// smaller type. `StorageT` defaults to `u32` if unspecified. The number of states does not
+    /// affect the choice of `StorageT`: state indexes are stored in a `u16` if the state table
+    /// has few enough states, and a `u32` otherwise.
     ///
     /// # Examples
     ///
@@ -189,8 +254,15 @@ where
             recoverer: RecoveryKind::CPCTPlus,
             yacckind: None,
             error_on_conflicts: true,
+            show_warnings: true,
             visibility: Visibility::Private,
+            glr: false,
+            direct_coded: false,
+            compress_tables: false,
+            report_file: None,
+            minimiser: Minimiser::Pager,
             conflicts: None,
+            compression_stats: None,
             phantom: PhantomData,
         }
     }
@@ -223,26 +295,104 @@ where
 
     /// If set to true, `process_file_in_src` will return an error if the given grammar contains
     /// any Shift/Reduce or Reduce/Reduce conflicts. Defaults to `true`.
+    ///
+    /// If the grammar contains `%expect` or `%expect-rr` declarations, this setting is ignored:
+    /// instead, an error is returned if the number of Shift/Reduce or Reduce/Reduce conflicts
+    /// differs from the declared number (an omitted declaration is taken to expect 0 conflicts).
     pub fn error_on_conflicts(mut self, b: bool) -> Self {
         self.error_on_conflicts = b;
         self
     }
 
+    /// If set to true, `process_file_in_src` will print any warnings about the grammar (e.g.
+    /// unused tokens; see `YaccGrammar::warnings`) as `cargo:warning=` lines, so that Cargo
+    /// displays them when the build script runs. Warnings about useless precedences (see
+    /// `StateTable::precedence_warnings`) need the grammar's state table, so are only printed
+    /// when the parser is regenerated. Defaults to `true`.
+    pub fn show_warnings(mut self, b: bool) -> Self {
+        self.show_warnings = b;
+        self
+    }
+
+    /// If set to true, the generated parse functions use a GLR parser (see
+    /// [`RTParserBuilder::parse_glr`](../parser/struct.RTParserBuilder.html#method.parse_glr)),
+    /// returning a [`Forest`](../glr/struct.Forest.html) of all possible parse trees rather than a
+    /// single parse tree. Since the parser follows all the actions of a conflict, conflicts are
+    /// not treated as errors (unless the grammar contains `%expect` or `%expect-rr`
+    /// declarations). Only `YaccKind::Original(YaccOriginalActionKind::GenericParseTree)`
+    /// grammars are supported. Defaults to `false`.
+    pub fn glr(mut self, b: bool) -> Self {
+        self.glr = b;
+        self
+    }
+
+    /// If set to true, the generated parse functions use a direct-coded parser: rather than
+    /// looking up actions and gotos in the state table, each state is compiled into a Rust `match`
+    /// on the lookahead, with reductions and gotos inlined and action wrappers called directly.
+    /// This makes parsing faster at the expense of larger generated code. If a parse error is
+    /// encountered, parsing continues with the table-driven parser, so that errors are recovered
+    /// from, and reported, exactly as they would be without this setting. Only grammars with
+    /// actions (i.e. `YaccKind::Grmtools`, `YaccKind::Bison`, and
+    /// `YaccKind::Original(YaccOriginalActionKind::UserAction)`) are supported. Defaults to
+    /// `false`.
+    pub fn direct_coded(mut self, b: bool) -> Self {
+        self.direct_coded = b;
+        self
+    }
+
+    /// If set to true, the generated parser's state table is compressed (see
+    /// [`StateTable::compress`](../../lrtable/statetable/struct.StateTable.html#method.compress)),
+    /// making the generated code smaller and the compiled binary use less memory, at the expense
+    /// of slightly slower table lookups. Compression does not change how the parser behaves,
+    /// including which errors are reported and how they are recovered from. The space saved can
+    /// be inspected with [`compression_stats`](#method.compression_stats). Defaults to `false`.
+    pub fn compress_tables(mut self, b: bool) -> Self {
+        self.compress_tables = b;
+        self
+    }
+
+    /// Set the algorithm used to create the grammar's state graph to `m`. `Minimiser::LR1`
+    /// creates the largest state tables but never introduces conflicts; `Minimiser::LALR` creates
+    /// the smallest state tables but can introduce reduce/reduce conflicts which the grammar
+    /// does not have under LR(1). `Minimiser::Pager` (the default) and `Minimiser::IELR` both
+    /// create tables of (nearly) LALR size without introducing conflicts.
+    pub fn minimiser(mut self, m: Minimiser) -> Self {
+        self.minimiser = m;
+        self
+    }
+
+    /// If set, a verbose report of the grammar's state table (in the style of the `.output` file
+    /// produced by `bison -v`; see [`lrtable::report`](../../lrtable/report/index.html)) is written
+    /// to `p` whenever the parser is generated. The report is written even if the grammar's
+    /// conflicts cause an error, which makes it useful for debugging misbehaving grammars. For
+    /// example, to write the report next to the generated file, set `p` to
+    /// `Path::new(&env::var("OUT_DIR")?).join("grm.output")`.
+    pub fn report_file<P: AsRef<Path>>(mut self, p: P) -> Self {
+        self.report_file = Some(p.as_ref().to_owned());
+        self
+    }
+
+    /// If [`compress_tables`](#method.compress_tables) was set to true and the parser was
+    /// generated, return a summary of the space saved by compressing its state table; otherwise
+    /// returns `None`. The summary is also included in the [`report_file`](#method.report_file),
+    /// if one was requested.
+    pub fn compression_stats(&self) -> Option<CompressionStats> {
+        self.compression_stats
+    }
+
     /// If there are any conflicts in the grammar, return a tuple which allows users to inspect
-    /// and pretty print them; otherwise returns `None`. Note: The conflicts feature is currently
-    /// unstable and may change in the future.
+    /// and pretty print them (via the state table's `conflicts` function); otherwise returns
+    /// `None`. Note: The conflicts feature is currently unstable and may change in the future.
     pub fn conflicts(
         &self,
     ) -> Option<(
         &YaccGrammar<StorageT>,
         &StateGraph<StorageT>,
-        &StateTable<StorageT>,
-        &Conflicts<StorageT>,
+        &CTStateTable<StorageT>,
     )> {
-        if let Some((grm, sgraph, stable)) = &self.conflicts {
-            return Some((grm, sgraph, stable, &stable.conflicts().unwrap()));
-        }
-        None
+        self.conflicts
+            .as_ref()
+            .map(|(grm, sgraph, stable)| (grm, sgraph, stable))
     }
 
     /// Given the filename `a/b.y` as input, statically compile the grammar `src/a/b.y` into a Rust
@@ -303,6 +453,16 @@ where
     ///    * the `%actiontype` value given to the grammar
     ///    * or, if the `yacckind` was set YaccKind::Original(YaccOriginalActionKind::UserAction),
     ///      it is [`Node<StorageT>`](../parser/enum.Node.html)
+    ///    * or, if [`glr`](#method.glr) was set to true, it is
+    ///      [`Forest<StorageT>`](../glr/struct.Forest.html)
+    ///
+    /// `parse` parses the first rule in the grammar's `%start` declaration. If the declaration
+    /// lists several rules (e.g. `%start Module Expr`), the module additionally contains a
+    /// function `parse_<rule>` (where `<rule>` is the rule's name in lower case, e.g.
+    /// `parse_module` and `parse_expr`) for each of them, whose `ActionT` is that rule's action
+    /// type. All of these functions share the same state table. An error is returned if two of the
+    /// rules' names differ only in case, or if a rule's name contains characters (e.g. `.`) which
+    /// can't appear in a Rust identifier.
     ///
     /// # Panics
     ///
@@ -321,33 +481,148 @@ where
             None => panic!("yacckind must be specified before processing."),
             Some(YaccKind::Original(x)) => YaccKind::Original(x),
             Some(YaccKind::Grmtools) => YaccKind::Grmtools,
+            Some(YaccKind::Bison) => YaccKind::Bison,
             Some(YaccKind::Eco) => panic!("Eco compile-time grammar generation not supported."),
         };
-        let inc = read_to_string(&inp).unwrap();
-        let grm = YaccGrammar::<StorageT>::new_with_storaget(yk, &inc)?;
+        if self.glr
+            && !matches!(
+                yk,
+                YaccKind::Original(YaccOriginalActionKind::GenericParseTree)
+            )
+        {
+            return Err("GLR parsing is only supported for \
+                        YaccKind::Original(YaccOriginalActionKind::GenericParseTree) grammars"
+                .into());
+        }
+        if self.direct_coded
+            && !matches!(
+                yk,
+                YaccKind::Original(YaccOriginalActionKind::UserAction)
+                    | YaccKind::Grmtools
+                    | YaccKind::Bison
+            )
+        {
+            return Err(
+                "Direct-coded parsers are only supported for YaccKind::Grmtools, \
+                        YaccKind::Bison, and \
+                        YaccKind::Original(YaccOriginalActionKind::UserAction) grammars"
+                    .into(),
+            );
+        }
+        let source = YaccSource::read(&inp)?;
+        let grm = YaccGrammar::<StorageT>::from_source(yk, &source)
+            .map_err(|errs| CTGrammarErrors { errs })?;
+        if let YaccKind::Original(YaccOriginalActionKind::UserAction)
+        | YaccKind::Grmtools
+        | YaccKind::Bison = yk
+        {
+            // Every production (except the start productions, which are added by cfgrammar) must
+            // have an action: report the first that doesn't with its location in the grammar.
+            for pidx in grm.iter_pidxs() {
+                if grm.prod_to_rule(pidx) == grm.start_rule_idx() || grm.action(pidx).is_some() {
+                    continue;
+                }
+                // Bison grammars only need an action when the default `$$ = $1` doesn't fit.
+                let why = match yk {
+                    YaccKind::Bison => format!(
+                        " (its first symbol does not have the rule's type '{}')",
+                        grm.actiontype(grm.prod_to_rule(pidx)).unwrap()
+                    ),
+                    _ => String::new(),
+                };
+                return Err(format!(
+                    "Production in rule '{}' must have an action body{} {}",
+                    grm.rule_name(grm.prod_to_rule(pidx)),
+                    why,
+                    source.pp_span(grm.prod_span(pidx).unwrap())
+                )
+                .into());
+            }
+            // Report every invalid `$`-reference in actions at once.
+            let mut errs = Vec::new();
+            for pidx in grm.iter_pidxs() {
+                if grm.prod_to_rule(pidx) == grm.start_rule_idx() {
+                    continue;
+                }
+                if let Err(e) = rewrite_action(&grm, pidx) {
+                    errs.push(format!(
+                        "{} in action of rule '{}' {}",
+                        e,
+                        grm.rule_name(grm.prod_to_rule(pidx)),
+                        source.pp_span(grm.prod_span(pidx).unwrap())
+                    ));
+                }
+            }
+            if !errs.is_empty() {
+                return Err(errs.join("\n").into());
+            }
+        }
+        // If there are several %start rules, each gets its own parse function, whose name must be
+        // a valid Rust identifier that no other start rule's parse function also has.
+        let start_ridxs = self.user_start_ridxs(&grm);
+        if start_ridxs.len() > 1 {
+            let mut names: HashMap<String, RIdx<StorageT>> = HashMap::new();
+            for ridx in start_ridxs {
+                let name = entry_name(&grm, ridx);
+                let loc = grm
+                    .rule_name_span(ridx)
+                    .map(|span| format!(" {}", source.pp_span(span)))
+                    .unwrap_or_default();
+                if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                    return Err(format!(
+                        "Start rule '{}'{} would have the parse function '{}', which is not a valid Rust identifier",
+                        grm.rule_name(ridx),
+                        loc,
+                        name
+                    )
+                    .into());
+                }
+                if let Some(other) = names.insert(name.clone(), ridx) {
+                    return Err(format!(
+                        "Start rules '{}' and '{}'{} would both have the parse function '{}'",
+                        grm.rule_name(other),
+                        grm.rule_name(ridx),
+                        loc,
+                        name
+                    )
+                    .into());
+                }
+            }
+        }
+        if self.show_warnings {
+            for w in grm.warnings() {
+                println!(
+                    "cargo:warning={}: {}",
+                    inp.as_ref().display(),
+                    w.pp_with_source(&source)
+                );
+            }
+        }
         let rule_ids = grm
             .tokens_map()
             .iter()
             .map(|(&n, &i)| (n.to_owned(), i.as_storaget()))
             .collect::<HashMap<_, _>>();
-        let cache = self.rebuild_cache(&grm);
+        let cache = self.rebuild_cache(&grm, &source);
 
         // We don't need to go through the full rigmarole of generating an output file if all of
-        // the following are true: the output file exists; it is newer than the input file; and the
-        // cache hasn't changed. The last of these might be surprising, but it's vital: we don't
+        // the following are true: the output file (and the report file, if one was requested)
+        // exists; it is newer than the input file and every file it includes; and the cache
+        // hasn't changed. The last of these might be surprising, but it's vital: we don't
         // know, for example, what the IDs map might be from one run to the next, and it might
         // change for reasons beyond lrpar's control. If it does change, that means that the lexer
         // and lrpar would get out of sync, so we have to play it safe and regenerate in such
         // cases.
-        if let Ok(ref inmd) = fs::metadata(&inp) {
-            if let Ok(ref out_rs_md) = fs::metadata(&outp) {
-                if FileTime::from_last_modification_time(out_rs_md)
-                    > FileTime::from_last_modification_time(inmd)
-                {
-                    if let Ok(outc) = read_to_string(&outp) {
-                        if outc.contains(&cache) {
-                            return Ok(rule_ids);
-                        }
+        let report_exists = self.report_file.as_ref().is_none_or(|p| p.exists());
+        if let (true, Ok(ref out_rs_md)) = (report_exists, fs::metadata(&outp)) {
+            let out_time = FileTime::from_last_modification_time(out_rs_md);
+            if source.paths().iter().all(|p| match fs::metadata(p) {
+                Ok(ref inmd) => out_time > FileTime::from_last_modification_time(inmd),
+                Err(_) => false,
+            }) {
+                if let Ok(outc) = read_to_string(&outp) {
+                    if outc.contains(&cache) {
+                        return Ok(rule_ids);
                     }
                 }
             }
@@ -361,15 +636,6 @@ where
         // confusing than the alternatives).
         fs::remove_file(&outp).ok();
 
-        let (sgraph, stable) = from_yacc(&grm, Minimiser::Pager)?;
-        if stable.conflicts().is_some() && self.error_on_conflicts {
-            return Err(Box::new(CTConflictsError {
-                grm,
-                sgraph,
-                stable,
-            }));
-        }
-
         let mod_name = match self.mod_name {
             Some(s) => s.to_owned(),
             None => {
@@ -388,21 +654,110 @@ where
                 format!("{}_y", stem)
             }
         };
-        self.output_file(&grm, &stable, &mod_name, &outp, &cache)?;
-        if stable.conflicts().is_some() {
-            self.conflicts = Some((grm, sgraph, stable));
+
+        // State indexes take up most of a state table, so we store them in the smallest type
+        // that can index all of the grammar's states.
+        let sgraph = stategraph(&grm, self.minimiser);
+        let warnings = if usize::from(sgraph.all_states_len()) <= usize::from(u16::MAX) {
+            self.process_stable::<u16>(
+                grm,
+                sgraph,
+                CTStateTable::U16,
+                &source,
+                &mod_name,
+                outp.as_ref(),
+                &cache,
+            )?
+        } else {
+            self.process_stable::<u32>(
+                grm,
+                sgraph,
+                CTStateTable::U32,
+                &source,
+                &mod_name,
+                outp.as_ref(),
+                &cache,
+            )?
+        };
+        if self.show_warnings {
+            for w in warnings {
+                println!(
+                    "cargo:warning={}: {}",
+                    inp.as_ref().display(),
+                    w.pp_with_source(&source)
+                );
+            }
         }
         Ok(rule_ids)
     }
 
-    fn output_file<P: AsRef<Path>>(
+    /// Build the state table for `grm` and `sgraph`, storing state indexes as `StIdxT`, check it
+    /// for conflicts, and write the output file. If the table has conflicts, it is kept (wrapped
+    /// with `wrap`) for [`conflicts`](#method.conflicts). Returns the warnings about precedences
+    /// which never resolved any of the state table's conflicts.
+    #[allow(clippy::too_many_arguments)]
+    fn process_stable<StIdxT>(
+        &mut self,
+        grm: YaccGrammar<StorageT>,
+        sgraph: StateGraph<StorageT>,
+        wrap: fn(StateTable<StorageT, StIdxT>) -> CTStateTable<StorageT>,
+        source: &YaccSource,
+        mod_name: &str,
+        outp: &Path,
+        cache: &str,
+    ) -> Result<Vec<YaccGrammarWarning>, Box<dyn Error>>
+    where
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+        usize: AsPrimitive<StIdxT>,
+    {
+        let mut stable = StateTable::<StorageT, StIdxT>::new(&grm, &sgraph)?;
+        let warnings = stable.precedence_warnings(&grm);
+        self.compression_stats = if self.compress_tables {
+            Some(stable.compress())
+        } else {
+            None
+        };
+        if let Some(ref p) = self.report_file {
+            let mut r = report(&grm, &sgraph, &stable, Some(source));
+            if let Some(stats) = self.compression_stats {
+                r.push_str(&format!("\n\nTable compression\n\n{}\n", stats));
+            }
+            fs::write(p, r)?;
+        }
+        let conflicts_err = match (grm.expect(), grm.expectrr()) {
+            (None, None) => stable.conflicts().is_some() && self.error_on_conflicts && !self.glr,
+            (expect, expectrr) => {
+                conflicts_len(&stable) != (expect.unwrap_or(0), expectrr.unwrap_or(0))
+            }
+        };
+        if conflicts_err {
+            return Err(Box::new(CTConflictsError {
+                grm,
+                sgraph,
+                stable,
+            }));
+        }
+
+        self.output_file(&grm, &sgraph, &stable, mod_name, outp, cache)?;
+        if stable.conflicts().is_some() {
+            self.conflicts = Some((grm, sgraph, wrap(stable)));
+        }
+        Ok(warnings)
+    }
+
+    fn output_file<P: AsRef<Path>, StIdxT>(
         &self,
         grm: &YaccGrammar<StorageT>,
-        stable: &StateTable<StorageT>,
+        sgraph: &StateGraph<StorageT>,
+        stable: &StateTable<StorageT, StIdxT>,
         mod_name: &str,
         outp_rs: P,
         cache: &str,
-    ) -> Result<(), Box<dyn Error>> {
+    ) -> Result<(), Box<dyn Error>>
+    where
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+        usize: AsPrimitive<StIdxT>,
+    {
         let mut outs = String::new();
         outs.push_str(&format!(
             "{} mod {} {{\n",
@@ -414,11 +769,16 @@ where
 ",
         );
 
-        outs.push_str(&self.gen_parse_function(grm, stable)?);
+        outs.push_str(&self.gen_parse_function(grm, stable));
+        if self.direct_coded {
+            outs.push_str(&self.gen_direct_parser(grm, sgraph, stable));
+        }
         outs.push_str(&self.gen_rule_consts(grm));
         outs.push_str(&self.gen_token_epp(&grm));
         match self.yacckind.unwrap() {
-            YaccKind::Original(YaccOriginalActionKind::UserAction) | YaccKind::Grmtools => {
+            YaccKind::Original(YaccOriginalActionKind::UserAction)
+            | YaccKind::Grmtools
+            | YaccKind::Bison => {
                 outs.push_str(&self.gen_wrappers(&grm));
                 outs.push_str(&self.gen_user_actions(&grm));
             }
@@ -439,7 +799,7 @@ where
 
     /// Generate the cache, which determines if anything's changed enough that we need to
     /// regenerate outputs and force rustc to recompile.
-    fn rebuild_cache(&self, grm: &YaccGrammar<StorageT>) -> String {
+    fn rebuild_cache(&self, grm: &YaccGrammar<StorageT>, source: &YaccSource) -> String {
         // We don't need to be particularly clever here: we just need to record the various things
         // that could change between builds.
         let mut cache = String::new();
@@ -461,6 +821,15 @@ where
             "   Error on conflicts: {:?}\n",
             self.error_on_conflicts
         ));
+        cache.push_str(&format!("   GLR: {:?}\n", self.glr));
+        cache.push_str(&format!("   Direct coded: {:?}\n", self.direct_coded));
+        cache.push_str(&format!("   Compress tables: {:?}\n", self.compress_tables));
+        cache.push_str(&format!("   Minimiser: {:?}\n", self.minimiser));
+        cache.push_str(&format!("   Report file: {:?}\n", self.report_file));
+        // Record the included files, so that adding or removing an `%include` causes a rebuild.
+        for p in &source.paths()[1..] {
+            cache.push_str(&format!("   Included: {}\n", p.display()));
+        }
 
         // Record the rule IDs map
         for tidx in grm.iter_tidxs() {
@@ -475,49 +844,89 @@ where
         cache
     }
 
-    /// Generate the main parse() function for the output file.
-    fn gen_parse_function(
+    /// Generate the parse functions for the output file.
+    fn gen_parse_function<StIdxT>(
         &self,
         grm: &YaccGrammar<StorageT>,
-        stable: &StateTable<StorageT>,
-    ) -> Result<String, Box<dyn Error>> {
+        stable: &StateTable<StorageT, StIdxT>,
+    ) -> String
+    where
+        StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+        usize: AsPrimitive<StIdxT>,
+    {
         let mut outs = String::new();
 
-        // bincode format is serialized into constants which the generated
-        // source code.
-        serialize_bin_output(grm, GRM_CONST_NAME, &mut outs)?;
-        serialize_bin_output(stable, STABLE_CONST_NAME, &mut outs)?;
+        // The grammar and statetable are constructed at compile-time, borrowing their data
+        // directly from static arrays, so the parse functions can use them without any set-up.
+        outs.push_str(&format!(
+            "#[allow(dead_code)] static {tables}: ::lrpar::ctbuilder::_CTTables<{storaget}, {stidxt}> = \
+             ::lrpar::ctbuilder::_CTTables::new({grm}, {stable});\n",
+            tables = TABLES_STATIC_NAME,
+            storaget = type_name::<StorageT>(),
+            stidxt = type_name::<StIdxT>(),
+            grm = grm._to_static(),
+            stable = stable._to_static("::lrpar::ctbuilder::_lrtable")
+        ));
+
+        // parse() parses the first %start rule. If there are several %start rules, each of them
+        // also gets its own parse_<rule>() function.
+        let start_ridxs = self.user_start_ridxs(grm);
+        outs.push_str(&self.gen_parse_entry(grm, "parse", start_ridxs[0]));
+        if start_ridxs.len() > 1 {
+            for &ridx in &start_ridxs {
+                outs.push_str(&self.gen_parse_entry(grm, &entry_name(grm, ridx), ridx));
+            }
+        }
+        outs
+    }
 
+    /// Generate a parse function `name` which parses input as an instance of the `%start` rule
+    /// `ridx`.
+    fn gen_parse_entry(
+        &self,
+        grm: &YaccGrammar<StorageT>,
+        name: &str,
+        ridx: RIdx<StorageT>,
+    ) -> String {
+        let mut outs = String::new();
         match self.yacckind.unwrap() {
-            YaccKind::Original(YaccOriginalActionKind::UserAction) | YaccKind::Grmtools => {
+            YaccKind::Original(YaccOriginalActionKind::UserAction)
+            | YaccKind::Grmtools
+            | YaccKind::Bison => {
                 outs.push_str(&format!(
                     "
     #[allow(dead_code)]
-    pub fn parse<'lexer, 'input: 'lexer>(lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>)
+    pub fn {name}<'lexer, 'input: 'lexer{lifetimes}>(lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>{params})
           -> (::std::option::Option<{actiont}>, ::std::vec::Vec<::lrpar::LexParseError<{storaget}>>)
     {{",
+                    name = name,
+                    lifetimes = self.param_lifetimes(grm),
+                    params = self.param_list(grm, true),
                     storaget = type_name::<StorageT>(),
-                    actiont = grm.actiontype(self.user_start_ridx(grm)).as_ref().unwrap()
+                    actiont = grm.actiontype(ridx).unwrap()
                 ));
             }
             YaccKind::Original(YaccOriginalActionKind::GenericParseTree) => {
                 outs.push_str(&format!(
                     "
     #[allow(dead_code)]
-    pub fn parse(lexer: &dyn ::lrpar::NonStreamingLexer<{storaget}>)
-          -> (::std::option::Option<::lrpar::Node<{storaget}>>,
+    pub fn {name}(lexer: &dyn ::lrpar::NonStreamingLexer<{storaget}>)
+          -> (::std::option::Option<::lrpar::{tree}<{storaget}>>,
               ::std::vec::Vec<::lrpar::LexParseError<{storaget}>>)
     {{",
-                    storaget = type_name::<StorageT>()
+                    name = name,
+                    storaget = type_name::<StorageT>(),
+                    tree = if self.glr { "Forest" } else { "Node" }
                 ));
             }
             YaccKind::Original(YaccOriginalActionKind::NoAction) => {
                 outs.push_str(&format!(
                     "
     #[allow(dead_code)]
-    pub fn parse(lexer: &dyn ::lrpar::NonStreamingLexer<{storaget}>)
+    pub fn {name}(lexer: &dyn ::lrpar::NonStreamingLexer<{storaget}>)
           -> ::std::vec::Vec<::lrpar::LexParseError<{storaget}>>
     {{",
+                    name = name,
                     storaget = type_name::<StorageT>()
                 ));
             }
@@ -526,72 +935,314 @@ where
 
         outs.push_str(&format!(
             "
-        let (grm, stable) = ::lrpar::ctbuilder::_reconstitute({}, {});",
-            GRM_CONST_NAME, STABLE_CONST_NAME
+        let (grm, stable) = {}.get();",
+            TABLES_STATIC_NAME
         ));
 
+        // If the grammar has several entry points, the parser has to be told which to start from.
+        let start_rule = if grm.start_prods().len() > 1 {
+            format!(
+                "\n            .start_rule(::cfgrammar::RIdx({}))",
+                usize::from(ridx)
+            )
+        } else {
+            String::new()
+        };
         let recoverer = match self.recoverer {
             RecoveryKind::CPCTPlus => "CPCTPlus",
             RecoveryKind::None => "None",
         };
         match self.yacckind.unwrap() {
-            YaccKind::Original(YaccOriginalActionKind::UserAction) | YaccKind::Grmtools => {
-                // action function references
-                outs.push_str(&format!(
-                    "\n        #[allow(clippy::type_complexity)]
-        let mut actions: ::std::vec::Vec<&dyn Fn(::cfgrammar::RIdx<{storaget}>,
-                       &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>,
-                       ::lrpar::Span,
-                       ::std::vec::Drain<::lrpar::parser::AStackType<{actionskind}<'input>, {storaget}>>)
-                    -> {actionskind}<'input>> = ::std::vec::Vec::new();\n",
-                    actionskind = ACTIONS_KIND,
-                    storaget = type_name::<StorageT>()
-                ));
-                for pidx in grm.iter_pidxs() {
-                    outs.push_str(&format!(
-                        "        actions.push(&{prefix}wrapper_{});\n",
-                        usize::from(pidx),
-                        prefix = ACTION_PREFIX
-                    ))
+            YaccKind::Original(YaccOriginalActionKind::UserAction)
+            | YaccKind::Grmtools
+            | YaccKind::Bison => {
+                // If every rule has the same action type, values are stored on the action stack
+                // directly; otherwise they are stored in the action values struct and the start
+                // rule's value has to be taken from it.
+                let value_types = self.value_types(grm);
+                // `%parse_param` arguments, and the action values struct, are passed on to the
+                // actions by wrapping the dispatch functions in closures which capture them.
+                let mut args = self.param_list(grm, false);
+                if value_types.is_some() {
+                    args.push_str(&format!(", &mut {}values.borrow_mut()", ACTION_PREFIX));
+                }
+                let (actions, direct) = if args.is_empty() {
+                    (
+                        format!("&{}actions", ACTION_PREFIX),
+                        format!("&{}direct", ACTION_PREFIX),
+                    )
+                } else {
+                    (
+                        format!(
+                            "&|pidx, ridx, lexer, span, args| {}actions(pidx, ridx, lexer, span, args{})",
+                            ACTION_PREFIX, args
+                        ),
+                        format!(
+                            "&|lexer, lexemes, laidx, pstack, astack, spans| {}direct(lexer, lexemes, laidx, pstack, astack, spans{})",
+                            ACTION_PREFIX, args
+                        ),
+                    )
+                };
+                let (setup, parse) = if self.direct_coded {
+                    (
+                        format!(
+                            "
+        let builder = ::lrpar::RTParserBuilder::new(grm, stable)
+            .recoverer(::lrpar::RecoveryKind::{recoverer}){start_rule};",
+                            recoverer = recoverer,
+                            start_rule = start_rule
+                        ),
+                        format!(
+                            "match builder._parse_direct(lexer, {direct}) {{
+            ::lrpar::parser::_DirectOutcome::Accept(v) => (Some(v), ::std::vec::Vec::new()),
+            ::lrpar::parser::_DirectOutcome::LexError(e) => (None, e),
+            ::lrpar::parser::_DirectOutcome::ParseError(state) => {{
+                builder._resume_dispatch(lexer, {actions}, state)
+            }}
+        }}",
+                            actions = actions,
+                            direct = direct
+                        ),
+                    )
+                } else {
+                    (
+                        String::new(),
+                        format!(
+                            "::lrpar::RTParserBuilder::new(grm, stable)
+            .recoverer(::lrpar::RecoveryKind::{recoverer}){start_rule}
+            ._parse_dispatch(lexer, {actions})",
+                            recoverer = recoverer,
+                            start_rule = start_rule,
+                            actions = actions
+                        ),
+                    )
+                };
+                match value_types {
+                    None => outs.push_str(&format!("{}\n        {}", setup, parse)),
+                    Some(types) => outs.push_str(&format!(
+                        "
+        let {prefix}values = ::std::cell::RefCell::new({values}::default());{setup}
+        let (v, errs) = {parse};
+        (v.map(|i| {prefix}values.into_inner().v{stack}.swap_remove(i)), errs)",
+                        prefix = ACTION_PREFIX,
+                        values = ACTION_VALUES,
+                        setup = setup,
+                        parse = parse,
+                        stack = value_stack(&types, grm, ridx)
+                    )),
                 }
+            }
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree) if self.glr => {
                 outs.push_str(&format!(
                     "
-        match ::lrpar::RTParserBuilder::new(&grm, &stable)
-            .recoverer(::lrpar::RecoveryKind::{recoverer})
-            .parse_actions(lexer, &actions) {{
-                (Some({actionskind}::{actionskindprefix}{ridx}(x)), y) => (Some(x), y),
-                (None, y) => (None, y),
-                _ => unreachable!()
-        }}",
-                    actionskind = ACTIONS_KIND,
-                    actionskindprefix = ACTIONS_KIND_PREFIX,
-                    ridx = usize::from(self.user_start_ridx(grm)),
-                    recoverer = recoverer,
+        ::lrpar::RTParserBuilder::new(grm, stable){}
+            .parse_glr(lexer)\n",
+                    start_rule
                 ));
             }
             YaccKind::Original(YaccOriginalActionKind::GenericParseTree) => {
                 outs.push_str(&format!(
                     "
-        ::lrpar::RTParserBuilder::new(&grm, &stable)
-            .recoverer(::lrpar::RecoveryKind::{})
+        ::lrpar::RTParserBuilder::new(grm, stable)
+            .recoverer(::lrpar::RecoveryKind::{}){}
             .parse_generictree(lexer)\n",
-                    recoverer
+                    recoverer, start_rule
                 ));
             }
             YaccKind::Original(YaccOriginalActionKind::NoAction) => {
                 outs.push_str(&format!(
                     "
-        ::lrpar::RTParserBuilder::new(&grm, &stable)
-            .recoverer(::lrpar::RecoveryKind::{})
+        ::lrpar::RTParserBuilder::new(grm, stable)
+            .recoverer(::lrpar::RecoveryKind::{}){}
             .parse_noaction(lexer)\n",
-                    recoverer
+                    recoverer, start_rule
                 ));
             }
             YaccKind::Eco => unreachable!(),
         };
 
         outs.push_str("\n    }\n\n");
-        Ok(outs)
+        outs
+    }
+
+    /// Generate a direct-coded parser (see [`direct_coded`](#method.direct_coded)): a function
+    /// `{ACTION_PREFIX}direct` which `match`es on the current state and lookahead rather than
+    /// looking them up in `stable`, and a function `{ACTION_PREFIX}reduce_<pidx>` for each
+    /// production which performs that production's reduction and goto, and calls its action
+    /// wrapper.
+    fn gen_direct_parser<StIdxT>(
+        &self,
+        grm: &YaccGrammar<StorageT>,
+        sgraph: &StateGraph<StorageT>,
+        stable: &StateTable<StorageT, StIdxT>,
+    ) -> String
+    where
+        StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+        usize: AsPrimitive<StIdxT>,
+    {
+        let storaget = type_name::<StorageT>();
+        let stidxt = type_name::<StIdxT>();
+        let lifetimes = self.param_lifetimes(grm);
+        let param_decls = self.param_list(grm, true) + &self.values_param(grm, true);
+        let param_args = self.param_list(grm, false) + &self.values_param(grm, false);
+        let mut outs = String::new();
+        outs.push_str(&format!(
+            "
+    #[allow(clippy::all, unused_mut)]
+    fn {prefix}direct<'lexer, 'input: 'lexer{lifetimes}>(
+        lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>,
+        lexemes: &[::lrpar::Lexeme<{storaget}>],
+        laidx: &mut usize,
+        pstack: &mut ::std::vec::Vec<::lrpar::StIdx<{stidxt}>>,
+        astack: &mut ::std::vec::Vec<::lrpar::parser::AStackType<{actionskind}<'input>, {storaget}>>,
+        spans: &mut ::std::vec::Vec<::lrpar::Span>{params})
+     -> ::std::option::Option<{actionskind}<'input>>
+    {{
+        loop {{
+            let la_tidx = match lexemes.get(*laidx) {{
+                Some(l) => l.tok_id(),
+                None => {eof},
+            }};
+            match usize::from(*pstack.last().unwrap()) {{\n",
+            prefix = ACTION_PREFIX,
+            lifetimes = lifetimes,
+            params = param_decls,
+            storaget = storaget,
+            stidxt = stidxt,
+            actionskind = ACTIONS_KIND,
+            eof = usize::from(grm.eof_token_idx())
+        ));
+
+        // The productions which are reduced in at least one state.
+        let mut reduced = Vob::from_elem(usize::from(grm.prods_len()), false);
+        for stidx in sgraph.iter_stidxs() {
+            // Group together the tokens which lead to the same action.
+            let mut actions: Vec<(Action<StorageT, StIdxT>, Vec<usize>)> = Vec::new();
+            for tidx in grm.iter_tidxs() {
+                let a = stable.action(st_stidx(stidx), tidx);
+                if a == Action::Error {
+                    continue;
+                }
+                match actions.iter_mut().find(|(b, _)| *b == a) {
+                    Some((_, toks)) => toks.push(usize::from(tidx)),
+                    None => actions.push((a, vec![usize::from(tidx)])),
+                }
+            }
+            outs.push_str(&format!(
+                "                {} => match la_tidx {{\n",
+                usize::from(stidx)
+            ));
+            for (a, toks) in actions {
+                let toks = toks
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect::<Vec<_>>()
+                    .join(" | ");
+                let body = match a {
+                    Action::Shift(s_stidx) => format!(
+                        "{{
+                        let l = lexemes[*laidx];
+                        pstack.push(::lrpar::StIdx::from({}{}));
+                        astack.push(::lrpar::parser::AStackType::Lexeme(l));
+                        spans.push(l.span());
+                        *laidx += 1;
+                    }}",
+                        usize::from(s_stidx),
+                        stidxt
+                    ),
+                    Action::Reduce(pidx) => {
+                        reduced.set(usize::from(pidx), true);
+                        format!(
+                            "{}reduce_{}(lexer, pstack, astack, spans{}),",
+                            ACTION_PREFIX,
+                            usize::from(pidx),
+                            param_args
+                        )
+                    }
+                    Action::Accept => "{
+                        debug_assert_eq!(astack.len(), 1);
+                        return match astack.pop().unwrap() {
+                            ::lrpar::parser::AStackType::ActionType(v) => Some(v),
+                            _ => unreachable!()
+                        };
+                    }"
+                    .to_owned(),
+                    Action::Error => unreachable!(),
+                };
+                outs.push_str(&format!("                    {} => {}\n", toks, body));
+            }
+            outs.push_str("                    _ => return None,\n                },\n");
+        }
+        outs.push_str(
+            "                _ => unreachable!()
+            }
+        }
+    }
+",
+        );
+
+        for pidx in grm.iter_pidxs().filter(|&pidx| reduced[usize::from(pidx)]) {
+            let ridx = grm.prod_to_rule(pidx);
+            // Group together the states which lead to the same goto.
+            let mut gotos: Vec<(StIdx<StIdxT>, Vec<usize>)> = Vec::new();
+            for stidx in sgraph.iter_stidxs() {
+                if let Some(g_stidx) = stable.goto(st_stidx(stidx), ridx) {
+                    match gotos.iter_mut().find(|(g, _)| *g == g_stidx) {
+                        Some((_, stidxs)) => stidxs.push(usize::from(stidx)),
+                        None => gotos.push((g_stidx, vec![usize::from(stidx)])),
+                    }
+                }
+            }
+            outs.push_str(&format!(
+                "
+    #[allow(clippy::all)]
+    #[inline]
+    fn {prefix}reduce_{pidx}<'lexer, 'input: 'lexer{lifetimes}>(
+        lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>,
+        pstack: &mut ::std::vec::Vec<::lrpar::StIdx<{stidxt}>>,
+        astack: &mut ::std::vec::Vec<::lrpar::parser::AStackType<{actionskind}<'input>, {storaget}>>,
+        spans: &mut ::std::vec::Vec<::lrpar::Span>{params})
+    {{
+        let pop_idx = pstack.len() - {len};
+        pstack.truncate(pop_idx);
+        let goto = match usize::from(*pstack.last().unwrap()) {{\n",
+                prefix = ACTION_PREFIX,
+                pidx = usize::from(pidx),
+                lifetimes = lifetimes,
+                params = param_decls,
+                storaget = storaget,
+                stidxt = stidxt,
+                actionskind = ACTIONS_KIND,
+                len = grm.prod(pidx).len()
+            ));
+            for (g_stidx, stidxs) in gotos {
+                outs.push_str(&format!(
+                    "            {} => {}{},\n",
+                    stidxs
+                        .iter()
+                        .map(|s| s.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" | "),
+                    usize::from(g_stidx),
+                    stidxt
+                ));
+            }
+            outs.push_str(&format!(
+                "            _ => unreachable!()
+        }};
+        pstack.push(::lrpar::StIdx::from(goto));
+        let span = ::lrpar::parser::_reduce_span(spans, pop_idx);
+        let v = {prefix}wrapper_{pidx}(::cfgrammar::RIdx({ridx}), lexer, span, astack{args});
+        astack.push(::lrpar::parser::AStackType::ActionType(v));
+    }}
+",
+                prefix = ACTION_PREFIX,
+                pidx = usize::from(pidx),
+                ridx = usize::from(ridx),
+                args = param_args
+            ));
+        }
+        outs
     }
 
     fn gen_rule_consts(&self, grm: &YaccGrammar<StorageT>) -> String {
@@ -638,43 +1289,65 @@ where
 
         outs.push_str("\n\n    // Wrappers\n\n");
 
+        let value_types = self.value_types(grm);
+        let lifetimes = self.param_lifetimes(grm);
+        let param_decls = self.param_list(grm, true) + &self.values_param(grm, true);
+        let param_args = self.param_list(grm, false) + &self.values_param(grm, false);
         for pidx in grm.iter_pidxs() {
             let ridx = grm.prod_to_rule(pidx);
 
             // Iterate over all $-arguments and replace them with their respective
-            // element from the argument vector (e.g. $1 is replaced by args[0]). At
-            // the same time extract &str from tokens and actiontype from nonterminals.
+            // element from the action stack (e.g. $1 is replaced by the element that was pushed
+            // first). At the same time extract &str from tokens and actiontype from nonterminals.
             outs.push_str(&format!(
-                "    fn {prefix}wrapper_{}<'lexer, 'input: 'lexer>({prefix}ridx: ::cfgrammar::RIdx<{storaget}>,
+                "    #[inline]
+    fn {prefix}wrapper_{}<'lexer, 'input: 'lexer{lifetimes}>({prefix}ridx: ::cfgrammar::RIdx<{storaget}>,
                       {prefix}lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>,
                       {prefix}span: ::lrpar::Span,
-                      mut {prefix}args: ::std::vec::Drain<::lrpar::parser::AStackType<{actionskind}<'input>, {storaget}>>)
+                      {prefix}args: &mut ::std::vec::Vec<::lrpar::parser::AStackType<{actionskind}<'input>, {storaget}>>{params})
                    -> {actionskind}<'input> {{",
                 usize::from(pidx),
+                lifetimes = lifetimes,
+                params = param_decls,
                 storaget = type_name::<StorageT>(),
                 prefix = ACTION_PREFIX,
                 actionskind = ACTIONS_KIND,
             ));
 
             if grm.action(pidx).is_some() {
-                // Unpack the arguments passed to us by the drain
-                for i in 0..grm.prod(pidx).len() {
+                // Pop the arguments from the action stack: since the last argument is on top of
+                // the stack, they are popped in reverse order.
+                for i in (0..grm.prod(pidx).len()).rev() {
                     match grm.prod(pidx)[i] {
-                        Symbol::Rule(ref_ridx) => outs.push_str(&format!(
-                            "
-        let {prefix}arg_{i} = match {prefix}args.next().unwrap() {{
-            ::lrpar::parser::AStackType::ActionType({actionskind}::{actionskindprefix}{ref_ridx}(x)) => x,
+                        Symbol::Rule(ref_ridx) => {
+                            // If rules have different action types, the action stack stores the
+                            // index of the value in its type's stack. Error recovery may have
+                            // discarded values above it, so those are discarded too.
+                            let x = match value_types {
+                                None => "x".to_owned(),
+                                Some(ref types) => format!(
+                                    "{{
+                {prefix}values.v{stack}.truncate(x + 1);
+                {prefix}values.v{stack}.pop().unwrap()
+            }}",
+                                    prefix = ACTION_PREFIX,
+                                    stack = value_stack(types, grm, ref_ridx)
+                                ),
+                            };
+                            outs.push_str(&format!(
+                                "
+        let {prefix}arg_{i} = match {prefix}args.pop().unwrap() {{
+            ::lrpar::parser::AStackType::ActionType(x) => {x},
             _ => unreachable!()
         }};",
-                            i = i + 1,
-                            ref_ridx = usize::from(ref_ridx),
-                            prefix = ACTION_PREFIX,
-                            actionskind = ACTIONS_KIND,
-                            actionskindprefix = ACTIONS_KIND_PREFIX
-                        )),
+                                i = i + 1,
+                                x = x,
+                                prefix = ACTION_PREFIX,
+                            ))
+                        }
                         Symbol::Token(_) => outs.push_str(&format!(
                             "
-        let {prefix}arg_{} = match {prefix}args.next().unwrap() {{
+        let {prefix}arg_{} = match {prefix}args.pop().unwrap() {{
             ::lrpar::parser::AStackType::Lexeme(l) => {{
                 if l.inserted() {{
                     Err(l)
@@ -686,40 +1359,46 @@ where
         }};",
                             i + 1,
                             prefix = ACTION_PREFIX
-                        ))
+                        )),
                     }
                 }
 
                 // Call the user code
                 let args = (0..grm.prod(pidx).len())
-                    .map(|i| format!("{prefix}arg_{i}", prefix = ACTION_PREFIX, i = i + 1))
-                    .collect::<Vec<_>>();
-                // If the rule `r` that we're calling has the unit type then Clippy will warn that
-                // `enum::A(wrapper_r())` is pointless. We thus have to split it into two:
-                // `wrapper_r(); enum::A(())`.
-                match grm.actiontype(ridx) {
-                    Some(s) if s == "()" => {
-                        outs.push_str(&format!("\n        {prefix}action_{pidx}({prefix}ridx, {prefix}lexer, {prefix}span, {args});
-        {actionskind}::{actionskindprefix}{ridx}(())",
-                            actionskind = ACTIONS_KIND,
-                            actionskindprefix = ACTIONS_KIND_PREFIX,
-                            prefix = ACTION_PREFIX,
-                            ridx = usize::from(ridx),
-                            pidx = usize::from(pidx),
-                            args = args.join(", ")));
-                    }
-                    _ => {
-                        outs.push_str(&format!("\n        {actionskind}::{actionskindprefix}{ridx}({prefix}action_{pidx}({prefix}ridx, {prefix}lexer, {prefix}span, {args}))",
-                            actionskind = ACTIONS_KIND,
-                            actionskindprefix = ACTIONS_KIND_PREFIX,
+                    .map(|i| format!(", {prefix}arg_{i}", prefix = ACTION_PREFIX, i = i + 1))
+                    .collect::<String>();
+                let call = format!(
+                    "{prefix}action_{pidx}({prefix}ridx, {prefix}lexer, {prefix}span{args}{params})",
+                    prefix = ACTION_PREFIX,
+                    pidx = usize::from(pidx),
+                    args = args,
+                    params = self.param_list(grm, false)
+                );
+                match value_types {
+                    None => outs.push_str(&format!("\n        {}", call)),
+                    Some(ref types) => {
+                        // If the rule `r` that we're calling has the unit type then Clippy will
+                        // warn that `let v = action_r();` is pointless. We thus have to split it
+                        // into `action_r(); stack.push(());`.
+                        let v = if grm.actiontype(ridx).unwrap() == "()" {
+                            outs.push_str(&format!("\n        {};", call));
+                            "()"
+                        } else {
+                            outs.push_str(&format!("\n        let v = {};", call));
+                            "v"
+                        };
+                        outs.push_str(&format!(
+                            "
+        {prefix}values.v{stack}.push({v});
+        {prefix}values.v{stack}.len() - 1",
                             prefix = ACTION_PREFIX,
-                            ridx = usize::from(ridx),
-                            pidx = usize::from(pidx),
-                            args = args.join(", ")));
+                            stack = value_stack(types, grm, ridx),
+                            v = v
+                        ));
                     }
                 }
-            } else if pidx == grm.start_prod() {
-                // The action for the start production (i.e. the extra rule/production
+            } else if ridx == grm.start_rule_idx() {
+                // The action for a start production (i.e. the extra rule/production
                 // added by lrpar) will never be executed, so a dummy function is all
                 // that's required. We add "unreachable" as a check in case some other
                 // detail of lrpar changes in the future.
@@ -733,30 +1412,63 @@ where
             outs.push_str("\n    }\n\n");
         }
 
-        // Wrappers enum
-
+        // The function which dispatches to each production's wrapper.
         outs.push_str(&format!(
-            "    #[allow(dead_code)]
-    enum {}<'input> {{\n",
-            ACTIONS_KIND
+            "    #[allow(clippy::all)]
+    fn {prefix}actions<'lexer, 'input: 'lexer{lifetimes}>({prefix}pidx: ::cfgrammar::PIdx<{storaget}>,
+                      {prefix}ridx: ::cfgrammar::RIdx<{storaget}>,
+                      {prefix}lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>,
+                      {prefix}span: ::lrpar::Span,
+                      {prefix}args: &mut ::std::vec::Vec<::lrpar::parser::AStackType<{actionskind}<'input>, {storaget}>>{params})
+                   -> {actionskind}<'input> {{
+        match usize::from({prefix}pidx) {{\n",
+            lifetimes = lifetimes,
+            params = param_decls,
+            storaget = type_name::<StorageT>(),
+            prefix = ACTION_PREFIX,
+            actionskind = ACTIONS_KIND,
         ));
-        for ridx in grm.iter_rules() {
-            if grm.actiontype(ridx).is_none() {
-                continue;
-            }
-
+        for pidx in grm.iter_pidxs() {
             outs.push_str(&format!(
-                "        {actionskindprefix}{ridx}({actiont}),\n",
-                actionskindprefix = ACTIONS_KIND_PREFIX,
-                ridx = usize::from(ridx),
-                actiont = grm.actiontype(ridx).as_ref().unwrap()
+                "            {pidx} => {prefix}wrapper_{pidx}({prefix}ridx, {prefix}lexer, {prefix}span, {prefix}args{params}),\n",
+                pidx = usize::from(pidx),
+                prefix = ACTION_PREFIX,
+                params = param_args
             ));
         }
+        outs.push_str("            _ => unreachable!()\n        }\n    }\n\n");
+
+        // Action values
+
+        let types = match value_types {
+            None => {
+                // Every rule has the same action type, so values are stored directly on the
+                // action stack.
+                outs.push_str(&format!(
+                    "    type {}<'input> = {};\n\n",
+                    ACTIONS_KIND,
+                    self.uniform_actiontype(grm).unwrap()
+                ));
+                return outs;
+            }
+            Some(types) => types,
+        };
+        // Otherwise each action type has its own stack of values, and the action stack stores
+        // indexes into them.
         outs.push_str(&format!(
-            "    _{actionskindhidden}(::std::marker::PhantomData<&'input ()>)
-    }}\n\n",
-            actionskindhidden = ACTIONS_KIND_HIDDEN
+            "    type {}<'input> = usize;
+
+    #[derive(Default)]
+    struct {}<'input> {{\n",
+            ACTIONS_KIND, ACTION_VALUES
         ));
+        for (i, actiont) in types.iter().enumerate() {
+            outs.push_str(&format!("        v{}: ::std::vec::Vec<{}>,\n", i, actiont));
+        }
+        outs.push_str(
+            "        _phantom: ::std::marker::PhantomData<&'input ()>
+    }\n\n",
+        );
 
         outs
     }
@@ -773,7 +1485,7 @@ where
         // Convert actions to functions
         outs.push_str("\n    // User actions\n\n");
         for pidx in grm.iter_pidxs() {
-            if pidx == grm.start_prod() {
+            if grm.prod_to_rule(pidx) == grm.start_rule_idx() {
                 continue;
             }
 
@@ -781,7 +1493,7 @@ where
             let mut args = Vec::with_capacity(grm.prod(pidx).len());
             for i in 0..grm.prod(pidx).len() {
                 let argt = match grm.prod(pidx)[i] {
-                    Symbol::Rule(ref_ridx) => grm.actiontype(ref_ridx).as_ref().unwrap().clone(),
+                    Symbol::Rule(ref_ridx) => grm.actiontype(ref_ridx).unwrap().to_owned(),
                     Symbol::Token(_) => format!(
                         "::std::result::Result<::lrpar::Lexeme<{storaget}>, ::lrpar::Lexeme<{storaget}>>",
                         storaget = type_name::<StorageT>()
@@ -789,12 +1501,22 @@ where
                 };
                 args.push(format!("mut {}arg_{}: {}", ACTION_PREFIX, i + 1, argt));
             }
+            // The `%parse_param` parameters are bound to the names (or patterns) the user gave
+            // them, so actions which don't use them would otherwise trigger warnings.
+            for (binding, typ) in grm.param_args() {
+                args.push(format!("{}: {}", binding, typ));
+            }
+            let allow = if grm.param_args().is_empty() {
+                "clippy::too_many_arguments"
+            } else {
+                "clippy::too_many_arguments, unused_variables"
+            };
 
             // If this rule's `actiont` is `()` then Clippy will warn that the return type `-> ()`
             // is pointless (which is true). We therefore avoid outputting a return type if actiont
             // is the unit type.
             let returnt = {
-                let actiont = grm.actiontype(grm.prod_to_rule(pidx)).as_ref().unwrap();
+                let actiont = grm.actiontype(grm.prod_to_rule(pidx)).unwrap();
                 if actiont == "()" {
                     "".to_owned()
                 } else {
@@ -803,148 +1525,314 @@ where
             };
             outs.push_str(&format!(
                 "    // {rulename}
-    #[allow(clippy::too_many_arguments)]
-    fn {prefix}action_{}<'lexer, 'input: 'lexer>({prefix}ridx: ::cfgrammar::RIdx<{storaget}>,
+    #[allow({allow})]
+    fn {prefix}action_{}<'lexer, 'input: 'lexer{lifetimes}>({prefix}ridx: ::cfgrammar::RIdx<{storaget}>,
                      {prefix}lexer: &'lexer dyn ::lrpar::NonStreamingLexer<'input, {storaget}>,
                      {prefix}span: ::lrpar::Span,
                      {args}) {returnt} {{\n",
                 usize::from(pidx),
                 rulename = grm.rule_name(grm.prod_to_rule(pidx)),
+                allow = allow,
+                lifetimes = self.param_lifetimes(grm),
                 storaget = type_name::<StorageT>(),
                 prefix = ACTION_PREFIX,
                 returnt = returnt,
                 args = args.join(",\n                     ")
             ));
 
-            // Iterate over all $-arguments and replace them with their respective
-            // element from the argument vector (e.g. $1 is replaced by args[0]).
-            let pre_action = grm.action(pidx).as_ref().unwrap();
-            let mut last = 0;
-            loop {
-                match pre_action[last..].find('$') {
-                    Some(off) => {
-                        if pre_action[last + off..].starts_with("$$") {
-                            outs.push_str(&pre_action[last..last + off + "$".len()]);
-                            last = last + off + "$$".len();
-                        } else if pre_action[last + off..].starts_with("$lexer") {
-                            outs.push_str(&pre_action[last..last + off]);
-                            outs.push_str(
-                                format!("{prefix}lexer", prefix = ACTION_PREFIX).as_str(),
-                            );
-                            last = last + off + "$lexer".len();
-                        } else if pre_action[last + off..].starts_with("$span") {
-                            outs.push_str(&pre_action[last..last + off]);
-                            outs.push_str(format!("{prefix}span", prefix = ACTION_PREFIX).as_str());
-                            last = last + off + "$span".len();
-                        } else if last + off + 1 < pre_action.len()
-                            && pre_action[last + off + 1..].starts_with(|c: char| c.is_numeric())
-                        {
-                            outs.push_str(&pre_action[last..last + off]);
-                            outs.push_str(format!("{prefix}arg_", prefix = ACTION_PREFIX).as_str());
-                            last = last + off + "$".len();
-                        } else {
-                            panic!(
-                                "Unknown text following '$' operator: {}",
-                                &pre_action[last + off..]
-                            );
-                        }
-                    }
-                    None => {
-                        outs.push_str(&pre_action[last..]);
-                        break;
-                    }
-                }
-            }
+            // Named references were checked when the grammar was loaded, so this can't fail.
+            outs.push_str(&rewrite_action(grm, pidx).unwrap());
 
             outs.push_str("\n    }\n\n");
         }
         outs
     }
 
-    /// Return the `RIdx` of the %start rule in the grammar (which will not be the same as
-    /// grm.start_rule_idx because the latter has an additional rule insert by cfgrammar
-    /// which then calls the user's %start rule).
-    fn user_start_ridx(&self, grm: &YaccGrammar<StorageT>) -> RIdx<StorageT> {
-        debug_assert_eq!(grm.prod(grm.start_prod()).len(), 1);
-        match grm.prod(grm.start_prod())[0] {
-            Symbol::Rule(ridx) => ridx,
-            _ => unreachable!(),
-        }
+    /// Return the lifetimes declared by `grm`'s `%parse_param` (each preceded by `, `), for
+    /// appending to the generic parameters of generated functions.
+    fn param_lifetimes(&self, grm: &YaccGrammar<StorageT>) -> String {
+        let mut lifetimes = grm.param_lifetimes().to_vec();
+        lifetimes.sort();
+        lifetimes.iter().map(|l| format!(", {}", l)).collect()
     }
-}
-
-/// Return a version of the string `s` which is safe to embed in source code as a string.
-fn str_escape(s: &str) -> String {
-    s.replace("\\", "\\\\").replace("\"", "\\\"")
-}
-
-/// This function is called by generated files; it exists so that generated files don't require a
-/// dependency on serde and rmps.
-#[doc(hidden)]
-pub fn _reconstitute<StorageT: DeserializeOwned + Hash + PrimInt + Unsigned>(
-    grm_buf: &[u8],
-    stable_buf: &[u8],
-) -> (YaccGrammar<StorageT>, StateTable<StorageT>) {
-    let grm = deserialize(grm_buf).unwrap();
-    let stable = deserialize(stable_buf).unwrap();
-    (grm, stable)
-}
 
-fn serialize_bin_output<T: Serialize + ?Sized>(
-    ser: &T,
-    name: &str,
-    buffer: &mut String,
-) -> Result<(), Box<dyn Error>> {
-    let mut w = ArrayWriter::new(name);
-    serialize_into(&mut w, ser)?;
-    let data = w.finish();
-    buffer.push_str(&data);
-    Ok(())
-}
-
-/// Makes formatting bytes into a rust array relatively painless.
-struct ArrayWriter {
-    buffer: String,
-}
+    /// Return the parameters declared by `grm`'s `%parse_param` (each preceded by `, `) as
+    /// generated functions pass them on to the actions: as declarations (e.g.
+    /// `, __gt_param_0: &'a u64`) if `decls` is true, or as arguments (e.g. `, __gt_param_0`)
+    /// otherwise. Since every action is passed the same values, their types must be `Copy`.
+    fn param_list(&self, grm: &YaccGrammar<StorageT>, decls: bool) -> String {
+        grm.param_args()
+            .iter()
+            .enumerate()
+            .map(|(i, (_, typ))| {
+                if decls {
+                    format!(", {}param_{}: {}", ACTION_PREFIX, i, typ)
+                } else {
+                    format!(", {}param_{}", ACTION_PREFIX, i)
+                }
+            })
+            .collect()
+    }
 
-impl ArrayWriter {
-    /// create a new array with the specified name
-    fn new(name: &str) -> Self {
-        Self {
-            buffer: format!(r#"#[allow(dead_code)] const {}: &[u8] = &["#, name),
+    /// If every rule in `grm` (other than the start rule) has the same action type, return it.
+    fn uniform_actiontype<'g>(&self, grm: &'g YaccGrammar<StorageT>) -> Option<&'g str> {
+        let mut actiont = None;
+        for ridx in grm.iter_rules() {
+            if ridx == grm.start_rule_idx() {
+                continue;
+            }
+            match (actiont, grm.actiontype(ridx)) {
+                (_, None) => return None,
+                (None, Some(t)) => actiont = Some(t),
+                (Some(s), Some(t)) if s != t => return None,
+                _ => (),
+            }
         }
+        actiont
     }
 
-    /// complete the array, and return the finished string
-    fn finish(mut self) -> String {
-        self.buffer.push_str("];\n");
-        self.buffer
+    /// If the rules in `grm` (other than the start rule) have different action types, return
+    /// each of those types once, in the order in which they are first used. The values of each
+    /// type are stored in their own stack in the generated action values struct.
+    fn value_types<'g>(&self, grm: &'g YaccGrammar<StorageT>) -> Option<Vec<&'g str>> {
+        if self.uniform_actiontype(grm).is_some() {
+            return None;
+        }
+        let mut types = Vec::new();
+        for ridx in grm.iter_rules() {
+            if let Some(t) = grm.actiontype(ridx) {
+                if ridx != grm.start_rule_idx() && !types.contains(&t) {
+                    types.push(t);
+                }
+            }
+        }
+        Some(types)
+    }
+
+    /// If the rules in `grm` have different action types, return the action values parameter
+    /// (preceded by `, `) which generated functions pass on to the actions: as a declaration if
+    /// `decls` is true, or as an argument otherwise.
+    fn values_param(&self, grm: &YaccGrammar<StorageT>, decls: bool) -> String {
+        match self.value_types(grm) {
+            None => String::new(),
+            Some(_) if decls => {
+                format!(", {}values: &mut {}<'input>", ACTION_PREFIX, ACTION_VALUES)
+            }
+            Some(_) => format!(", {}values", ACTION_PREFIX),
+        }
+    }
+
+    /// Return the `RIdx`s of the %start rules in the grammar, in the order they were declared
+    /// (which will not be the same as grm.start_rule_idx because the latter is an additional rule
+    /// inserted by cfgrammar which then calls the user's %start rules).
+    fn user_start_ridxs(&self, grm: &YaccGrammar<StorageT>) -> Vec<RIdx<StorageT>> {
+        grm.start_prods()
+            .iter()
+            .map(|&pidx| {
+                debug_assert_eq!(grm.prod(pidx).len(), 1);
+                match grm.prod(pidx)[0] {
+                    Symbol::Rule(ridx) => ridx,
+                    _ => unreachable!(),
+                }
+            })
+            .collect()
     }
 }
 
-impl Write for ArrayWriter {
-    #[allow(dead_code)]
-    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        for b in buf {
-            self.buffer.write_fmt(format_args!("{},", b)).unwrap();
+/// Return the name of the parse function generated for the %start rule `ridx` when a grammar has
+/// several %start rules.
+fn entry_name<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    ridx: RIdx<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    format!("parse_{}", grm.rule_name(ridx).to_ascii_lowercase())
+}
+
+/// Return the index in `types` (as returned by `value_types`) of rule `ridx`'s action type, i.e.
+/// which of the action values struct's stacks its values are stored in.
+fn value_stack<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    types: &[&str],
+    grm: &YaccGrammar<StorageT>,
+    ridx: RIdx<StorageT>,
+) -> usize
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let actiont = grm.actiontype(ridx).unwrap();
+    types.iter().position(|t| *t == actiont).unwrap()
+}
+
+/// Rewrite the `$`-references in production `pidx`'s action into references to the action
+/// function's parameters (e.g. `$1`, `$lhs`, and `$Expr` might all be rewritten to `__gt_arg_1`).
+/// Named references can be to a symbol's label (e.g. `lhs` in `lhs=Expr`) or, if it occurs only
+/// once in the production, the name of a rule or token. If a reference is invalid, a description
+/// of the problem is returned.
+fn rewrite_action<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    pidx: PIdx<StorageT>,
+) -> Result<String, String>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let pre_action = grm.action(pidx).unwrap();
+    let mut outs = String::new();
+    let mut last = 0;
+    // Iterate over all $-arguments and replace them with their respective element from the
+    // argument vector (e.g. $1 is replaced by args[0]).
+    while let Some(off) = pre_action[last..].find('$') {
+        let rest = &pre_action[last + off..];
+        outs.push_str(&pre_action[last..last + off]);
+        if rest.starts_with("$$") {
+            outs.push('$');
+            last = last + off + "$$".len();
+        } else if rest[1..].starts_with(|c: char| c.is_numeric()) {
+            outs.push_str(&format!("{prefix}arg_", prefix = ACTION_PREFIX));
+            last = last + off + "$".len();
+        } else if let Some(m) = RE_DOL_NAME.captures(rest) {
+            // `$lexer` and `$span` are only special if they are the whole name (e.g. `$spanned`
+            // is a reference to a symbol or label).
+            match m.get(1).map(|n| n.as_str()) {
+                Some("lexer") => outs.push_str(&format!("{prefix}lexer", prefix = ACTION_PREFIX)),
+                Some("span") => outs.push_str(&format!("{prefix}span", prefix = ACTION_PREFIX)),
+                _ => {
+                    let name = m.get(1).or_else(|| m.get(2)).unwrap().as_str();
+                    let i = resolve_name(grm, pidx, name)?;
+                    outs.push_str(&format!("{prefix}arg_{}", i + 1, prefix = ACTION_PREFIX));
+                }
+            }
+            last = last + off + m.get(0).unwrap().end();
+        } else {
+            return Err(format!("Unknown text following '$' operator: {}", rest));
         }
-        Ok(buf.len())
     }
+    outs.push_str(&pre_action[last..]);
+    Ok(outs)
+}
 
-    #[allow(dead_code)]
-    fn flush(&mut self) -> io::Result<()> {
-        Ok(())
+/// Return the index of the symbol in production `pidx` that `name` refers to.
+fn resolve_name<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    pidx: PIdx<StorageT>,
+    name: &str,
+) -> Result<usize, String>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    if let Some((sidx, _)) = grm.prod_labels(pidx).iter().find(|(_, l)| l == name) {
+        return Ok(usize::from(*sidx));
+    }
+    let matches = grm
+        .prod(pidx)
+        .iter()
+        .enumerate()
+        .filter(|(_, sym)| match **sym {
+            Symbol::Rule(ridx) => grm.rule_name(ridx) == name,
+            Symbol::Token(tidx) => grm.token_name(tidx) == Some(name),
+        })
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => Err(format!("Unknown name '${}'", name)),
+        [i] => Ok(*i),
+        _ => Err(format!(
+            "Ambiguous name '${}' (it could refer to {})",
+            name,
+            matches
+                .iter()
+                .map(|i| format!("${}", i + 1))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )),
+    }
+}
+
+/// Return a version of the string `s` which is safe to embed in source code as a string.
+fn str_escape(s: &str) -> String {
+    s.replace("\\", "\\\\").replace("\"", "\\\"")
+}
+
+/// The path at which generated files refer to `lrtable`, which they don't otherwise depend on.
+#[doc(hidden)]
+pub use lrtable as _lrtable;
+
+/// The grammar and statetable of a generated parser. Generated files store this in a `static`
+/// which is constructed at compile-time: the grammar and statetable borrow all of their data from
+/// `static` arrays in the generated file, so using them requires no work at run-time.
+#[doc(hidden)]
+pub struct _CTTables<StorageT: 'static, StIdxT: 'static> {
+    grm: YaccGrammar<StorageT>,
+    stable: StateTable<StorageT, StIdxT>,
+}
+
+impl<StorageT, StIdxT> _CTTables<StorageT, StIdxT> {
+    pub const fn new(grm: YaccGrammar<StorageT>, stable: StateTable<StorageT, StIdxT>) -> Self {
+        _CTTables { grm, stable }
+    }
+
+    /// Return the grammar and statetable.
+    pub fn get(&self) -> (&YaccGrammar<StorageT>, &StateTable<StorageT, StIdxT>) {
+        (&self.grm, &self.stable)
     }
 }
 
+/// Convert the state graph index `stidx` into the equivalent state table index: the state table's
+/// states are numbered in the same way as the state graph's.
+fn st_stidx<StIdxT>(stidx: StIdx<u32>) -> StIdx<StIdxT>
+where
+    StIdxT: 'static + PrimInt + Unsigned,
+    usize: AsPrimitive<StIdxT>,
+{
+    StIdx::from(AsPrimitive::<StIdxT>::as_(usize::from(stidx)))
+}
+
 #[cfg(test)]
 mod test {
-    use std::{fs::File, io::Write, path::PathBuf};
+    use std::{
+        fs::{metadata, read_to_string, remove_file, File, OpenOptions},
+        io::Write,
+        path::PathBuf,
+    };
 
-    use super::{CTConflictsError, CTParserBuilder};
+    use super::{CTConflictsError, CTParserBuilder, CTStateTable};
     use cfgrammar::yacc::{YaccKind, YaccOriginalActionKind};
+    use filetime::{set_file_mtime, FileTime};
+    use lrtable::Minimiser;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_cttables() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut out_path = PathBuf::from(temp.as_ref());
+        out_path.push("grm.rs");
+        File::create(&file_path)
+            .unwrap()
+            .write_all("%start A\n%epp a 'an a'\n%%\nA: 'a' | 'a' A;".as_bytes())
+            .unwrap();
+
+        CTParserBuilder::new()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file(&file_path, &out_path)
+            .unwrap();
+        // The grammar and statetable are constructed by const functions from static arrays,
+        // rather than being deserialised at run-time.
+        let out = read_to_string(&out_path).unwrap();
+        assert!(out.contains(
+            "static __TABLES: ::lrpar::ctbuilder::_CTTables<u32, u16> = \
+             ::lrpar::ctbuilder::_CTTables::new({"
+        ));
+        assert!(out.contains("YaccGrammar::_from_static(_StaticYaccGrammar {"));
+        assert!(out.contains("StateTable::_from_static(_StaticStateTable {"));
+        assert!(out.contains("use ::lrpar::ctbuilder::_lrtable::{"));
+        assert!(out.contains(r#"token_epp: &[Some(Cow::Borrowed("an a")), None],"#));
+        assert!(!out.contains("&[u8]"));
+        assert!(!out.to_lowercase().contains("deserial"));
+    }
+
     #[test]
     fn test_conflicts() {
         let temp = TempDir::new().unwrap();
@@ -966,10 +1854,12 @@ C : 'a';"
         ct.process_file_in_src(file_path.to_str().unwrap()).unwrap();
 
         match ct.conflicts() {
-            Some((_, _, _, conflicts)) => {
+            Some((_, _, CTStateTable::U16(stable))) => {
+                let conflicts = stable.conflicts().unwrap();
                 assert_eq!(conflicts.sr_len(), 1);
                 assert_eq!(conflicts.rr_len(), 1);
             }
+            Some((_, _, CTStateTable::U32(_))) => panic!("Expected a u16 state table"),
             None => panic!("Expected error data"),
         }
     }
@@ -995,10 +1885,505 @@ C : 'a';"
         {
             Ok(_) => panic!("Expected error"),
             Err(e) => {
-                let cs = e.downcast_ref::<CTConflictsError<u32>>();
+                let cs = e.downcast_ref::<CTConflictsError<u32, u16>>();
                 assert_eq!(cs.unwrap().stable.conflicts().unwrap().sr_len(), 1);
                 assert_eq!(cs.unwrap().stable.conflicts().unwrap().rr_len(), 1);
             }
         }
     }
+
+    #[test]
+    fn test_expect() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%expect 1
+%expect-rr 1
+%%
+A : 'a' 'b' | B 'b';
+B : 'a' | C;
+C : 'a';"
+                .as_bytes(),
+        );
+
+        let mut ct = CTParserBuilder::new()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree));
+        ct.process_file_in_src(file_path.to_str().unwrap()).unwrap();
+        match ct.conflicts() {
+            Some((_, _, CTStateTable::U16(stable))) => {
+                let conflicts = stable.conflicts().unwrap();
+                assert_eq!(conflicts.sr_len(), 1);
+                assert_eq!(conflicts.rr_len(), 1);
+            }
+            Some((_, _, CTStateTable::U32(_))) => panic!("Expected a u16 state table"),
+            None => panic!("Expected error data"),
+        }
+    }
+
+    #[test]
+    fn test_expect_error() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%expect 1
+%%
+A : 'a' 'b' | B 'b';
+B : 'a' | C;
+C : 'a';"
+                .as_bytes(),
+        );
+
+        // The omitted `%expect-rr` expects 0 Reduce/Reduce conflicts, so this grammar should be
+        // rejected even though `error_on_conflicts` is false.
+        match CTParserBuilder::new()
+            .error_on_conflicts(false)
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => {
+                let cs = e.downcast_ref::<CTConflictsError<u32, u16>>();
+                assert_eq!(cs.unwrap().stable.conflicts().unwrap().sr_len(), 1);
+                assert_eq!(cs.unwrap().stable.conflicts().unwrap().rr_len(), 1);
+                assert_eq!(
+                    e.to_string(),
+                    r#"CTConflictsError{1 Shift/Reduce (expected 1), 1 Reduce/Reduce (expected 0)}
+Shift/Reduce conflict on "b" in state 2 (ambiguous):
+  Example: "a" • "b"
+  Reduce derivation: A -> [ B -> [ "a" • ] "b" ]
+  Shift derivation: A -> [ "a" • "b" ]
+Reduce/Reduce conflict on "b" in state 2 (ambiguous):
+  Example: "a" • "b"
+  First reduce derivation: A -> [ B -> [ "a" • ] "b" ]
+  Second reduce derivation: A -> [ B -> [ C -> [ "a" • ] ] "b" ]"#
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_grammar_errors() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%%
+A : 'a' B;
+B : 'b' ) ;
+C : D;"
+                .as_bytes(),
+        );
+
+        match CTParserBuilder::new()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Illegal string at line 4 column 9
+Unknown reference to rule 'D' at line 5 column 5"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_missing_action() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%%
+A -> (): 'a' { }
+  | 'b';"
+                .as_bytes(),
+        );
+
+        match CTParserBuilder::new()
+            .yacckind(YaccKind::Grmtools)
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Production in rule 'A' must have an action body at line 4 column 5"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_bison_missing_action() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%type <u64> A B
+%start A
+%%
+A: B
+  | 'a';
+B: 'b' { 2 };"
+                .as_bytes(),
+        );
+
+        match CTParserBuilder::new()
+            .yacckind(YaccKind::Bison)
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Production in rule 'A' must have an action body (its first symbol does not have \
+the rule's type 'u64') at line 5 column 5"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_named_references() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%%
+A -> (): A 'a' A { $A }
+  | x=B 'a' { $x; $y }
+  | 'b' { $span; $[b] };
+B -> (): 'b' { $lexer; $b };"
+                .as_bytes(),
+        );
+
+        match CTParserBuilder::new()
+            .yacckind(YaccKind::Grmtools)
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "Ambiguous name '$A' (it could refer to $1 or $3) in action of rule 'A' at line 3 \
+                 column 10
+Unknown name '$y' in action of rule 'A' at line 4 column 5"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_expect_no_conflicts() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%expect 2
+%%
+A : 'a';"
+                .as_bytes(),
+        );
+
+        match CTParserBuilder::new()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "CTConflictsError{0 Shift/Reduce (expected 2), 0 Reduce/Reduce (expected 0)}"
+            ),
+        }
+    }
+
+    #[test]
+    fn test_many_states() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let run = vec!["'a'"; 100].join(" ");
+        let _ = f.write_all(
+            format!(
+                "%start S
+%%
+S : 'b' {run} | 'c' {run} | 'd' {run};",
+                run = run
+            )
+            .as_bytes(),
+        );
+        let mut out_path = PathBuf::from(temp.as_ref());
+        out_path.push("grm_y.rs");
+
+        // The grammar has more than 255 states, but that doesn't stop its tokens, rules, and
+        // productions being stored in a u8: its states are indexed by a u16.
+        CTParserBuilder::<u8>::new_with_storaget()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file(&file_path, &out_path)
+            .unwrap();
+        let out = std::fs::read_to_string(&out_path).unwrap();
+        assert!(out.contains("::lrpar::ctbuilder::_CTTables<u8, u16>"));
+    }
+
+    #[test]
+    fn test_glr_yacckind() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%%
+A : 'a' | A A;"
+                .as_bytes(),
+        );
+
+        // GLR parsers can't run actions...
+        match CTParserBuilder::new()
+            .glr(true)
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::NoAction))
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                "GLR parsing is only supported for \
+                 YaccKind::Original(YaccOriginalActionKind::GenericParseTree) grammars"
+            ),
+        }
+        // ...but they can parse grammars with conflicts.
+        CTParserBuilder::new()
+            .glr(true)
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_minimiser() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        // This grammar is LR(1) but not LALR(1).
+        let _ = f.write_all(
+            "%start S
+%%
+S : 'a' E 'c' | 'a' F 'd' | 'b' F 'c' | 'b' E 'd';
+E : 'e';
+F : 'e';"
+                .as_bytes(),
+        );
+
+        for m in &[Minimiser::Pager, Minimiser::LR1] {
+            CTParserBuilder::new()
+                .minimiser(*m)
+                .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+                .process_file_in_src(file_path.to_str().unwrap())
+                .unwrap();
+        }
+        match CTParserBuilder::new()
+            .minimiser(Minimiser::LALR)
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert!(e
+                .to_string()
+                .starts_with("CTConflictsError{0 Shift/Reduce, 2 Reduce/Reduce}")),
+        }
+    }
+
+    #[test]
+    fn test_direct_coded() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start A
+%%
+A : 'a';"
+                .as_bytes(),
+        );
+
+        match CTParserBuilder::new()
+            .direct_coded(true)
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert!(e
+                .to_string()
+                .starts_with("Direct-coded parsers are only supported for")),
+        }
+    }
+
+    #[test]
+    fn test_report_file() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut report_path = PathBuf::from(temp.as_ref());
+        report_path.push("grm.output");
+        let mut f = File::create(&file_path).unwrap();
+        let _ = f.write_all(
+            "%start E
+%%
+E : E '+' E | 'INT';"
+                .as_bytes(),
+        );
+
+        // The report should be written even though the grammar's conflicts are an error.
+        assert!(CTParserBuilder::new()
+            .report_file(&report_path)
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .process_file_in_src(file_path.to_str().unwrap())
+            .is_err());
+        let report = read_to_string(&report_path).unwrap();
+        assert!(report.starts_with("Grammar\n"));
+        assert!(report.contains(" conflicts: 1 shift/reduce\n"));
+        assert!(report.contains("    '+'  [reduce using rule 0 (E)]\n"));
+
+        // A missing report is regenerated even if the generated parser is up to date.
+        let mut out_path = PathBuf::from(temp.as_ref());
+        out_path.push("grm.rs");
+        File::create(&file_path)
+            .unwrap()
+            .write_all("%start E\n%%\nE : 'INT';".as_bytes())
+            .unwrap();
+        let build = || {
+            CTParserBuilder::new()
+                .report_file(&report_path)
+                .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+                .process_file(&file_path, &out_path)
+                .unwrap();
+        };
+        build();
+        remove_file(&report_path).unwrap();
+        build();
+        assert!(read_to_string(&report_path)
+            .unwrap()
+            .starts_with("Grammar\n"));
+    }
+
+    #[test]
+    fn test_compression_stats() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut out_path = PathBuf::from(temp.as_ref());
+        out_path.push("grm.rs");
+        let mut report_path = PathBuf::from(temp.as_ref());
+        report_path.push("grm.output");
+        File::create(&file_path)
+            .unwrap()
+            .write_all("%start E\n%left '+'\n%%\nE : E '+' E | 'INT';".as_bytes())
+            .unwrap();
+
+        let mut ctp = CTParserBuilder::new()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .report_file(&report_path);
+        ctp.process_file(&file_path, &out_path).unwrap();
+        assert!(ctp.compression_stats().is_none());
+
+        remove_file(&out_path).unwrap();
+        let mut ctp = CTParserBuilder::new()
+            .yacckind(YaccKind::Original(YaccOriginalActionKind::GenericParseTree))
+            .compress_tables(true)
+            .report_file(&report_path);
+        ctp.process_file(&file_path, &out_path).unwrap();
+        let stats = ctp.compression_stats().unwrap();
+        assert!(stats.action_slots < stats.action_cells);
+        let report = read_to_string(&report_path).unwrap();
+        assert!(report.contains(&format!("\n\nTable compression\n\n{}\n", stats)));
+    }
+
+    #[test]
+    fn test_include() {
+        let temp = TempDir::new().unwrap();
+        let mut file_path = PathBuf::from(temp.as_ref());
+        file_path.push("grm.y");
+        let mut inc_path = PathBuf::from(temp.as_ref());
+        inc_path.push("rules.y");
+        let mut out_path = PathBuf::from(temp.as_ref());
+        out_path.push("grm.rs");
+        File::create(&file_path)
+            .unwrap()
+            .write_all(
+                "%start A
+%%
+A -> (): B { };
+%include \"rules.y\"
+"
+                .as_bytes(),
+            )
+            .unwrap();
+        File::create(&inc_path)
+            .unwrap()
+            .write_all("B -> (): 'b' { }\n  | 'c';".as_bytes())
+            .unwrap();
+
+        match CTParserBuilder::<u32>::new()
+            .yacckind(YaccKind::Grmtools)
+            .process_file(&file_path, &out_path)
+        {
+            Ok(_) => panic!("Expected error"),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                format!(
+                    "Production in rule 'B' must have an action body in {} at line 2 column 5",
+                    inc_path.display()
+                )
+            ),
+        }
+
+        File::create(&inc_path)
+            .unwrap()
+            .write_all("B -> (): 'b' { };".as_bytes())
+            .unwrap();
+        // Filesystem timestamps can be coarse, so make sure the grammar files are older than the
+        // output will be.
+        let past = FileTime::from_unix_time(FileTime::now().unix_seconds() - 10, 0);
+        set_file_mtime(&file_path, past).unwrap();
+        set_file_mtime(&inc_path, past).unwrap();
+        CTParserBuilder::<u32>::new()
+            .yacckind(YaccKind::Grmtools)
+            .process_file(&file_path, &out_path)
+            .unwrap();
+        // If neither grammar file has changed, the output isn't regenerated...
+        OpenOptions::new()
+            .append(true)
+            .open(&out_path)
+            .unwrap()
+            .write_all(b"// marker")
+            .unwrap();
+        CTParserBuilder::<u32>::new()
+            .yacckind(YaccKind::Grmtools)
+            .process_file(&file_path, &out_path)
+            .unwrap();
+        assert!(read_to_string(&out_path).unwrap().ends_with("// marker"));
+        // ...but if the included file has changed, it is.
+        let out_time = FileTime::from_last_modification_time(&metadata(&out_path).unwrap());
+        set_file_mtime(
+            &inc_path,
+            FileTime::from_unix_time(out_time.unix_seconds() + 10, 0),
+        )
+        .unwrap();
+        CTParserBuilder::<u32>::new()
+            .yacckind(YaccKind::Grmtools)
+            .process_file(&file_path, &out_path)
+            .unwrap();
+        assert!(!read_to_string(&out_path).unwrap().ends_with("// marker"));
+    }
 }
@@ -0,0 +1,680 @@
+//! A GLR parser, which can parse input using grammars with unresolved conflicts.
+//!
+//! Rather than following only the action that Yacc's default conflict resolution chooses, the GLR
+//! parser follows all of a state table's [`glr_actions`](../../lrtable/statetable/struct.StateTable.html#method.glr_actions),
+//! using a graph-structured stack to share work between the different parses. The result is a
+//! shared packed parse forest, which compactly represents every possible parse tree of the
+//! input: each nonterminal in the forest covers a particular span of the input, and has one or
+//! more alternative derivations of that span.
+
+use std::{collections::HashMap, hash::Hash};
+
+use cfgrammar::{yacc::YaccGrammar, PIdx, RIdx, TIdx};
+use lrtable::{Action, StIdx, StateTable};
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+
+use crate::{lex::Lexeme, parser::ParseError, Node};
+
+/// The index of a node in a [`Forest`].
+pub type ForestIdx = usize;
+
+/// One way of deriving a nonterminal in a [`Forest`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Alternative<StorageT> {
+    /// The production used.
+    pub pidx: PIdx<StorageT>,
+    /// The nodes which were derived from each of the production's symbols.
+    pub children: Vec<ForestIdx>,
+}
+
+/// A node in a [`Forest`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ForestNode<StorageT> {
+    /// Terminals store a single lexeme.
+    Term { lexeme: Lexeme<StorageT> },
+    /// Nonterminals reference a rule and have one or more alternative derivations. A nonterminal
+    /// with more than one alternative is ambiguous.
+    Nonterm {
+        ridx: RIdx<StorageT>,
+        alternatives: Vec<Alternative<StorageT>>,
+    },
+}
+
+/// A shared packed parse forest, representing all the parse trees for an input.
+#[derive(Clone, Debug)]
+pub struct Forest<StorageT> {
+    nodes: Vec<ForestNode<StorageT>>,
+    root: ForestIdx,
+}
+
+impl<StorageT: 'static + PrimInt + Unsigned> Forest<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    /// Return the index of the root node.
+    pub fn root(&self) -> ForestIdx {
+        self.root
+    }
+
+    /// Return the node at `idx`. Panics if `idx` doesn't exist.
+    pub fn node(&self, idx: ForestIdx) -> &ForestNode<StorageT> {
+        &self.nodes[idx]
+    }
+
+    /// How many nodes does this forest contain?
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Does this forest contain no nodes? Since every forest has a root, this is always false.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Does this forest contain more than one parse tree?
+    pub fn is_ambiguous(&self) -> bool {
+        self.nodes.iter().any(|n| match n {
+            ForestNode::Term { .. } => false,
+            ForestNode::Nonterm { alternatives, .. } => alternatives.len() > 1,
+        })
+    }
+
+    /// Return every parse tree in this forest. Note that the number of parse trees can be
+    /// exponential in the length of the input. Derivations which are cyclic (e.g. `A: A | 'a';`
+    /// can derive `a` in an infinite number of ways) are omitted.
+    pub fn trees(&self) -> Vec<Node<StorageT>> {
+        self.trees_from(self.root, &mut Vec::new())
+    }
+
+    fn trees_from(&self, idx: ForestIdx, active: &mut Vec<ForestIdx>) -> Vec<Node<StorageT>> {
+        match self.nodes[idx] {
+            ForestNode::Term { lexeme } => vec![Node::Term { lexeme }],
+            ForestNode::Nonterm {
+                ridx,
+                ref alternatives,
+            } => {
+                active.push(idx);
+                let mut trees = Vec::new();
+                for alt in alternatives {
+                    if alt.children.iter().any(|c| active.contains(c)) {
+                        continue;
+                    }
+                    // Build the cartesian product of the children's trees.
+                    let mut partials = vec![Vec::new()];
+                    for &c in &alt.children {
+                        let c_trees = self.trees_from(c, active);
+                        partials = partials
+                            .iter()
+                            .flat_map(|p| {
+                                c_trees.iter().map(move |t| {
+                                    let mut p = p.clone();
+                                    p.push(t.clone());
+                                    p
+                                })
+                            })
+                            .collect();
+                    }
+                    trees.extend(
+                        partials
+                            .into_iter()
+                            .map(|nodes| Node::Nonterm { ridx, nodes }),
+                    );
+                }
+                active.pop();
+                trees
+            }
+        }
+    }
+
+    /// Return a single parse tree from this forest. Whenever a nonterminal has more than one
+    /// alternative, `choose(forest, alternatives)` is called and must return the index (in
+    /// `alternatives`) of the alternative to use. Alternatives which would lead to cyclic
+    /// derivations are never passed to `choose`.
+    ///
+    /// For example, Yacc's default resolution of reduce/reduce conflicts can be mimicked by
+    /// choosing the alternative whose production appears earliest in the grammar:
+    ///
+    /// ```text
+    /// forest.disambiguate(|_, alts| (0..alts.len()).min_by_key(|&i| alts[i].pidx).unwrap())
+    /// ```
+    pub fn disambiguate<F>(&self, mut choose: F) -> Node<StorageT>
+    where
+        F: FnMut(&Self, &[&Alternative<StorageT>]) -> usize,
+    {
+        // Every node created by the parser has at least one acyclic derivation, so this can't
+        // fail.
+        self.disambiguate_from(self.root, &mut Vec::new(), &mut choose)
+            .unwrap()
+    }
+
+    fn disambiguate_from<F>(
+        &self,
+        idx: ForestIdx,
+        active: &mut Vec<ForestIdx>,
+        choose: &mut F,
+    ) -> Option<Node<StorageT>>
+    where
+        F: FnMut(&Self, &[&Alternative<StorageT>]) -> usize,
+    {
+        match self.nodes[idx] {
+            ForestNode::Term { lexeme } => Some(Node::Term { lexeme }),
+            ForestNode::Nonterm {
+                ridx,
+                ref alternatives,
+            } => {
+                active.push(idx);
+                let mut alts = alternatives
+                    .iter()
+                    .filter(|alt| !alt.children.iter().any(|c| active.contains(c)))
+                    .collect::<Vec<_>>();
+                let mut node = None;
+                // If the chosen alternative turns out to be cyclic further down, try another.
+                while !alts.is_empty() {
+                    let i = if alts.len() == 1 {
+                        0
+                    } else {
+                        choose(self, &alts)
+                    };
+                    let alt = alts.remove(i);
+                    if let Some(nodes) = alt
+                        .children
+                        .iter()
+                        .map(|&c| self.disambiguate_from(c, active, choose))
+                        .collect::<Option<Vec<_>>>()
+                    {
+                        node = Some(Node::Nonterm { ridx, nodes });
+                        break;
+                    }
+                }
+                active.pop();
+                node
+            }
+        }
+    }
+
+    /// Return a pretty-printed version of this forest. Each alternative of an ambiguous
+    /// nonterminal is printed in turn.
+    pub fn pp(&self, grm: &YaccGrammar<StorageT>, input: &str) -> String {
+        let mut s = String::new();
+        self.pp_from(grm, input, self.root, 0, &mut Vec::new(), &mut s);
+        s
+    }
+
+    fn pp_from(
+        &self,
+        grm: &YaccGrammar<StorageT>,
+        input: &str,
+        idx: ForestIdx,
+        indent: usize,
+        active: &mut Vec<ForestIdx>,
+        s: &mut String,
+    ) {
+        s.push_str(&" ".repeat(indent));
+        match self.nodes[idx] {
+            ForestNode::Term { lexeme } => {
+                let tidx = TIdx(lexeme.tok_id());
+                let tn = grm.token_name(tidx).unwrap();
+                let lt = &input[lexeme.span().start()..lexeme.span().end()];
+                s.push_str(&format!("{} {}\n", tn, lt));
+            }
+            ForestNode::Nonterm {
+                ridx,
+                ref alternatives,
+            } => {
+                if active.contains(&idx) {
+                    s.push_str(&format!("{} (cycle)\n", grm.rule_name(ridx)));
+                    return;
+                }
+                active.push(idx);
+                if alternatives.len() == 1 {
+                    s.push_str(&format!("{}\n", grm.rule_name(ridx)));
+                    for &c in &alternatives[0].children {
+                        self.pp_from(grm, input, c, indent + 1, active, s);
+                    }
+                } else {
+                    s.push_str(&format!(
+                        "{} ({} alternatives)\n",
+                        grm.rule_name(ridx),
+                        alternatives.len()
+                    ));
+                    for (i, alt) in alternatives.iter().enumerate() {
+                        s.push_str(&format!("{}#{}\n", " ".repeat(indent + 1), i + 1));
+                        for &c in &alt.children {
+                            self.pp_from(grm, input, c, indent + 2, active, s);
+                        }
+                    }
+                }
+                active.pop();
+            }
+        }
+    }
+}
+
+/// A node in the graph-structured stack.
+struct GSSNode<StIdxT> {
+    stidx: StIdx<StIdxT>,
+    /// The index of the lexeme at which this node was created.
+    laidx: usize,
+    /// Edges to the nodes below this one, each labelled with the forest node derived between the
+    /// two.
+    edges: Vec<(usize, ForestIdx)>,
+}
+
+/// Parse `lexemes` starting from the state `start_state`, returning a forest of all possible
+/// parses. Parsing stops at the first error: there is no error recovery.
+pub(crate) fn parse<StorageT, StIdxT>(
+    grm: &YaccGrammar<StorageT>,
+    stable: &StateTable<StorageT, StIdxT>,
+    start_state: StIdx<StIdxT>,
+    lexemes: &[Lexeme<StorageT>],
+) -> Result<Forest<StorageT>, ParseError<StorageT>>
+where
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
+    u32: AsPrimitive<StorageT>,
+{
+    let mut gss = vec![GSSNode {
+        stidx: start_state,
+        laidx: 0,
+        edges: Vec::new(),
+    }];
+    // The GSS nodes created at the current lexeme.
+    let mut frontier = vec![0];
+    let mut nodes = Vec::new();
+    // Nonterminals are shared between parses if they derive the same rule from the same span of
+    // the input.
+    let mut nonterms: HashMap<(RIdx<StorageT>, usize, usize), ForestIdx> = HashMap::new();
+    for laidx in 0..=lexemes.len() {
+        let lexeme = match lexemes.get(laidx) {
+            Some(&l) => l,
+            None => Lexeme::new(
+                StorageT::from(u32::from(grm.eof_token_idx())).unwrap(),
+                lexemes.last().map(|l| l.span().end()).unwrap_or(0),
+                None,
+            ),
+        };
+        let tidx = TIdx(lexeme.tok_id());
+
+        // Perform all possible reductions. A reduction can add a new edge to an existing node,
+        // creating new paths for reductions we've already performed, so we repeat until nothing
+        // changes.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let mut i = 0;
+            while i < frontier.len() {
+                let v = frontier[i];
+                i += 1;
+                for action in stable.glr_actions(gss[v].stidx, tidx) {
+                    let pidx = match action {
+                        Action::Reduce(pidx) => pidx,
+                        _ => continue,
+                    };
+                    let ridx = grm.prod_to_rule(pidx);
+                    for (u, children) in paths(&gss, v, grm.prod(pidx).len()) {
+                        let goto_stidx = stable.goto(gss[u].stidx, ridx).unwrap();
+                        let w = match frontier.iter().find(|&&w| gss[w].stidx == goto_stidx) {
+                            Some(&w) => w,
+                            None => {
+                                gss.push(GSSNode {
+                                    stidx: goto_stidx,
+                                    laidx,
+                                    edges: Vec::new(),
+                                });
+                                frontier.push(gss.len() - 1);
+                                gss.len() - 1
+                            }
+                        };
+                        let fidx =
+                            *nonterms
+                                .entry((ridx, gss[u].laidx, laidx))
+                                .or_insert_with(|| {
+                                    nodes.push(ForestNode::Nonterm {
+                                        ridx,
+                                        alternatives: Vec::new(),
+                                    });
+                                    nodes.len() - 1
+                                });
+                        if let ForestNode::Nonterm {
+                            ref mut alternatives,
+                            ..
+                        } = nodes[fidx]
+                        {
+                            let alt = Alternative { pidx, children };
+                            if !alternatives.contains(&alt) {
+                                alternatives.push(alt);
+                            }
+                        }
+                        // Since each state has a unique accessing symbol, any existing edge from
+                        // `w` to `u` must be labelled with `fidx`.
+                        if !gss[w].edges.iter().any(|&(x, _)| x == u) {
+                            gss[w].edges.push((u, fidx));
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        if laidx == lexemes.len() {
+            for &v in &frontier {
+                if stable
+                    .glr_actions(gss[v].stidx, tidx)
+                    .contains(&Action::Accept)
+                {
+                    // The accepting node's only edge is to the start node, and is labelled with
+                    // the start rule's derivation of the entire input.
+                    let (_, root) = gss[v].edges[0];
+                    return Ok(compact(nodes, root));
+                }
+            }
+        } else {
+            let term = nodes.len();
+            nodes.push(ForestNode::Term { lexeme });
+            let mut next_frontier: Vec<usize> = Vec::new();
+            for &v in &frontier {
+                for action in stable.glr_actions(gss[v].stidx, tidx) {
+                    if let Action::Shift(stidx) = action {
+                        let w = match next_frontier.iter().find(|&&w| gss[w].stidx == stidx) {
+                            Some(&w) => w,
+                            None => {
+                                gss.push(GSSNode {
+                                    stidx,
+                                    laidx: laidx + 1,
+                                    edges: Vec::new(),
+                                });
+                                next_frontier.push(gss.len() - 1);
+                                gss.len() - 1
+                            }
+                        };
+                        gss[w].edges.push((v, term));
+                    }
+                }
+            }
+            if !next_frontier.is_empty() {
+                frontier = next_frontier;
+                continue;
+            }
+        }
+        return Err(ParseError::new(gss[frontier[0]].stidx, lexeme, Vec::new()));
+    }
+    unreachable!();
+}
+
+/// Return every path of `len` edges from the GSS node `v` as pairs of the node at the end of the
+/// path and the forest nodes the edges are labelled with (in the order they were derived).
+fn paths<StIdxT>(gss: &[GSSNode<StIdxT>], v: usize, len: usize) -> Vec<(usize, Vec<ForestIdx>)> {
+    let mut paths = Vec::new();
+    let mut todo = vec![(v, Vec::new())];
+    while let Some((n, mut children)) = todo.pop() {
+        if children.len() == len {
+            children.reverse();
+            paths.push((n, children));
+            continue;
+        }
+        for &(m, fidx) in &gss[n].edges {
+            let mut children = children.clone();
+            children.push(fidx);
+            todo.push((m, children));
+        }
+    }
+    paths
+}
+
+/// Remove the nodes not reachable from `root`, preserving the order of the remaining nodes.
+fn compact<StorageT: Copy>(nodes: Vec<ForestNode<StorageT>>, root: ForestIdx) -> Forest<StorageT> {
+    let mut reachable = vec![false; nodes.len()];
+    let mut todo = vec![root];
+    while let Some(idx) = todo.pop() {
+        if reachable[idx] {
+            continue;
+        }
+        reachable[idx] = true;
+        if let ForestNode::Nonterm { alternatives, .. } = &nodes[idx] {
+            for alt in alternatives {
+                todo.extend(&alt.children);
+            }
+        }
+    }
+    let mut map = vec![0; nodes.len()];
+    let mut off = 0;
+    for (idx, &r) in reachable.iter().enumerate() {
+        if r {
+            map[idx] = off;
+            off += 1;
+        }
+    }
+    let nodes = nodes
+        .into_iter()
+        .zip(reachable)
+        .filter(|(_, r)| *r)
+        .map(|(n, _)| match n {
+            ForestNode::Term { lexeme } => ForestNode::Term { lexeme },
+            ForestNode::Nonterm { ridx, alternatives } => ForestNode::Nonterm {
+                ridx,
+                alternatives: alternatives
+                    .into_iter()
+                    .map(|alt| Alternative {
+                        pidx: alt.pidx,
+                        children: alt.children.iter().map(|&c| map[c]).collect(),
+                    })
+                    .collect(),
+            },
+        })
+        .collect();
+    Forest {
+        nodes,
+        root: map[root],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind};
+    use lrtable::{from_yacc, Minimiser};
+    use num_traits::ToPrimitive;
+
+    use super::*;
+    use crate::{
+        parser::{
+            test::{small_lex, small_lexer, SmallLexer},
+            LexParseError, RTParserBuilder,
+        },
+        Node,
+    };
+
+    fn glr_parse(
+        lexs: &str,
+        grms: &str,
+        input: &str,
+    ) -> (
+        YaccGrammar<u16>,
+        (Option<Forest<u16>>, Vec<LexParseError<u16>>),
+    ) {
+        let grm = YaccGrammar::<u16>::new_with_storaget(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            grms,
+        )
+        .unwrap();
+        let (_, stable) = from_yacc::<_, u16>(&grm, Minimiser::Pager).unwrap();
+        let rule_ids = grm
+            .tokens_map()
+            .iter()
+            .map(|(&n, &i)| (n.to_owned(), u32::from(i).to_u16().unwrap()))
+            .collect();
+        let lexemes = small_lex(small_lexer(lexs, rule_ids), input);
+        let lexer = SmallLexer { lexemes };
+        let r = RTParserBuilder::new(&grm, &stable).parse_glr(&lexer);
+        (grm, r)
+    }
+
+    #[test]
+    fn test_unambiguous() {
+        let lexs = "[0-9]+ 'INT'
+                    \\+ '+'
+                    \\* '*'";
+        let grms = "%start Expr
+%%
+Expr: Term '+' Expr | Term;
+Term: Factor '*' Term | Factor;
+Factor: 'INT';";
+        let (grm, (f, errs)) = glr_parse(lexs, grms, "2+3*4");
+        assert!(errs.is_empty());
+        let f = f.unwrap();
+        assert!(!f.is_ambiguous());
+        let trees = f.trees();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(
+            trees[0].pp(&grm, "2+3*4"),
+            "Expr
+ Term
+  Factor
+   INT 2
+ + +
+ Expr
+  Term
+   Factor
+    INT 3
+   * *
+   Term
+    Factor
+     INT 4
+"
+        );
+        assert_eq!(f.pp(&grm, "2+3*4"), trees[0].pp(&grm, "2+3*4"));
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let lexs = "[0-9]+ 'INT'
+                    \\+ '+'";
+        let grms = "%start E
+%%
+E: E '+' E | 'INT';";
+        let (grm, (f, errs)) = glr_parse(lexs, grms, "1+2+3");
+        assert!(errs.is_empty());
+        let f = f.unwrap();
+        assert!(f.is_ambiguous());
+        let trees = f.trees();
+        assert_eq!(trees.len(), 2);
+        let left = "E
+ E
+  E
+   INT 1
+  + +
+  E
+   INT 2
+ + +
+ E
+  INT 3
+";
+        let right = "E
+ E
+  INT 1
+ + +
+ E
+  E
+   INT 2
+  + +
+  E
+   INT 3
+";
+        let pps = trees
+            .iter()
+            .map(|t| t.pp(&grm, "1+2+3"))
+            .collect::<Vec<_>>();
+        assert!(pps.contains(&left.to_owned()));
+        assert!(pps.contains(&right.to_owned()));
+        assert!(f.pp(&grm, "1+2+3").starts_with("E (2 alternatives)\n #1\n"));
+
+        // Choose the alternative whose first child is itself an addition, i.e. left associativity.
+        let t = f.disambiguate(|f, alts| {
+            let first_len = |i: usize| match f.node(alts[i].children[0]) {
+                ForestNode::Nonterm { alternatives, .. } => alternatives[0].children.len(),
+                ForestNode::Term { .. } => 1,
+            };
+            (0..alts.len()).max_by_key(|&i| first_len(i)).unwrap()
+        });
+        assert_eq!(t.pp(&grm, "1+2+3"), left);
+        // Five operands have 14 parse trees (the 4th Catalan number).
+        let (_, (f, _)) = glr_parse(lexs, grms, "1+2+3+4+5");
+        assert_eq!(f.unwrap().trees().len(), 14);
+    }
+
+    #[test]
+    fn test_lr2() {
+        // This grammar isn't LR(1), and Yacc's default resolution of the reduce/reduce conflict
+        // means that a deterministic parser can't parse "a x z".
+        let lexs = "a 'a'
+                    x 'x'
+                    y 'y'
+                    z 'z'";
+        let grms = "%start S
+%%
+S: A 'x' 'y' | B 'x' 'z';
+A: 'a';
+B: 'a';";
+        for (input, rule) in &[("axy", "A"), ("axz", "B")] {
+            let (grm, (f, errs)) = glr_parse(lexs, grms, input);
+            assert!(errs.is_empty());
+            let f = f.unwrap();
+            assert!(!f.is_ambiguous());
+            match &f.trees()[..] {
+                [Node::Nonterm { nodes, .. }] => match nodes[0] {
+                    Node::Nonterm { ridx, .. } => assert_eq!(grm.rule_name(ridx), *rule),
+                    _ => panic!(),
+                },
+                _ => panic!(),
+            }
+        }
+
+        let (_, (f, errs)) = glr_parse(lexs, grms, "axx");
+        assert!(f.is_none());
+        match &errs[..] {
+            [LexParseError::ParseError(e)] => {
+                assert_eq!(e.lexeme().span().start(), 2);
+                assert!(e.repairs().is_empty());
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn test_cyclic() {
+        // `A` can derive itself, so there are infinitely many derivations of "a"; only the acyclic
+        // one is returned.
+        let lexs = "a 'a'";
+        let grms = "%start S
+%%
+S: A;
+A: B | 'a';
+B: A;";
+        let (grm, (f, errs)) = glr_parse(lexs, grms, "a");
+        assert!(errs.is_empty());
+        let f = f.unwrap();
+        assert!(f.is_ambiguous());
+        let trees = f.trees();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].pp(&grm, "a"), "S\n A\n  a a\n");
+        assert_eq!(f.disambiguate(|_, _| 0), trees[0]);
+    }
+
+    #[test]
+    fn test_epsilon() {
+        let lexs = "a 'a'
+                    b 'b'";
+        let grms = "%start S
+%%
+S: N S 'b' | 'a';
+N: ;";
+        let (grm, (f, errs)) = glr_parse(lexs, grms, "abb");
+        assert!(errs.is_empty());
+        let trees = f.unwrap().trees();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(
+            trees[0].pp(&grm, "abb"),
+            "S\n N\n S\n  N\n  S\n   a a\n  b b\n b b\n"
+        );
+    }
+}
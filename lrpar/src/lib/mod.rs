@@ -182,14 +182,18 @@ mod cpctplus;
 pub mod ctbuilder;
 mod dijkstra;
 #[doc(hidden)]
+pub mod glr;
+#[doc(hidden)]
 pub mod lex;
 pub use crate::lex::{LexError, Lexeme, Lexer, NonStreamingLexer};
 #[doc(hidden)]
 pub mod parser;
 pub use crate::{
-    ctbuilder::{CTParserBuilder, Visibility},
+    ctbuilder::{CTParserBuilder, CTStateTable, Visibility},
+    glr::{Alternative, Forest, ForestIdx, ForestNode},
     parser::{LexParseError, Node, ParseError, ParseRepair, RTParserBuilder, RecoveryKind},
 };
+pub use lrtable::{Minimiser, StIdx};
 
 /// A convenience macro for including statically compiled `.y` files. A file `src/a/b/c.y` which is
 /// statically compiled by lrpar can then be used in a crate with `lrpar_mod!("a/b/c.y")`.
@@ -203,44 +207,4 @@ macro_rules! lrpar_mod {
 #[doc(hidden)]
 pub use cfgrammar::RIdx;
 
-/// A `Span` records what portion of the user's input something (e.g. a lexeme or production)
-/// references (i.e. the `Span` doesn't hold a reference / copy of the actual input).
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
-pub struct Span {
-    start: usize,
-    end: usize,
-}
-
-impl Span {
-    /// Create a new span starting at byte `start` and ending at byte `end`.
-    ///
-    /// # Panics
-    ///
-    /// If `end` is less than `start`.
-    pub fn new(start: usize, end: usize) -> Self {
-        if end < start {
-            panic!("Span starts ({}) after it ends ({})!", start, end);
-        }
-        Span { start, end }
-    }
-
-    /// Byte offset of the start of the span.
-    pub fn start(&self) -> usize {
-        self.start
-    }
-
-    /// Byte offset of the end of the span.
-    pub fn end(&self) -> usize {
-        self.end
-    }
-
-    /// Length in bytes of the span.
-    pub fn len(&self) -> usize {
-        self.end - self.start
-    }
-
-    /// Returns `true` if this `Span` covers 0 bytes, or `false` otherwise.
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
-    }
-}
+pub use cfgrammar::Span;
@@ -8,12 +8,13 @@ use std::{
 };
 
 use cactus::Cactus;
-use cfgrammar::{yacc::YaccGrammar, RIdx, TIdx};
+use cfgrammar::{yacc::YaccGrammar, PIdx, RIdx, Symbol, TIdx};
 use lrtable::{Action, StIdx, StateTable};
 use num_traits::{AsPrimitive, PrimInt, Unsigned};
 
 use crate::{
     cpctplus,
+    glr::{self, Forest},
     lex::{LexError, Lexeme, NonStreamingLexer},
     Span,
 };
@@ -67,7 +68,7 @@ where
     }
 }
 
-pub(crate) type PStack = Vec<StIdx>; // Parse stack
+pub(crate) type PStack<StIdxT> = Vec<StIdx<StIdxT>>; // Parse stack
 pub(crate) type TokenCostFn<'a, StorageT> = &'a (dyn Fn(TIdx<StorageT>) -> u8 + 'a);
 pub(crate) type ActionFn<'a, 'b, 'input, StorageT, ActionT> = &'a dyn Fn(
     RIdx<StorageT>,
@@ -76,35 +77,84 @@ pub(crate) type ActionFn<'a, 'b, 'input, StorageT, ActionT> = &'a dyn Fn(
     vec::Drain<AStackType<ActionT, StorageT>>,
 ) -> ActionT;
 
+/// A function which executes the action for production `pidx` (of rule `ridx`), popping that
+/// production's arguments from the end of the action stack and returning the resulting value.
+/// Unlike an array of `ActionFn`s, a single such function (generated by `CTParserBuilder` as a
+/// `match` on `pidx`, wrapped in a closure which passes on any `%parse_param` arguments)
+/// dispatches to every action statically.
+#[doc(hidden)]
+pub type DispatchFn<'a, 'b, 'input, StorageT, ActionT> = &'a dyn Fn(
+    PIdx<StorageT>,
+    RIdx<StorageT>,
+    &'b dyn NonStreamingLexer<'input, StorageT>,
+    Span,
+    &mut Vec<AStackType<ActionT, StorageT>>,
+) -> ActionT;
+
+/// The actions a `Parser` executes when it reduces a production.
+pub(crate) enum Actions<'a, 'b, 'input, StorageT, ActionT> {
+    /// One action function per production, indexed by `PIdx`.
+    Dyn(&'a [ActionFn<'a, 'b, 'input, StorageT, ActionT>]),
+    /// A single function which dispatches to each production's action.
+    Dispatch(DispatchFn<'a, 'b, 'input, StorageT, ActionT>),
+}
+
+/// A direct-coded parser generated by `CTParserBuilder`. It parses the lexemes from `laidx`
+/// onwards, updating `laidx` and the parse, action, and span stacks exactly as `Parser::lr` would.
+/// It returns `Some(value)` if it reaches an accept state, or `None` if it encounters an error, in
+/// which case `laidx` and the stacks are left as they were when the error was encountered.
+#[doc(hidden)]
+pub type DirectFn<'a, 'b, 'input, StorageT, StIdxT, ActionT> = &'a dyn Fn(
+    &'b dyn NonStreamingLexer<'input, StorageT>,
+    &[Lexeme<StorageT>],
+    &mut usize,
+    &mut Vec<StIdx<StIdxT>>,
+    &mut Vec<AStackType<ActionT, StorageT>>,
+    &mut Vec<Span>,
+) -> Option<ActionT>;
+
 #[derive(Debug)]
 pub enum AStackType<ActionT, StorageT> {
     ActionType(ActionT),
     Lexeme(Lexeme<StorageT>),
 }
 
-pub struct Parser<'a, 'b: 'a, 'input: 'b, StorageT: 'static + Eq + Hash, ActionT: 'a> {
+pub struct Parser<
+    'a,
+    'b: 'a,
+    'input: 'b,
+    StorageT: 'static + Eq + Hash,
+    StIdxT: 'static + Eq + Hash,
+    ActionT: 'a,
+> {
     pub(crate) rcvry_kind: RecoveryKind,
     pub(crate) grm: &'a YaccGrammar<StorageT>,
     pub(crate) token_cost: Box<TokenCostFn<'a, StorageT>>,
-    pub(crate) stable: &'a StateTable<StorageT>,
+    pub(crate) stable: &'a StateTable<StorageT, StIdxT>,
     pub(crate) lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
     // In the long term, we should remove the `lexemes` field entirely, as the `NonStreamingLexer` API is
     // powerful enough to allow us to incrementally obtain lexemes and buffer them when necessary.
     pub(crate) lexemes: Vec<Lexeme<StorageT>>,
-    actions: &'a [ActionFn<'a, 'b, 'input, StorageT, ActionT>],
+    actions: Actions<'a, 'b, 'input, StorageT, ActionT>,
 }
 
-impl<'a, 'b: 'a, 'input: 'b, StorageT: 'static + Debug + Hash + PrimInt + Unsigned>
-    Parser<'a, 'b, 'input, StorageT, Node<StorageT>>
+impl<
+        'a,
+        'b: 'a,
+        'input: 'b,
+        StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    > Parser<'a, 'b, 'input, StorageT, StIdxT, Node<StorageT>>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     fn parse_generictree(
         rcvry_kind: RecoveryKind,
         grm: &YaccGrammar<StorageT>,
         token_cost: TokenCostFn<'a, StorageT>,
-        stable: &StateTable<StorageT>,
+        stable: &StateTable<StorageT, StIdxT>,
+        start_state: StIdx<StIdxT>,
         lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
         lexemes: Vec<Lexeme<StorageT>>,
     ) -> (Option<Node<StorageT>>, Vec<LexParseError<StorageT>>) {
@@ -112,7 +162,7 @@ where
             assert!(token_cost(tidx) > 0);
         }
         let mut actions: Vec<ActionFn<'a, 'b, 'input, StorageT, Node<StorageT>>> = Vec::new();
-        actions.resize(usize::from(grm.prods_len()), &Parser::generic_ptree);
+        actions.resize(usize::from(grm.prods_len()), &Self::generic_ptree);
         let psr = Parser {
             rcvry_kind,
             grm,
@@ -120,9 +170,9 @@ where
             stable,
             lexer,
             lexemes,
-            actions: actions.as_slice(),
+            actions: Actions::Dyn(actions.as_slice()),
         };
-        let mut pstack = vec![stable.start_state()];
+        let mut pstack = vec![start_state];
         let mut astack = Vec::new();
         let mut errors = Vec::new();
         let mut spans = Vec::new();
@@ -147,17 +197,23 @@ where
     }
 }
 
-impl<'a, 'b: 'a, 'input: 'b, StorageT: 'static + Debug + Hash + PrimInt + Unsigned>
-    Parser<'a, 'b, 'input, StorageT, ()>
+impl<
+        'a,
+        'b: 'a,
+        'input: 'b,
+        StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    > Parser<'a, 'b, 'input, StorageT, StIdxT, ()>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     fn parse_noaction(
         rcvry_kind: RecoveryKind,
         grm: &YaccGrammar<StorageT>,
         token_cost: TokenCostFn<'a, StorageT>,
-        stable: &StateTable<StorageT>,
+        stable: &StateTable<StorageT, StIdxT>,
+        start_state: StIdx<StIdxT>,
         lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
         lexemes: Vec<Lexeme<StorageT>>,
     ) -> Vec<LexParseError<StorageT>> {
@@ -165,7 +221,7 @@ where
             assert!(token_cost(tidx) > 0);
         }
         let mut actions: Vec<ActionFn<'a, 'b, 'input, StorageT, ()>> = Vec::new();
-        actions.resize(usize::from(grm.prods_len()), &Parser::noaction);
+        actions.resize(usize::from(grm.prods_len()), &Self::noaction);
         let psr = Parser {
             rcvry_kind,
             grm,
@@ -173,9 +229,9 @@ where
             stable,
             lexer,
             lexemes,
-            actions: actions.as_slice(),
+            actions: Actions::Dyn(actions.as_slice()),
         };
-        let mut pstack = vec![stable.start_state()];
+        let mut pstack = vec![start_state];
         let mut astack = Vec::new();
         let mut errors = Vec::new();
         let mut spans = Vec::new();
@@ -197,20 +253,22 @@ impl<
         'b: 'a,
         'input: 'b,
         StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
         ActionT: 'a,
-    > Parser<'a, 'b, 'input, StorageT, ActionT>
+    > Parser<'a, 'b, 'input, StorageT, StIdxT, ActionT>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     fn parse_actions(
         rcvry_kind: RecoveryKind,
         grm: &'a YaccGrammar<StorageT>,
         token_cost: TokenCostFn<'a, StorageT>,
-        stable: &'a StateTable<StorageT>,
+        stable: &'a StateTable<StorageT, StIdxT>,
+        start_state: StIdx<StIdxT>,
         lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
         lexemes: Vec<Lexeme<StorageT>>,
-        actions: &'a [ActionFn<'a, 'b, 'input, StorageT, ActionT>],
+        actions: Actions<'a, 'b, 'input, StorageT, ActionT>,
     ) -> (Option<ActionT>, Vec<LexParseError<StorageT>>) {
         for tidx in grm.iter_tidxs() {
             assert!(token_cost(tidx) > 0);
@@ -224,7 +282,7 @@ where
             lexemes,
             actions,
         };
-        let mut pstack = vec![stable.start_state()];
+        let mut pstack = vec![start_state];
         let mut astack = Vec::new();
         let mut errors = Vec::new();
         let mut spans = Vec::new();
@@ -248,7 +306,7 @@ where
     pub fn lr(
         &self,
         mut laidx: usize,
-        pstack: &mut PStack,
+        pstack: &mut PStack<StIdxT>,
         astack: &mut Vec<AStackType<ActionT, StorageT>>,
         errors: &mut Vec<LexParseError<StorageT>>,
         spans: &mut Vec<Span>,
@@ -269,22 +327,8 @@ where
                     let prior = *pstack.last().unwrap();
                     pstack.push(self.stable.goto(prior, ridx).unwrap());
 
-                    let span = if spans.is_empty() {
-                        Span::new(0, 0)
-                    } else if pop_idx - 1 < spans.len() {
-                        Span::new(spans[pop_idx - 1].start(), spans[spans.len() - 1].end())
-                    } else {
-                        Span::new(spans[spans.len() - 1].start(), spans[spans.len() - 1].end())
-                    };
-                    spans.truncate(pop_idx - 1);
-                    spans.push(span);
-
-                    let v = AStackType::ActionType(self.actions[usize::from(pidx)](
-                        ridx,
-                        self.lexer,
-                        span,
-                        astack.drain(pop_idx - 1..),
-                    ));
+                    let span = _reduce_span(spans, pop_idx);
+                    let v = AStackType::ActionType(self.action(pidx, ridx, span, astack, pop_idx));
                     astack.push(v);
                 }
                 Action::Shift(state_id) => {
@@ -309,14 +353,7 @@ where
                             RecoveryKind::CPCTPlus => cpctplus::recoverer(self),
                             RecoveryKind::None => {
                                 let la_lexeme = self.next_lexeme(laidx);
-                                errors.push(
-                                    ParseError {
-                                        stidx,
-                                        lexeme: la_lexeme,
-                                        repairs: vec![],
-                                    }
-                                    .into(),
-                                );
+                                errors.push(ParseError::new(stidx, la_lexeme, vec![]).into());
                                 return None;
                             }
                         });
@@ -335,14 +372,7 @@ where
                         .unwrap_or_else(|| Duration::new(0, 0));
                     let keep_going = !repairs.is_empty();
                     let la_lexeme = self.next_lexeme(laidx);
-                    errors.push(
-                        ParseError {
-                            stidx,
-                            lexeme: la_lexeme,
-                            repairs,
-                        }
-                        .into(),
-                    );
+                    errors.push(ParseError::new(stidx, la_lexeme, repairs).into());
                     if !keep_going {
                         return None;
                     }
@@ -361,7 +391,7 @@ where
         lexeme_prefix: Option<Lexeme<StorageT>>,
         mut laidx: usize,
         end_laidx: usize,
-        pstack: &mut PStack,
+        pstack: &mut PStack<StIdxT>,
         astack: &mut Option<&mut Vec<AStackType<ActionT, StorageT>>>,
         spans: &mut Option<&mut Vec<Span>>,
     ) -> usize {
@@ -396,12 +426,9 @@ where
                             spans_uw.truncate(pop_idx - 1);
                             spans_uw.push(span);
 
-                            let v = AStackType::ActionType(self.actions[usize::from(pidx)](
-                                ridx,
-                                self.lexer,
-                                span,
-                                astack_uw.drain(pop_idx - 1..),
-                            ));
+                            let v = AStackType::ActionType(
+                                self.action(pidx, ridx, span, astack_uw, pop_idx),
+                            );
                             astack_uw.push(v);
                         } else {
                             unreachable!();
@@ -475,6 +502,28 @@ where
         }
     }
 
+    /// Execute the action for production `pidx` of rule `ridx`, whose arguments are the elements
+    /// of `astack` from `pop_idx - 1` onwards, removing those arguments from `astack`.
+    #[inline]
+    fn action(
+        &self,
+        pidx: PIdx<StorageT>,
+        ridx: RIdx<StorageT>,
+        span: Span,
+        astack: &mut Vec<AStackType<ActionT, StorageT>>,
+        pop_idx: usize,
+    ) -> ActionT {
+        match self.actions {
+            Actions::Dyn(actions) => {
+                actions[usize::from(pidx)](ridx, self.lexer, span, astack.drain(pop_idx - 1..))
+            }
+            Actions::Dispatch(dispatch) => {
+                debug_assert_eq!(astack.len() - (pop_idx - 1), self.grm.prod(pidx).len());
+                dispatch(pidx, ridx, self.lexer, span, astack)
+            }
+        }
+    }
+
     /// Start parsing text at `laidx` (using the lexeme in `lexeme_prefix`, if it is not `None`,
     /// as the first lexeme) up to (but excluding) `end_laidx`. If an error is encountered, parsing
     /// immediately terminates (without recovery).
@@ -487,9 +536,9 @@ where
         lexeme_prefix: Option<Lexeme<StorageT>>,
         mut laidx: usize,
         end_laidx: usize,
-        mut pstack: Cactus<StIdx>,
+        mut pstack: Cactus<StIdx<StIdxT>>,
         tstack: &mut Option<&mut Vec<Node<StorageT>>>,
-    ) -> (usize, Cactus<StIdx>) {
+    ) -> (usize, Cactus<StIdx<StIdxT>>) {
         assert!(lexeme_prefix.is_none() || end_laidx == laidx + 1);
         while laidx != end_laidx {
             let stidx = *pstack.val().unwrap();
@@ -544,13 +593,52 @@ where
     }
 }
 
-pub trait Recoverer<StorageT: Hash + PrimInt + Unsigned, ActionT> {
+/// Replace the spans of the symbols being reduced (i.e. those from `pop_idx - 1` onwards) in
+/// `spans` with the span of the rule they reduce to, returning the latter.
+#[doc(hidden)]
+pub fn _reduce_span(spans: &mut Vec<Span>, pop_idx: usize) -> Span {
+    let span = if spans.is_empty() {
+        Span::new(0, 0)
+    } else if pop_idx - 1 < spans.len() {
+        Span::new(spans[pop_idx - 1].start(), spans[spans.len() - 1].end())
+    } else {
+        Span::new(spans[spans.len() - 1].start(), spans[spans.len() - 1].end())
+    };
+    spans.truncate(pop_idx - 1);
+    spans.push(span);
+    span
+}
+
+/// The state of a direct-coded parser when it encountered a parse error, from which
+/// [`RTParserBuilder::_resume_dispatch`](struct.RTParserBuilder.html#method._resume_dispatch)
+/// continues parsing.
+#[doc(hidden)]
+pub struct _DirectState<ActionT, StorageT, StIdxT> {
+    lexemes: Vec<Lexeme<StorageT>>,
+    laidx: usize,
+    pstack: PStack<StIdxT>,
+    astack: Vec<AStackType<ActionT, StorageT>>,
+    spans: Vec<Span>,
+}
+
+/// The outcome of [`RTParserBuilder::_parse_direct`](struct.RTParserBuilder.html#method._parse_direct).
+#[doc(hidden)]
+pub enum _DirectOutcome<ActionT, StorageT: Hash, StIdxT> {
+    /// The input was parsed without errors.
+    Accept(ActionT),
+    /// The input could not be lexed.
+    LexError(Vec<LexParseError<StorageT>>),
+    /// A parse error was encountered.
+    ParseError(_DirectState<ActionT, StorageT, StIdxT>),
+}
+
+pub trait Recoverer<StorageT: Hash + PrimInt + Unsigned, StIdxT: Eq + Hash, ActionT> {
     fn recover(
         &self,
         finish_by: Instant,
-        parser: &Parser<StorageT, ActionT>,
+        parser: &Parser<StorageT, StIdxT, ActionT>,
         in_laidx: usize,
-        in_pstack: &mut PStack,
+        in_pstack: &mut PStack<StIdxT>,
         astack: &mut Vec<AStackType<ActionT, StorageT>>,
         spans: &mut Vec<Span>,
     ) -> (usize, Vec<Vec<ParseRepair<StorageT>>>);
@@ -664,26 +752,32 @@ impl<StorageT: Hash> From<ParseError<StorageT>> for LexParseError<StorageT> {
 }
 
 /// A run-time parser builder.
-pub struct RTParserBuilder<'a, StorageT: Eq + Hash> {
+pub struct RTParserBuilder<'a, StorageT: 'static + Eq + Hash, StIdxT: 'static> {
     grm: &'a YaccGrammar<StorageT>,
-    stable: &'a StateTable<StorageT>,
+    stable: &'a StateTable<StorageT, StIdxT>,
     recoverer: RecoveryKind,
     term_costs: &'a dyn Fn(TIdx<StorageT>) -> u8,
+    start_state: StIdx<StIdxT>,
     phantom: PhantomData<StorageT>,
 }
 
-impl<'a, StorageT: 'static + Debug + Hash + PrimInt + Unsigned> RTParserBuilder<'a, StorageT>
+impl<
+        'a,
+        StorageT: 'static + Debug + Hash + PrimInt + Unsigned,
+        StIdxT: 'static + Debug + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    > RTParserBuilder<'a, StorageT, StIdxT>
 where
-    usize: AsPrimitive<StorageT>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     /// Create a new run-time parser from a `YaccGrammar`, a `StateGraph`, and a `StateTable`.
-    pub fn new(grm: &'a YaccGrammar<StorageT>, stable: &'a StateTable<StorageT>) -> Self {
+    pub fn new(grm: &'a YaccGrammar<StorageT>, stable: &'a StateTable<StorageT, StIdxT>) -> Self {
         RTParserBuilder {
             grm,
             stable,
             recoverer: RecoveryKind::CPCTPlus,
             term_costs: &|_| 1,
+            start_state: stable.start_state(),
             phantom: PhantomData,
         }
     }
@@ -699,6 +793,20 @@ where
         self
     }
 
+    /// Parse input as an instance of rule `ridx`, which must be one of the rules in the grammar's
+    /// `%start` declaration (by default, the first such rule is used). Panics if `ridx` is not a
+    /// `%start` rule.
+    pub fn start_rule(mut self, ridx: RIdx<StorageT>) -> Self {
+        let i = self
+            .grm
+            .start_prods()
+            .iter()
+            .position(|&pidx| self.grm.prod(pidx) == [Symbol::Rule(ridx)])
+            .unwrap_or_else(|| panic!("Rule '{}' is not a %start rule.", self.grm.rule_name(ridx)));
+        self.start_state = self.stable.start_states()[i];
+        self
+    }
+
     /// Parse input, and (if possible) return a generic parse tree. See the arguments for
     /// [`parse_actions`](#method.parse_actions) for more details about the return value.
     pub fn parse_generictree(
@@ -712,16 +820,40 @@ where
                 Err(e) => return (None, vec![e.into()]),
             }
         }
-        Parser::<StorageT, Node<StorageT>>::parse_generictree(
+        Parser::<StorageT, StIdxT, Node<StorageT>>::parse_generictree(
             self.recoverer,
             self.grm,
             self.term_costs,
             self.stable,
+            self.start_state,
             lexer,
             lexemes,
         )
     }
 
+    /// Parse input with a GLR parser, and (if possible) return a [`Forest`](../glr/struct.Forest.html)
+    /// of all possible parse trees. Unlike the other `parse_*` functions, all of the actions
+    /// discarded by Yacc's default conflict resolution (see
+    /// [`StateTable::glr_actions`](../../lrtable/statetable/struct.StateTable.html#method.glr_actions))
+    /// are followed, so grammars with conflicts can be parsed without losing parses. There is no
+    /// error recovery: parsing stops at the first error, which is returned without any repairs.
+    pub fn parse_glr(
+        &self,
+        lexer: &dyn NonStreamingLexer<StorageT>,
+    ) -> (Option<Forest<StorageT>>, Vec<LexParseError<StorageT>>) {
+        let mut lexemes = vec![];
+        for e in lexer.iter().collect::<Vec<_>>() {
+            match e {
+                Ok(l) => lexemes.push(l),
+                Err(e) => return (None, vec![e.into()]),
+            }
+        }
+        match glr::parse(self.grm, self.stable, self.start_state, &lexemes) {
+            Ok(f) => (Some(f), vec![]),
+            Err(e) => (None, vec![e.into()]),
+        }
+    }
+
     /// Parse input, returning any errors found. See the arguments for
     /// [`parse_actions`](#method.parse_actions) for more details about the return value.
     pub fn parse_noaction(
@@ -735,11 +867,12 @@ where
                 Err(e) => return vec![e.into()],
             }
         }
-        Parser::<StorageT, ()>::parse_noaction(
+        Parser::<StorageT, StIdxT, ()>::parse_noaction(
             self.recoverer,
             self.grm,
             self.term_costs,
             self.stable,
+            self.start_state,
             lexer,
             lexemes,
         )
@@ -768,11 +901,113 @@ where
             self.grm,
             self.term_costs,
             self.stable,
+            self.start_state,
             lexer,
             lexemes,
-            actions,
+            Actions::Dyn(actions),
+        )
+    }
+
+    /// Parse input, executing actions through `dispatch`, with the same return value as
+    /// [`parse_actions`](#method.parse_actions). This function is called by generated files.
+    #[doc(hidden)]
+    pub fn _parse_dispatch<'b: 'a, 'input: 'b, ActionT: 'a>(
+        &self,
+        lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
+        dispatch: DispatchFn<'a, 'b, 'input, StorageT, ActionT>,
+    ) -> (Option<ActionT>, Vec<LexParseError<StorageT>>) {
+        let mut lexemes = vec![];
+        for e in lexer.iter().collect::<Vec<_>>() {
+            match e {
+                Ok(l) => lexemes.push(l),
+                Err(e) => return (None, vec![e.into()]),
+            }
+        }
+        Parser::parse_actions(
+            self.recoverer,
+            self.grm,
+            self.term_costs,
+            self.stable,
+            self.start_state,
+            lexer,
+            lexemes,
+            Actions::Dispatch(dispatch),
         )
     }
+
+    /// Parse input with the direct-coded parser `direct`. If a parse error is encountered, the
+    /// parser's state is returned so that it can be passed to
+    /// [`_resume_dispatch`](#method._resume_dispatch). This function is called by generated files.
+    #[doc(hidden)]
+    pub fn _parse_direct<'b, 'input: 'b, ActionT>(
+        &self,
+        lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
+        direct: DirectFn<'_, 'b, 'input, StorageT, StIdxT, ActionT>,
+    ) -> _DirectOutcome<ActionT, StorageT, StIdxT> {
+        let mut lexemes = vec![];
+        for e in lexer.iter().collect::<Vec<_>>() {
+            match e {
+                Ok(l) => lexemes.push(l),
+                Err(e) => return _DirectOutcome::LexError(vec![e.into()]),
+            }
+        }
+        let mut laidx = 0;
+        let mut pstack = vec![self.start_state];
+        let mut astack = Vec::new();
+        let mut spans = Vec::new();
+        match direct(
+            lexer,
+            &lexemes,
+            &mut laidx,
+            &mut pstack,
+            &mut astack,
+            &mut spans,
+        ) {
+            Some(v) => _DirectOutcome::Accept(v),
+            None => _DirectOutcome::ParseError(_DirectState {
+                lexemes,
+                laidx,
+                pstack,
+                astack,
+                spans,
+            }),
+        }
+    }
+
+    /// Continue parsing from the point at which a direct-coded parser encountered a parse error,
+    /// recovering from the error (if possible) and executing actions through `dispatch` as
+    /// [`_parse_dispatch`](#method._parse_dispatch) does. This function is called by generated
+    /// files.
+    #[doc(hidden)]
+    pub fn _resume_dispatch<'b: 'a, 'input: 'b, ActionT: 'a>(
+        &self,
+        lexer: &'b dyn NonStreamingLexer<'input, StorageT>,
+        dispatch: DispatchFn<'a, 'b, 'input, StorageT, ActionT>,
+        state: _DirectState<ActionT, StorageT, StIdxT>,
+    ) -> (Option<ActionT>, Vec<LexParseError<StorageT>>) {
+        let _DirectState {
+            lexemes,
+            laidx,
+            mut pstack,
+            mut astack,
+            mut spans,
+        } = state;
+        for tidx in self.grm.iter_tidxs() {
+            assert!((self.term_costs)(tidx) > 0);
+        }
+        let psr = Parser {
+            rcvry_kind: self.recoverer,
+            grm: self.grm,
+            token_cost: Box::new(self.term_costs),
+            stable: self.stable,
+            lexer,
+            lexemes,
+            actions: Actions::Dispatch(dispatch),
+        };
+        let mut errors = Vec::new();
+        let accpt = psr.lr(laidx, &mut pstack, &mut astack, &mut errors, &mut spans);
+        (accpt, errors)
+    }
 }
 
 /// After a parse error is encountered, the parser attempts to find a way of recovering. Each entry
@@ -790,7 +1025,7 @@ pub enum ParseRepair<StorageT: Hash> {
 /// Records a single parse error.
 #[derive(Clone, Debug, PartialEq)]
 pub struct ParseError<StorageT: Hash> {
-    stidx: StIdx,
+    stidx: StIdx<u32>,
     lexeme: Lexeme<StorageT>,
     repairs: Vec<Vec<ParseRepair<StorageT>>>,
 }
@@ -804,8 +1039,22 @@ impl<StorageT: Debug + Hash> Display for ParseError<StorageT> {
 impl<StorageT: Debug + Hash> Error for ParseError<StorageT> {}
 
 impl<StorageT: Hash + PrimInt + Unsigned> ParseError<StorageT> {
+    pub(crate) fn new<StIdxT: AsPrimitive<usize>>(
+        stidx: StIdx<StIdxT>,
+        lexeme: Lexeme<StorageT>,
+        repairs: Vec<Vec<ParseRepair<StorageT>>>,
+    ) -> Self {
+        ParseError {
+            // State indexes always fit in a u32 (see `StateGraph`), whatever width the state table
+            // stores them in.
+            stidx: StIdx::from(usize::from(stidx) as u32),
+            lexeme,
+            repairs,
+        }
+    }
+
     /// Return the state table index where this error was detected.
-    pub fn stidx(&self) -> StIdx {
+    pub fn stidx(&self) -> StIdx<u32> {
         self.stidx
     }
 
@@ -863,7 +1112,7 @@ pub(crate) mod test {
             grms,
         )
         .unwrap();
-        let (_, stable) = from_yacc(&grm, Minimiser::Pager).unwrap();
+        let (_, stable) = from_yacc::<_, u16>(&grm, Minimiser::Pager).unwrap();
         let rule_ids = grm
             .tokens_map()
             .iter()
@@ -898,8 +1147,8 @@ pub(crate) mod test {
     // lrlex as a dependency of lrpar). The format is the same as lrlex *except*:
     //   * The initial "%%" isn't needed, and only "'" is valid as a rule name delimiter.
     //   * "Unnamed" rules aren't allowed (e.g. you can't have a rule which discards whitespaces).
-    struct SmallLexer<StorageT> {
-        lexemes: Vec<Lexeme<StorageT>>,
+    pub(crate) struct SmallLexer<StorageT> {
+        pub(crate) lexemes: Vec<Lexeme<StorageT>>,
     }
 
     impl<StorageT: Hash + PrimInt + Unsigned> Lexer<StorageT> for SmallLexer<StorageT> {
@@ -924,7 +1173,7 @@ pub(crate) mod test {
         }
     }
 
-    fn small_lexer(lexs: &str, ids_map: HashMap<String, u16>) -> Vec<(u16, Regex)> {
+    pub(crate) fn small_lexer(lexs: &str, ids_map: HashMap<String, u16>) -> Vec<(u16, Regex)> {
         let mut rules = Vec::new();
         for l in lexs.split("\n").map(|x| x.trim()).filter(|x| !x.is_empty()) {
             assert!(l.rfind('\'') == Some(l.len() - 1));
@@ -939,7 +1188,7 @@ pub(crate) mod test {
         rules
     }
 
-    fn small_lex(rules: Vec<(u16, Regex)>, input: &str) -> Vec<Lexeme<u16>> {
+    pub(crate) fn small_lex(rules: Vec<(u16, Regex)>, input: &str) -> Vec<Lexeme<u16>> {
         let mut lexemes = vec![];
         let mut i = 0;
         while i < input.len() {
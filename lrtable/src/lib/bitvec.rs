@@ -0,0 +1,137 @@
+use std::ops::Range;
+
+use cfgrammar::MaybeStatic;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use vob::Vob;
+
+/// A fixed-length vector of bits. Unlike a `Vob`, this is stored as `u32` blocks (least
+/// significant bit first), so that bit vectors generated at compile-time can be borrowed directly
+/// from `static` arrays on any platform.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct BitVec {
+    len: usize,
+    blocks: MaybeStatic<u32>,
+}
+
+impl BitVec {
+    /// Construct a bit vector with the same bits as `v`.
+    pub(crate) fn from_vob(v: &Vob) -> BitVec {
+        let mut blocks = vec![0u32; v.len().div_ceil(32)];
+        for i in v.iter_set_bits(..) {
+            blocks[i / 32] |= 1 << (i % 32);
+        }
+        BitVec {
+            len: v.len(),
+            blocks: blocks.into(),
+        }
+    }
+
+    /// Construct a bit vector `len` bits long from the `static` array returned by
+    /// [`blocks`](#method.blocks).
+    pub(crate) const fn from_static(len: usize, blocks: &'static [u32]) -> BitVec {
+        BitVec {
+            len,
+            blocks: MaybeStatic::Static(blocks),
+        }
+    }
+
+    /// Return the blocks this bit vector is stored in.
+    pub(crate) fn blocks(&self) -> &[u32] {
+        &self.blocks
+    }
+
+    /// How many bits does this bit vector have?
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Return the value of bit `i`. Panics if `i` is out of bounds.
+    pub(crate) fn get(&self, i: usize) -> bool {
+        assert!(i < self.len);
+        self.blocks[i / 32] & (1 << (i % 32)) != 0
+    }
+
+    /// How many bits are set?
+    pub(crate) fn count_ones(&self) -> usize {
+        self.blocks.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    /// Return an iterator over the indexes of the set bits in `range`. Panics if `range` is out
+    /// of bounds.
+    pub(crate) fn iter_set_bits(&self, range: Range<usize>) -> IterSetBits<'_> {
+        assert!(range.start <= range.end && range.end <= self.len);
+        IterSetBits {
+            blocks: &self.blocks,
+            i: range.start,
+            end: range.end,
+        }
+    }
+}
+
+pub(crate) struct IterSetBits<'a> {
+    blocks: &'a [u32],
+    i: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for IterSetBits<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.i < self.end {
+            let b = self.blocks[self.i / 32] >> (self.i % 32);
+            if b == 0 {
+                // Skip the rest of this block.
+                self.i = (self.i / 32 + 1) * 32;
+                continue;
+            }
+            let i = self.i + b.trailing_zeros() as usize;
+            if i >= self.end {
+                break;
+            }
+            self.i = i + 1;
+            return Some(i);
+        }
+        self.i = self.end;
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BitVec;
+    use vob::Vob;
+
+    #[test]
+    fn test_bitvec() {
+        let mut v = Vob::from_elem(100, false);
+        for &i in &[0, 5, 31, 32, 63, 64, 99] {
+            v.set(i, true);
+        }
+        let bv = BitVec::from_vob(&v);
+        assert_eq!(bv.len(), 100);
+        assert_eq!(bv.count_ones(), 7);
+        for i in 0..100 {
+            assert_eq!(bv.get(i), v[i]);
+        }
+        assert_eq!(
+            bv.iter_set_bits(0..100).collect::<Vec<_>>(),
+            vec![0, 5, 31, 32, 63, 64, 99]
+        );
+        assert_eq!(
+            bv.iter_set_bits(6..64).collect::<Vec<_>>(),
+            vec![31, 32, 63]
+        );
+        assert_eq!(bv.iter_set_bits(33..63).count(), 0);
+        assert_eq!(bv.iter_set_bits(99..99).count(), 0);
+
+        static BLOCKS: [u32; 4] = [0x8000_0021, 0x8000_0001, 0x1, 0x8];
+        let sbv = BitVec::from_static(100, &BLOCKS);
+        assert_eq!(sbv.blocks(), bv.blocks());
+        assert_eq!(
+            sbv.iter_set_bits(0..100).collect::<Vec<_>>(),
+            vec![0, 5, 31, 32, 63, 64, 99]
+        );
+    }
+}
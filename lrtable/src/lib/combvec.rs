@@ -0,0 +1,174 @@
+use std::{collections::hash_map::HashMap, convert::TryFrom};
+
+use cfgrammar::MaybeStatic;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A comb vector: a compressed two-dimensional table of `usize`s in which `0` is considered
+/// "empty". Identical rows are shared, and the non-empty cells of the remaining rows are packed
+/// into a single vector by row displacement. Each packed slot records which row owns it, so
+/// looking up a cell needs no per-cell bitmap: a slot owned by another row is an empty cell.
+/// Everything is stored as `u32`s, so that comb vectors generated at compile-time can be borrowed
+/// directly from `static` arrays on any platform.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub(crate) struct CombVec {
+    /// The row length of the original table.
+    row_length: usize,
+    /// Maps each row of the original table to a unique row.
+    rows: MaybeStatic<u32>,
+    /// The displacement of each unique row in `next`.
+    base: MaybeStatic<u32>,
+    /// The packed cell values.
+    next: MaybeStatic<u32>,
+    /// For each slot in `next`, the unique row which owns it plus 1, or 0 if the slot is unused.
+    check: MaybeStatic<u32>,
+}
+
+impl CombVec {
+    /// Construct a comb vector from the table `v` with rows `row_length` long. Panics if a value
+    /// in `v` does not fit into a `u32`.
+    pub(crate) fn from(v: &[usize], row_length: usize) -> CombVec {
+        let mut uniq = HashMap::new();
+        let mut uniq_rows = Vec::new();
+        let mut rows = Vec::with_capacity(v.len().checked_div(row_length).unwrap_or(0));
+        for row in v.chunks(row_length.max(1)) {
+            let i = *uniq.entry(row).or_insert_with(|| {
+                uniq_rows.push(row);
+                uniq_rows.len() - 1
+            });
+            rows.push(i);
+        }
+
+        // Fit the fullest rows first, as suggested in "Smaller faster table driven parser" by
+        // S. F. Ziegler.
+        let mut order = (0..uniq_rows.len()).collect::<Vec<_>>();
+        order.sort_by_key(|&i| uniq_rows[i].iter().filter(|&&x| x == 0).count());
+
+        let mut base = vec![0; uniq_rows.len()];
+        let mut next = Vec::new();
+        let mut check = Vec::new();
+        for i in order {
+            let cols = uniq_rows[i]
+                .iter()
+                .enumerate()
+                .filter(|(_, &x)| x != 0)
+                .map(|(c, _)| c)
+                .collect::<Vec<_>>();
+            if cols.is_empty() {
+                continue;
+            }
+            let mut d = 0;
+            while cols
+                .iter()
+                .any(|&c| check.get(d + c).map(|&x| x != 0).unwrap_or(false))
+            {
+                d += 1;
+            }
+            let end = d + cols.last().unwrap() + 1;
+            if end > next.len() {
+                next.resize(end, 0);
+                check.resize(end, 0);
+            }
+            for c in cols {
+                next[d + c] = uniq_rows[i][c];
+                check[d + c] = i + 1;
+            }
+            base[i] = d;
+        }
+
+        CombVec {
+            row_length,
+            rows: to_u32s(rows),
+            base: to_u32s(base),
+            next: to_u32s(next),
+            check: to_u32s(check),
+        }
+    }
+
+    /// Construct a comb vector from the `static` arrays returned by [`parts`](#method.parts).
+    pub(crate) const fn from_static(row_length: usize, parts: [&'static [u32]; 4]) -> CombVec {
+        CombVec {
+            row_length,
+            rows: MaybeStatic::Static(parts[0]),
+            base: MaybeStatic::Static(parts[1]),
+            next: MaybeStatic::Static(parts[2]),
+            check: MaybeStatic::Static(parts[3]),
+        }
+    }
+
+    /// Return this comb vector's `rows`, `base`, `next`, and `check` arrays.
+    pub(crate) fn parts(&self) -> [&[u32]; 4] {
+        [&self.rows, &self.base, &self.next, &self.check]
+    }
+
+    /// Return the value of the cell at row `r` and column `c`.
+    pub(crate) fn get(&self, r: usize, c: usize) -> usize {
+        debug_assert!(c < self.row_length);
+        let u = self.rows[r] as usize;
+        let i = self.base[u] as usize + c;
+        match self.check.get(i) {
+            Some(&x) if x as usize == u + 1 => self.next[i] as usize,
+            _ => 0,
+        }
+    }
+
+    /// Return the number of cells in the original table.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.rows.len() * self.row_length
+    }
+
+    /// Return the number of unique rows.
+    pub(crate) fn rows_len(&self) -> usize {
+        self.base.len()
+    }
+
+    /// Return the number of slots in the packed vector.
+    pub(crate) fn slots_len(&self) -> usize {
+        self.next.len()
+    }
+}
+
+fn to_u32s(v: Vec<usize>) -> MaybeStatic<u32> {
+    v.into_iter()
+        .map(|x| u32::try_from(x).expect("Comb vector value does not fit into a u32"))
+        .collect::<Vec<_>>()
+        .into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::CombVec;
+
+    #[test]
+    fn test_combvec() {
+        let v = vec![
+            1, 0, 2, 0, //
+            0, 3, 0, 0, //
+            1, 0, 2, 0, //
+            0, 0, 0, 0, //
+            0, 0, 0, 4, //
+        ];
+        let cv = CombVec::from(&v, 4);
+        assert_eq!(cv.len(), 20);
+        assert_eq!(cv.rows_len(), 4);
+        // Rows 0 and 2 are shared; rows 1 and 4 slot into the gaps in row 0.
+        assert_eq!(cv.slots_len(), 4);
+        for r in 0..5 {
+            for c in 0..4 {
+                assert_eq!(cv.get(r, c), v[r * 4 + c]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_combvec_empty() {
+        let cv = CombVec::from(&[], 3);
+        assert_eq!(cv.len(), 0);
+        assert_eq!(cv.slots_len(), 0);
+        let cv = CombVec::from(&[0, 0, 0, 0], 2);
+        assert_eq!(cv.rows_len(), 1);
+        assert_eq!(cv.get(1, 1), 0);
+    }
+}
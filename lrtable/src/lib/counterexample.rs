@@ -0,0 +1,773 @@
+//! Counterexamples which explain why a conflict occurs.
+//!
+//! For a conflict on the token `t` in a state `s`, we first search for the shortest sequence of
+//! symbols which takes the parser from a start state to `s` such that `t` can really follow the
+//! (first) item being reduced: a "lookahead-sensitive path" in the terminology of Isradisaikul and
+//! Myers ("Finding counterexamples from parsing conflicts", PLDI 2015). The competing item (the
+//! item which shifts `t`, or the second item being reduced) is then derived along the same
+//! sequence of states, preferring (within a bounded search) a derivation which leads to the same
+//! sentence as the first. Both derivations are completed into full sentences using the grammar's
+//! minimal sentences. If the two sentences are identical, the counterexample is *unifying*: the
+//! sentence has two distinct parse trees, and the grammar is ambiguous. Otherwise, the grammar may
+//! not be ambiguous at all (e.g. the conflict might be resolvable with more lookahead), though
+//! since we only consider minimal completions, a non-unifying counterexample does not prove that
+//! the grammar is unambiguous.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
+use cfgrammar::{
+    yacc::{firsts::YaccFirsts, SentenceGenerator, YaccGrammar},
+    PIdx, SIdx, Symbol, TIdx,
+};
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+
+use crate::{stategraph::StateGraph, StIdx};
+
+/// A counterexample for a conflict: two derivations of sentences which reach the conflicting state
+/// with the conflicting token as the next token, but which require the parser to take different
+/// actions at that point.
+#[derive(Debug)]
+pub struct Counterexample<StorageT> {
+    tidx: TIdx<StorageT>,
+    stidx: StIdx<u32>,
+    first: Derivation<StorageT>,
+    second: Derivation<StorageT>,
+}
+
+/// One side of a counterexample: the items being parsed at the conflict point, and a sentence
+/// derived in that way.
+#[derive(Debug)]
+pub struct Derivation<StorageT> {
+    items: Vec<(PIdx<StorageT>, SIdx<StorageT>)>,
+    sentence: Vec<TIdx<StorageT>>,
+    conflict_off: usize,
+}
+
+impl<StorageT: 'static + Hash + PrimInt + Unsigned> Counterexample<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    /// The token the conflict is on.
+    pub fn tidx(&self) -> TIdx<StorageT> {
+        self.tidx
+    }
+
+    /// The state the conflict is in.
+    pub fn stidx(&self) -> StIdx<u32> {
+        self.stidx
+    }
+
+    /// The derivation which reduces the (first) conflicting production.
+    pub fn first(&self) -> &Derivation<StorageT> {
+        &self.first
+    }
+
+    /// The competing derivation: for a shift/reduce conflict, the derivation which shifts the
+    /// conflicting token; for a reduce/reduce conflict, the derivation which reduces the second
+    /// conflicting production.
+    pub fn second(&self) -> &Derivation<StorageT> {
+        &self.second
+    }
+
+    /// The shortest input which takes the parser from a start state to the conflicting state.
+    pub fn input_prefix(&self) -> &[TIdx<StorageT>] {
+        self.first.input_prefix()
+    }
+
+    /// Does this counterexample unify, i.e. do both derivations derive the same sentence? If so,
+    /// the grammar is ambiguous.
+    pub fn is_unifying(&self) -> bool {
+        self.first.sentence == self.second.sentence
+            && self.first.conflict_off == self.second.conflict_off
+    }
+
+    /// Returns a pretty-printed version of this counterexample.
+    pub fn pp(&self, grm: &YaccGrammar<StorageT>) -> String {
+        let (kind, first, second) = if self.second.is_reduce(grm) {
+            ("Reduce/Reduce", "First reduce", "Second reduce")
+        } else {
+            ("Shift/Reduce", "Reduce", "Shift")
+        };
+        let mut s = format!(
+            "{} conflict on {} in state {}",
+            kind,
+            pp_sym(grm, Symbol::Token(self.tidx)),
+            usize::from(self.stidx)
+        );
+        if self.is_unifying() {
+            s.push_str(" (ambiguous):\n");
+            s.push_str(&format!("  Example: {}\n", self.first.pp_sentence(grm)));
+        } else {
+            s.push_str(" (no unifying counterexample found):\n");
+            s.push_str(&format!(
+                "  {} example: {}\n",
+                first,
+                self.first.pp_sentence(grm)
+            ));
+        }
+        s.push_str(&format!("  {} derivation: {}\n", first, self.first.pp(grm)));
+        if !self.is_unifying() {
+            s.push_str(&format!(
+                "  {} example: {}\n",
+                second,
+                self.second.pp_sentence(grm)
+            ));
+        }
+        s.push_str(&format!(
+            "  {} derivation: {}\n",
+            second,
+            self.second.pp(grm)
+        ));
+        s
+    }
+}
+
+impl<StorageT: 'static + Hash + PrimInt + Unsigned> Derivation<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    /// The items being parsed at the conflict point, outermost (i.e. the start rule's production)
+    /// first. In every item but the last, the dot is before the rule of the next item; the last
+    /// item is the conflicting item.
+    pub fn items(&self) -> &[(PIdx<StorageT>, SIdx<StorageT>)] {
+        &self.items
+    }
+
+    /// A complete sentence derived in this way.
+    pub fn sentence(&self) -> &[TIdx<StorageT>] {
+        &self.sentence
+    }
+
+    /// The input consumed before the parser reaches the conflicting state.
+    pub fn input_prefix(&self) -> &[TIdx<StorageT>] {
+        &self.sentence[..self.conflict_off]
+    }
+
+    /// The input remaining when the parser reaches the conflicting state (which starts with the
+    /// conflicting token, unless it is the end of the input).
+    pub fn input_suffix(&self) -> &[TIdx<StorageT>] {
+        &self.sentence[self.conflict_off..]
+    }
+
+    fn is_reduce(&self, grm: &YaccGrammar<StorageT>) -> bool {
+        let (pidx, dot) = *self.items.last().unwrap();
+        usize::from(dot) == grm.prod(pidx).len()
+    }
+
+    /// Returns the sentence with `•` marking the conflict point.
+    pub fn pp_sentence(&self, grm: &YaccGrammar<StorageT>) -> String {
+        self.input_prefix()
+            .iter()
+            .map(|&tidx| pp_sym(grm, Symbol::Token(tidx)))
+            .chain(Some("•".to_owned()))
+            .chain(
+                self.input_suffix()
+                    .iter()
+                    .map(|&tidx| pp_sym(grm, Symbol::Token(tidx))),
+            )
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Returns the derivation in the form `A -> [ x B -> [ y • z ] ]`, where each `[ ... ]`
+    /// contains the symbols of a production, and `•` marks the conflict point.
+    pub fn pp(&self, grm: &YaccGrammar<StorageT>) -> String {
+        // The first item is the start rule's, which was added by cfgrammar.
+        let mut s = String::new();
+        for (i, &(pidx, dot)) in self.items.iter().enumerate().skip(1) {
+            let prod = grm.prod(pidx);
+            let dot = usize::from(dot);
+            s.push_str(grm.rule_name(grm.prod_to_rule(pidx)));
+            s.push_str(" -> [");
+            for &sym in &prod[..dot] {
+                s.push_str(&format!(" {}", pp_sym(grm, sym)));
+            }
+            if i == self.items.len() - 1 {
+                s.push_str(" •");
+                for &sym in &prod[dot..] {
+                    s.push_str(&format!(" {}", pp_sym(grm, sym)));
+                }
+            } else {
+                // The next item is the derivation of the symbol at the dot.
+                s.push(' ');
+            }
+        }
+        for (i, &(pidx, dot)) in self.items.iter().enumerate().skip(1).rev() {
+            if i < self.items.len() - 1 {
+                for &sym in &grm.prod(pidx)[usize::from(dot) + 1..] {
+                    s.push_str(&format!(" {}", pp_sym(grm, sym)));
+                }
+            }
+            s.push_str(" ]");
+        }
+        s
+    }
+}
+
+fn pp_sym<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    sym: Symbol<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    match sym {
+        Symbol::Rule(ridx) => grm.rule_name(ridx).to_owned(),
+        Symbol::Token(tidx) => match grm.token_name(tidx) {
+            Some(n) => format!("\"{}\"", n),
+            None => "$".to_owned(),
+        },
+    }
+}
+
+/// Return a counterexample for the shift/reduce conflict between shifting `tidx` and reducing
+/// `pidx` in state `stidx`, or `None` if one can't be found.
+pub fn sr_counterexample<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    sg: &StateGraph<StorageT>,
+    tidx: TIdx<StorageT>,
+    pidx: PIdx<StorageT>,
+    stidx: StIdx<u32>,
+) -> Option<Counterexample<StorageT>>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let shifts = sg
+        .closed_state(stidx)
+        .items
+        .keys()
+        .filter(|&&(s_pidx, dot)| {
+            grm.prod(s_pidx).get(usize::from(dot)) == Some(&Symbol::Token(tidx))
+        })
+        .map(|&(s_pidx, dot)| (s_pidx, usize::from(dot)))
+        .collect::<Vec<_>>();
+    Search::new(grm, sg, tidx, stidx).counterexample(pidx, &shifts, false)
+}
+
+/// Return a counterexample for the reduce/reduce conflict between reducing `pidx` and `r_pidx` in
+/// state `stidx`, or `None` if one can't be found.
+pub fn rr_counterexample<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    sg: &StateGraph<StorageT>,
+    pidx: PIdx<StorageT>,
+    r_pidx: PIdx<StorageT>,
+    stidx: StIdx<u32>,
+) -> Option<Counterexample<StorageT>>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    // Reduce/reduce conflicts don't record the token they're on, so we pick the first token
+    // that both productions can be reduced on.
+    let st = sg.closed_state(stidx);
+    let ctx = |pidx: PIdx<StorageT>| {
+        let dot = SIdx(grm.prod(pidx).len().as_());
+        st.items.get(&(pidx, dot))
+    };
+    let mut both = ctx(pidx)?.clone();
+    both.and(ctx(r_pidx)?);
+    let tidx = TIdx(both.iter_set_bits(..).next()?.as_());
+    let r_item = (r_pidx, grm.prod(r_pidx).len());
+    Search::new(grm, sg, tidx, stidx).counterexample(pidx, &[r_item], true)
+}
+
+/// How many nodes do we visit when searching for a unifying counterexample before giving up?
+const UNIFYING_BUDGET: usize = 10_000;
+
+/// A node in the search: an offset (a state index in the unconstrained search, or an offset into
+/// a path of states in the constrained search), an item, and whether the conflicting token can
+/// follow the item's rule.
+type Node<StorageT> = (usize, PIdx<StorageT>, usize, bool);
+
+struct Search<'a, StorageT: 'static + Eq + Hash> {
+    grm: &'a YaccGrammar<StorageT>,
+    sg: &'a StateGraph<StorageT>,
+    firsts: YaccFirsts<StorageT>,
+    sentgen: SentenceGenerator<'a, StorageT>,
+    tidx: TIdx<StorageT>,
+    stidx: StIdx<u32>,
+}
+
+impl<'a, StorageT: 'static + Hash + PrimInt + Unsigned> Search<'a, StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    fn new(
+        grm: &'a YaccGrammar<StorageT>,
+        sg: &'a StateGraph<StorageT>,
+        tidx: TIdx<StorageT>,
+        stidx: StIdx<u32>,
+    ) -> Self {
+        Search {
+            grm,
+            sg,
+            firsts: grm.firsts(),
+            sentgen: grm.sentence_generator(|_| 1),
+            tidx,
+            stidx,
+        }
+    }
+
+    /// Find a counterexample where the first derivation reduces `pidx` and the second derivation
+    /// ends in one of the items in `others` (if `others_reduce` is true, with the conflicting
+    /// token following).
+    fn counterexample(
+        &self,
+        pidx: PIdx<StorageT>,
+        others: &[(PIdx<StorageT>, usize)],
+        others_reduce: bool,
+    ) -> Option<Counterexample<StorageT>> {
+        let reduce = (pidx, self.grm.prod(pidx).len());
+        let first_path = self.shortest_path(&[reduce], true)?;
+        // The states on the first derivation's path, and the symbols which move between them.
+        let mut states = vec![first_path[0].0];
+        let mut syms = Vec::new();
+        for &(stidx, n_pidx, dot, _) in &first_path[1..] {
+            if dot > 0 {
+                states.push(stidx);
+                syms.push(self.grm.prod(n_pidx)[dot - 1]);
+            }
+        }
+        let first = self.derivation(&first_path);
+        // Prefer a competing derivation which derives the same sentence as the first (i.e. which
+        // shows that the grammar is ambiguous), falling back to the shortest derivation we can
+        // find.
+        let start = &first_path[0];
+        let second = match self.unifying_along(start, &syms, others, others_reduce, &first) {
+            Some(d) => d,
+            None => {
+                let path = match self.path_along(start, &states, &syms, others, others_reduce) {
+                    Some(p) => p,
+                    None => self.shortest_path(others, others_reduce)?,
+                };
+                self.derivation(&path)
+            }
+        };
+        Some(Counterexample {
+            tidx: self.tidx,
+            stidx: self.stidx,
+            first,
+            second,
+        })
+    }
+
+    /// Does the conflicting token follow `syms` (which may be derived from the empty string),
+    /// given that `follows` says whether it follows the rule `syms` is part of?
+    fn token_follows(&self, syms: &[Symbol<StorageT>], follows: bool) -> bool {
+        let (starts, nullable) = self.starts_with_token(syms);
+        starts || (nullable && follows)
+    }
+
+    /// Return a pair: can `syms` derive a sentence which starts with the conflicting token; and can
+    /// `syms` derive the empty string?
+    fn starts_with_token(&self, syms: &[Symbol<StorageT>]) -> (bool, bool) {
+        let mut starts = false;
+        for &sym in syms {
+            match sym {
+                Symbol::Token(tidx) => return (starts || tidx == self.tidx, false),
+                Symbol::Rule(ridx) => {
+                    starts |= self.firsts.is_set(ridx, self.tidx);
+                    if !self.firsts.is_epsilon_set(ridx) {
+                        return (starts, false);
+                    }
+                }
+            }
+        }
+        (starts, true)
+    }
+
+    /// Push onto `succs` the nodes reachable from `n` in the state `stidx`: either by moving the
+    /// dot over a symbol (`true`) or by starting to parse a rule (`false`).
+    fn succs(&self, n: Node<StorageT>, succs: &mut Vec<(Symbol<StorageT>, Node<StorageT>, bool)>) {
+        let (off, pidx, dot, follows) = n;
+        let prod = self.grm.prod(pidx);
+        if dot == prod.len() {
+            return;
+        }
+        succs.push((prod[dot], (off, pidx, dot + 1, follows), true));
+        if let Symbol::Rule(ridx) = prod[dot] {
+            let follows = self.token_follows(&prod[dot + 1..], follows);
+            for &r_pidx in self.grm.rule_to_prods(ridx) {
+                succs.push((prod[dot], (off, r_pidx, 0, follows), false));
+            }
+        }
+    }
+
+    /// Return the shortest path (measured in symbols) from a start state to one of the `targets`
+    /// items in the conflicting state (if `need_follows` is true, with the conflicting token able
+    /// to follow the item's rule).
+    fn shortest_path(
+        &self,
+        targets: &[(PIdx<StorageT>, usize)],
+        need_follows: bool,
+    ) -> Option<Vec<Node<StorageT>>> {
+        let eof = self.tidx == self.grm.eof_token_idx();
+        let starts = self
+            .grm
+            .start_prods()
+            .iter()
+            .zip(self.sg.start_states())
+            .map(|(&pidx, &stidx)| (usize::from(stidx), pidx, 0, eof))
+            .collect();
+        let stidx = usize::from(self.stidx);
+        search(
+            starts,
+            |n, succs| {
+                self.succs(n, succs);
+                for (sym, m, shift) in succs.iter_mut() {
+                    if *shift {
+                        let st = self.sg.edge(StIdx(m.0 as u32), *sym).unwrap();
+                        m.0 = usize::from(st);
+                    }
+                }
+            },
+            |(off, pidx, dot, follows)| {
+                off == stidx && targets.contains(&(pidx, dot)) && (follows || !need_follows)
+            },
+        )
+    }
+
+    /// Push onto `succs` the nodes reachable from `n` by moving the dot over the next symbol in
+    /// `syms` or by starting to parse a rule. Offsets in nodes are offsets into `syms`.
+    fn succs_along(
+        &self,
+        n: Node<StorageT>,
+        syms: &[Symbol<StorageT>],
+        succs: &mut Vec<(Symbol<StorageT>, Node<StorageT>, bool)>,
+    ) {
+        self.succs(n, succs);
+        succs.retain(|(sym, m, shift)| !*shift || syms.get(m.0) == Some(sym));
+        for (_, m, shift) in succs.iter_mut() {
+            if *shift {
+                m.0 += 1;
+            }
+        }
+    }
+
+    /// Return a path which starts at `start`, moves through `states` by way of the symbols
+    /// `syms`, and ends at one of the `targets` items (if `need_follows` is true, with the
+    /// conflicting token able to follow the item's rule). Nodes in the returned path have state
+    /// indices rather than offsets into `states`.
+    fn path_along(
+        &self,
+        start: &Node<StorageT>,
+        states: &[usize],
+        syms: &[Symbol<StorageT>],
+        targets: &[(PIdx<StorageT>, usize)],
+        need_follows: bool,
+    ) -> Option<Vec<Node<StorageT>>> {
+        let path = search(
+            vec![(0, start.1, start.2, start.3)],
+            |n, succs| self.succs_along(n, syms, succs),
+            |(off, pidx, dot, follows)| {
+                off == syms.len() && targets.contains(&(pidx, dot)) && (follows || !need_follows)
+            },
+        )?;
+        Some(
+            path.into_iter()
+                .map(|(off, pidx, dot, follows)| (states[off], pidx, dot, follows))
+                .collect(),
+        )
+    }
+
+    /// As `path_along`, but search (up to a fixed bound) for a path whose derivation unifies with
+    /// `first`, returning that derivation.
+    fn unifying_along(
+        &self,
+        start: &Node<StorageT>,
+        syms: &[Symbol<StorageT>],
+        targets: &[(PIdx<StorageT>, usize)],
+        need_follows: bool,
+        first: &Derivation<StorageT>,
+    ) -> Option<Derivation<StorageT>> {
+        // Different paths can lead to the same derivation, and there can be exponentially many
+        // paths, so we do a depth first search over them, giving up after visiting `budget` nodes.
+        let mut budget = UNIFYING_BUDGET;
+        let mut path = vec![(0, start.1, start.2, start.3)];
+        let mut todo = vec![Vec::new()];
+        let mut succs = Vec::new();
+        self.succs_along(path[0], syms, &mut succs);
+        todo[0].extend(succs.iter().map(|&(_, m, _)| m));
+        while let Some(next) = todo.last_mut() {
+            let n = match next.pop() {
+                Some(n) => n,
+                None => {
+                    todo.pop();
+                    path.pop();
+                    continue;
+                }
+            };
+            if budget == 0 {
+                return None;
+            }
+            budget -= 1;
+            // Rules which can derive themselves without consuming input would otherwise lead to
+            // infinite paths.
+            if path.contains(&n) {
+                continue;
+            }
+            path.push(n);
+            let (off, pidx, dot, follows) = n;
+            if off == syms.len() && targets.contains(&(pidx, dot)) && (follows || !need_follows) {
+                let d = self.derivation(&path);
+                if d.sentence == first.sentence && d.conflict_off == first.conflict_off {
+                    return Some(d);
+                }
+            }
+            succs.clear();
+            self.succs_along(n, syms, &mut succs);
+            todo.push(succs.iter().map(|&(_, m, _)| m).collect());
+        }
+        None
+    }
+
+    /// Convert a path into a derivation, completing it into a full sentence.
+    fn derivation(&self, path: &[Node<StorageT>]) -> Derivation<StorageT> {
+        // Starting to parse a rule pushes an item; moving the dot over a symbol advances the
+        // innermost item.
+        let mut items: Vec<(PIdx<StorageT>, usize)> = Vec::new();
+        for &(_, pidx, dot, _) in path {
+            if dot == 0 {
+                items.push((pidx, 0));
+            } else {
+                items.last_mut().unwrap().1 = dot;
+            }
+        }
+
+        let mut sentence = Vec::new();
+        for &(pidx, dot) in &items {
+            self.min_sentence(&self.grm.prod(pidx)[..dot], &mut sentence);
+        }
+        let conflict_off = sentence.len();
+        let (pidx, dot) = *items.last().unwrap();
+        let prod = self.grm.prod(pidx);
+        // Does the conflicting token still need to be generated?
+        let mut pending = self.tidx != self.grm.eof_token_idx();
+        if dot < prod.len() {
+            // A shift item, whose next symbol is the conflicting token.
+            sentence.push(self.tidx);
+            self.min_sentence(&prod[dot + 1..], &mut sentence);
+            pending = false;
+        }
+        for &(pidx, dot) in items.iter().rev().skip(1) {
+            let rest = &self.grm.prod(pidx)[dot + 1..];
+            if pending && self.starts_with_token(rest).0 {
+                self.token_sentence(rest, &mut sentence);
+                pending = false;
+            } else {
+                self.min_sentence(rest, &mut sentence);
+            }
+        }
+
+        Derivation {
+            items: items
+                .into_iter()
+                .map(|(pidx, dot)| (pidx, SIdx(dot.as_())))
+                .collect(),
+            sentence,
+            conflict_off,
+        }
+    }
+
+    /// Append a minimal sentence for `syms` to `sentence`.
+    fn min_sentence(&self, syms: &[Symbol<StorageT>], sentence: &mut Vec<TIdx<StorageT>>) {
+        for &sym in syms {
+            match sym {
+                Symbol::Rule(ridx) => sentence.extend(self.sentgen.min_sentence(ridx)),
+                Symbol::Token(tidx) => sentence.push(tidx),
+            }
+        }
+    }
+
+    /// Append to `sentence` a sentence for `syms` which starts with the conflicting token. `syms`
+    /// must be able to derive such a sentence.
+    fn token_sentence(&self, syms: &[Symbol<StorageT>], sentence: &mut Vec<TIdx<StorageT>>) {
+        // Skip the nullable symbols (whose minimal sentences are empty) before the first symbol
+        // which can start with the conflicting token.
+        let i = syms
+            .iter()
+            .position(|&sym| match sym {
+                Symbol::Token(tidx) => tidx == self.tidx,
+                Symbol::Rule(ridx) => self.firsts.is_set(ridx, self.tidx),
+            })
+            .unwrap();
+        match syms[i] {
+            Symbol::Token(tidx) => sentence.push(tidx),
+            Symbol::Rule(ridx) => {
+                // Find the shortest chain of productions from `ridx` which leads to the
+                // conflicting token (there must be one, since it is in `ridx`'s firsts).
+                let path = search(
+                    self.grm
+                        .rule_to_prods(ridx)
+                        .iter()
+                        .map(|&pidx| (0, pidx, 0, false))
+                        .collect(),
+                    |n, succs| {
+                        let (_, pidx, dot, _) = n;
+                        let prod = self.grm.prod(pidx);
+                        if dot == prod.len() {
+                            return;
+                        }
+                        if let Symbol::Rule(r_ridx) = prod[dot] {
+                            if self.firsts.is_epsilon_set(r_ridx) {
+                                succs.push((prod[dot], (0, pidx, dot + 1, false), true));
+                            }
+                            for &r_pidx in self.grm.rule_to_prods(r_ridx) {
+                                succs.push((prod[dot], (0, r_pidx, 0, false), false));
+                            }
+                        }
+                    },
+                    |(_, pidx, dot, _)| {
+                        self.grm.prod(pidx).get(dot) == Some(&Symbol::Token(self.tidx))
+                    },
+                )
+                .unwrap();
+                // Each item in the path either starts a rule or skips over a nullable rule.
+                // Generate the token, followed by the remainder of each production started,
+                // innermost first.
+                let mut items: Vec<(PIdx<StorageT>, usize)> = Vec::new();
+                for &(_, pidx, dot, _) in &path {
+                    if dot == 0 {
+                        items.push((pidx, 0));
+                    } else {
+                        items.last_mut().unwrap().1 = dot;
+                    }
+                }
+                sentence.push(self.tidx);
+                for &(pidx, dot) in items.iter().rev() {
+                    self.min_sentence(&self.grm.prod(pidx)[dot + 1..], sentence);
+                }
+            }
+        }
+        self.min_sentence(&syms[i + 1..], sentence);
+    }
+}
+
+/// Search for the shortest path from one of the nodes in `starts` to a node satisfying
+/// `is_target`. `succs(n, v)` must push onto `v` the successors of `n` as `(symbol, node, cost)`
+/// triples, where `cost` is true if moving to the successor should be counted as a step; moving
+/// to a successor whose `cost` is false is free.
+fn search<StorageT, F, G>(
+    starts: Vec<Node<StorageT>>,
+    succs: F,
+    is_target: G,
+) -> Option<Vec<Node<StorageT>>>
+where
+    StorageT: Copy + Eq + Hash,
+    F: Fn(Node<StorageT>, &mut Vec<(Symbol<StorageT>, Node<StorageT>, bool)>),
+    G: Fn(Node<StorageT>) -> bool,
+{
+    // A 0-1 breadth first search: free successors are pushed to the front of the queue, so that
+    // nodes are popped in order of their distance from the start nodes.
+    let mut dists = HashMap::new();
+    let mut parents = HashMap::new();
+    let mut todo = VecDeque::new();
+    for n in starts {
+        dists.insert(n, 0);
+        todo.push_back(n);
+    }
+    let mut v = Vec::new();
+    while let Some(n) = todo.pop_front() {
+        if is_target(n) {
+            let mut path = vec![n];
+            while let Some(&p) = parents.get(path.last().unwrap()) {
+                path.push(p);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        let dist = dists[&n];
+        v.clear();
+        succs(n, &mut v);
+        for &(_, m, cost) in &v {
+            let m_dist = dist + usize::from(cost);
+            if dists.get(&m).is_none_or(|&d| m_dist < d) {
+                dists.insert(m, m_dist);
+                parents.insert(m, n);
+                if cost {
+                    todo.push_back(m);
+                } else {
+                    todo.push_front(m);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind};
+
+    use crate::{pager::pager_stategraph, StateTable};
+
+    fn counterexamples(src: &str) -> String {
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        st.conflicts().unwrap().pp_counterexamples(&grm, &sg)
+    }
+
+    #[test]
+    fn test_ambiguous_expr() {
+        let s = counterexamples(
+            "
+            %start E
+            %%
+            E: E '+' E | 'INT';
+          ",
+        );
+        assert_eq!(
+            s,
+            "Shift/Reduce conflict on \"+\" in state 4 (ambiguous):
+  Example: \"INT\" \"+\" \"INT\" • \"+\" \"INT\"
+  Reduce derivation: E -> [ E -> [ E \"+\" E • ] \"+\" E ]
+  Shift derivation: E -> [ E \"+\" E -> [ E • \"+\" E ] ]
+"
+        );
+    }
+
+    #[test]
+    fn test_dangling_else() {
+        let s = counterexamples(
+            "
+            %start S
+            %%
+            S: 'IF' 'E' 'THEN' S | 'IF' 'E' 'THEN' S 'ELSE' S | 'X';
+          ",
+        );
+        assert!(s.contains("(ambiguous)"));
+        assert!(s.contains(
+            "Example: \"IF\" \"E\" \"THEN\" \"IF\" \"E\" \"THEN\" \"X\" • \"ELSE\" \"X\""
+        ));
+    }
+
+    #[test]
+    fn test_non_unifying() {
+        // This grammar is LR(2), but not LR(1), so it has a conflict but isn't ambiguous.
+        let s = counterexamples(
+            "
+            %start S
+            %%
+            S: A 'x' 'y' | B 'x' 'z';
+            A: 'a';
+            B: 'a';
+          ",
+        );
+        assert_eq!(
+            s,
+            "Reduce/Reduce conflict on \"x\" in state 4 (no unifying counterexample found):
+  First reduce example: \"a\" • \"x\" \"y\"
+  First reduce derivation: S -> [ A -> [ \"a\" • ] \"x\" \"y\" ]
+  Second reduce example: \"a\" • \"x\" \"z\"
+  Second reduce derivation: S -> [ B -> [ \"a\" • ] \"x\" \"z\" ]
+"
+        );
+    }
+}
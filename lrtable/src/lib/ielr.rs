@@ -0,0 +1,346 @@
+use std::{collections::hash_map::HashMap, hash::Hash};
+
+use cfgrammar::{yacc::YaccGrammar, PIdx, SIdx, Symbol};
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+use vob::Vob;
+
+use crate::{
+    itemset::Itemset,
+    lr1::{lalr_stategraph, stategraph},
+    stategraph::StateGraph,
+};
+
+// This file creates IELR(1) stategraphs, which have the same conflicts as canonical LR(1)
+// stategraphs but (nearly) as few states as LALR(1) stategraphs. The approach is that of:
+//
+//   The IELR(1) algorithm for generating minimal LR(1) parser tables for non-LR(1) grammars with
+//   conflict resolution
+//     Joel E. Denny and Brian A. Malloy, Science of Computer Programming 75, 943--979, 2010
+//
+// though considerably simplified. We first create the LALR(1) stategraph: if it has no conflicts,
+// we are done. Otherwise we determine, for each item in each LALR(1) state, which tokens of its
+// context can flow (through closures and gotos) to a reduction in a state which has a conflict
+// on that token: we call these the item's "relevant" tokens. We then create a new stategraph,
+// merging states with the same core only if their contexts agree on all relevant tokens. Since
+// irrelevant tokens can never cause a conflict, merging states which differ only on such tokens
+// cannot introduce conflicts which canonical LR(1) does not have.
+//
+// Unlike Denny and Malloy's algorithm, we do not take conflict resolution (i.e. precedences) into
+// account: states are split whenever they might lead to a conflict, even if that conflict would
+// be resolved in the same way in each of the split states. This can lead to more states than
+// necessary, but never to different behaviour.
+
+/// Create an IELR(1) `StateGraph` from `grm`.
+pub(crate) fn ielr_stategraph<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+) -> StateGraph<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let lalr = lalr_stategraph(grm);
+    let relevant = match relevant_tokens(grm, &lalr) {
+        Some(r) => r,
+        None => return lalr,
+    };
+
+    // Map each LALR(1) state's core items to that state. Since LALR(1) merges all states with the
+    // same core, every state in the new stategraph has the same core as exactly one of these.
+    let cores = lalr
+        .iter_stidxs()
+        .map(|stidx| (core_key(lalr.core_state(stidx)), usize::from(stidx)))
+        .collect::<HashMap<_, _>>();
+
+    stategraph(grm, |cnd, nstate| {
+        if !cnd.same_core(nstate) {
+            return false;
+        }
+        let rel = &relevant[cores[&core_key(cnd)]];
+        cnd.items
+            .iter()
+            .all(|(k, ctx)| !vob_differ_on(ctx, &nstate.items[k], &rel[k]))
+    })
+}
+
+/// Return the sorted items in the core state `state`.
+fn core_key<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    state: &Itemset<StorageT>,
+) -> Vec<(PIdx<StorageT>, SIdx<StorageT>)> {
+    let mut items = state.items.keys().cloned().collect::<Vec<_>>();
+    items.sort();
+    items
+}
+
+/// Returns true if two identically sized bitvecs differ in any of the bits set in `mask`.
+fn vob_differ_on(v1: &Vob, v2: &Vob, mask: &Vob) -> bool {
+    for ((b1, b2), m) in v1
+        .iter_storage()
+        .zip(v2.iter_storage())
+        .zip(mask.iter_storage())
+    {
+        if (b1 ^ b2) & m != 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Return the tokens on which the closed state `cl_state` has more than one action (i.e. those on
+/// which it has a shift/reduce or reduce/reduce conflict).
+fn conflict_tokens<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    cl_state: &Itemset<StorageT>,
+) -> Vob
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let tokens_len = usize::from(grm.tokens_len());
+    let mut shifts = Vob::from_elem(tokens_len, false);
+    let mut reduces = Vob::from_elem(tokens_len, false);
+    let mut conflicts = Vob::from_elem(tokens_len, false);
+    for (&(pidx, dot), ctx) in &cl_state.items {
+        if dot == grm.prod_len(pidx) {
+            let mut rr = reduces.clone();
+            rr.and(ctx);
+            conflicts.or(&rr);
+            reduces.or(ctx);
+        } else if let Symbol::Token(tidx) = grm.prod(pidx)[usize::from(dot)] {
+            shifts.set(usize::from(tidx), true);
+        }
+    }
+    shifts.and(&reduces);
+    conflicts.or(&shifts);
+    conflicts
+}
+
+/// For each state in the LALR(1) stategraph `sg`, return a map from each of its closed items to
+/// the tokens of that item's context which can flow to a conflicting reduction. If `sg` has no
+/// conflicts, return `None`.
+fn relevant_tokens<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    sg: &StateGraph<StorageT>,
+) -> Option<Vec<HashMap<(PIdx<StorageT>, SIdx<StorageT>), Vob>>>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let tokens_len = usize::from(grm.tokens_len());
+    let mut has_conflicts = false;
+    let mut relevant = Vec::with_capacity(usize::from(sg.all_states_len()));
+    for cl_state in sg.iter_closed_states() {
+        let conflicts = conflict_tokens(grm, cl_state);
+        let mut rel = HashMap::with_capacity(cl_state.items.len());
+        for (&(pidx, dot), ctx) in &cl_state.items {
+            let mut toks = Vob::from_elem(tokens_len, false);
+            if dot == grm.prod_len(pidx) {
+                toks.or(&conflicts);
+                toks.and(ctx);
+                if !has_conflicts && toks.iter_set_bits(..).next().is_some() {
+                    has_conflicts = true;
+                }
+            }
+            rel.insert((pidx, dot), toks);
+        }
+        relevant.push(rel);
+    }
+    if !has_conflicts {
+        return None;
+    }
+
+    // Propagate relevant tokens backwards until a fixed point is reached. A token in the context
+    // of an item `A: a . X b` flows to the item `A: a X . b` in the state reached by `X` and, if
+    // `X` is a rule and `b` can derive the empty string, to the items `X: . c` in the same state.
+    let firsts = grm.firsts();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for stidx in sg.iter_stidxs() {
+            let st_i = usize::from(stidx);
+            for (&(pidx, dot), ctx) in &sg.closed_state(stidx).items {
+                let prod = grm.prod(pidx);
+                if dot == grm.prod_len(pidx) {
+                    continue;
+                }
+                let sym = prod[usize::from(dot)];
+                let goto_i = usize::from(sg.edge(stidx, sym).unwrap());
+                let mut toks = relevant[goto_i][&(pidx, SIdx(dot.0 + StorageT::one()))].clone();
+                if let Symbol::Rule(ridx) = sym {
+                    let nullable = prod[usize::from(dot) + 1..].iter().all(|s| match *s {
+                        Symbol::Rule(s_ridx) => firsts.is_epsilon_set(s_ridx),
+                        Symbol::Token(_) => false,
+                    });
+                    if nullable {
+                        for &r_pidx in grm.rule_to_prods(ridx) {
+                            toks.or(&relevant[st_i][&(r_pidx, SIdx(StorageT::zero()))]);
+                        }
+                    }
+                }
+                toks.and(ctx);
+                if relevant[st_i].get_mut(&(pidx, dot)).unwrap().or(&toks) {
+                    changed = true;
+                }
+            }
+        }
+    }
+    Some(relevant)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::ielr_stategraph;
+    use crate::{
+        from_yacc,
+        lr1::{lalr_stategraph, lr1_stategraph},
+        pager::test::{grammar3, grammar_pager},
+        Action, Minimiser, StIdx, StateTable,
+    };
+    use cfgrammar::{
+        yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind},
+        PIdx, TIdx,
+    };
+
+    fn grammar(src: &str) -> YaccGrammar {
+        YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap()
+    }
+
+    /// Does `st` accept the sequence of tokens `toks`?
+    fn accepts(grm: &YaccGrammar, st: &StateTable<u32, u16>, toks: &[TIdx<u32>]) -> bool {
+        let mut pstack = vec![st.start_state()];
+        let mut i = 0;
+        loop {
+            let la = toks.get(i).cloned().unwrap_or_else(|| grm.eof_token_idx());
+            match st.action(*pstack.last().unwrap(), la) {
+                Action::Shift(stidx) => {
+                    pstack.push(stidx);
+                    i += 1;
+                }
+                Action::Reduce(pidx) => {
+                    pstack.truncate(pstack.len() - grm.prod(pidx).len());
+                    let prior = *pstack.last().unwrap();
+                    pstack.push(st.goto(prior, grm.prod_to_rule(pidx)).unwrap());
+                }
+                Action::Accept => return true,
+                Action::Error => return false,
+            }
+        }
+    }
+
+    fn conflicts(
+        st: &StateTable<u32, u16>,
+    ) -> (
+        HashSet<(TIdx<u32>, PIdx<u32>)>,
+        HashSet<(PIdx<u32>, PIdx<u32>)>,
+    ) {
+        match st.conflicts() {
+            Some(c) => (
+                c.sr_conflicts()
+                    .map(|&(tidx, pidx, _)| (tidx, pidx))
+                    .collect(),
+                c.rr_conflicts().map(|&(p1, p2, _)| (p1, p2)).collect(),
+            ),
+            None => (HashSet::new(), HashSet::new()),
+        }
+    }
+
+    /// Check that the IELR(1) and canonical LR(1) state tables for `grm` have the same conflicts
+    /// and accept the same sequences of (up to `max_len`) tokens.
+    fn check_as_lr1(grm: &YaccGrammar, max_len: usize) {
+        let (_, ielr_st) = from_yacc(grm, Minimiser::IELR).unwrap();
+        let (_, lr1_st) = from_yacc(grm, Minimiser::LR1).unwrap();
+        assert_eq!(conflicts(&ielr_st), conflicts(&lr1_st));
+
+        let tidxs = grm
+            .iter_tidxs()
+            .filter(|&tidx| tidx != grm.eof_token_idx())
+            .collect::<Vec<_>>();
+        let mut toks = vec![];
+        for len in 0..=max_len {
+            // Iterate over all token sequences of length `len`, treating `counter` as a number in
+            // base `tidxs.len()`.
+            let mut counter = vec![0; len];
+            loop {
+                toks.clear();
+                toks.extend(counter.iter().map(|&i| tidxs[i]));
+                assert_eq!(accepts(grm, &ielr_st, &toks), accepts(grm, &lr1_st, &toks));
+                match counter.iter().rposition(|&i| i + 1 < tidxs.len()) {
+                    Some(j) => {
+                        counter[j] += 1;
+                        for c in counter.iter_mut().skip(j + 1) {
+                            *c = 0;
+                        }
+                    }
+                    None => break,
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_pager_grammars() {
+        let grm = grammar3();
+        check_as_lr1(&grm, 5);
+        // This grammar has no LALR(1) conflicts, so IELR(1) creates the LALR(1) stategraph.
+        assert_eq!(
+            ielr_stategraph(&grm).all_states_len(),
+            lalr_stategraph(&grm).all_states_len()
+        );
+
+        // Pager's grammar is LR(1) but not LALR(1). IELR(1) needs the same number of states as
+        // Pager's algorithm to remove the LALR(1) conflicts.
+        let grm = grammar_pager();
+        check_as_lr1(&grm, 5);
+        let (_, st) = from_yacc::<_, u16>(&grm, Minimiser::LALR).unwrap();
+        assert!(st.conflicts().is_some());
+        let (_, st) = from_yacc::<_, u16>(&grm, Minimiser::IELR).unwrap();
+        assert!(st.conflicts().is_none());
+        assert_eq!(ielr_stategraph(&grm).all_states_len(), StIdx(23));
+        assert_eq!(lalr_stategraph(&grm).all_states_len(), StIdx(21));
+    }
+
+    #[test]
+    fn test_lalr_conflict() {
+        // This grammar is LR(1) but not LALR(1): IELR(1) needs to split the LALR(1) state
+        // containing `E: 'e' .` and `F: 'e' .` to avoid a reduce/reduce conflict.
+        let grm = grammar(
+            "
+            %start S
+            %%
+            S: 'a' E 'c' | 'a' F 'd' | 'b' F 'c' | 'b' E 'd';
+            E: 'e';
+            F: 'e';
+          ",
+        );
+        check_as_lr1(&grm, 4);
+        let (_, st) = from_yacc::<_, u16>(&grm, Minimiser::IELR).unwrap();
+        assert!(st.conflicts().is_none());
+        let lalr_len = usize::from(lalr_stategraph(&grm).all_states_len());
+        let ielr_len = usize::from(ielr_stategraph(&grm).all_states_len());
+        assert_eq!(ielr_len, lalr_len + 1);
+        assert_eq!(usize::from(lr1_stategraph(&grm).all_states_len()), ielr_len);
+    }
+
+    #[test]
+    fn test_real_conflicts() {
+        // This grammar has both genuine shift/reduce conflicts, which IELR(1) must preserve, and
+        // LALR(1)-induced reduce/reduce conflicts, which it must remove.
+        let grm = grammar(
+            "
+            %start S
+            %%
+            S: 'a' E 'c' | 'a' F 'd' | 'b' F 'c' | 'b' E 'd' | X;
+            E: 'e';
+            F: 'e';
+            X: X '+' X | 'i';
+          ",
+        );
+        check_as_lr1(&grm, 4);
+        let (_, st) = from_yacc::<_, u16>(&grm, Minimiser::IELR).unwrap();
+        let c = st.conflicts().unwrap();
+        assert_eq!(c.sr_len(), 1);
+        assert_eq!(c.rr_len(), 0);
+    }
+}
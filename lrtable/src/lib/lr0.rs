@@ -0,0 +1,120 @@
+use std::hash::Hash;
+
+use cfgrammar::yacc::YaccGrammar;
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+use vob::Vob;
+
+use crate::{lr1::lalr_stategraph, stategraph::StateGraph};
+
+// This file creates LR(0) and SLR(1) stategraphs. Both have the same states as an LALR(1)
+// stategraph (i.e. the LR(0) states), but differ in the contexts of their items: an LR(0) parser
+// reduces an item irrespective of the lookahead, whereas an SLR(1) parser reduces an item `R: a .`
+// when the lookahead is in the Follow set of `R`. Since LALR(1) contexts are always a subset of
+// SLR(1) contexts, which in turn are a subset of LR(0) contexts, a grammar can be LALR(1) but not
+// SLR(1), or SLR(1) but not LR(0). These algorithms are thus mostly of use to explain why more
+// powerful algorithms are needed.
+
+/// Create an LR(0) `StateGraph` from `grm`. Every item (other than those of the start rule, which
+/// only accept at the end of the input) has all tokens as its context.
+pub(crate) fn lr0_stategraph<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+) -> StateGraph<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let mut sg = lalr_stategraph(grm);
+    let all = Vob::from_elem(usize::from(grm.tokens_len()), true);
+    let mut eof = Vob::from_elem(usize::from(grm.tokens_len()), false);
+    eof.set(usize::from(grm.eof_token_idx()), true);
+    sg.set_ctxs(|pidx| {
+        if grm.prod_to_rule(pidx) == grm.start_rule_idx() {
+            eof.clone()
+        } else {
+            all.clone()
+        }
+    });
+    sg
+}
+
+/// Create an SLR(1) `StateGraph` from `grm`. Every item `R: ...` has the Follow set of `R` as its
+/// context.
+pub(crate) fn slr_stategraph<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+) -> StateGraph<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let mut sg = lalr_stategraph(grm);
+    let follows = grm.follows();
+    sg.set_ctxs(|pidx| follows.follows(grm.prod_to_rule(pidx)).clone());
+    sg
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lr0_stategraph, slr_stategraph};
+    use crate::{from_yacc, lr1::lalr_stategraph, Minimiser};
+    use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind};
+
+    fn grammar(src: &str) -> YaccGrammar {
+        YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap()
+    }
+
+    /// Return the number of (Shift/Reduce, Reduce/Reduce) conflicts in `grm` with minimiser `m`.
+    fn conflicts(grm: &YaccGrammar, m: Minimiser) -> (usize, usize) {
+        let (_, st) = from_yacc::<_, u16>(grm, m).unwrap();
+        st.conflicts()
+            .map(|c| (c.sr_len(), c.rr_len()))
+            .unwrap_or((0, 0))
+    }
+
+    #[test]
+    fn test_not_lr0() {
+        // This grammar is SLR(1) but not LR(0): in the state containing `E: T . '+' E` and
+        // `E: T .`, an LR(0) parser can't decide whether to shift or reduce.
+        let grm = grammar(
+            "
+            %start E
+            %%
+            E: T '+' E | T;
+            T: 'i';
+          ",
+        );
+        assert_eq!(conflicts(&grm, Minimiser::LR0), (1, 0));
+        assert_eq!(conflicts(&grm, Minimiser::SLR), (0, 0));
+        assert_eq!(
+            lr0_stategraph(&grm).all_states_len(),
+            lalr_stategraph(&grm).all_states_len()
+        );
+    }
+
+    #[test]
+    fn test_not_slr() {
+        // Grammar 4.49 from the Dragon book, which is LALR(1) but not SLR(1): '=' is in the Follow
+        // set of R, so an SLR(1) parser can't decide between shifting '=' and reducing `R: L`.
+        let grm = grammar(
+            "
+            %start S
+            %%
+            S: L '=' R | R;
+            L: '*' R | 'id';
+            R: L;
+          ",
+        );
+        assert_eq!(conflicts(&grm, Minimiser::SLR), (1, 0));
+        assert_eq!(conflicts(&grm, Minimiser::LALR), (0, 0));
+        assert_eq!(conflicts(&grm, Minimiser::Pager), (0, 0));
+
+        let sg = slr_stategraph(&grm);
+        let follows = grm.follows();
+        for cl_state in sg.iter_closed_states() {
+            for (&(pidx, _), ctx) in &cl_state.items {
+                assert_eq!(ctx, follows.follows(grm.prod_to_rule(pidx)));
+            }
+        }
+    }
+}
@@ -0,0 +1,254 @@
+use std::{collections::hash_map::HashMap, hash::Hash};
+
+use cfgrammar::{yacc::YaccGrammar, SIdx, Symbol};
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+use vob::Vob;
+
+use crate::{itemset::Itemset, stategraph::StateGraph, StIdx};
+
+// This file creates canonical LR(1) and LALR(1) stategraphs from grammars using the textbook
+// algorithms (see e.g. "Compilers: Principles, Techniques, and Tools" by Aho, Lam, Sethi, and
+// Ullman). Both repeatedly close a state and compute the gotos from it: canonical LR(1) only
+// reuses an existing state if it is identical (including contexts) to the new one, whereas
+// LALR(1) reuses any existing state with the same core items, merging the new state's contexts
+// into it.
+
+impl<StorageT: 'static + Hash + PrimInt + Unsigned> Itemset<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    /// Return true if `self` and `other` contain the same items, ignoring their contexts.
+    pub(crate) fn same_core(&self, other: &Self) -> bool {
+        self.items.len() == other.items.len()
+            && self.items.keys().all(|k| other.items.contains_key(k))
+    }
+
+    /// Merge the contexts of `other` into `self`, returning `true` if this led to any changes. If
+    /// `other` does not have the same core as `self`, this function's effects and return value
+    /// are undefined.
+    fn core_merge(&mut self, other: &Self) -> bool {
+        let mut changed = false;
+        for (&(pidx, dot), ctx) in &mut self.items {
+            if ctx.or(&other.items[&(pidx, dot)]) {
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Create a canonical LR(1) `StateGraph` from `grm`. States are never merged, so the resulting
+/// graph can be substantially larger than that created by other algorithms.
+pub(crate) fn lr1_stategraph<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+) -> StateGraph<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    stategraph(grm, |cnd, nstate| cnd == nstate)
+}
+
+/// Create an LALR(1) `StateGraph` from `grm`. States with the same core are merged, which can
+/// introduce reduce/reduce conflicts that the grammar does not have under LR(1).
+pub(crate) fn lalr_stategraph<StorageT: 'static + Hash + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+) -> StateGraph<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+{
+    stategraph(grm, Itemset::same_core)
+}
+
+/// Create a `StateGraph` from `grm`, reusing an existing (core) state `cnd` for a new core state
+/// `nstate` reached by the same symbol if `compatible(cnd, nstate)` returns true. `compatible`
+/// must only return true for states with the same core; the contexts of `nstate` are then merged
+/// into `cnd`.
+pub(crate) fn stategraph<StorageT: 'static + Hash + PrimInt + Unsigned, F>(
+    grm: &YaccGrammar<StorageT>,
+    compatible: F,
+) -> StateGraph<StorageT>
+where
+    usize: AsPrimitive<StorageT>,
+    F: Fn(&Itemset<StorageT>, &Itemset<StorageT>) -> bool,
+{
+    let firsts = grm.firsts();
+    // As in the Pager algorithm, closed states which are None are those which require
+    // processing. todo contains the indexes of all such states (possibly more than once).
+    let mut closed_states = Vec::new();
+    let mut core_states = Vec::new();
+    let mut edges: Vec<HashMap<Symbol<StorageT>, StIdx<u32>>> = Vec::new();
+    let mut todo = Vec::new();
+
+    let mut start_states = Vec::with_capacity(grm.start_prods().len());
+    let mut ctx = Vob::from_elem(usize::from(grm.tokens_len()), false);
+    ctx.set(usize::from(grm.eof_token_idx()), true);
+    for &pidx in grm.start_prods() {
+        let mut state = Itemset::new(grm);
+        state.add(pidx, SIdx(StorageT::zero()), &ctx);
+        start_states.push(StIdx(core_states.len() as u32));
+        todo.push(core_states.len());
+        closed_states.push(None);
+        core_states.push(state);
+        edges.push(HashMap::new());
+    }
+
+    // cnd_[rule|token]_states represent which states are reached by a given symbol, and are thus
+    // the only possible matches for a new state reached by that symbol.
+    let mut cnd_rule_states: Vec<Vec<StIdx<u32>>> = vec![Vec::new(); usize::from(grm.rules_len())];
+    let mut cnd_token_states: Vec<Vec<StIdx<u32>>> =
+        vec![Vec::new(); usize::from(grm.tokens_len()).checked_add(1).unwrap()];
+    let mut seen_rules = Vob::from_elem(usize::from(grm.rules_len()), false);
+    let mut seen_tokens = Vob::from_elem(usize::from(grm.tokens_len()), false);
+    let mut new_states = Vec::new();
+
+    while let Some(state_i) = todo.pop() {
+        if closed_states[state_i].is_some() {
+            continue;
+        }
+        {
+            closed_states[state_i] = Some(core_states[state_i].close(grm, &firsts));
+            let cl_state = closed_states[state_i].as_ref().unwrap();
+            seen_rules.set_all(false);
+            seen_tokens.set_all(false);
+            for &(pidx, dot) in cl_state.items.keys() {
+                if dot == grm.prod_len(pidx) {
+                    continue;
+                }
+                let sym = grm.prod(pidx)[usize::from(dot)];
+                let seen = match sym {
+                    Symbol::Rule(s_ridx) => {
+                        let seen = seen_rules[usize::from(s_ridx)];
+                        seen_rules.set(usize::from(s_ridx), true);
+                        seen
+                    }
+                    Symbol::Token(s_tidx) => {
+                        let seen = seen_tokens[usize::from(s_tidx)];
+                        seen_tokens.set(usize::from(s_tidx), true);
+                        seen
+                    }
+                };
+                if !seen {
+                    new_states.push((sym, cl_state.goto(grm, &sym)));
+                }
+            }
+        }
+
+        for (sym, nstate) in new_states.drain(..) {
+            let cnd_states = match sym {
+                Symbol::Rule(s_ridx) => &mut cnd_rule_states[usize::from(s_ridx)],
+                Symbol::Token(s_tidx) => &mut cnd_token_states[usize::from(s_tidx)],
+            };
+            let m = cnd_states
+                .iter()
+                .cloned()
+                .find(|&cnd| compatible(&core_states[usize::from(cnd)], &nstate));
+            match m {
+                Some(k) => {
+                    edges[state_i].insert(sym, k);
+                    // With canonical LR(1), k's contexts are identical to nstate's, so this
+                    // merge never changes anything.
+                    if core_states[usize::from(k)].core_merge(&nstate)
+                        && closed_states[usize::from(k)].is_some()
+                    {
+                        // k's contexts have grown, so it (and, transitively, the states it
+                        // leads to) must be reprocessed. edges[k] is regenerated in its
+                        // entirety when this happens.
+                        closed_states[usize::from(k)] = None;
+                        todo.push(usize::from(k));
+                    }
+                }
+                None => {
+                    assert!(core_states.len() <= usize::from(StIdx(u32::MAX)));
+                    // The assert above guarantees that the cast below is safe.
+                    let stidx = StIdx(core_states.len() as u32);
+                    cnd_states.push(stidx);
+                    edges[state_i].insert(sym, stidx);
+                    todo.push(core_states.len());
+                    edges.push(HashMap::new());
+                    closed_states.push(None);
+                    core_states.push(nstate);
+                }
+            }
+        }
+    }
+
+    StateGraph::new(
+        core_states
+            .into_iter()
+            .zip(closed_states.into_iter().map(Option::unwrap))
+            .collect(),
+        start_states,
+        edges,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lalr_stategraph, lr1_stategraph};
+    use crate::{from_yacc, pager::pager_stategraph, Minimiser, StIdx};
+    use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind};
+
+    fn grammar(src: &str) -> YaccGrammar {
+        YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_state_counts() {
+        // The grammar 4.55 from the Dragon book has 10 canonical LR(1) states and 7 LALR(1) states.
+        let grm = grammar(
+            "
+            %start S
+            %%
+            S: C C;
+            C: 'c' C | 'd';
+          ",
+        );
+        assert_eq!(lr1_stategraph(&grm).all_states_len(), StIdx(10));
+        assert_eq!(lalr_stategraph(&grm).all_states_len(), StIdx(7));
+        assert_eq!(pager_stategraph(&grm).all_states_len(), StIdx(7));
+    }
+
+    #[test]
+    fn test_lalr_conflict() {
+        // This grammar is LR(1) but not LALR(1): merging the two states containing `E: 'e' .`
+        // and `F: 'e' .` leads to a reduce/reduce conflict.
+        let grm = grammar(
+            "
+            %start S
+            %%
+            S: 'a' E 'c' | 'a' F 'd' | 'b' F 'c' | 'b' E 'd';
+            E: 'e';
+            F: 'e';
+          ",
+        );
+        for m in &[Minimiser::LR1, Minimiser::Pager] {
+            let (_, st) = from_yacc::<_, u16>(&grm, *m).unwrap();
+            assert!(st.conflicts().is_none());
+        }
+        let (_, st) = from_yacc::<_, u16>(&grm, Minimiser::LALR).unwrap();
+        let c = st.conflicts().unwrap();
+        assert_eq!(c.sr_len(), 0);
+        assert_eq!(c.rr_len(), 2);
+    }
+
+    #[test]
+    fn test_multiple_start_states() {
+        let grm = YaccGrammar::new(
+            YaccKind::Grmtools,
+            "
+            %start A B
+            %%
+            A -> (): 'a' { } | 'a' B { };
+            B -> (): 'b' { } | 'b' A { };
+          ",
+        )
+        .unwrap();
+        for sg in &[lr1_stategraph(&grm), lalr_stategraph(&grm)] {
+            assert_eq!(sg.start_states(), &[StIdx(0), StIdx(1)]);
+        }
+    }
+}
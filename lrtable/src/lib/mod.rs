@@ -7,72 +7,138 @@ use std::{hash::Hash, mem::size_of};
 use num_traits::{AsPrimitive, PrimInt, Unsigned};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use static_assertions::const_assert;
 
+mod bitvec;
+mod combvec;
+pub mod counterexample;
+mod ielr;
 mod itemset;
+mod lr0;
+mod lr1;
 mod pager;
+pub mod report;
 mod stategraph;
 pub mod statetable;
 
 pub use crate::{
     stategraph::StateGraph,
-    statetable::{Action, StateTable, StateTableError, StateTableErrorKind},
+    statetable::{
+        Action, CompressionStats, ConflictExplanation, ConflictItem, ConflictResolution,
+        ResolutionReason, StateTable, StateTableError, StateTableErrorKind,
+    },
 };
 use cfgrammar::yacc::YaccGrammar;
 
-/// The type of the inner value of an StIdx.
+/// The type of the inner value of an StIdx in versions of lrtable where `StIdx` was not generic.
+#[deprecated(
+    since = "0.10.0",
+    note = "StIdx is now generic over its storage type: please use StIdx<u16>"
+)]
 pub type StIdxStorageT = u16;
 
-/// StIdx is a wrapper for a state index. Its internal type is `StIdxStorageT`. The only guarantee
-/// we make about `StIdx' is that it can be infallibly converted to usize.
+/// StIdx is a wrapper for a state index. Its internal type is `StIdxT`, which is independent of
+/// the grammar's `StorageT`: a `StateTable` using `StIdx<StIdxT>` can have at most
+/// `StIdxT::max_value()` states (e.g. 65,535 with `u16`). `StateGraph`s, which are only used while
+/// building a parser, always use `StIdx<u32>`. The only guarantee we make about `StIdx' is that it
+/// can be infallibly converted to usize.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct StIdx<StIdxT>(StIdxT);
 
-pub struct StIdx(StIdxStorageT);
-
-impl StIdx {
-    fn max_value() -> StIdx {
-        StIdx(StIdxStorageT::max_value())
+impl<StIdxT: PrimInt + Unsigned> StIdx<StIdxT> {
+    /// Return the value of this state index as a `StIdxT`.
+    pub fn as_storaget(&self) -> StIdxT {
+        self.0
     }
 }
 
-impl From<StIdxStorageT> for StIdx {
-    fn from(v: StIdxStorageT) -> Self {
+impl<StIdxT> StIdx<StIdxT> {
+    /// Create a state index in a state table generated at compile-time.
+    #[doc(hidden)]
+    pub const fn _from_static(v: StIdxT) -> Self {
         StIdx(v)
     }
 }
 
-impl From<StIdx> for usize {
-    fn from(st: StIdx) -> Self {
-        const_assert!(size_of::<usize>() >= size_of::<StIdxStorageT>());
-        st.0 as usize
+impl<StIdxT: AsPrimitive<usize>> StIdx<StIdxT> {
+    /// Convert this state index into a `StIdx<T>`. The caller must ensure that the index fits
+    /// into a `T` (e.g. because it indexes a `StateTable` whose states were checked to fit).
+    pub(crate) fn cast<T: 'static + Copy>(self) -> StIdx<T>
+    where
+        usize: AsPrimitive<T>,
+    {
+        StIdx(usize::from(self).as_())
+    }
+}
+
+impl<StIdxT: PrimInt + Unsigned> From<StIdxT> for StIdx<StIdxT> {
+    fn from(v: StIdxT) -> Self {
+        StIdx(v)
     }
 }
 
-impl From<StIdx> for StIdxStorageT {
-    fn from(st: StIdx) -> Self {
-        st.0 as StIdxStorageT
+impl<StIdxT: AsPrimitive<usize>> From<StIdx<StIdxT>> for usize {
+    fn from(st: StIdx<StIdxT>) -> Self {
+        debug_assert!(size_of::<usize>() >= size_of::<StIdxT>());
+        st.0.as_()
     }
 }
 
-#[derive(Clone, Copy)]
+/// The algorithm used to create a `StateGraph` from a grammar.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Minimiser {
+    /// Pager's algorithm, which merges states only when doing so cannot introduce conflicts that
+    /// canonical LR(1) would not have. This is the default.
     Pager,
+    /// Canonical LR(1), which never merges states.
+    LR1,
+    /// LALR(1), which merges all states with the same core items.
+    LALR,
+    /// IELR(1), which merges states with the same core items unless doing so would introduce
+    /// conflicts that canonical LR(1) would not have.
+    IELR,
+    /// LR(0), which has the same states as LALR(1), but reduces irrespective of the lookahead.
+    /// Few grammars are LR(0): this is mostly of use for teaching and diagnostics.
+    LR0,
+    /// SLR(1), which has the same states as LALR(1), but reduces a production of a rule `R`
+    /// whenever the lookahead is in the Follow set of `R`. Few grammars are SLR(1): this is mostly
+    /// of use for teaching and diagnostics.
+    SLR,
 }
 
-pub fn from_yacc<StorageT: 'static + Hash + PrimInt + Unsigned>(
+/// Create a `StateGraph` for `grm` using the minimiser `m`.
+pub fn stategraph<StorageT: 'static + Hash + PrimInt + Unsigned>(
     grm: &YaccGrammar<StorageT>,
     m: Minimiser,
-) -> Result<(StateGraph<StorageT>, StateTable<StorageT>), StateTableError<StorageT>>
+) -> StateGraph<StorageT>
 where
     usize: AsPrimitive<StorageT>,
     u32: AsPrimitive<StorageT>,
 {
     match m {
-        Minimiser::Pager => {
-            let sg = pager::pager_stategraph(grm);
-            let st = StateTable::new(grm, &sg)?;
-            Ok((sg, st))
-        }
+        Minimiser::Pager => pager::pager_stategraph(grm),
+        Minimiser::LR1 => lr1::lr1_stategraph(grm),
+        Minimiser::LALR => lr1::lalr_stategraph(grm),
+        Minimiser::IELR => ielr::ielr_stategraph(grm),
+        Minimiser::LR0 => lr0::lr0_stategraph(grm),
+        Minimiser::SLR => lr0::slr_stategraph(grm),
     }
 }
+
+/// Create a `StateGraph` and a `StateTable` for `grm` using the minimiser `m`. If the state graph
+/// has more states than a `StIdx<StIdxT>` can index, a
+/// [`TooManyStates`](statetable/enum.StateTableErrorKind.html) error is returned.
+pub fn from_yacc<StorageT, StIdxT>(
+    grm: &YaccGrammar<StorageT>,
+    m: Minimiser,
+) -> Result<(StateGraph<StorageT>, StateTable<StorageT, StIdxT>), StateTableError<StorageT>>
+where
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
+    u32: AsPrimitive<StorageT>,
+{
+    let sg = stategraph(grm, m);
+    let st = StateTable::new(grm, &sg)?;
+    Ok((sg, st))
+}
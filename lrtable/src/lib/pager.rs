@@ -4,10 +4,10 @@ use std::{
 };
 
 use cfgrammar::{yacc::YaccGrammar, SIdx, Symbol};
-use num_traits::{AsPrimitive, PrimInt, Unsigned, Zero};
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
 use vob::Vob;
 
-use crate::{itemset::Itemset, stategraph::StateGraph, StIdx, StIdxStorageT};
+use crate::{itemset::Itemset, stategraph::StateGraph, StIdx};
 
 // This file creates stategraphs from grammars. Unfortunately there is no perfect guide to how to
 // do this that I know of -- certainly not one that talks about sensible ways to arrange data and
@@ -128,16 +128,23 @@ where
     // closed_states also implicitly serves as a todo list.
     let mut closed_states = Vec::new();
     let mut core_states = Vec::new();
-    let mut edges: Vec<HashMap<Symbol<StorageT>, StIdx>> = Vec::new();
+    let mut edges: Vec<HashMap<Symbol<StorageT>, StIdx<u32>>> = Vec::new();
 
-    let start_state = StIdx::from(StIdxStorageT::zero());
-    let mut state0 = Itemset::new(grm);
+    // Each of the start rule's productions gets its own start state: since those states differ in
+    // their core items, they are never merged, and they occupy the first states of the graph.
+    let mut start_states = Vec::with_capacity(grm.start_prods().len());
     let mut ctx = Vob::from_elem(usize::from(grm.tokens_len()), false);
     ctx.set(usize::from(grm.eof_token_idx()), true);
-    state0.add(grm.start_prod(), SIdx(StorageT::zero()), &ctx);
-    closed_states.push(None);
-    core_states.push(state0);
-    edges.push(HashMap::new());
+    for &pidx in grm.start_prods() {
+        let mut state = Itemset::new(grm);
+        state.add(pidx, SIdx(StorageT::zero()), &ctx);
+        // If this cast were to truncate, the graph would have more states than fit into a u32,
+        // which StateGraph::new checks for.
+        start_states.push(StIdx(core_states.len() as u32));
+        closed_states.push(None);
+        core_states.push(state);
+        edges.push(HashMap::new());
+    }
 
     // We maintain two lists of which rules and tokens we've seen; when processing a given
     // state there's no point processing a rule or token more than once.
@@ -147,11 +154,12 @@ where
     let mut new_states = Vec::new();
     // cnd_[rule|token]_weaklies represent which states are possible weakly compatible
     // matches for a given symbol.
-    let mut cnd_rule_weaklies: Vec<Vec<StIdx>> = vec![Vec::new(); usize::from(grm.rules_len())];
-    let mut cnd_token_weaklies: Vec<Vec<StIdx>> =
+    let mut cnd_rule_weaklies: Vec<Vec<StIdx<u32>>> =
+        vec![Vec::new(); usize::from(grm.rules_len())];
+    let mut cnd_token_weaklies: Vec<Vec<StIdx<u32>>> =
         vec![Vec::new(); usize::from(grm.tokens_len()).checked_add(1).unwrap()];
 
-    let mut todo = 1; // How many None values are there in closed_states?
+    let mut todo = start_states.len(); // How many None values are there in closed_states?
     let mut todo_off = 0; // Offset in closed states to start searching for the next todo.
     while todo > 0 {
         debug_assert_eq!(core_states.len(), closed_states.len());
@@ -250,9 +258,9 @@ where
                     }
                 }
                 None => {
-                    assert!(core_states.len() <= usize::from(StIdxStorageT::max_value()));
+                    assert!(core_states.len() <= usize::from(StIdx(u32::MAX)));
                     // The assert above guarantees that the cast below is safe.
-                    let stidx = StIdx(core_states.len() as StIdxStorageT);
+                    let stidx = StIdx(core_states.len() as u32);
                     match sym {
                         Symbol::Rule(s_ridx) => {
                             cnd_rule_weaklies[usize::from(s_ridx)].push(stidx);
@@ -282,26 +290,25 @@ where
             .drain(..)
             .zip(closed_states.drain(..).map(Option::unwrap))
             .collect(),
-        start_state,
+        &start_states,
         edges,
     );
-    StateGraph::new(gc_states, start_state, gc_edges)
+    StateGraph::new(gc_states, start_states, gc_edges)
 }
 
 /// Garbage collect `zip_states` (of `(core_states, closed_state)`) and `edges`. Returns a new pair
 /// with unused states and their corresponding edges removed.
 fn gc<StorageT: Eq + Hash + PrimInt>(
     mut states: Vec<(Itemset<StorageT>, Itemset<StorageT>)>,
-    start_state: StIdx,
-    mut edges: Vec<HashMap<Symbol<StorageT>, StIdx>>,
+    start_states: &[StIdx<u32>],
+    mut edges: Vec<HashMap<Symbol<StorageT>, StIdx<u32>>>,
 ) -> (
     Vec<(Itemset<StorageT>, Itemset<StorageT>)>,
-    Vec<HashMap<Symbol<StorageT>, StIdx>>,
+    Vec<HashMap<Symbol<StorageT>, StIdx<u32>>>,
 ) {
     // First of all, do a simple pass over all states. All state indexes reachable from the
-    // start state will be inserted into the 'seen' set.
-    let mut todo = HashSet::new();
-    todo.insert(start_state);
+    // start states will be inserted into the 'seen' set.
+    let mut todo = start_states.iter().cloned().collect::<HashSet<_>>();
     let mut seen = HashSet::new();
     while !todo.is_empty() {
         // XXX This is the clumsy way we're forced to do what we'd prefer to be:
@@ -341,13 +348,13 @@ fn gc<StorageT: Eq + Hash + PrimInt>(
     for (state_i, zstate) in states
         .drain(..)
         .enumerate()
-        // edges goes from 0..states_len(), and we know the latter can safely fit into an
-        // StIdxStorageT, so the cast is safe.
-        .map(|(x, y)| (StIdx(x as StIdxStorageT), y))
+        // edges goes from 0..states_len(), and we know the latter can safely fit into a u32, so
+        // the cast is safe.
+        .map(|(x, y)| (StIdx(x as u32), y))
     {
-        // state_i <= states_len(), which fits in StIdxStorageT, so state_i - offset must also be
+        // state_i <= states_len(), which fits in a u32, so state_i - offset must also be
         // <= states_len, making the cast safe
-        offsets.push(StIdx((usize::from(state_i) - offset) as StIdxStorageT));
+        offsets.push(StIdx((usize::from(state_i) - offset) as u32));
         if !seen.contains(&state_i) {
             offset += 1;
             continue;
@@ -361,9 +368,9 @@ fn gc<StorageT: Eq + Hash + PrimInt>(
     for (st_edge_i, st_edges) in edges
         .drain(..)
         .enumerate()
-        // edges goes from 0..states_len(), and we know the latter can safely fit into an
-        // StIdxStorageT, so the cast is safe.
-        .map(|(x, y)| (StIdx(x as StIdxStorageT), y))
+        // edges goes from 0..states_len(), and we know the latter can safely fit into a u32, so
+        // the cast is safe.
+        .map(|(x, y)| (StIdx(x as u32), y))
     {
         if !seen.contains(&st_edge_i) {
             continue;
@@ -380,7 +387,7 @@ fn gc<StorageT: Eq + Hash + PrimInt>(
 }
 
 #[cfg(test)]
-mod test {
+pub(crate) mod test {
     use vob::Vob;
 
     use crate::{pager::pager_stategraph, stategraph::state_exists, StIdx};
@@ -423,7 +430,7 @@ mod test {
     // A : aSc
     //     a
     //     aSb
-    fn grammar3() -> YaccGrammar {
+    pub(crate) fn grammar3() -> YaccGrammar {
         YaccGrammar::new(
             YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
             &"
@@ -502,7 +509,7 @@ mod test {
     }
 
     // Pager grammar
-    fn grammar_pager() -> YaccGrammar {
+    pub(crate) fn grammar_pager() -> YaccGrammar {
         YaccGrammar::new(
             YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
             &"
@@ -679,4 +686,31 @@ mod test {
         assert_eq!(sg.core_state(sg.start_state()).items.len(), 1);
         state_exists(&grm, &sg.core_state(sg.start_state()), "^", 0, SIdx(0), vec!["$"]);
     }
+
+    // A grammar with a small number of tokens, rules, and productions, but which needs just over
+    // 300 states.
+    fn grammar_many_states() -> String {
+        let run = vec!["'a'"; 100].join(" ");
+        format!(
+            "
+            %start S
+            %%
+            S : 'b' {run} | 'c' {run} | 'd' {run};
+            ",
+            run = run
+        )
+    }
+
+    #[test]
+    fn test_many_states() {
+        // State indexes don't depend on the grammar's storage type, so a grammar using u8 can have
+        // more than 255 states.
+        let grm = YaccGrammar::<u8>::new_with_storaget(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            &grammar_many_states(),
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        assert!(usize::from(sg.all_states_len()) > usize::from(u8::MAX));
+    }
 }
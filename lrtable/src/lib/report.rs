@@ -0,0 +1,507 @@
+//! A verbose, human-readable report of a grammar's state table, in the spirit of the `.output`
+//! file produced by `bison -v`.
+//!
+//! The report lists, in order: the grammar's productions (numbered by their `PIdx`); the
+//! productions each token and rule appear in; the grammar's warnings (e.g. unused rules or
+//! tokens, or useless precedences); the productions which are never reduced because of
+//! conflicts; a summary of the conflicts in each state; and then, for each state, its core and
+//! closed items (with their lookaheads), its shifts, reductions and gotos, and how any conflicts
+//! in it were resolved.
+//! Actions discarded by Yacc's default conflict resolution are shown in square brackets.
+
+use std::{collections::HashMap, hash::Hash};
+
+use cfgrammar::{
+    yacc::{AssocKind, YaccGrammar, YaccSource},
+    PIdx, SIdx, Symbol, TIdx,
+};
+use num_traits::{AsPrimitive, PrimInt, Unsigned};
+
+use crate::{
+    stategraph::StateGraph,
+    statetable::{Action, ConflictResolution, ResolutionReason, StateTable},
+    StIdx,
+};
+
+/// Return a verbose report of the state table `st`, which must have been created from the
+/// grammar `grm` and state graph `sg`. If `source` (the grammar's source) is provided, warnings
+/// are reported with their location (including the file they are in, if it was included).
+pub fn report<StorageT, StIdxT>(
+    grm: &YaccGrammar<StorageT>,
+    sg: &StateGraph<StorageT>,
+    st: &StateTable<StorageT, StIdxT>,
+    source: Option<&YaccSource>,
+) -> String
+where
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
+    u32: AsPrimitive<StorageT>,
+{
+    let mut o = String::new();
+
+    o.push_str("Grammar\n\n");
+    for pidx in grm.iter_pidxs() {
+        o.push_str(&format!(
+            "{:>5} {}\n",
+            usize::from(pidx),
+            pp_prod(grm, pidx)
+        ));
+    }
+
+    o.push_str("\n\nTerminals, with rules where they appear\n\n");
+    for tidx in grm.iter_tidxs() {
+        let pidxs = grm
+            .iter_pidxs()
+            .filter(|&pidx| grm.prod(pidx).contains(&Symbol::Token(tidx)))
+            .collect::<Vec<_>>();
+        o.push_str(&format!(
+            "    {} ({}){}\n",
+            pp_token(grm, tidx),
+            usize::from(tidx),
+            pp_pidxs(&pidxs)
+        ));
+    }
+
+    o.push_str("\n\nNonterminals, with rules where they appear\n\n");
+    for ridx in grm.iter_rules() {
+        let on_right = grm
+            .iter_pidxs()
+            .filter(|&pidx| grm.prod(pidx).contains(&Symbol::Rule(ridx)))
+            .collect::<Vec<_>>();
+        o.push_str(&format!(
+            "    {} ({})\n        on left:{}",
+            grm.rule_name(ridx),
+            usize::from(ridx),
+            pp_pidxs(grm.rule_to_prods(ridx))
+        ));
+        if !on_right.is_empty() {
+            o.push_str(&format!(", on right:{}", pp_pidxs(&on_right)));
+        }
+        o.push('\n');
+    }
+
+    let mut warnings = grm.warnings().to_vec();
+    warnings.extend(st.precedence_warnings(grm));
+    warnings.sort_by_key(|w| w.span.map(|s| s.start()));
+    if !warnings.is_empty() {
+        o.push_str("\n\nWarnings\n\n");
+        for w in &warnings {
+            let s = match source {
+                Some(source) => w.pp_with_source(source),
+                None => w.to_string(),
+            };
+            o.push_str(&format!("    {}\n", s));
+        }
+    }
+
+    // A production which no state reduces (other than the start productions, which are accepted
+    // rather than reduced, and the productions of rules which are already warned about) can only
+    // have lost out in conflicts.
+    let mut reduced = vec![false; usize::from(grm.prods_len())];
+    for &pidx in grm.start_prods() {
+        reduced[usize::from(pidx)] = true;
+    }
+    for stidx in sg.iter_stidxs().map(|s| s.cast::<StIdxT>()) {
+        for tidx in st.state_actions(stidx) {
+            if let Action::Reduce(pidx) = st.action(stidx, tidx) {
+                reduced[usize::from(pidx)] = true;
+            }
+        }
+    }
+    let useless = grm
+        .iter_pidxs()
+        .filter(|&pidx| !reduced[usize::from(pidx)])
+        .filter(|&pidx| {
+            let name = grm.rule_name(grm.prod_to_rule(pidx));
+            grm.warnings().iter().all(|w| w.name != name)
+        })
+        .collect::<Vec<_>>();
+    if !useless.is_empty() {
+        o.push_str("\n\nRules useless in parser due to conflicts\n\n");
+        for pidx in useless {
+            o.push_str(&format!(
+                "{:>5} {}\n",
+                usize::from(pidx),
+                pp_prod(grm, pidx)
+            ));
+        }
+    }
+
+    // How many shift/reduce and reduce/reduce conflicts each state has.
+    let mut conflicts: HashMap<StIdx<StIdxT>, (usize, usize)> = HashMap::new();
+    if let Some(c) = st.conflicts() {
+        for (_, _, stidx) in c.sr_conflicts() {
+            conflicts.entry(*stidx).or_insert((0, 0)).0 += 1;
+        }
+        for (_, _, stidx) in c.rr_conflicts() {
+            conflicts.entry(*stidx).or_insert((0, 0)).1 += 1;
+        }
+    }
+    if !conflicts.is_empty() {
+        o.push_str("\n\n");
+        let mut conflicts = conflicts.into_iter().collect::<Vec<_>>();
+        conflicts.sort_by_key(|(stidx, _)| usize::from(*stidx));
+        for (stidx, (sr, rr)) in conflicts {
+            let mut cs = Vec::new();
+            if sr > 0 {
+                cs.push(format!("{} shift/reduce", sr));
+            }
+            if rr > 0 {
+                cs.push(format!("{} reduce/reduce", rr));
+            }
+            o.push_str(&format!(
+                "State {} conflicts: {}\n",
+                usize::from(stidx),
+                cs.join(", ")
+            ));
+        }
+    }
+
+    for stidx in sg.iter_stidxs() {
+        o.push_str(&format!("\n\nState {}\n\n", usize::from(stidx)));
+        report_state(grm, sg, st, stidx, &mut o);
+    }
+    o
+}
+
+/// Append the report for the state `stidx` to `o`.
+fn report_state<StorageT, StIdxT>(
+    grm: &YaccGrammar<StorageT>,
+    sg: &StateGraph<StorageT>,
+    st: &StateTable<StorageT, StIdxT>,
+    stidx: StIdx<u32>,
+    o: &mut String,
+) where
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
+    u32: AsPrimitive<StorageT>,
+{
+    // The core items, followed by the items the closure added.
+    let core_st = sg.core_state(stidx);
+    let closed_st = sg.closed_state(stidx);
+    let mut core_items = core_st.items.keys().cloned().collect::<Vec<_>>();
+    core_items.sort();
+    let mut closure_items = closed_st
+        .items
+        .keys()
+        .filter(|k| !core_st.items.contains_key(k))
+        .cloned()
+        .collect::<Vec<_>>();
+    closure_items.sort();
+    for (i, items) in [core_items, closure_items].iter().enumerate() {
+        if i > 0 && !items.is_empty() {
+            o.push('\n');
+        }
+        for &(pidx, dot) in items {
+            let la = closed_st.items[&(pidx, dot)]
+                .iter_set_bits(..)
+                .map(|bidx| pp_token(grm, TIdx(bidx.as_())))
+                .collect::<Vec<_>>();
+            o.push_str(&format!(
+                "{:>5} {}  [{}]\n",
+                usize::from(pidx),
+                pp_item(grm, pidx, dot),
+                la.join(", ")
+            ));
+        }
+    }
+
+    // The state table's states are numbered in the same way as the state graph's.
+    let st_stidx = stidx.cast::<StIdxT>();
+    let resolutions = st
+        .resolutions()
+        .iter()
+        .filter(|r| r.stidx == st_stidx)
+        .collect::<Vec<_>>();
+    // Each action and goto is a (symbol, description) pair, so that the descriptions can be
+    // aligned once we know the widest symbol.
+    let mut shifts = Vec::new();
+    let mut reduces = Vec::new();
+    for tidx in grm.iter_tidxs() {
+        let tok = pp_token(grm, tidx);
+        match st.action(st_stidx, tidx) {
+            Action::Shift(s_stidx) => shifts.push((
+                tok.clone(),
+                format!("shift, and go to state {}", usize::from(s_stidx)),
+            )),
+            Action::Reduce(pidx) => reduces.push((tok.clone(), pp_reduce(grm, pidx))),
+            Action::Accept => reduces.push((tok.clone(), "accept".to_owned())),
+            Action::Error => {
+                if resolutions
+                    .iter()
+                    .any(|r| r.tidx == tidx && r.chosen == Action::Error)
+                {
+                    reduces.push((tok.clone(), "error (nonassociative)".to_owned()));
+                }
+            }
+        }
+        // Actions which a reported conflict resolution discarded.
+        for r in resolutions
+            .iter()
+            .filter(|r| r.tidx == tidx && r.is_reported())
+        {
+            for &a in &[r.actions.0, r.actions.1] {
+                if a != r.chosen {
+                    let s = match a {
+                        Action::Shift(s_stidx) => {
+                            format!("shift, and go to state {}", usize::from(s_stidx))
+                        }
+                        Action::Reduce(pidx) => pp_reduce(grm, pidx),
+                        Action::Accept => "accept".to_owned(),
+                        Action::Error => unreachable!(),
+                    };
+                    reduces.push((tok.clone(), format!("[{}]", s)));
+                }
+            }
+        }
+    }
+    let gotos = grm
+        .iter_rules()
+        .filter_map(|ridx| {
+            st.goto(st_stidx, ridx).map(|g_stidx| {
+                (
+                    grm.rule_name(ridx).to_owned(),
+                    format!("go to state {}", usize::from(g_stidx)),
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    let width = shifts
+        .iter()
+        .chain(reduces.iter())
+        .chain(gotos.iter())
+        .map(|(sym, _)| sym.len())
+        .max()
+        .unwrap_or(0);
+    for section in &[shifts, reduces, gotos] {
+        if !section.is_empty() {
+            o.push('\n');
+            for (sym, desc) in section {
+                o.push_str(&format!("    {:width$}  {}\n", sym, desc, width = width));
+            }
+        }
+    }
+
+    let prec = resolutions
+        .iter()
+        .filter(|r| !r.is_reported())
+        .collect::<Vec<_>>();
+    if !prec.is_empty() {
+        o.push('\n');
+        for r in prec {
+            o.push_str(&format!("    {}\n", pp_resolution(grm, r)));
+        }
+    }
+}
+
+/// Pretty print a conflict resolved by precedence.
+fn pp_resolution<StorageT: 'static + Hash + PrimInt + Unsigned, StIdxT>(
+    grm: &YaccGrammar<StorageT>,
+    r: &ConflictResolution<StorageT, StIdxT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let pidx = match r.actions.1 {
+        Action::Reduce(pidx) => pidx,
+        _ => unreachable!(),
+    };
+    let tok = pp_token(grm, r.tidx);
+    let chosen = match r.chosen {
+        Action::Shift(_) => "shift",
+        Action::Reduce(_) => "reduce",
+        _ => "an error",
+    };
+    let why = match r.reason {
+        ResolutionReason::TokenPrecedence => format!("{} has higher precedence", tok),
+        ResolutionReason::ProductionPrecedence => {
+            format!("rule {} has higher precedence", usize::from(pidx))
+        }
+        ResolutionReason::Associativity(kind) => {
+            let kind = match kind {
+                AssocKind::Left => "%left",
+                AssocKind::Right => "%right",
+                AssocKind::Nonassoc => "%nonassoc",
+                AssocKind::Precedence => "%precedence",
+            };
+            format!("{} {}", kind, tok)
+        }
+        ResolutionReason::Default | ResolutionReason::NoAssociativity => unreachable!(),
+    };
+    format!(
+        "Conflict between rule {} and token {} resolved as {} ({}).",
+        usize::from(pidx),
+        tok,
+        chosen,
+        why
+    )
+}
+
+fn pp_token<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    tidx: TIdx<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    if tidx == grm.eof_token_idx() {
+        "'$'".to_owned()
+    } else {
+        format!("'{}'", grm.token_name(tidx).unwrap_or(""))
+    }
+}
+
+fn pp_sym<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    sym: Symbol<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    match sym {
+        Symbol::Rule(ridx) => grm.rule_name(ridx).to_owned(),
+        Symbol::Token(tidx) => pp_token(grm, tidx),
+    }
+}
+
+fn pp_prod<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    pidx: PIdx<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let mut s = format!("{}:", grm.rule_name(grm.prod_to_rule(pidx)));
+    if grm.prod(pidx).is_empty() {
+        s.push_str(" %empty");
+    }
+    for &sym in grm.prod(pidx) {
+        s.push(' ');
+        s.push_str(&pp_sym(grm, sym));
+    }
+    s
+}
+
+fn pp_item<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    pidx: PIdx<StorageT>,
+    dot: SIdx<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    let mut s = format!("{}:", grm.rule_name(grm.prod_to_rule(pidx)));
+    for (i, &sym) in grm.prod(pidx).iter().enumerate() {
+        if i == usize::from(dot) {
+            s.push_str(" .");
+        }
+        s.push(' ');
+        s.push_str(&pp_sym(grm, sym));
+    }
+    if usize::from(dot) == grm.prod(pidx).len() {
+        s.push_str(" .");
+    }
+    s
+}
+
+fn pp_reduce<StorageT: 'static + PrimInt + Unsigned>(
+    grm: &YaccGrammar<StorageT>,
+    pidx: PIdx<StorageT>,
+) -> String
+where
+    usize: AsPrimitive<StorageT>,
+{
+    format!(
+        "reduce using rule {} ({})",
+        usize::from(pidx),
+        grm.rule_name(grm.prod_to_rule(pidx))
+    )
+}
+
+fn pp_pidxs<StorageT: PrimInt + Unsigned>(pidxs: &[PIdx<StorageT>]) -> String {
+    pidxs
+        .iter()
+        .map(|pidx| format!(" {}", usize::from(*pidx)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{env, fs, process};
+
+    use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind, YaccSource};
+
+    use super::report;
+    use crate::{pager::pager_stategraph, StateTable};
+
+    fn report_str(src: &str) -> String {
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        report(&grm, &sg, &st, None)
+    }
+
+    #[test]
+    fn test_report() {
+        let s = report_str(
+            "
+            %start E
+            %token 'unused'
+            %left '+'
+            %%
+            E: E '+' E | E '*' E | 'INT' | F;
+            F: 'INT';
+          ",
+        );
+        assert!(s.starts_with("Grammar\n\n    0 E: E '+' E\n"));
+        assert!(s.contains("    'INT' (3) 2 4\n"));
+        assert!(s.contains("    F (2)\n        on left: 4, on right: 3\n"));
+        assert!(s.contains("Warnings\n\n    Token 'unused' is not used in any production\n"));
+        assert!(s.contains("Rules useless in parser due to conflicts\n\n    4 F: 'INT'\n"));
+        assert!(s.contains(" conflicts: 3 reduce/reduce\n"));
+        assert!(s.contains(" conflicts: 2 shift/reduce\n"));
+        assert!(
+            s.contains("State 0\n\n    5 ^: . E  ['$']\n\n    0 E: . E '+' E  ['+', '*', '$']\n")
+        );
+        assert!(s.contains("    '$'  accept\n"));
+        assert!(
+            s.contains("    '+'  reduce using rule 2 (E)\n    '+'  [reduce using rule 4 (F)]\n")
+        );
+        assert!(s.contains(
+            "    Conflict between rule 0 and token '+' resolved as reduce (%left '+').\n"
+        ));
+    }
+
+    #[test]
+    fn test_report_include() {
+        // Warnings about included files are reported with the file's name and line.
+        let dir = env::temp_dir().join(format!("lrtable_report_{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("main.y"),
+            "%start E\n%include \"toks.y\"\n%%\nE: 'INT';\n",
+        )
+        .unwrap();
+        fs::write(dir.join("toks.y"), "%token 'INT'\n%token 'unused'\n").unwrap();
+        let source = YaccSource::read(dir.join("main.y")).unwrap();
+        let grm = YaccGrammar::<u32>::from_source(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            &source,
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        let s = report(&grm, &sg, &st, Some(&source));
+        assert!(s.contains(&format!(
+            "    Token 'unused' is not used in any production in {} at line 2 column 8\n",
+            dir.join("toks.y").display()
+        )));
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,18 +1,22 @@
-use std::{collections::hash_map::HashMap, hash::Hash};
+use std::{collections::hash_map::HashMap, convert::TryFrom, hash::Hash};
 
-use cfgrammar::{yacc::YaccGrammar, Symbol, TIdx};
+use cfgrammar::{yacc::YaccGrammar, PIdx, Symbol, TIdx};
 use num_traits::{AsPrimitive, PrimInt, Unsigned};
-use try_from::TryFrom;
 
-use crate::{itemset::Itemset, StIdx, StIdxStorageT};
+use crate::{
+    itemset::{Ctx, Itemset},
+    StIdx,
+};
 
 #[derive(Debug)]
 pub struct StateGraph<StorageT: Eq + Hash> {
     /// A vector of `(core_states, closed_states)` tuples.
     states: Vec<(Itemset<StorageT>, Itemset<StorageT>)>,
-    start_state: StIdx,
+    /// One start state for each of the grammar's start productions (in the same order as
+    /// `YaccGrammar::start_prods`).
+    start_states: Vec<StIdx<u32>>,
     /// For each state in `states`, edges is a hashmap from symbols to state offsets.
-    edges: Vec<HashMap<Symbol<StorageT>, StIdx>>,
+    edges: Vec<HashMap<Symbol<StorageT>, StIdx<u32>>>,
 }
 
 impl<StorageT: 'static + Hash + PrimInt + Unsigned> StateGraph<StorageT>
@@ -21,34 +25,41 @@ where
 {
     pub(crate) fn new(
         states: Vec<(Itemset<StorageT>, Itemset<StorageT>)>,
-        start_state: StIdx,
-        edges: Vec<HashMap<Symbol<StorageT>, StIdx>>,
+        start_states: Vec<StIdx<u32>>,
+        edges: Vec<HashMap<Symbol<StorageT>, StIdx<u32>>>,
     ) -> Self {
-        // states.len() needs to fit into StIdxStorageT; however we don't need to worry about
+        // states.len() needs to fit into a u32; however we don't need to worry about
         // edges.len() (which merely needs to fit in a usize)
-        assert!(StIdxStorageT::try_from(states.len()).is_ok());
+        assert!(u32::try_from(states.len()).is_ok());
         StateGraph {
             states,
-            start_state,
+            start_states,
             edges,
         }
     }
 
-    /// Return this state graph's start state.
-    pub fn start_state(&self) -> StIdx {
-        self.start_state
+    /// Return this state graph's start state (i.e. the start state for the grammar's first
+    /// `%start` rule).
+    pub fn start_state(&self) -> StIdx<u32> {
+        self.start_states[0]
+    }
+
+    /// Return this state graph's start states: one for each of the grammar's start productions, in
+    /// the same order as `YaccGrammar::start_prods`.
+    pub fn start_states(&self) -> &[StIdx<u32>] {
+        &self.start_states
     }
 
     /// Return an iterator which produces (in order from `0..self.rules_len()`) all this
     /// grammar's valid `RIdx`s.
-    pub fn iter_stidxs(&self) -> Box<dyn Iterator<Item = StIdx>> {
+    pub fn iter_stidxs(&self) -> Box<dyn Iterator<Item = StIdx<u32>>> {
         // We can use as safely, because we know that we're only generating integers from
-        // 0..self.states.len() which we've already checked fits within StIdxStorageT.
-        Box::new((0..self.states.len()).map(|x| StIdx(x as StIdxStorageT)))
+        // 0..self.states.len() which we've already checked fits within a u32.
+        Box::new((0..self.states.len()).map(|x| StIdx(x as u32)))
     }
 
     /// Return the itemset for closed state `stidx`. Panics if `stidx` doesn't exist.
-    pub fn closed_state(&self, stidx: StIdx) -> &Itemset<StorageT> {
+    pub fn closed_state(&self, stidx: StIdx<u32>) -> &Itemset<StorageT> {
         &self.states[usize::from(stidx)].1
     }
 
@@ -60,7 +71,7 @@ where
     }
 
     /// Return the itemset for core state `stidx` or `None` if it doesn't exist.
-    pub fn core_state(&self, stidx: StIdx) -> &Itemset<StorageT> {
+    pub fn core_state(&self, stidx: StIdx<u32>) -> &Itemset<StorageT> {
         &self.states[usize::from(stidx)].0
     }
 
@@ -71,13 +82,13 @@ where
 
     /// How many states does this `StateGraph` contain? NB: By definition the `StateGraph` contains
     /// the same number of core and closed states.
-    pub fn all_states_len(&self) -> StIdx {
-        // We checked in the constructor that self.states.len() can fit into StIdxStorageT
-        StIdx::from(self.states.len() as StIdxStorageT)
+    pub fn all_states_len(&self) -> StIdx<u32> {
+        // We checked in the constructor that self.states.len() can fit into a u32
+        StIdx(self.states.len() as u32)
     }
 
     /// Return the state pointed to by `sym` from `stidx` or `None` otherwise.
-    pub fn edge(&self, stidx: StIdx, sym: Symbol<StorageT>) -> Option<StIdx> {
+    pub fn edge(&self, stidx: StIdx<u32>, sym: Symbol<StorageT>) -> Option<StIdx<u32>> {
         self.edges
             .get(usize::from(stidx))
             .and_then(|x| x.get(&sym))
@@ -85,10 +96,27 @@ where
     }
 
     /// Return the edges for state `stidx`. Panics if `stidx` doesn't exist.
-    pub fn edges(&self, stidx: StIdx) -> &HashMap<Symbol<StorageT>, StIdx> {
+    pub fn edges(&self, stidx: StIdx<u32>) -> &HashMap<Symbol<StorageT>, StIdx<u32>> {
         &self.edges[usize::from(stidx)]
     }
 
+    /// Replace the context of every item in every (core and closed) state with `ctx(pidx)`, where
+    /// `pidx` is the item's production.
+    pub(crate) fn set_ctxs<F>(&mut self, ctx: F)
+    where
+        F: Fn(PIdx<StorageT>) -> Ctx,
+    {
+        for (core_state, closed_state) in &mut self.states {
+            for ((pidx, _), item_ctx) in core_state
+                .items
+                .iter_mut()
+                .chain(closed_state.items.iter_mut())
+            {
+                *item_ctx = ctx(*pidx);
+            }
+        }
+    }
+
     /// How many edges does this `StateGraph` contain?
     pub fn all_edges_len(&self) -> usize {
         self.edges.iter().fold(0, |a, x| a + x.len())
@@ -98,7 +126,7 @@ where
     /// states are pretty printed; if set to false, all states (including non-core states) are
     /// pretty printed.
     pub fn pp(&self, grm: &YaccGrammar<StorageT>, core_states: bool) -> String {
-        fn num_digits(i: StIdx) -> usize {
+        fn num_digits(i: StIdx<u32>) -> usize {
             if usize::from(i) == 0 {
                 1
             } else {
@@ -121,16 +149,12 @@ where
 
         let mut o = String::new();
         for (stidx, &(ref core_st, ref closed_st)) in self.iter_stidxs().zip(self.states.iter()) {
-            if stidx != self.start_state {
+            if usize::from(stidx) != 0 {
                 o.push_str(&"\n");
             }
             {
                 let padding = num_digits(self.all_states_len()) - num_digits(stidx);
-                o.push_str(&format!(
-                    "{}:{}",
-                    StIdxStorageT::from(stidx),
-                    " ".repeat(padding)
-                ));
+                o.push_str(&format!("{}:{}", usize::from(stidx), " ".repeat(padding)));
             }
 
             let st = if core_states { core_st } else { closed_st };
@@ -1,6 +1,6 @@
 use std::{
     cmp::Ordering,
-    collections::hash_map::HashMap,
+    collections::{hash_map::HashMap, HashSet},
     error::Error,
     fmt::{self, Debug},
     hash::Hash,
@@ -8,36 +8,48 @@ use std::{
 };
 
 use cfgrammar::{
-    yacc::{AssocKind, YaccGrammar},
-    PIdx, RIdx, Symbol, TIdx,
+    yacc::{AssocKind, YaccGrammar, YaccGrammarWarning, YaccGrammarWarningKind},
+    MaybeStatic, PIdx, RIdx, SIdx, Symbol, TIdx,
 };
 use num_traits::{AsPrimitive, PrimInt, Unsigned};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use sparsevec::SparseVec;
-use vob::{IterSetBits, Vob};
-
-use crate::{stategraph::StateGraph, StIdx, StIdxStorageT};
+use vob::Vob;
+
+use crate::{
+    bitvec::{self, BitVec},
+    combvec::CombVec,
+    counterexample::{rr_counterexample, sr_counterexample},
+    stategraph::StateGraph,
+    StIdx,
+};
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug)]
-pub struct Conflicts<StorageT> {
-    reduce_reduce: Vec<(PIdx<StorageT>, PIdx<StorageT>, StIdx)>,
-    shift_reduce: Vec<(TIdx<StorageT>, PIdx<StorageT>, StIdx)>,
+pub struct Conflicts<StorageT, StIdxT> {
+    reduce_reduce: Vec<(PIdx<StorageT>, PIdx<StorageT>, StIdx<StIdxT>)>,
+    shift_reduce: Vec<(TIdx<StorageT>, PIdx<StorageT>, StIdx<StIdxT>)>,
 }
 
-impl<StorageT: 'static + Hash + PrimInt + Unsigned> Conflicts<StorageT>
+impl<StorageT, StIdxT> Conflicts<StorageT, StIdxT>
 where
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
     usize: AsPrimitive<StorageT>,
     u32: AsPrimitive<StorageT>,
 {
     /// Return an iterator over all shift/reduce conflicts.
-    pub fn sr_conflicts(&self) -> impl Iterator<Item = &(TIdx<StorageT>, PIdx<StorageT>, StIdx)> {
+    pub fn sr_conflicts(
+        &self,
+    ) -> impl Iterator<Item = &(TIdx<StorageT>, PIdx<StorageT>, StIdx<StIdxT>)> {
         self.shift_reduce.iter()
     }
 
     /// Return an iterator over all reduce/reduce conflicts.
-    pub fn rr_conflicts(&self) -> impl Iterator<Item = &(PIdx<StorageT>, PIdx<StorageT>, StIdx)> {
+    pub fn rr_conflicts(
+        &self,
+    ) -> impl Iterator<Item = &(PIdx<StorageT>, PIdx<StorageT>, StIdx<StIdxT>)> {
         self.reduce_reduce.iter()
     }
 
@@ -53,6 +65,19 @@ where
 
     /// Returns a pretty-printed version of the conflicts.
     pub fn pp(&self, grm: &YaccGrammar<StorageT>) -> String {
+        self.pp_with_src(grm, None)
+    }
+
+    /// Returns a pretty-printed version of the conflicts. If `src` (the source of the grammar
+    /// `grm`) is not `None`, the line and column of each production is included.
+    pub fn pp_with_src(&self, grm: &YaccGrammar<StorageT>, src: Option<&str>) -> String {
+        let pp_prod = |pidx: PIdx<StorageT>| match (src, grm.prod_span(pidx)) {
+            (Some(src), Some(span)) => {
+                let (line, col) = span.line_col(src);
+                format!("{} at line {} column {}", grm.pp_prod(pidx), line, col)
+            }
+            _ => grm.pp_prod(pidx),
+        };
         let mut s = String::new();
         if self.sr_len() > 0 {
             s.push_str("Shift/Reduce conflicts:\n");
@@ -61,7 +86,7 @@ where
                     "   State {:?}: Shift(\"{}\") / Reduce({})\n",
                     usize::from(*stidx),
                     grm.token_name(*tidx).unwrap(),
-                    grm.pp_prod(*pidx)
+                    pp_prod(*pidx)
                 ));
             }
         }
@@ -72,62 +97,216 @@ where
                 s.push_str(&format!(
                     "   State {:?}: Reduce({}) / Reduce({})\n",
                     usize::from(*stidx),
-                    grm.pp_prod(*pidx),
-                    grm.pp_prod(*r_pidx)
+                    pp_prod(*pidx),
+                    pp_prod(*r_pidx)
                 ));
             }
         }
         s
     }
+
+    /// Returns a pretty-printed counterexample for each conflict (see the
+    /// [`counterexample`](crate::counterexample) module). `sg` must be the state graph the
+    /// conflicts were found in.
+    pub fn pp_counterexamples(
+        &self,
+        grm: &YaccGrammar<StorageT>,
+        sg: &StateGraph<StorageT>,
+    ) -> String {
+        let mut s = String::new();
+        for &(tidx, pidx, stidx) in self.sr_conflicts() {
+            match sr_counterexample(grm, sg, tidx, pidx, stidx.cast::<u32>()) {
+                Some(c) => s.push_str(&c.pp(grm)),
+                None => s.push_str(&format!(
+                    "Shift/Reduce conflict in state {}: no counterexample found\n",
+                    usize::from(stidx)
+                )),
+            }
+        }
+        for &(pidx, r_pidx, stidx) in self.rr_conflicts() {
+            match rr_counterexample(grm, sg, pidx, r_pidx, stidx.cast::<u32>()) {
+                Some(c) => s.push_str(&c.pp(grm)),
+                None => s.push_str(&format!(
+                    "Reduce/Reduce conflict in state {}: no counterexample found\n",
+                    usize::from(stidx)
+                )),
+            }
+        }
+        s
+    }
+}
+
+/// Why a conflict between two actions was resolved the way it was.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ResolutionReason {
+    /// Precedence couldn't be used, so Yacc's default resolution (in favour of the shift, or of
+    /// the production which appears earlier in the grammar) was used. The conflict is reported.
+    Default,
+    /// The token and production have the same precedence level, but no associativity (i.e. they
+    /// were declared with `%precedence`), so Yacc's default resolution was used. The conflict is
+    /// reported.
+    NoAssociativity,
+    /// The token has a higher precedence than the production.
+    TokenPrecedence,
+    /// The production has a higher precedence than the token.
+    ProductionPrecedence,
+    /// The token and production have the same precedence level, and the conflict was resolved by
+    /// their associativity.
+    Associativity(AssocKind),
+}
+
+/// A record of how a conflict between two actions on a token was resolved.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConflictResolution<StorageT, StIdxT> {
+    /// The state the conflict occurred in.
+    pub stidx: StIdx<StIdxT>,
+    /// The token the conflict occurred on.
+    pub tidx: TIdx<StorageT>,
+    /// The conflicting actions: for a shift/reduce conflict, the shift and then the reduce; for a
+    /// reduce/reduce conflict, the reduction of the earlier production in the grammar and then the
+    /// reduction of the later production.
+    pub actions: (Action<StorageT, StIdxT>, Action<StorageT, StIdxT>),
+    /// The action chosen, which is `Action::Error` if the token is `%nonassoc`.
+    pub chosen: Action<StorageT, StIdxT>,
+    pub reason: ResolutionReason,
+}
+
+impl<StorageT, StIdxT> ConflictResolution<StorageT, StIdxT> {
+    /// Is this conflict reported (i.e. was it not resolved by precedence)?
+    pub fn is_reported(&self) -> bool {
+        matches!(
+            self.reason,
+            ResolutionReason::Default | ResolutionReason::NoAssociativity
+        )
+    }
+}
+
+/// An item in a conflicting state.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConflictItem<StorageT> {
+    pub pidx: PIdx<StorageT>,
+    pub dot: SIdx<StorageT>,
+    /// The tokens which can follow the item's production.
+    pub lookahead: Vec<TIdx<StorageT>>,
+}
+
+/// An explanation of all the conflicts (whether reported or resolved by precedence) in a state.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConflictExplanation<StorageT, StIdxT> {
+    pub stidx: StIdx<StIdxT>,
+    /// The items which shift or reduce on one of the tokens in `lookahead`, in the order they
+    /// appear in the grammar.
+    pub items: Vec<ConflictItem<StorageT>>,
+    /// The tokens on which the items conflict.
+    pub lookahead: Vec<TIdx<StorageT>>,
+    /// How each conflict in this state was resolved.
+    pub resolutions: Vec<ConflictResolution<StorageT, StIdxT>>,
 }
 
 /// The various different possible Yacc parser errors.
 #[derive(Debug)]
 pub enum StateTableErrorKind {
     AcceptReduceConflict,
+    /// The state graph has more states (the value) than the state table's `StIdx` type can index.
+    TooManyStates(usize),
 }
 
 /// Any error from the Yacc parser returns an instance of this struct.
 #[derive(Debug)]
 pub struct StateTableError<StorageT> {
     pub kind: StateTableErrorKind,
-    pub pidx: PIdx<StorageT>,
+    /// The production the error relates to, if any.
+    pub pidx: Option<PIdx<StorageT>>,
 }
 
 impl<StorageT: Debug> Error for StateTableError<StorageT> {}
 
 impl<StorageT> fmt::Display for StateTableError<StorageT> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let s;
         match self.kind {
-            StateTableErrorKind::AcceptReduceConflict => s = "Accept/reduce conflict",
+            StateTableErrorKind::AcceptReduceConflict => write!(f, "Accept/reduce conflict"),
+            StateTableErrorKind::TooManyStates(n) => {
+                write!(f, "Too many states ({}) for the state index type", n)
+            }
+        }
+    }
+}
+
+/// The cells of an action or goto table, in which `0` is the "empty" value. Tables start out
+/// stored as a `SparseVec`, and are converted into a `CombVec` by
+/// [`StateTable::compress`](struct.StateTable.html#method.compress).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+enum Cells {
+    Sparse(SparseVec<usize>),
+    Comb(CombVec),
+}
+
+impl Cells {
+    fn get(&self, r: usize, c: usize) -> usize {
+        match self {
+            Cells::Sparse(sv) => sv.get(r, c).unwrap(),
+            Cells::Comb(cv) => cv.get(r, c),
+        }
+    }
+
+    /// Return these cells, which have `rows_len` rows `row_length` long, as a comb vector.
+    fn to_combvec(&self, rows_len: usize, row_length: usize) -> CombVec {
+        match self {
+            Cells::Sparse(sv) => {
+                let v = (0..rows_len)
+                    .flat_map(|r| (0..row_length).map(move |c| (r, c)))
+                    .map(|(r, c)| sv.get(r, c).unwrap())
+                    .collect::<Vec<_>>();
+                CombVec::from(&v, row_length)
+            }
+            Cells::Comb(cv) => cv.clone(),
+        }
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        match self {
+            Cells::Sparse(sv) => sv.len(),
+            Cells::Comb(cv) => cv.len(),
         }
-        write!(f, "{}", s)
     }
 }
 
 /// A representation of a `StateTable` for a grammar. `actions` and `gotos` are split into two
 /// separate hashmaps, rather than a single table, due to the different types of their values.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub struct StateTable<StorageT> {
-    actions: SparseVec<usize>,
-    state_actions: Vob,
-    gotos: SparseVec<usize>,
-    start_state: StIdx,
-    core_reduces: Vob,
-    state_shifts: Vob,
-    reduce_states: Vob,
+pub struct StateTable<StorageT: 'static, StIdxT: 'static> {
+    actions: Cells,
+    /// Which cells in `actions` are non-error. This is never compressed, so that the error
+    /// entries of a table remain exact even when a state has a default reduction.
+    state_actions: BitVec,
+    gotos: Cells,
+    /// The default reduction of each state, if it has one. Empty until the table is compressed.
+    default_reduces: MaybeStatic<Option<PIdx<StorageT>>>,
+    start_states: MaybeStatic<StIdx<StIdxT>>,
+    core_reduces: BitVec,
+    state_shifts: BitVec,
+    reduce_states: BitVec,
     prods_len: PIdx<StorageT>,
+    rules_len: RIdx<StorageT>,
     tokens_len: TIdx<StorageT>,
-    conflicts: Option<Conflicts<StorageT>>,
-    pub final_state: StIdx,
+    conflicts: Option<Conflicts<StorageT, StIdxT>>,
+    resolutions: Vec<ConflictResolution<StorageT, StIdxT>>,
+    /// For each cell in `actions` (keyed by offset, in ascending order) which has an unresolved
+    /// conflict, all of the cell's actions.
+    glr_actions: MaybeStatic<(usize, MaybeStatic<Action<StorageT, StIdxT>>)>,
+    pub final_state: StIdx<StIdxT>,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-pub enum Action<StorageT> {
+pub enum Action<StorageT, StIdxT> {
     /// Shift to state X in the statetable.
-    Shift(StIdx),
+    Shift(StIdx<StIdxT>),
     /// Reduce production X in the grammar.
     Reduce(PIdx<StorageT>),
     /// Accept this input.
@@ -141,15 +320,25 @@ const REDUCE: usize = 2;
 const ACCEPT: usize = 3;
 const ERROR: usize = 0;
 
-impl<StorageT: 'static + Hash + PrimInt + Unsigned> StateTable<StorageT>
+impl<StorageT, StIdxT> StateTable<StorageT, StIdxT>
 where
-    usize: AsPrimitive<StorageT>,
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     pub fn new(
         grm: &YaccGrammar<StorageT>,
         sg: &StateGraph<StorageT>,
     ) -> Result<Self, StateTableError<StorageT>> {
+        // Every state index must fit into a StIdxT.
+        if usize::from(sg.all_states_len()) > StIdxT::max_value().as_() {
+            return Err(StateTableError {
+                kind: StateTableErrorKind::TooManyStates(usize::from(sg.all_states_len())),
+                pidx: None,
+            });
+        }
+
         let mut state_actions = Vob::from_elem(
             usize::from(sg.all_states_len())
                 .checked_mul(usize::from(grm.tokens_len()))
@@ -163,23 +352,18 @@ where
         assert!(usize::from(grm.rules_len()) < (usize::max_value() - 4));
         let mut actions: Vec<usize> = vec![0; maxa];
 
-        // Since 0 is reserved for the error type, and states are encoded by adding 1, we can only
-        // store max_value - 1 states within the goto table
-        assert!(usize::from(sg.all_states_len()) < (usize::from(StIdx::max_value()) - 1));
         let mut gotos: Vec<usize> = vec![0; maxg];
 
         // Store automatically resolved conflicts, so we can print them out later
         let mut reduce_reduce = Vec::new();
         let mut shift_reduce = Vec::new();
+        // Store how every conflict, including those resolved by precedence, was resolved
+        let mut resolutions = Vec::new();
         let mut final_state = None;
 
-        for (stidx, state) in sg
-            .iter_closed_states()
-            .enumerate()
-            // x goes from 0..states_len(), and we know the latter can safely fit into an
-            // StIdxStorageT, so the cast is safe.
-            .map(|(x, y)| (StIdx(x as StIdxStorageT), y))
-        {
+        for (sg_stidx, state) in sg.iter_stidxs().zip(sg.iter_closed_states()) {
+            // We checked above that every state index fits into a StIdxT.
+            let stidx = sg_stidx.cast::<StIdxT>();
             // Populate reduce and accepts
             for (&(pidx, dot), ctx) in &state.items {
                 if dot < grm.prod_len(pidx) {
@@ -194,40 +378,52 @@ where
                         TIdx(tidx.as_()),
                     );
                     state_actions.set(off, true);
-                    match StateTable::decode(actions[off]) {
+                    match Self::decode(actions[off]) {
                         Action::Reduce(r_pidx) => {
-                            if pidx == grm.start_prod() && tidx == usize::from(grm.eof_token_idx())
+                            if grm.start_prods().contains(&pidx)
+                                && tidx == usize::from(grm.eof_token_idx())
                             {
                                 return Err(StateTableError {
                                     kind: StateTableErrorKind::AcceptReduceConflict,
-                                    pidx,
+                                    pidx: Some(pidx),
                                 });
                             }
                             // By default, Yacc resolves reduce/reduce conflicts in favour
                             // of the earlier production in the grammar.
-                            match pidx.cmp(&r_pidx) {
+                            let (e_pidx, l_pidx) = match pidx.cmp(&r_pidx) {
                                 Ordering::Less => {
-                                    reduce_reduce.push((pidx, r_pidx, stidx));
-                                    actions[off] = StateTable::encode(Action::Reduce(pidx));
+                                    actions[off] = Self::encode(Action::Reduce(pidx));
+                                    (pidx, r_pidx)
                                 }
-                                Ordering::Greater => reduce_reduce.push((r_pidx, pidx, stidx)),
-                                Ordering::Equal => (),
-                            }
+                                Ordering::Greater => (r_pidx, pidx),
+                                Ordering::Equal => continue,
+                            };
+                            reduce_reduce.push((e_pidx, l_pidx, stidx));
+                            resolutions.push(ConflictResolution {
+                                stidx,
+                                tidx: TIdx(tidx.as_()),
+                                actions: (Action::Reduce(e_pidx), Action::Reduce(l_pidx)),
+                                chosen: Action::Reduce(e_pidx),
+                                reason: ResolutionReason::Default,
+                            });
                         }
                         Action::Accept => {
                             return Err(StateTableError {
                                 kind: StateTableErrorKind::AcceptReduceConflict,
-                                pidx,
+                                pidx: Some(pidx),
                             });
                         }
                         Action::Error => {
-                            if pidx == grm.start_prod() && tidx == usize::from(grm.eof_token_idx())
+                            if grm.start_prods().contains(&pidx)
+                                && tidx == usize::from(grm.eof_token_idx())
                             {
-                                assert!(final_state.is_none());
-                                final_state = Some(stidx);
-                                actions[off] = StateTable::encode(Action::Accept);
+                                if pidx == grm.start_prod() {
+                                    assert!(final_state.is_none());
+                                    final_state = Some(stidx);
+                                }
+                                actions[off] = Self::encode(Action::Accept);
                             } else {
-                                actions[off] = StateTable::encode(Action::Reduce(pidx));
+                                actions[off] = Self::encode(Action::Reduce(pidx));
                             }
                         }
                         _ => panic!("Internal error"),
@@ -236,21 +432,21 @@ where
             }
 
             let nt_len = grm.rules_len();
-            for (&sym, ref_stidx) in sg.edges(stidx) {
+            for (&sym, &ref_stidx) in sg.edges(sg_stidx) {
                 match sym {
                     Symbol::Rule(s_ridx) => {
                         // Populate gotos
                         let off = (usize::from(stidx) * usize::from(nt_len)) + usize::from(s_ridx);
                         debug_assert!(gotos[off] == 0);
                         // Since 0 is reserved for no entry, encode states by adding 1
-                        gotos[off] = usize::from(*ref_stidx) + 1;
+                        gotos[off] = usize::from(ref_stidx) + 1;
                     }
                     Symbol::Token(s_tidx) => {
                         // Populate shifts
                         let off = actions_offset(grm.tokens_len(), stidx, s_tidx);
                         state_actions.set(off, true);
-                        match StateTable::decode(actions[off]) {
-                            Action::Shift(x) => assert_eq!(*ref_stidx, x),
+                        match Self::decode(actions[off]) {
+                            Action::Shift(x) => assert!(ref_stidx.cast::<StIdxT>() == x),
                             Action::Reduce(r_pidx) => {
                                 resolve_shift_reduce(
                                     grm,
@@ -258,14 +454,16 @@ where
                                     off,
                                     s_tidx,
                                     r_pidx,
-                                    *ref_stidx,
+                                    ref_stidx.cast::<StIdxT>(),
                                     &mut shift_reduce,
+                                    &mut resolutions,
                                     stidx,
                                 );
                             }
                             Action::Accept => panic!("Internal error"),
                             Action::Error => {
-                                actions[off] = StateTable::encode(Action::Shift(*ref_stidx));
+                                actions[off] =
+                                    Self::encode(Action::Shift(ref_stidx.cast::<StIdxT>()));
                             }
                         }
                     }
@@ -293,7 +491,7 @@ where
             let mut only_reduces = true;
             for tidx in grm.iter_tidxs() {
                 let off = actions_offset(grm.tokens_len(), stidx, tidx);
-                match StateTable::decode(actions[off]) {
+                match Self::decode(actions[off]) {
                     Action::Reduce(pidx) => {
                         let prod_len = grm.prod(pidx).len();
                         let ridx = grm.prod_to_rule(pidx);
@@ -327,6 +525,26 @@ where
             }
         }
 
+        // Record the actions discarded by Yacc's default conflict resolution, so that a GLR parser
+        // can follow all of them. Conflicts resolved by precedence are not recorded.
+        let mut glr_actions = HashMap::new();
+        for r in resolutions.iter().filter(|r| r.is_reported()) {
+            let off = actions_offset(grm.tokens_len(), r.stidx, r.tidx);
+            let cell = glr_actions
+                .entry(off)
+                .or_insert_with(|| vec![Self::decode(actions[off])]);
+            for &a in &[r.actions.0, r.actions.1] {
+                if !cell.contains(&a) {
+                    cell.push(a);
+                }
+            }
+        }
+        let mut glr_actions = glr_actions
+            .into_iter()
+            .map(|(off, acts)| (off, MaybeStatic::from(acts)))
+            .collect::<Vec<_>>();
+        glr_actions.sort_by_key(|(off, _)| *off);
+
         let actions_sv = SparseVec::<usize>::from(&actions, 0, usize::from(grm.tokens_len()));
         let gotos_sv = SparseVec::<usize>::from(&gotos, 0, usize::from(grm.rules_len()));
 
@@ -340,29 +558,38 @@ where
         };
 
         Ok(StateTable {
-            actions: actions_sv,
-            state_actions,
-            gotos: gotos_sv,
-            start_state: sg.start_state(),
-            state_shifts,
-            core_reduces,
-            reduce_states,
+            actions: Cells::Sparse(actions_sv),
+            state_actions: BitVec::from_vob(&state_actions),
+            gotos: Cells::Sparse(gotos_sv),
+            default_reduces: MaybeStatic::default(),
+            start_states: sg
+                .start_states()
+                .iter()
+                .map(|s| s.cast::<StIdxT>())
+                .collect::<Vec<_>>()
+                .into(),
+            state_shifts: BitVec::from_vob(&state_shifts),
+            core_reduces: BitVec::from_vob(&core_reduces),
+            reduce_states: BitVec::from_vob(&reduce_states),
             prods_len: grm.prods_len(),
+            rules_len: grm.rules_len(),
             tokens_len: grm.tokens_len(),
             conflicts,
+            resolutions,
+            glr_actions: glr_actions.into(),
             final_state: final_state.unwrap(),
         })
     }
 
-    fn decode(bits: usize) -> Action<StorageT> {
+    fn decode(bits: usize) -> Action<StorageT, StIdxT> {
         let action = bits & 0b11;
         let val = bits >> 2;
 
         match action {
             SHIFT => {
-                // Since val was originally stored in an StIdxStorageT, we know that it's safe to
-                // cast it back to an StIdxStorageT here.
-                Action::Shift(StIdx::from(val as StIdxStorageT))
+                // Since val was originally stored in a StorageT, we know that it's safe to
+                // cast it back to a StorageT here.
+                Action::Shift(StIdx(val.as_()))
             }
             REDUCE => Action::Reduce(PIdx(val.as_())),
             ACCEPT => Action::Accept,
@@ -371,7 +598,7 @@ where
         }
     }
 
-    fn encode(action: Action<StorageT>) -> usize {
+    fn encode(action: Action<StorageT, StIdxT>) -> usize {
         match action {
             Action::Shift(stidx) => SHIFT | (usize::from(stidx) << 2),
             Action::Reduce(ridx) => REDUCE | (usize::from(ridx) << 2),
@@ -381,16 +608,111 @@ where
     }
 
     /// Return the action for `stidx` and `sym`, or `None` if there isn't any.
-    pub fn action(&self, stidx: StIdx, tidx: TIdx<StorageT>) -> Action<StorageT> {
-        StateTable::decode(
-            self.actions
-                .get(usize::from(stidx), usize::from(tidx))
-                .unwrap(),
-        )
+    pub fn action(&self, stidx: StIdx<StIdxT>, tidx: TIdx<StorageT>) -> Action<StorageT, StIdxT> {
+        match self.actions.get(usize::from(stidx), usize::from(tidx)) {
+            // A compressed state's reductions are elided in favour of its default reduction, but
+            // its error entries are still recorded exactly in `state_actions`.
+            ERROR
+                if self
+                    .state_actions
+                    .get(actions_offset(self.tokens_len, stidx, tidx)) =>
+            {
+                Action::Reduce(self.default_reduces[usize::from(stidx)].unwrap())
+            }
+            bits => Self::decode(bits),
+        }
+    }
+
+    /// Return the default reduction of `stidx`, if the table has been
+    /// [compressed](#method.compress) and `stidx` has one. A state has a default reduction if all
+    /// of its reduce actions reduce the same production.
+    pub fn default_reduce(&self, stidx: StIdx<StIdxT>) -> Option<PIdx<StorageT>> {
+        self.default_reduces
+            .get(usize::from(stidx))
+            .cloned()
+            .flatten()
+    }
+
+    /// Compress the action and goto tables. Each state whose reduce actions all reduce the same
+    /// production is given a default reduction, and those reduce actions are removed from its
+    /// row; identical rows are then shared; and the remaining rows are packed into a comb vector
+    /// by row displacement. Compression does not change the actions or gotos this table returns
+    /// (in particular, error entries remain exact, so error recovery is unaffected). Returns a
+    /// summary of the space saved.
+    pub fn compress(&mut self) -> CompressionStats {
+        let states_len = self.reduce_states.len();
+        let tokens_len = usize::from(self.tokens_len);
+        let rules_len = usize::from(self.rules_len);
+
+        let mut default_reduces = Vec::with_capacity(states_len);
+        let mut actions = Vec::with_capacity(states_len * tokens_len);
+        for i in 0..states_len {
+            let stidx = StIdx(i.as_());
+            let row = (0..tokens_len)
+                .map(|j| self.action(stidx, TIdx(j.as_())))
+                .collect::<Vec<_>>();
+            let mut pidxs = row.iter().filter_map(|a| match a {
+                Action::Reduce(pidx) => Some(*pidx),
+                _ => None,
+            });
+            let default_reduce = match pidxs.next() {
+                Some(pidx) if pidxs.all(|x| x == pidx) => Some(pidx),
+                _ => None,
+            };
+            for a in row {
+                match (a, default_reduce) {
+                    (Action::Reduce(pidx), Some(d_pidx)) if pidx == d_pidx => actions.push(ERROR),
+                    _ => actions.push(Self::encode(a)),
+                }
+            }
+            default_reduces.push(default_reduce);
+        }
+        let gotos = (0..states_len)
+            .flat_map(|i| (0..rules_len).map(move |j| (i, j)))
+            .map(|(i, j)| self.gotos.get(i, j))
+            .collect::<Vec<_>>();
+
+        let actions_cv = CombVec::from(&actions, tokens_len);
+        let gotos_cv = CombVec::from(&gotos, rules_len);
+        let stats = CompressionStats {
+            states: states_len,
+            default_reduces: default_reduces.iter().filter(|x| x.is_some()).count(),
+            action_entries: self.state_actions.count_ones(),
+            action_cells: actions.len(),
+            action_rows: actions_cv.rows_len(),
+            action_slots: actions_cv.slots_len(),
+            goto_entries: gotos.iter().filter(|&&x| x != 0).count(),
+            goto_cells: gotos.len(),
+            goto_rows: gotos_cv.rows_len(),
+            goto_slots: gotos_cv.slots_len(),
+        };
+        self.actions = Cells::Comb(actions_cv);
+        self.gotos = Cells::Comb(gotos_cv);
+        self.default_reduces = default_reduces.into();
+        stats
+    }
+
+    /// Return all of the actions for `stidx` and `tidx`: the action returned by
+    /// [`action`](#method.action) followed by any which Yacc's default conflict resolution
+    /// discarded (conflicts resolved by precedence are not included). These are the actions which
+    /// a GLR parser must follow. If there is no valid action, the vector returned is empty.
+    pub fn glr_actions(
+        &self,
+        stidx: StIdx<StIdxT>,
+        tidx: TIdx<StorageT>,
+    ) -> Vec<Action<StorageT, StIdxT>> {
+        let off = actions_offset(self.tokens_len, stidx, tidx);
+        match self.glr_actions.binary_search_by_key(&off, |(off, _)| *off) {
+            Ok(i) => self.glr_actions[i].1.to_vec(),
+            Err(_) => match self.action(stidx, tidx) {
+                Action::Error => vec![],
+                a => vec![a],
+            },
+        }
     }
 
     /// Return an iterator over the indexes of all non-empty actions of `stidx`.
-    pub fn state_actions(&self, stidx: StIdx) -> StateActionsIterator<StorageT> {
+    pub fn state_actions(&self, stidx: StIdx<StIdxT>) -> StateActionsIterator<StorageT> {
         let start = usize::from(stidx) * usize::from(self.tokens_len);
         let end = start + usize::from(self.tokens_len);
         StateActionsIterator {
@@ -402,7 +724,7 @@ where
 
     /// Return an iterator over the indexes of all shift actions of `stidx`. By definition this
     /// is a subset of the indexes produced by [`state_actions`](#method.state_actions).
-    pub fn state_shifts(&self, stidx: StIdx) -> StateActionsIterator<StorageT> {
+    pub fn state_shifts(&self, stidx: StIdx<StIdxT>) -> StateActionsIterator<StorageT> {
         let start = usize::from(stidx) * usize::from(self.tokens_len);
         let end = start + usize::from(self.tokens_len);
         StateActionsIterator {
@@ -414,8 +736,8 @@ where
 
     /// Does the state `stidx` 1) only contain reduce (and error) actions 2) do those
     /// reductions all reduce to the same production?
-    pub fn reduce_only_state(&self, stidx: StIdx) -> bool {
-        self.reduce_states[usize::from(stidx)]
+    pub fn reduce_only_state(&self, stidx: StIdx<StIdxT>) -> bool {
+        self.reduce_states.get(usize::from(stidx))
     }
 
     /// Return an iterator over a set of "core" reduces of `stidx`. This is a minimal set of
@@ -433,7 +755,7 @@ where
     ///   And:    [F -> c., $]
     ///
     /// since the two [E -> ...] items both have the same effects on a parse stack.
-    pub fn core_reduces(&self, stidx: StIdx) -> CoreReducesIterator<StorageT> {
+    pub fn core_reduces(&self, stidx: StIdx<StIdxT>) -> CoreReducesIterator<StorageT> {
         let start = usize::from(stidx) * usize::from(self.prods_len);
         let end = start + usize::from(self.prods_len);
         CoreReducesIterator {
@@ -444,39 +766,302 @@ where
     }
 
     /// Return the goto state for `stidx` and `ridx`, or `None` if there isn't any.
-    pub fn goto(&self, stidx: StIdx, ridx: RIdx<StorageT>) -> Option<StIdx> {
+    pub fn goto(&self, stidx: StIdx<StIdxT>, ridx: RIdx<StorageT>) -> Option<StIdx<StIdxT>> {
         // Goto entries are encoded by adding 1 to their value, while 0 is reserved for no entry
         // (i.e. error)
         match self.gotos.get(usize::from(stidx), usize::from(ridx)) {
-            Some(0) => None,
-            // gotos can only contain state id's which we know can fit into StIdxStorageT so this
+            0 => None,
+            // gotos can only contain state id's which we know can fit into StorageT so this
             // cast is safe
-            Some(i) => Some(StIdx((i - 1) as StIdxStorageT)),
-            None => unreachable!(),
+            i => Some(StIdx((i - 1).as_())),
         }
     }
 
-    /// Return this state table's start state.
-    pub fn start_state(&self) -> StIdx {
-        self.start_state
+    /// Return this state table's start state (i.e. the start state for the grammar's first
+    /// `%start` rule).
+    pub fn start_state(&self) -> StIdx<StIdxT> {
+        self.start_states[0]
+    }
+
+    /// Return this state table's start states: one for each of the grammar's start productions, in
+    /// the same order as `YaccGrammar::start_prods`.
+    pub fn start_states(&self) -> &[StIdx<StIdxT>] {
+        &self.start_states
     }
 
     /// Return a struct containing all conflicts or `None` if there aren't any.
-    pub fn conflicts(&self) -> Option<&Conflicts<StorageT>> {
+    pub fn conflicts(&self) -> Option<&Conflicts<StorageT, StIdxT>> {
         self.conflicts.as_ref()
     }
+
+    /// Return how every conflict in the state table, including those silently resolved by
+    /// precedence, was resolved.
+    pub fn resolutions(&self) -> &[ConflictResolution<StorageT, StIdxT>] {
+        &self.resolutions
+    }
+
+    /// Return a `UselessPrecedence` warning for each token in `grm` whose
+    /// precedence didn't resolve any of this state table's conflicts, ordered by their position
+    /// in the grammar source. `grm` must be the grammar this state table was created from.
+    pub fn precedence_warnings(&self, grm: &YaccGrammar<StorageT>) -> Vec<YaccGrammarWarning> {
+        let mut used = HashSet::new();
+        for r in self.resolutions.iter().filter(|r| !r.is_reported()) {
+            used.extend(grm.token_name(r.tidx));
+            if let Action::Reduce(pidx) = r.actions.1 {
+                used.extend(grm.prod_precedence_name(pidx));
+            }
+        }
+        grm.precedence_names()
+            .iter()
+            .filter(|(n, _)| !used.contains(&**n))
+            .map(|(n, span)| YaccGrammarWarning {
+                kind: YaccGrammarWarningKind::UselessPrecedence,
+                name: n.to_string(),
+                span: *span,
+            })
+            .collect()
+    }
+
+    /// Return an explanation of the conflicts (including those silently resolved by precedence)
+    /// in each state which has any, in state order. `sg` must be the state graph this state table
+    /// was created from.
+    pub fn explain_conflicts(
+        &self,
+        grm: &YaccGrammar<StorageT>,
+        sg: &StateGraph<StorageT>,
+    ) -> Vec<ConflictExplanation<StorageT, StIdxT>> {
+        let mut by_state: Vec<(StIdx<StIdxT>, Vec<ConflictResolution<StorageT, StIdxT>>)> =
+            Vec::new();
+        for r in &self.resolutions {
+            match by_state.iter_mut().find(|(stidx, _)| *stidx == r.stidx) {
+                Some((_, rs)) => rs.push(r.clone()),
+                None => by_state.push((r.stidx, vec![r.clone()])),
+            }
+        }
+        by_state.sort_by_key(|(stidx, _)| usize::from(*stidx));
+
+        let mut explanations = Vec::with_capacity(by_state.len());
+        for (stidx, resolutions) in by_state {
+            let mut lookahead = resolutions.iter().map(|r| r.tidx).collect::<Vec<_>>();
+            lookahead.sort();
+            lookahead.dedup();
+            let mut items = sg
+                .closed_state(stidx.cast::<u32>())
+                .items
+                .iter()
+                .filter(|(&(pidx, dot), _)| {
+                    match grm.prod(pidx).get(usize::from(dot)) {
+                        // A reduce item is involved if one of the conflicting actions reduces it.
+                        None => resolutions.iter().any(|r| {
+                            r.actions.0 == Action::Reduce(pidx)
+                                || r.actions.1 == Action::Reduce(pidx)
+                        }),
+                        // A shift item is involved if it shifts a token in a shift/reduce
+                        // conflict.
+                        Some(&Symbol::Token(tidx)) => resolutions
+                            .iter()
+                            .any(|r| r.tidx == tidx && matches!(r.actions.0, Action::Shift(_))),
+                        Some(&Symbol::Rule(_)) => false,
+                    }
+                })
+                .map(|(&(pidx, dot), ctx)| ConflictItem {
+                    pidx,
+                    dot,
+                    lookahead: ctx.iter_set_bits(..).map(|tidx| TIdx(tidx.as_())).collect(),
+                })
+                .collect::<Vec<_>>();
+            items.sort_by_key(|i| (i.pidx, i.dot));
+            explanations.push(ConflictExplanation {
+                stidx,
+                items,
+                lookahead,
+                resolutions,
+            });
+        }
+        explanations
+    }
+
+    /// Return Rust source code for an expression which evaluates (in a `const` context) to a
+    /// state table equivalent to this one, borrowing all of its data from `static` arrays, where
+    /// `lrtable` is a path to this crate (e.g. a re-export of it). The expression's `StorageT` and
+    /// `StIdxT` are inferred from the context it is used in. The actions and gotos are stored as
+    /// comb vectors even if this table has not been [compressed](#method.compress); the conflicts
+    /// and resolutions are not included.
+    #[doc(hidden)]
+    pub fn _to_static(&self, lrtable: &str) -> String {
+        fn list<T>(xs: &[T], f: impl Fn(&T) -> String) -> String {
+            format!("&[{}]", xs.iter().map(f).collect::<Vec<_>>().join(", "))
+        }
+        let states_len = self.reduce_states.len();
+        let comb = |cells: &Cells, row_length: usize| {
+            let cv = cells.to_combvec(states_len, row_length);
+            let parts = cv
+                .parts()
+                .iter()
+                .map(|p| list(p, u32::to_string))
+                .collect::<Vec<_>>();
+            format!("({}, [{}])", row_length, parts.join(", "))
+        };
+        let bits = |bv: &BitVec| format!("({}, {})", bv.len(), list(bv.blocks(), u32::to_string));
+        let stidx = |stidx: StIdx<StIdxT>| format!("StIdx::_from_static({})", usize::from(stidx));
+        let action = |a: &Action<StorageT, StIdxT>| match *a {
+            Action::Shift(s) => format!("Action::Shift({})", stidx(s)),
+            Action::Reduce(pidx) => format!("Action::Reduce(PIdx({}))", usize::from(pidx)),
+            Action::Accept => "Action::Accept".to_owned(),
+            Action::Error => "Action::Error".to_owned(),
+        };
+        let fields = [
+            ("actions", comb(&self.actions, usize::from(self.tokens_len))),
+            ("state_actions", bits(&self.state_actions)),
+            ("gotos", comb(&self.gotos, usize::from(self.rules_len))),
+            (
+                "default_reduces",
+                list(&self.default_reduces, |d| match d {
+                    Some(pidx) => format!("Some(PIdx({}))", usize::from(*pidx)),
+                    None => "None".to_owned(),
+                }),
+            ),
+            ("start_states", list(&self.start_states, |s| stidx(*s))),
+            ("core_reduces", bits(&self.core_reduces)),
+            ("state_shifts", bits(&self.state_shifts)),
+            ("reduce_states", bits(&self.reduce_states)),
+            (
+                "prods_len",
+                format!("PIdx({})", usize::from(self.prods_len)),
+            ),
+            (
+                "rules_len",
+                format!("RIdx({})", usize::from(self.rules_len)),
+            ),
+            (
+                "tokens_len",
+                format!("TIdx({})", usize::from(self.tokens_len)),
+            ),
+            (
+                "glr_actions",
+                list(&self.glr_actions, |(off, acts)| {
+                    format!("({}, MaybeStatic::Static({}))", off, list(acts, action))
+                }),
+            ),
+            ("final_state", stidx(self.final_state)),
+        ];
+        format!(
+            "{{
+    #[allow(unused_imports)]
+    use ::std::option::Option::{{None, Some}};
+    #[allow(unused_imports)]
+    use ::cfgrammar::{{MaybeStatic, PIdx, RIdx, TIdx}};
+    #[allow(unused_imports)]
+    use {lrtable}::{{statetable::_StaticStateTable, Action, StIdx, StateTable}};
+    StateTable::_from_static(_StaticStateTable {{
+{fields}
+    }})
+}}",
+            lrtable = lrtable,
+            fields = fields
+                .iter()
+                .map(|(f, v)| format!("        {}: {},", f, v))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }
+}
+
+impl<StorageT: Copy, StIdxT: Copy> StateTable<StorageT, StIdxT> {
+    /// Create a state table which borrows all of its data from `parts` (see
+    /// [`_to_static`](#method._to_static)).
+    #[doc(hidden)]
+    pub const fn _from_static(parts: _StaticStateTable<StorageT, StIdxT>) -> Self {
+        StateTable {
+            actions: Cells::Comb(CombVec::from_static(parts.actions.0, parts.actions.1)),
+            state_actions: BitVec::from_static(parts.state_actions.0, parts.state_actions.1),
+            gotos: Cells::Comb(CombVec::from_static(parts.gotos.0, parts.gotos.1)),
+            default_reduces: MaybeStatic::Static(parts.default_reduces),
+            start_states: MaybeStatic::Static(parts.start_states),
+            core_reduces: BitVec::from_static(parts.core_reduces.0, parts.core_reduces.1),
+            state_shifts: BitVec::from_static(parts.state_shifts.0, parts.state_shifts.1),
+            reduce_states: BitVec::from_static(parts.reduce_states.0, parts.reduce_states.1),
+            prods_len: parts.prods_len,
+            rules_len: parts.rules_len,
+            tokens_len: parts.tokens_len,
+            conflicts: None,
+            resolutions: Vec::new(),
+            glr_actions: MaybeStatic::Static(parts.glr_actions),
+            final_state: parts.final_state,
+        }
+    }
+}
+
+/// The `static` data of a state table created by
+/// [`StateTable::_from_static`](struct.StateTable.html#method._from_static). Comb vectors are
+/// given as their row length and their `rows`, `base`, `next`, and `check` arrays; bit vectors as
+/// their length and their `u32` blocks. Every other field is as the field of the same name in
+/// `StateTable`.
+#[doc(hidden)]
+pub struct _StaticStateTable<StorageT: 'static, StIdxT: 'static> {
+    pub actions: (usize, [&'static [u32]; 4]),
+    pub state_actions: (usize, &'static [u32]),
+    pub gotos: (usize, [&'static [u32]; 4]),
+    pub default_reduces: &'static [Option<PIdx<StorageT>>],
+    pub start_states: &'static [StIdx<StIdxT>],
+    pub core_reduces: (usize, &'static [u32]),
+    pub state_shifts: (usize, &'static [u32]),
+    pub reduce_states: (usize, &'static [u32]),
+    pub prods_len: PIdx<StorageT>,
+    pub rules_len: RIdx<StorageT>,
+    pub tokens_len: TIdx<StorageT>,
+    pub glr_actions: &'static [(usize, MaybeStatic<Action<StorageT, StIdxT>>)],
+    pub final_state: StIdx<StIdxT>,
+}
+
+/// A summary of the space saved by [`StateTable::compress`](struct.StateTable.html#method.compress).
+/// "Cells" are the cells of the uncompressed table; "entries" are the non-error (or, for gotos,
+/// non-empty) cells; "rows" are the unique rows left after default reductions have been applied;
+/// and "slots" are the length of the comb vector the rows were packed into.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CompressionStats {
+    pub states: usize,
+    pub default_reduces: usize,
+    pub action_cells: usize,
+    pub action_entries: usize,
+    pub action_rows: usize,
+    pub action_slots: usize,
+    pub goto_cells: usize,
+    pub goto_entries: usize,
+    pub goto_rows: usize,
+    pub goto_slots: usize,
+}
+
+impl fmt::Display for CompressionStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "Default reductions: {} of {} states",
+            self.default_reduces, self.states
+        )?;
+        writeln!(
+            f,
+            "Actions: {} cells ({} entries) -> {} rows packed into {} slots",
+            self.action_cells, self.action_entries, self.action_rows, self.action_slots
+        )?;
+        write!(
+            f,
+            "Gotos: {} cells ({} entries) -> {} rows packed into {} slots",
+            self.goto_cells, self.goto_entries, self.goto_rows, self.goto_slots
+        )
+    }
 }
 
-fn actions_offset<StorageT: PrimInt + Unsigned>(
+fn actions_offset<StorageT: PrimInt + Unsigned, StIdxT: AsPrimitive<usize>>(
     tokens_len: TIdx<StorageT>,
-    stidx: StIdx,
+    stidx: StIdx<StIdxT>,
     tidx: TIdx<StorageT>,
 ) -> usize {
     usize::from(stidx) * usize::from(tokens_len) + usize::from(tidx)
 }
 
 pub struct StateActionsIterator<'a, StorageT> {
-    iter: IterSetBits<'a, usize>,
+    iter: bitvec::IterSetBits<'a>,
     start: usize,
     phantom: PhantomData<StorageT>,
 }
@@ -495,7 +1080,7 @@ where
 }
 
 pub struct CoreReducesIterator<'a, StorageT> {
-    iter: IterSetBits<'a, usize>,
+    iter: bitvec::IterSetBits<'a>,
     start: usize,
     phantom: PhantomData<StorageT>,
 }
@@ -513,76 +1098,128 @@ where
     }
 }
 
-fn resolve_shift_reduce<StorageT: 'static + Hash + PrimInt + Unsigned>(
+fn resolve_shift_reduce<StorageT, StIdxT>(
     grm: &YaccGrammar<StorageT>,
     actions: &mut Vec<usize>,
     off: usize,
     tidx: TIdx<StorageT>,
     pidx: PIdx<StorageT>,
-    stidx: StIdx, // State we want to shift to
-    shift_reduce: &mut Vec<(TIdx<StorageT>, PIdx<StorageT>, StIdx)>,
-    conflict_stidx: StIdx, // State in which the conflict occured
+    stidx: StIdx<StIdxT>, // State we want to shift to
+    shift_reduce: &mut Vec<(TIdx<StorageT>, PIdx<StorageT>, StIdx<StIdxT>)>,
+    resolutions: &mut Vec<ConflictResolution<StorageT, StIdxT>>,
+    conflict_stidx: StIdx<StIdxT>, // State in which the conflict occured
 ) where
-    usize: AsPrimitive<StorageT>,
+    StorageT: 'static + Hash + PrimInt + Unsigned,
+    StIdxT: 'static + Hash + PrimInt + Unsigned + AsPrimitive<usize>,
+    usize: AsPrimitive<StorageT> + AsPrimitive<StIdxT>,
     u32: AsPrimitive<StorageT>,
 {
     let tidx_prec = grm.token_precedence(tidx);
     let pidx_prec = grm.prod_precedence(pidx);
-    match (tidx_prec, pidx_prec) {
+    let (chosen, reason) = match (tidx_prec, pidx_prec) {
         (_, None) | (None, _) => {
             // If the token and production don't both have precedences, we use Yacc's default
             // resolution, which is in favour of the shift.
-            actions[off] = StateTable::encode(Action::Shift(stidx));
             shift_reduce.push((tidx, pidx, conflict_stidx));
+            (Action::Shift(stidx), ResolutionReason::Default)
         }
         (Some(token_prec), Some(prod_prec)) => {
             match token_prec.level.cmp(&prod_prec.level) {
                 Ordering::Equal => {
-                    // Both token and production have the same level precedence, so we need to look
-                    // at the precedence kind.
-                    match (token_prec.kind, prod_prec.kind) {
-                        (AssocKind::Left, AssocKind::Left) => {
+                    // Both token and production have the same level precedence. As in Bison, the
+                    // conflict is resolved by the token's associativity alone.
+                    let assoc = ResolutionReason::Associativity(token_prec.kind);
+                    match token_prec.kind {
+                        AssocKind::Left => {
                             // Left associativity is resolved in favour of the reduce (i.e. leave
                             // as-is).
+                            (Action::Reduce(pidx), assoc)
                         }
-                        (AssocKind::Right, AssocKind::Right) => {
+                        AssocKind::Right => {
                             // Right associativity is resolved in favour of the shift.
-                            actions[off] = StateTable::encode(Action::Shift(stidx));
+                            (Action::Shift(stidx), assoc)
                         }
-                        (AssocKind::Nonassoc, AssocKind::Nonassoc) => {
+                        AssocKind::Nonassoc => {
                             // Nonassociativity leads to a run-time parsing error, so we need to
                             // remove the action entirely.
-                            actions[off] = StateTable::encode(Action::Error);
+                            (Action::Error, assoc)
                         }
-                        (_, _) => {
-                            panic!("Not supported.");
+                        AssocKind::Precedence => {
+                            // Precedence without associativity can't resolve a conflict at the
+                            // same level, so we use Yacc's default resolution in favour of the
+                            // shift, and report the conflict.
+                            shift_reduce.push((tidx, pidx, conflict_stidx));
+                            (Action::Shift(stidx), ResolutionReason::NoAssociativity)
                         }
                     }
                 }
                 Ordering::Greater => {
                     // The token has higher level precedence, so resolve in favour of shift.
-                    actions[off] = StateTable::encode(Action::Shift(stidx));
+                    (Action::Shift(stidx), ResolutionReason::TokenPrecedence)
                 }
                 Ordering::Less => {
                     // If token_lev < prod_lev, then the production has higher level precedence and
                     // we keep the reduce as-is.
+                    (Action::Reduce(pidx), ResolutionReason::ProductionPrecedence)
                 }
             }
         }
-    }
+    };
+    actions[off] = StateTable::<StorageT, StIdxT>::encode(chosen);
+    resolutions.push(ConflictResolution {
+        stidx: conflict_stidx,
+        tidx,
+        actions: (Action::Shift(stidx), Action::Reduce(pidx)),
+        chosen,
+        reason,
+    });
 }
 
 #[cfg(test)]
 mod test {
     use cfgrammar::{
-        yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind},
-        PIdx, Symbol, TIdx,
+        yacc::{AssocKind, YaccGrammar, YaccGrammarWarningKind, YaccKind, YaccOriginalActionKind},
+        MaybeStatic, PIdx, Symbol, TIdx,
     };
     use std::collections::HashSet;
 
-    use super::{Action, StateTable, StateTableError, StateTableErrorKind};
+    use super::{
+        _StaticStateTable, Action, BitVec, Cells, ResolutionReason, StateTable, StateTableError,
+        StateTableErrorKind,
+    };
     use crate::{pager::pager_stategraph, StIdx};
 
+    #[test]
+    #[rustfmt::skip]
+    fn test_multiple_start_states() {
+        let grm = YaccGrammar::new(
+            YaccKind::Grmtools,
+            "
+            %start Expr Term
+            %%
+            Expr -> (): Term '-' Expr { } | Term { };
+            Term -> (): Factor '*' Term { } | Factor { };
+            Factor -> (): 'id' { };
+          "
+        ).unwrap();
+        assert_eq!(grm.start_prods().len(), 2);
+        let sg = pager_stategraph(&grm);
+        assert_eq!(sg.start_states(), &[StIdx(0), StIdx(1)]);
+        let st = StateTable::new(&grm, &sg).unwrap();
+        assert_eq!(st.start_states(), sg.start_states());
+        assert_eq!(st.start_state(), StIdx(0));
+
+        // Each start state can only accept input derived from its own rule.
+        let expr = sg.edge(StIdx(0), Symbol::Rule(grm.rule_idx("Expr").unwrap())).unwrap();
+        let term = sg.edge(StIdx(1), Symbol::Rule(grm.rule_idx("Term").unwrap())).unwrap();
+        assert_eq!(sg.edge(StIdx(1), Symbol::Rule(grm.rule_idx("Expr").unwrap())), None);
+        assert_eq!(st.action(expr, grm.eof_token_idx()), Action::Accept);
+        assert_eq!(st.action(term, grm.eof_token_idx()), Action::Accept);
+        assert_eq!(st.final_state, expr);
+        assert_eq!(st.action(term, grm.token_idx("-").unwrap()), Action::Error);
+        assert!(st.conflicts().is_none());
+    }
+
     #[test]
     #[rustfmt::skip]
     fn test_statetable() {
@@ -614,7 +1251,7 @@ mod test {
 
         // Actions
         assert_eq!(st.actions.len(), 9*4);
-        let assert_reduce = |stidx: StIdx, tidx: TIdx<_>, rule: &str, prod_off: usize| {
+        let assert_reduce = |stidx: StIdx<_>, tidx: TIdx<_>, rule: &str, prod_off: usize| {
             let pidx = grm.rule_to_prods(grm.rule_idx(rule).unwrap())[prod_off];
             assert_eq!(st.action(stidx, tidx), Action::Reduce(pidx));
         };
@@ -717,6 +1354,15 @@ mod test {
 
         assert_eq!(st.action(s6, grm.token_idx("+").unwrap()), Action::Shift(s3));
         assert_eq!(st.action(s6, grm.token_idx("*").unwrap()), Action::Shift(s4));
+
+        // The discarded reductions are still available to GLR parsers.
+        let prods = grm.rule_to_prods(grm.rule_idx("Expr").unwrap());
+        assert_eq!(st.glr_actions(s6, grm.token_idx("+").unwrap()),
+                   vec![Action::Shift(s3), Action::Reduce(prods[0])]);
+        assert_eq!(st.glr_actions(s5, grm.token_idx("*").unwrap()),
+                   vec![Action::Shift(s4), Action::Reduce(prods[1])]);
+        assert_eq!(st.glr_actions(s6, grm.eof_token_idx()), vec![Action::Reduce(prods[0])]);
+        assert_eq!(st.glr_actions(s0, grm.token_idx("+").unwrap()), vec![]);
     }
 
     #[test]
@@ -915,6 +1561,225 @@ mod test {
                    Action::Reduce(grm.rule_to_prods(grm.rule_idx("Expr").unwrap())[0]));
     }
 
+    #[test]
+    fn test_precedence_without_associativity() {
+        let grm = YaccGrammar::new(
+            YaccKind::Bison,
+            "
+%precedence '*'
+%precedence '-'
+%%
+Expr: Expr '*' Expr | '-' Expr | 'id';
+          ",
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::new(&grm, &sg).unwrap();
+
+        let s0 = sg.start_state();
+        let s1 = sg
+            .edge(s0, Symbol::Rule(grm.rule_idx("Expr").unwrap()))
+            .unwrap();
+        let s2 = sg
+            .edge(s1, Symbol::Token(grm.token_idx("*").unwrap()))
+            .unwrap();
+        let s3 = sg
+            .edge(s2, Symbol::Rule(grm.rule_idx("Expr").unwrap()))
+            .unwrap();
+        let s4 = sg
+            .edge(s0, Symbol::Token(grm.token_idx("-").unwrap()))
+            .unwrap();
+        let s5 = sg
+            .edge(s4, Symbol::Rule(grm.rule_idx("Expr").unwrap()))
+            .unwrap();
+        // Different precedence levels are resolved as normal...
+        assert_eq!(
+            st.action(s5, grm.token_idx("*").unwrap()),
+            Action::Reduce(grm.rule_to_prods(grm.rule_idx("Expr").unwrap())[1])
+        );
+        // ...but the same level is left as a conflict, resolved in favour of the shift.
+        assert_eq!(
+            st.action(s3, grm.token_idx("*").unwrap()),
+            Action::Shift(s2)
+        );
+        let conflicts = st.conflicts().unwrap();
+        assert_eq!(conflicts.sr_len(), 1);
+        assert_eq!(conflicts.rr_len(), 0);
+        // Both precedences resolved the conflict between `'-' Expr` and '*'.
+        assert!(st.precedence_warnings(&grm).is_empty());
+    }
+
+    #[test]
+    fn test_precedence_warnings() {
+        let precedence_warnings = |src| {
+            let grm = YaccGrammar::new(
+                YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+                src,
+            )
+            .unwrap();
+            let sg = pager_stategraph(&grm);
+            let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+            st.precedence_warnings(&grm)
+                .iter()
+                .map(|w| {
+                    assert_eq!(w.kind, YaccGrammarWarningKind::UselessPrecedence);
+                    w.name.clone()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Tokens which aren't used in the grammar, or which are used but never in a conflict,
+        // have useless precedences.
+        assert_eq!(
+            precedence_warnings("%start A\n%left '+' '*'\n%%\nA: A '+' A | 'a';"),
+            vec!["*"]
+        );
+        assert_eq!(
+            precedence_warnings("%start A\n%left '+'\n%right '^'\n%%\nA: 'a' '+' 'a' | 'a' '^';"),
+            vec!["+", "^"]
+        );
+        // A `%prec` token is used if a conflict involving its production is resolved by
+        // precedence.
+        assert_eq!(
+            precedence_warnings(
+                "%start E\n%token 'UMINUS'\n%left '+'\n%right 'UMINUS' 'UNUSED'\n%%\nE: E '+' E | '-' E %prec 'UMINUS' | 'a';"
+            ),
+            vec!["UNUSED"]
+        );
+    }
+
+    #[test]
+    fn test_mixed_associativity_same_level() {
+        // The parser never puts tokens with different associativities at the same level, but an
+        // AST built by hand can: the conflict is then resolved by the token's associativity.
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "
+%start Expr
+%left '+'
+%right '*'
+%%
+Expr: Expr '+' Expr | Expr '*' Expr | 'id';
+          ",
+        )
+        .unwrap();
+        let mut ast = grm.ast();
+        let plus_level = ast.precs["+"].level;
+        ast.precs.get_mut("*").unwrap().level = plus_level;
+        let grm = YaccGrammar::<u32>::from_ast(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            ast,
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::new(&grm, &sg).unwrap();
+
+        let expr = grm.rule_idx("Expr").unwrap();
+        let s0 = sg.start_state();
+        let s1 = sg.edge(s0, Symbol::Rule(expr)).unwrap();
+        let s2 = sg
+            .edge(s1, Symbol::Token(grm.token_idx("+").unwrap()))
+            .unwrap();
+        let s3 = sg.edge(s2, Symbol::Rule(expr)).unwrap();
+        let s4 = sg
+            .edge(s1, Symbol::Token(grm.token_idx("*").unwrap()))
+            .unwrap();
+        let s5 = sg.edge(s4, Symbol::Rule(expr)).unwrap();
+        // `Expr '+' Expr . '*'`: '*' is right associative, so shift.
+        assert_eq!(
+            st.action(s3, grm.token_idx("*").unwrap()),
+            Action::Shift(s4)
+        );
+        // `Expr '*' Expr . '+'`: '+' is left associative, so reduce.
+        assert_eq!(
+            st.action(s5, grm.token_idx("+").unwrap()),
+            Action::Reduce(grm.rule_to_prods(expr)[1])
+        );
+        assert!(st.conflicts().is_none());
+    }
+
+    #[test]
+    fn test_conflict_resolutions() {
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "
+%start Expr
+%left '+'
+%nonassoc '<'
+%%
+Expr: Expr '+' Expr | Expr '<' Expr | 'id';
+          ",
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::new(&grm, &sg).unwrap();
+        // Every conflict is resolved by precedence, so none are reported...
+        assert!(st.conflicts().is_none());
+        // ...but all of them are recorded.
+        assert_eq!(st.resolutions().len(), 4);
+        assert!(st.resolutions().iter().all(|r| !r.is_reported()));
+
+        let plus = grm.token_idx("+").unwrap();
+        let lt = grm.token_idx("<").unwrap();
+        let prods = grm.rule_to_prods(grm.rule_idx("Expr").unwrap());
+        let s0 = sg.start_state();
+        let s1 = sg
+            .edge(s0, Symbol::Rule(grm.rule_idx("Expr").unwrap()))
+            .unwrap();
+        let s_plus = sg.edge(s1, Symbol::Token(plus)).unwrap();
+        let s_lt = sg.edge(s1, Symbol::Token(lt)).unwrap();
+        let s_plus_expr = sg
+            .edge(s_plus, Symbol::Rule(grm.rule_idx("Expr").unwrap()))
+            .unwrap();
+        let s_lt_expr = sg
+            .edge(s_lt, Symbol::Rule(grm.rule_idx("Expr").unwrap()))
+            .unwrap();
+
+        let find = |stidx, tidx| {
+            st.resolutions()
+                .iter()
+                .find(|r| r.stidx == stidx && r.tidx == tidx)
+                .unwrap()
+        };
+        let r = find(s_plus_expr, plus);
+        assert_eq!(r.actions, (Action::Shift(s_plus), Action::Reduce(prods[0])));
+        assert_eq!(r.chosen, Action::Reduce(prods[0]));
+        assert_eq!(r.reason, ResolutionReason::Associativity(AssocKind::Left));
+        let r = find(s_plus_expr, lt);
+        assert_eq!(r.chosen, Action::Shift(s_lt));
+        assert_eq!(r.reason, ResolutionReason::TokenPrecedence);
+        let r = find(s_lt_expr, plus);
+        assert_eq!(r.chosen, Action::Reduce(prods[1]));
+        assert_eq!(r.reason, ResolutionReason::ProductionPrecedence);
+        let r = find(s_lt_expr, lt);
+        assert_eq!(r.chosen, Action::Error);
+        assert_eq!(
+            r.reason,
+            ResolutionReason::Associativity(AssocKind::Nonassoc)
+        );
+
+        let explanations = st.explain_conflicts(&grm, &sg);
+        assert_eq!(explanations.len(), 2);
+        let e = explanations
+            .iter()
+            .find(|e| e.stidx == s_plus_expr)
+            .unwrap();
+        assert_eq!(e.lookahead.len(), 2);
+        assert!(e.lookahead.contains(&plus) && e.lookahead.contains(&lt));
+        assert_eq!(e.resolutions.len(), 2);
+        assert_eq!(
+            e.items
+                .iter()
+                .map(|i| (i.pidx, usize::from(i.dot)))
+                .collect::<Vec<_>>(),
+            vec![(prods[0], 1), (prods[0], 3), (prods[1], 1)]
+        );
+        let reduce = &e.items[1];
+        assert!(reduce.lookahead.contains(&plus));
+        assert!(reduce.lookahead.contains(&lt));
+        assert!(reduce.lookahead.contains(&grm.eof_token_idx()));
+    }
+
     #[test]
     fn conflicts() {
         let grm = YaccGrammar::new(
@@ -929,7 +1794,7 @@ C : 'a';
         )
         .unwrap();
         let sg = pager_stategraph(&grm);
-        let st = StateTable::new(&grm, &sg).unwrap();
+        let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
         let conflicts = st.conflicts().unwrap();
         assert_eq!(conflicts.sr_len(), 1);
         assert_eq!(conflicts.rr_len(), 1);
@@ -938,7 +1803,7 @@ C : 'a';
             &(
                 grm.token_idx("b").unwrap(),
                 grm.rule_to_prods(grm.rule_idx("B").unwrap())[0],
-                StIdx::from(2)
+                StIdx::from(2u16)
             )
         );
         assert_eq!(
@@ -946,11 +1811,32 @@ C : 'a';
             &(
                 grm.rule_to_prods(grm.rule_idx("B").unwrap())[0],
                 grm.rule_to_prods(grm.rule_idx("C").unwrap())[0],
-                StIdx::from(2)
+                StIdx::from(2u16)
             )
         );
     }
 
+    #[test]
+    fn conflicts_pp_with_src() {
+        let src = "%start A\n%%\nA : 'a' 'b' | B 'b';\nB : 'a' | C;\nC : 'a';";
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            src,
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        let conflicts = st.conflicts().unwrap();
+        assert_eq!(
+            conflicts.pp_with_src(&grm, Some(src)),
+            "Shift/Reduce conflicts:
+   State 2: Shift(\"b\") / Reduce(B: \"a\" at line 4 column 5)
+Reduce/Reduce conflicts:
+   State 2: Reduce(B: \"a\" at line 4 column 5) / Reduce(C: \"a\" at line 5 column 5)
+"
+        );
+    }
+
     #[test]
     fn accept_reduce_conflict() {
         let grm = YaccGrammar::new(
@@ -963,13 +1849,184 @@ D : D;
         )
         .unwrap();
         let sg = pager_stategraph(&grm);
-        match StateTable::new(&grm, &sg) {
+        match StateTable::<_, u16>::new(&grm, &sg) {
             Ok(_) => panic!("Infinitely recursive rule let through"),
             Err(StateTableError {
                 kind: StateTableErrorKind::AcceptReduceConflict,
                 pidx,
-            }) if pidx == PIdx(1) => (),
+            }) if pidx == Some(PIdx(1)) => (),
+            Err(e) => panic!("Incorrect error returned {:?}", e),
+        }
+    }
+
+    #[test]
+    fn too_many_states() {
+        let run = vec!["'a'"; 100].join(" ");
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            &format!(
+                "
+%start S
+%%
+S : 'b' {run} | 'c' {run} | 'd' {run};
+          ",
+                run = run
+            ),
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let num_states = usize::from(sg.all_states_len());
+        assert!(num_states > usize::from(u8::MAX));
+        match StateTable::<_, u8>::new(&grm, &sg) {
+            Ok(_) => panic!("Too many states let through"),
+            Err(StateTableError {
+                kind: StateTableErrorKind::TooManyStates(n),
+                pidx: None,
+            }) if n == num_states => (),
             Err(e) => panic!("Incorrect error returned {:?}", e),
         }
+        assert!(StateTable::<_, u16>::new(&grm, &sg).is_ok());
+    }
+
+    #[test]
+    fn test_compress() {
+        let grm = YaccGrammar::new(
+            YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
+            "
+            %start Expr
+            %%
+            Expr : Expr '+' Expr | Expr '*' Expr | Term;
+            Term : Factor '-' Term | Factor;
+            Factor : 'id' | '(' Expr ')';
+          ",
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        let mut cst = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        let stats = cst.compress();
+        assert_eq!(stats.states, usize::from(sg.all_states_len()));
+        assert!(stats.default_reduces > 0);
+        assert!(stats.action_rows < stats.states);
+        assert!(stats.action_slots < stats.action_cells);
+        assert!(stats.goto_slots < stats.goto_cells);
+
+        let s0 = sg.start_state();
+        let s_id = sg
+            .edge(s0, Symbol::Token(grm.token_idx("id").unwrap()))
+            .unwrap();
+        let factor_id = grm.rule_to_prods(grm.rule_idx("Factor").unwrap())[0];
+        assert_eq!(st.default_reduce(s_id.cast()), None);
+        assert_eq!(cst.default_reduce(s_id.cast()), Some(factor_id));
+
+        let check = |cst: &StateTable<u32, u16>| {
+            for i in 0..usize::from(sg.all_states_len()) {
+                let stidx = StIdx::from(i as u16);
+                for tidx in grm.iter_tidxs() {
+                    assert_eq!(st.action(stidx, tidx), cst.action(stidx, tidx));
+                    assert_eq!(st.glr_actions(stidx, tidx), cst.glr_actions(stidx, tidx));
+                }
+                for ridx in grm.iter_rules() {
+                    assert_eq!(st.goto(stidx, ridx), cst.goto(stidx, ridx));
+                }
+                assert_eq!(
+                    st.state_actions(stidx).collect::<Vec<_>>(),
+                    cst.state_actions(stidx).collect::<Vec<_>>()
+                );
+            }
+        };
+        check(&cst);
+        // Compressing an already compressed table changes nothing.
+        assert_eq!(cst.compress(), stats);
+        check(&cst);
+    }
+
+    /// Return a state table equivalent to `st` which borrows its data from (leaked) `static`
+    /// arrays, as those generated by `_to_static` do.
+    fn static_copy(st: &StateTable<u32, u16>) -> StateTable<u32, u16> {
+        fn leak<T: Clone>(xs: &[T]) -> &'static [T] {
+            Box::leak(xs.to_vec().into_boxed_slice())
+        }
+        let states_len = st.reduce_states.len();
+        let comb = |cells: &Cells, row_length: usize| {
+            let cv = cells.to_combvec(states_len, row_length);
+            let [rows, base, next, check] = cv.parts();
+            (
+                row_length,
+                [leak(rows), leak(base), leak(next), leak(check)],
+            )
+        };
+        let bits = |bv: &BitVec| (bv.len(), leak(bv.blocks()));
+        StateTable::_from_static(_StaticStateTable {
+            actions: comb(&st.actions, usize::from(st.tokens_len)),
+            state_actions: bits(&st.state_actions),
+            gotos: comb(&st.gotos, usize::from(st.rules_len)),
+            default_reduces: leak(&st.default_reduces),
+            start_states: leak(&st.start_states),
+            core_reduces: bits(&st.core_reduces),
+            state_shifts: bits(&st.state_shifts),
+            reduce_states: bits(&st.reduce_states),
+            prods_len: st.prods_len,
+            rules_len: st.rules_len,
+            tokens_len: st.tokens_len,
+            glr_actions: leak(
+                &st.glr_actions
+                    .iter()
+                    .map(|(off, acts)| (*off, MaybeStatic::Static(leak(acts))))
+                    .collect::<Vec<_>>(),
+            ),
+            final_state: st.final_state,
+        })
+    }
+
+    #[test]
+    fn test_from_static() {
+        let grm = YaccGrammar::new(
+            YaccKind::Grmtools,
+            "
+            %start Expr Term
+            %%
+            Expr -> (): Expr '+' Expr { } | Term { };
+            Term -> (): Factor '*' Term { } | Factor { };
+            Factor -> (): 'id' { } | '(' Expr ')' { };
+          ",
+        )
+        .unwrap();
+        let sg = pager_stategraph(&grm);
+        let mut st = StateTable::<_, u16>::new(&grm, &sg).unwrap();
+        assert!(!st.glr_actions.is_empty());
+        for compress in &[false, true] {
+            if *compress {
+                st.compress();
+            }
+            let sst = static_copy(&st);
+            assert!(sst.conflicts().is_none());
+            assert_eq!(sst.start_states(), st.start_states());
+            assert_eq!(sst.final_state, st.final_state);
+            for i in 0..usize::from(sg.all_states_len()) {
+                let stidx = StIdx::from(i as u16);
+                for tidx in grm.iter_tidxs() {
+                    assert_eq!(st.action(stidx, tidx), sst.action(stidx, tidx));
+                    assert_eq!(st.glr_actions(stidx, tidx), sst.glr_actions(stidx, tidx));
+                }
+                for ridx in grm.iter_rules() {
+                    assert_eq!(st.goto(stidx, ridx), sst.goto(stidx, ridx));
+                }
+                assert_eq!(
+                    st.state_actions(stidx).collect::<Vec<_>>(),
+                    sst.state_actions(stidx).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    st.state_shifts(stidx).collect::<Vec<_>>(),
+                    sst.state_shifts(stidx).collect::<Vec<_>>()
+                );
+                assert_eq!(
+                    st.core_reduces(stidx).collect::<Vec<_>>(),
+                    sst.core_reduces(stidx).collect::<Vec<_>>()
+                );
+                assert_eq!(st.reduce_only_state(stidx), sst.reduce_only_state(stidx));
+                assert_eq!(st.default_reduce(stidx), sst.default_reduce(stidx));
+            }
+        }
     }
 }
@@ -6,11 +6,11 @@ use std::{
     process,
 };
 
-use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind};
+use cfgrammar::yacc::{YaccGrammar, YaccKind, YaccOriginalActionKind, YaccSource};
 use getopts::Options;
 use lrlex::{LRNonStreamingLexerDef, LexerDef};
 use lrpar::parser::{RTParserBuilder, RecoveryKind};
-use lrtable::{from_yacc, Minimiser};
+use lrtable::{from_yacc, report::report, Minimiser, StateTable};
 use num_traits::ToPrimitive;
 
 fn usage(prog: &str, msg: &str) -> ! {
@@ -24,13 +24,55 @@ fn usage(prog: &str, msg: &str) -> ! {
     }
     writeln!(
         &mut stderr(),
-        "Usage: {} [-r <cpctplus|none>] [-y <eco|grmtools|original>] [-q] <lexer.l> <parser.y> <input file>",
+        "Usage: {} [-c <minimiser>] [-m <minimiser>] [-r <cpctplus|none>] [-y <bison|eco|grmtools|original>] [-q] [-v] <lexer.l> <parser.y> <input file>",
         leaf
     )
     .ok();
+    writeln!(
+        &mut stderr(),
+        "where <minimiser> is one of: ielr, lalr, lr0, lr1, pager, slr"
+    )
+    .ok();
     process::exit(1);
 }
 
+/// Convert the name of a minimiser (e.g. `pager`) into a `Minimiser`.
+fn minimiser(prog: &str, name: &str) -> Minimiser {
+    match &*name.to_lowercase() {
+        "ielr" => Minimiser::IELR,
+        "lalr" => Minimiser::LALR,
+        "lr0" => Minimiser::LR0,
+        "lr1" => Minimiser::LR1,
+        "pager" => Minimiser::Pager,
+        "slr" => Minimiser::SLR,
+        _ => usage(prog, &format!("Unknown minimiser '{}'.", name)),
+    }
+}
+
+/// Return the text `left` and `right` side by side as a `String`, with `left_title` and
+/// `right_title` as the titles of the two columns.
+fn side_by_side(left_title: &str, left: &str, right_title: &str, right: &str) -> String {
+    let left = [left_title, ""]
+        .iter()
+        .cloned()
+        .chain(left.lines())
+        .collect::<Vec<_>>();
+    let right = [right_title, ""]
+        .iter()
+        .cloned()
+        .chain(right.lines())
+        .collect::<Vec<_>>();
+    let width = left.iter().map(|l| l.chars().count()).max().unwrap();
+    let mut s = String::new();
+    for i in 0..left.len().max(right.len()) {
+        let l = left.get(i).cloned().unwrap_or("");
+        let r = right.get(i).cloned().unwrap_or("");
+        s.push_str(format!("{:width$} | {}", l, r, width = width).trim_end());
+        s.push('\n');
+    }
+    s
+}
+
 fn read_file(path: &str) -> String {
     let mut f = match File::open(path) {
         Ok(r) => r,
@@ -48,19 +90,41 @@ fn main() {
     let args: Vec<String> = env::args().collect();
     let prog = &args[0];
     let matches = match Options::new()
+        .optopt(
+            "c",
+            "compare",
+            "Print the conflicts found with a second state graph algorithm side by side with \
+             those found with the algorithm selected by -m",
+            "MINIMISER",
+        )
         .optflag("h", "help", "")
-        .optflag("q", "quiet", "Don't print warnings such as conflicts")
+        .optopt(
+            "m",
+            "minimiser",
+            "State graph algorithm to be used (default: pager)",
+            "MINIMISER",
+        )
+        .optflag(
+            "q",
+            "quiet",
+            "Don't print warnings such as conflicts or unused tokens",
+        )
         .optopt(
             "r",
             "recoverer",
             "Recoverer to be used (default: cpctplus)",
             "cpctplus|none",
         )
+        .optflag(
+            "v",
+            "verbose",
+            "Print a verbose report of the grammar's state table (similar to bison -v)",
+        )
         .optopt(
             "y",
             "yaccvariant",
             "Yacc variant to be parsed (default: original)",
-            "eco|original|grmtools",
+            "bison|eco|original|grmtools",
         )
         .parse(&args[1..])
     {
@@ -73,6 +137,7 @@ fn main() {
     }
 
     let quiet = matches.opt_present("q");
+    let verbose = matches.opt_present("v");
 
     let recoverykind = match matches.opt_str("r") {
         None => RecoveryKind::CPCTPlus,
@@ -83,9 +148,16 @@ fn main() {
         },
     };
 
+    let m = match matches.opt_str("m") {
+        None => Minimiser::Pager,
+        Some(s) => minimiser(prog, &s),
+    };
+    let compare_m = matches.opt_str("c").map(|s| (minimiser(prog, &s), s));
+
     let yacckind = match matches.opt_str("y") {
         None => YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
         Some(s) => match &*s.to_lowercase() {
+            "bison" => YaccKind::Bison,
             "eco" => YaccKind::Eco,
             "grmtools" => YaccKind::Grmtools,
             "original" => YaccKind::Original(YaccOriginalActionKind::GenericParseTree),
@@ -98,7 +170,7 @@ fn main() {
     }
 
     let lex_l_path = &matches.free[0];
-    let mut lexerdef = match LRNonStreamingLexerDef::<u16>::from_str(&read_file(lex_l_path)) {
+    let mut lexerdef = match LRNonStreamingLexerDef::<u32>::from_str(&read_file(lex_l_path)) {
         Ok(ast) => ast,
         Err(s) => {
             writeln!(&mut stderr(), "{}: {}", &lex_l_path, &s).ok();
@@ -107,14 +179,36 @@ fn main() {
     };
 
     let yacc_y_path = &matches.free[1];
-    let grm = match YaccGrammar::<u16>::new_with_storaget(yacckind, &read_file(yacc_y_path)) {
+    let yacc_source = match YaccSource::read(yacc_y_path) {
         Ok(x) => x,
-        Err(s) => {
-            writeln!(&mut stderr(), "{}: {}", &yacc_y_path, &s).ok();
+        Err(e) => {
+            writeln!(&mut stderr(), "{}", e).ok();
             process::exit(1);
         }
     };
-    let (sgraph, stable) = match from_yacc(&grm, Minimiser::Pager) {
+    let yacc_src = yacc_source.src();
+    let grm = match YaccGrammar::<u32>::from_source(yacckind, &yacc_source) {
+        Ok(x) => x,
+        Err(errs) => {
+            for e in errs {
+                writeln!(&mut stderr(), "{}: {}", &yacc_y_path, &e).ok();
+            }
+            process::exit(1);
+        }
+    };
+    if !quiet {
+        for w in grm.warnings() {
+            writeln!(
+                &mut stderr(),
+                "{}: Warning: {}",
+                &yacc_y_path,
+                w.pp_with_source(&yacc_source)
+            )
+            .ok();
+        }
+    }
+
+    let (sgraph, stable) = match from_yacc::<_, u32>(&grm, m) {
         Ok(x) => x,
         Err(s) => {
             writeln!(&mut stderr(), "{}: {}", &yacc_y_path, &s).ok();
@@ -122,18 +216,76 @@ fn main() {
         }
     };
 
+    if !quiet {
+        for w in stable.precedence_warnings(&grm) {
+            writeln!(
+                &mut stderr(),
+                "{}: Warning: {}",
+                &yacc_y_path,
+                w.pp_with_source(&yacc_source)
+            )
+            .ok();
+        }
+    }
+
+    if verbose {
+        println!("{}\n", report(&grm, &sgraph, &stable, Some(&yacc_source)));
+    }
+
     if !quiet {
         if let Some(c) = stable.conflicts() {
-            println!("{}", c.pp(&grm));
+            println!("{}", c.pp_with_src(&grm, Some(yacc_src)));
+            println!("Counterexamples:\n{}", c.pp_counterexamples(&grm, &sgraph));
             println!("Stategraph:\n{}\n", sgraph.pp_core_states(&grm));
         }
     }
 
+    if let Some((compare_m, compare_name)) = compare_m {
+        let compare_stable = match from_yacc(&grm, compare_m) {
+            Ok((_, x)) => x,
+            Err(s) => {
+                writeln!(&mut stderr(), "{}: {}", &yacc_y_path, &s).ok();
+                process::exit(1);
+            }
+        };
+        let pp_conflicts = |stable: &StateTable<u32, u32>| match stable.conflicts() {
+            Some(c) => c.pp_with_src(&grm, Some(yacc_src)),
+            None => "No conflicts\n".to_owned(),
+        };
+        println!(
+            "{}",
+            side_by_side(
+                &matches.opt_str("m").unwrap_or_else(|| "pager".to_owned()),
+                &pp_conflicts(&stable),
+                &compare_name,
+                &pp_conflicts(&compare_stable)
+            )
+        );
+    }
+
+    if grm.expect().is_some() || grm.expectrr().is_some() {
+        let (sr_len, rr_len) = stable
+            .conflicts()
+            .map(|c| (c.sr_len(), c.rr_len()))
+            .unwrap_or((0, 0));
+        let expect = grm.expect().unwrap_or(0);
+        let expectrr = grm.expectrr().unwrap_or(0);
+        if sr_len != expect || rr_len != expectrr {
+            writeln!(
+                &mut stderr(),
+                "{}: expected {} Shift/Reduce and {} Reduce/Reduce conflicts but found {} Shift/Reduce and {} Reduce/Reduce conflicts",
+                &yacc_y_path, expect, expectrr, sr_len, rr_len
+            )
+            .ok();
+            process::exit(1);
+        }
+    }
+
     {
         let rule_ids = grm
             .tokens_map()
             .iter()
-            .map(|(&n, &i)| (n, usize::from(i).to_u16().unwrap()))
+            .map(|(&n, &i)| (n, usize::from(i).to_u32().unwrap()))
             .collect();
         let (missing_from_lexer, missing_from_parser) = lexerdef.set_rule_ids(&rule_ids);
         if !quiet {